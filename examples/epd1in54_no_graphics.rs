@@ -60,7 +60,7 @@ fn main() -> Result<(), anyhow::Error> {
     // Setup of the needed pins is finished here
     // Now the "real" usage of the eink-waveshare-rs crate begins
     let mut epd =
-        Epd1in54::new(&mut spi, busy, dc, rst, Some(5)).await.map_err(anyhow::Error::msg)?;
+        Epd1in54::new(&mut spi, busy, dc, Some(rst), Some(5)).await.map_err(anyhow::Error::msg)?;
 
     // Clear the full screen
     epd.clear_frame(&mut spi).await
@@ -0,0 +1,229 @@
+#![deny(warnings)]
+
+use core::cell::RefCell;
+use core::future::Future;
+use core::pin::pin;
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+use embedded_graphics::{
+    mono_font::MonoTextStyleBuilder,
+    prelude::*,
+    text::{Baseline, Text, TextStyleBuilder},
+};
+use embedded_hal::{
+    digital::{self, InputPin, OutputPin},
+    spi,
+};
+use embedded_hal_async::{delay::DelayNs, spi::Operation};
+use epd_waveshare_async::{
+    color::*,
+    epd2in13_v2::{Display2in13, Epd2in13},
+    prelude::*,
+    PollingBusyPin, SharedPin,
+};
+use linux_embedded_hal::{
+    spidev::{self, SpidevOptions},
+    sysfs_gpio::Direction,
+    SpidevDevice, SysfsPin,
+};
+
+// Two panels on one SPI bus, each with its own chip select (CE0 and CE1), BUSY and
+// RST, sharing the DC line.
+// The Linux HAL is blocking, see the adapters at the end for the async traits.
+// activate spi, gpio in raspi-config
+// needs to be run with sudo because of some sysfs_gpio permission problems and follow-up timing problems
+// see https://github.com/rust-embedded/rust-sysfs-gpio/issues/5 and follow-up issues
+
+fn main() -> Result<(), anyhow::Error> {
+    block_on(run())
+}
+
+async fn run() -> Result<(), anyhow::Error> {
+    // The kernel toggles CE0 for spidev0.0 and CE1 for spidev0.1
+    let mut first_spi = open_spi("/dev/spidev0.0");
+    let mut second_spi = open_spi("/dev/spidev0.1");
+
+    let first_busy = export_pin(24, Direction::In); // GPIO 24, board J-18
+    let first_rst = export_pin(17, Direction::Out); // GPIO 17, board J-11
+    let second_busy = export_pin(23, Direction::In); // GPIO 23, board J-16
+    let second_rst = export_pin(27, Direction::Out); // GPIO 27, board J-13
+
+    // Both panels take turns on DC, each with a SharedPin of its own
+    let dc = RefCell::new(export_pin(25, Direction::Out)); // GPIO 25, board J-22
+
+    // The sysfs pins can't wait for an edge, BUSY is polled every ms instead
+    let first_busy = PollingBusyPin::new(first_busy, SleepDelay, 1_000);
+    let second_busy = PollingBusyPin::new(second_busy, SleepDelay, 1_000);
+
+    let mut first = Epd2in13::new(
+        &mut first_spi,
+        first_busy,
+        SharedPin::new(&dc),
+        Some(first_rst),
+        SleepDelay,
+        None,
+    )
+    .await
+    .map_err(epd_error)?;
+    let mut second = Epd2in13::new(
+        &mut second_spi,
+        second_busy,
+        SharedPin::new(&dc),
+        Some(second_rst),
+        SleepDelay,
+        None,
+    )
+    .await
+    .map_err(epd_error)?;
+
+    let mut display = Display2in13::default();
+
+    // The updates run one after the other, the panels never use DC at the same time
+    draw_text(&mut display, "First panel", 5, 50);
+    first
+        .update_and_display_frame(&mut first_spi, display.buffer())
+        .await
+        .map_err(epd_error)?;
+
+    display.clear(Color::White).ok();
+    draw_text(&mut display, "Second panel", 5, 50);
+    second
+        .update_and_display_frame(&mut second_spi, display.buffer())
+        .await
+        .map_err(epd_error)?;
+
+    println!("Finished - going to sleep");
+    first.sleep(&mut first_spi).await.map_err(epd_error)?;
+    second.sleep(&mut second_spi).await.map_err(epd_error)
+}
+
+// The errors borrow the shared DC pin, anyhow only takes 'static ones
+fn epd_error(error: impl core::fmt::Display) -> anyhow::Error {
+    anyhow::anyhow!("{}", error)
+}
+
+fn open_spi(path: &str) -> Spi {
+    let mut spi = SpidevDevice::open(path).expect("spidev directory");
+    let options = SpidevOptions::new()
+        .bits_per_word(8)
+        .max_speed_hz(4_000_000)
+        .mode(spidev::SpiModeFlags::SPI_MODE_0)
+        .build();
+    spi.configure(&options).expect("spi configuration");
+    Spi(spi)
+}
+
+fn export_pin(number: u64, direction: Direction) -> Pin {
+    let pin = SysfsPin::new(number);
+    pin.export().expect("pin export");
+    while !pin.is_exported() {}
+    pin.set_direction(direction).expect("pin direction");
+    if direction == Direction::Out {
+        pin.set_value(1).expect("pin value set to 1");
+    }
+    Pin(pin)
+}
+
+fn draw_text(display: &mut Display2in13, text: &str, x: i32, y: i32) {
+    let style = MonoTextStyleBuilder::new()
+        .font(&embedded_graphics::mono_font::ascii::FONT_6X10)
+        .text_color(Color::White)
+        .background_color(Color::Black)
+        .build();
+
+    let text_style = TextStyleBuilder::new().baseline(Baseline::Top).build();
+
+    let _ = Text::with_text_style(text, Point::new(x, y), style, text_style).draw(display);
+}
+
+// The drivers need errors which are Copy, the ones of the Linux HAL carry an io::Error
+#[derive(Debug, Clone, Copy)]
+struct HalError;
+
+impl core::fmt::Display for HalError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Linux HAL error")
+    }
+}
+
+impl digital::Error for HalError {
+    fn kind(&self) -> digital::ErrorKind {
+        digital::ErrorKind::Other
+    }
+}
+
+impl spi::Error for HalError {
+    fn kind(&self) -> spi::ErrorKind {
+        spi::ErrorKind::Other
+    }
+}
+
+// Async SpiDevice on the blocking spidev, every transaction blocks until it's done
+struct Spi(SpidevDevice);
+
+impl spi::ErrorType for Spi {
+    type Error = HalError;
+}
+
+impl embedded_hal_async::spi::SpiDevice for Spi {
+    async fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), HalError> {
+        spi::SpiDevice::transaction(&mut self.0, operations).map_err(|_| HalError)
+    }
+}
+
+// Sysfs GPIO with the Copy error
+struct Pin(SysfsPin);
+
+impl digital::ErrorType for Pin {
+    type Error = HalError;
+}
+
+impl InputPin for Pin {
+    fn is_high(&mut self) -> Result<bool, HalError> {
+        self.0.is_high().map_err(|_| HalError)
+    }
+
+    fn is_low(&mut self) -> Result<bool, HalError> {
+        self.0.is_low().map_err(|_| HalError)
+    }
+}
+
+impl OutputPin for Pin {
+    fn set_low(&mut self) -> Result<(), HalError> {
+        self.0.set_low().map_err(|_| HalError)
+    }
+
+    fn set_high(&mut self) -> Result<(), HalError> {
+        self.0.set_high().map_err(|_| HalError)
+    }
+}
+
+// Sleeps the thread, like the Linux HAL blocks in every call
+struct SleepDelay;
+
+impl DelayNs for SleepDelay {
+    async fn delay_ns(&mut self, ns: u32) {
+        std::thread::sleep(std::time::Duration::from_nanos(ns.into()));
+    }
+}
+
+// Polls `future` until it's done, the Linux HAL blocks in every call anyway
+fn block_on<F: Future>(future: F) -> F::Output {
+    fn noop_raw_waker() -> RawWaker {
+        fn no_op(_: *const ()) {}
+        fn clone(_: *const ()) -> RawWaker {
+            noop_raw_waker()
+        }
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, no_op, no_op, no_op);
+        RawWaker::new(core::ptr::null(), &VTABLE)
+    }
+
+    let waker = unsafe { Waker::from_raw(noop_raw_waker()) };
+    let mut context = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+    }
+}
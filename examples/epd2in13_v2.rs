@@ -64,7 +64,7 @@ fn main() -> Result<(), anyhow::Error> {
     let mut delay = Delay {};
 
     let mut epd2in13 =
-        Epd2in13::new(&mut spi, busy, dc, rst, None).expect("eink initalize error");
+        Epd2in13::new(&mut spi, busy, dc, Some(rst), None).expect("eink initalize error");
 
     //println!("Test all the rotations");
     let mut display = Display2in13::default();
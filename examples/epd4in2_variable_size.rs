@@ -64,7 +64,7 @@ fn main() -> Result<(), anyhow::Error> {
     let mut delay = Delay {};
 
     let mut epd4in2 =
-        Epd4in2::new(&mut spi, busy, dc, rst, None).expect("eink initalize error");
+        Epd4in2::new(&mut spi, busy, dc, Some(rst), None).expect("eink initalize error");
 
     println!("Test all the rotations");
 
@@ -0,0 +1,167 @@
+//! Booster soft start settings of the UC8151, UC8176 and UC8179 controllers
+//!
+//! Panels fed by a weak supply can brown out while the booster starts, a longer soft
+//! start period or a lower driving strength spreads the inrush current.
+
+/// Soft start period of a booster phase
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SoftStartPeriod {
+    /// 10 ms
+    Ms10 = 0b00,
+    /// 20 ms
+    Ms20 = 0b01,
+    /// 30 ms
+    Ms30 = 0b10,
+    /// 40 ms
+    Ms40 = 0b11,
+}
+
+/// Minimum off time of the booster's gate driver (GDR)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MinOffTime {
+    /// 0.27 µs
+    Ns270 = 0b000,
+    /// 0.34 µs
+    Ns340 = 0b001,
+    /// 0.40 µs
+    Ns400 = 0b010,
+    /// 0.54 µs
+    Ns540 = 0b011,
+    /// 0.80 µs
+    Ns800 = 0b100,
+    /// 1.54 µs
+    Ns1540 = 0b101,
+    /// 3.34 µs
+    Ns3340 = 0b110,
+    /// 6.58 µs
+    Ns6580 = 0b111,
+}
+
+/// Settings of one booster phase
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoosterPhase {
+    /// Soft start period, only phases A and B have one
+    pub soft_start_period: SoftStartPeriod,
+    /// Driving strength from 1 (weakest) to 8 (strongest), other values are clamped
+    pub driving_strength: u8,
+    /// Minimum off time of the gate driver
+    pub min_off_time: MinOffTime,
+}
+
+impl BoosterPhase {
+    /// Byte of the phase in the booster soft start command (0x06)
+    pub fn byte(&self) -> u8 {
+        (self.soft_start_period as u8) << 6
+            | (self.driving_strength.clamp(1, 8) - 1) << 3
+            | self.min_off_time as u8
+    }
+
+    /// Decodes the byte of a phase
+    pub const fn from_byte(byte: u8) -> Self {
+        let soft_start_period = match byte >> 6 {
+            0b00 => SoftStartPeriod::Ms10,
+            0b01 => SoftStartPeriod::Ms20,
+            0b10 => SoftStartPeriod::Ms30,
+            _ => SoftStartPeriod::Ms40,
+        };
+        let min_off_time = match byte & 0b111 {
+            0b000 => MinOffTime::Ns270,
+            0b001 => MinOffTime::Ns340,
+            0b010 => MinOffTime::Ns400,
+            0b011 => MinOffTime::Ns540,
+            0b100 => MinOffTime::Ns800,
+            0b101 => MinOffTime::Ns1540,
+            0b110 => MinOffTime::Ns3340,
+            _ => MinOffTime::Ns6580,
+        };
+        BoosterPhase {
+            soft_start_period,
+            driving_strength: ((byte >> 3) & 0b111) + 1,
+            min_off_time,
+        }
+    }
+}
+
+/// Booster soft start (command 0x06) of the UC81xx controllers
+///
+/// The UC8151 and UC8176 have the phases A, B and C, the UC8179 splits phase C into
+/// C1 and C2. The three phase controllers don't send `phase_c2`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoosterSoftStart {
+    /// Phase A
+    pub phase_a: BoosterPhase,
+    /// Phase B
+    pub phase_b: BoosterPhase,
+    /// Phase C, or C1 on the UC8179
+    pub phase_c: BoosterPhase,
+    /// Phase C2 of the UC8179
+    pub phase_c2: BoosterPhase,
+}
+
+impl BoosterSoftStart {
+    /// Decodes the data of the booster soft start command
+    ///
+    /// `phase_c2` is taken from `c2`, use the byte of phase C for the three phase
+    /// controllers.
+    pub const fn from_bytes(a: u8, b: u8, c: u8, c2: u8) -> Self {
+        BoosterSoftStart {
+            phase_a: BoosterPhase::from_byte(a),
+            phase_b: BoosterPhase::from_byte(b),
+            phase_c: BoosterPhase::from_byte(c),
+            phase_c2: BoosterPhase::from_byte(c2),
+        }
+    }
+
+    /// Data of the booster soft start command, the three phase controllers only send
+    /// the first three bytes
+    pub fn bytes(&self) -> [u8; 4] {
+        [
+            self.phase_a.byte(),
+            self.phase_b.byte(),
+            self.phase_c.byte(),
+            self.phase_c2.byte(),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn phase_bytes() {
+        // 0x17 of the Waveshare examples: 10 ms, strength 3, 6.58 µs
+        let phase = BoosterPhase {
+            soft_start_period: SoftStartPeriod::Ms10,
+            driving_strength: 3,
+            min_off_time: MinOffTime::Ns6580,
+        };
+        assert_eq!(phase.byte(), 0x17);
+        assert_eq!(BoosterPhase::from_byte(0x17), phase);
+
+        let phase = BoosterPhase {
+            soft_start_period: SoftStartPeriod::Ms40,
+            driving_strength: 1,
+            min_off_time: MinOffTime::Ns800,
+        };
+        assert_eq!(phase.byte(), 0xC4);
+        assert_eq!(BoosterPhase::from_byte(0xC4), phase);
+
+        let clamped = BoosterPhase {
+            driving_strength: 12,
+            ..phase
+        };
+        assert_eq!(clamped.byte(), 0xFC);
+    }
+
+    #[test]
+    fn round_trip() {
+        let booster = BoosterSoftStart::from_bytes(0x17, 0x17, 0x27, 0x17);
+        assert_eq!(booster.phase_c.driving_strength, 5);
+        assert_eq!(booster.bytes(), [0x17, 0x17, 0x27, 0x17]);
+
+        for byte in 0..=u8::MAX {
+            assert_eq!(BoosterPhase::from_byte(byte).byte(), byte);
+        }
+    }
+}
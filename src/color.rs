@@ -7,6 +7,8 @@
 use embedded_graphics_core::pixelcolor::BinaryColor;
 #[cfg(feature = "graphics")]
 use embedded_graphics_core::pixelcolor::PixelColor;
+#[cfg(feature = "graphics")]
+use embedded_graphics_core::pixelcolor::{Gray2, GrayColor};
 
 /// When trying to parse u8 to one of the color types
 #[derive(Debug, PartialEq, Eq)]
@@ -65,6 +67,22 @@ pub enum OctColor {
     HiZ = 0x07,
 }
 
+/// For the 4 Color Displays (Black/White/Yellow/Red, G variants)
+///
+/// Every pixel takes 2 bits, four pixels are packed into one byte with the first
+/// pixel in the most significant bits.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum QuadColor {
+    /// Black Color
+    Black = 0b00,
+    /// White Color
+    White = 0b01,
+    /// Yellow Color
+    Yellow = 0b10,
+    /// Red Color
+    Red = 0b11,
+}
+
 /// Color trait for use in `Display`s
 pub trait ColorType {
     /// Number of bit used to represent this color type in a single buffer.
@@ -130,6 +148,29 @@ impl ColorType for OctColor {
     }
 }
 
+impl ColorType for QuadColor {
+    const BITS_PER_PIXEL_PER_BUFFER: usize = 2;
+    const BUFFER_COUNT: usize = 1;
+    fn bitmask(&self, _bwrbit: bool, pos: u32) -> (u8, u16) {
+        let shift = 6 - 2 * (pos % 4);
+        let mask = !(0b11 << shift);
+        (mask, (self.get_bits() as u16) << shift)
+    }
+}
+
+/// 4 level grayscale, packed like [QuadColor] with the luma as bits: `0b00` is black
+/// and `0b11` white
+#[cfg(feature = "graphics")]
+impl ColorType for Gray2 {
+    const BITS_PER_PIXEL_PER_BUFFER: usize = 2;
+    const BUFFER_COUNT: usize = 1;
+    fn bitmask(&self, _bwrbit: bool, pos: u32) -> (u8, u16) {
+        let shift = 6 - 2 * (pos % 4);
+        let mask = !(0b11 << shift);
+        (mask, (self.luma() as u16) << shift)
+    }
+}
+
 #[cfg(feature = "graphics")]
 impl From<BinaryColor> for OctColor {
     fn from(b: BinaryColor) -> OctColor {
@@ -240,6 +281,119 @@ impl OctColor {
         }
     }
 }
+
+impl QuadColor {
+    /// Gets the 2 bit representation of the Color as needed by the display
+    pub fn get_bits(self) -> u8 {
+        self as u8
+    }
+
+    /// Gets a full byte of four pixels of this color
+    pub fn get_byte_value(self) -> u8 {
+        QuadColor::colors_byte([self; 4])
+    }
+
+    /// Converts four colors into a single byte for the Display, the first one
+    /// ends up in the most significant bits
+    pub fn colors_byte(colors: [QuadColor; 4]) -> u8 {
+        colors
+            .iter()
+            .fold(0, |byte, color| (byte << 2) | color.get_bits())
+    }
+
+    /// Take the lower 2 bits and convert them to a QuadColor
+    pub fn from_bits(bits: u8) -> QuadColor {
+        match bits & 0b11 {
+            0b00 => QuadColor::Black,
+            0b01 => QuadColor::White,
+            0b10 => QuadColor::Yellow,
+            _ => QuadColor::Red,
+        }
+    }
+
+    /// Split a single byte into the colors of its four pixels
+    pub fn split_byte(byte: u8) -> [QuadColor; 4] {
+        [
+            QuadColor::from_bits(byte >> 6),
+            QuadColor::from_bits(byte >> 4),
+            QuadColor::from_bits(byte >> 2),
+            QuadColor::from_bits(byte),
+        ]
+    }
+
+    /// Reverses the order of the four pixels of a byte, for panels with a mirrored RAM
+    pub fn mirror_byte(byte: u8) -> u8 {
+        ((byte & 0b0000_0011) << 6)
+            | ((byte & 0b0000_1100) << 2)
+            | ((byte & 0b0011_0000) >> 2)
+            | ((byte & 0b1100_0000) >> 6)
+    }
+
+    /// Converts to limited range of RGB values.
+    pub fn rgb(self) -> (u8, u8, u8) {
+        match self {
+            QuadColor::White => (0xff, 0xff, 0xff),
+            QuadColor::Black => (0x00, 0x00, 0x00),
+            QuadColor::Yellow => (0xff, 0xff, 0x00),
+            QuadColor::Red => (0xff, 0x00, 0x00),
+        }
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl PixelColor for QuadColor {
+    type Raw = embedded_graphics_core::pixelcolor::raw::RawU2;
+}
+
+#[cfg(feature = "graphics")]
+impl From<embedded_graphics_core::pixelcolor::raw::RawU2> for QuadColor {
+    fn from(b: embedded_graphics_core::pixelcolor::raw::RawU2) -> Self {
+        use embedded_graphics_core::prelude::RawData;
+        QuadColor::from_bits(b.into_inner())
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl From<BinaryColor> for QuadColor {
+    fn from(b: BinaryColor) -> QuadColor {
+        match b {
+            BinaryColor::On => QuadColor::Black,
+            BinaryColor::Off => QuadColor::White,
+        }
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl From<QuadColor> for embedded_graphics_core::pixelcolor::Rgb888 {
+    fn from(b: QuadColor) -> Self {
+        let (r, g, b) = b.rgb();
+        Self::new(r, g, b)
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl From<embedded_graphics_core::pixelcolor::Rgb888> for QuadColor {
+    fn from(p: embedded_graphics_core::pixelcolor::Rgb888) -> QuadColor {
+        use embedded_graphics_core::prelude::RgbColor;
+        let colors = [
+            QuadColor::Black,
+            QuadColor::White,
+            QuadColor::Yellow,
+            QuadColor::Red,
+        ];
+        // pick the nearest color
+        *colors
+            .iter()
+            .min_by_key(|c| {
+                let (r, g, b) = c.rgb();
+                (i32::from(r) - i32::from(p.r())).pow(2)
+                    + (i32::from(g) - i32::from(p.g())).pow(2)
+                    + (i32::from(b) - i32::from(p.b())).pow(2)
+            })
+            .unwrap_or(&QuadColor::White)
+    }
+}
+
 //TODO: Rename get_bit_value to bit() and get_byte_value to byte() ?
 
 impl Color {
@@ -433,4 +587,56 @@ mod tests {
             Ok((left, right))
         );
     }
+
+    #[test]
+    fn test_quad() {
+        let colors = [
+            QuadColor::Red,
+            QuadColor::White,
+            QuadColor::Black,
+            QuadColor::Yellow,
+        ];
+        assert_eq!(QuadColor::colors_byte(colors), 0b11_01_00_10);
+        assert_eq!(QuadColor::split_byte(0b11_01_00_10), colors);
+        assert_eq!(QuadColor::White.get_byte_value(), 0x55);
+    }
+
+    #[test]
+    fn quad_mirror() {
+        let colors = [
+            QuadColor::Black,
+            QuadColor::White,
+            QuadColor::Yellow,
+            QuadColor::Red,
+        ];
+        let mirrored = QuadColor::mirror_byte(QuadColor::colors_byte(colors));
+        assert_eq!(
+            QuadColor::split_byte(mirrored),
+            [
+                QuadColor::Red,
+                QuadColor::Yellow,
+                QuadColor::White,
+                QuadColor::Black
+            ]
+        );
+        assert_eq!(QuadColor::mirror_byte(0x55), 0x55);
+    }
+
+    #[test]
+    fn quad_bitmask() {
+        // third pixel of a byte
+        let (mask, bits) = QuadColor::Yellow.bitmask(false, 6);
+        assert_eq!(mask, 0b11_11_00_11);
+        assert_eq!(bits, 0b00_00_10_00);
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn gray2_bitmask() {
+        // second pixel of a byte, light gray
+        let (mask, bits) = Gray2::new(0b10).bitmask(false, 1);
+        assert_eq!(mask, 0b11_00_11_11);
+        assert_eq!(bits, 0b00_10_00_00);
+        assert_eq!(Gray2::WHITE.bitmask(false, 3).1, 0b11);
+    }
 }
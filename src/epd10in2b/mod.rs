@@ -0,0 +1,409 @@
+//! A simple Driver for the Waveshare 10.2" (B) E-Ink Display via SPI
+//!
+//! # References
+//!
+//! - [Datasheet](https://www.waveshare.com/10.2inch-e-paper-b.htm)
+//! - [Waveshare C driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_10in2b.c)
+//! - [Waveshare Python driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd10in2b.py)
+
+use core::fmt::{Debug, Display};
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+
+use crate::color::Color;
+use crate::interface::{BusyLevel, DisplayDelay, DisplayInterface, ResetTiming};
+use crate::prelude::{ErrorKind, WaveshareDisplay, WaveshareThreeColorDisplay};
+use crate::traits::{ErrorType, InternalWiAdditions, RefreshLut};
+
+pub(crate) mod command;
+use self::command::Command;
+use crate::buffer_len;
+
+/// Full size buffer for use with the 10in2b EPD
+#[cfg(feature = "graphics")]
+pub type Display10in2b = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize, HEIGHT as usize * 2) },
+    crate::color::TriColor,
+>;
+
+/// Width of the display
+pub const WIDTH: u32 = 960;
+/// Height of the display
+pub const HEIGHT: u32 = 640;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+const BUSY_LEVEL: BusyLevel = BusyLevel::Low;
+const RESET_TIMING: ResetTiming = ResetTiming::new(10_000, 2_000);
+const NUM_DISPLAY_BITS: u32 = WIDTH * HEIGHT / 8;
+const SINGLE_BYTE_WRITE: bool = false;
+
+/// Fill value of the black/white plane for a blank (white) frame
+const CLEAR_ACHROMATIC: u8 = 0xFF;
+/// Fill value of the chromatic plane for a blank frame, a set bit is red
+const CLEAR_CHROMATIC: u8 = 0x00;
+
+/// Epd10in2b driver
+///
+pub struct Epd10in2b<SPI, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    color: Color,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST>
+    for Epd10in2b<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    type Error = ErrorKind<SPI, BUSY, DC, RST>;
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Epd10in2b<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        // Reset the device
+        self.interface.reset().await?;
+
+        // Start the booster with a stronger phase A/B than the controller defaults
+        self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x27, 0x27, 0x18, 0x17])
+            .await?;
+
+        // Set the power settings: VGH=20V,VGL=-20V,VDH=15V,VDL=-15V
+        self.cmd_with_data(spi, Command::PowerSetting, &[0x07, 0x07, 0x3F, 0x3F])
+            .await?;
+
+        // Power on
+        self.command(spi, Command::PowerOn).await?;
+        self.interface.delay(100_000).await;
+        self.wait_until_idle(spi).await?;
+
+        // Set the panel settings: BWROTP
+        self.cmd_with_data(spi, Command::PanelSetting, &[0x0F])
+            .await?;
+
+        // Set the real resolution
+        self.send_resolution(spi).await?;
+
+        // Disable dual SPI
+        self.cmd_with_data(spi, Command::DualSPI, &[0x00]).await?;
+
+        // Set Vcom and data interval
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x11, 0x07])
+            .await?;
+
+        // Set S2G and G2S non-overlap periods to 12 (default)
+        self.cmd_with_data(spi, Command::TconSetting, &[0x22])
+            .await?;
+
+        self.wait_until_idle(spi).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd10in2b<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    async fn update_color_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+        chromatic: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_achromatic_frame(spi, black).await?;
+        self.update_chromatic_frame(spi, chromatic).await
+    }
+
+    /// Update only the black/white data of the display.
+    ///
+    /// This can be called on its own, the chromatic plane keeps its last content.
+    async fn update_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::DataStartTransmission1, black)
+            .await
+    }
+
+    /// Update only the chromatic data of the display.
+    ///
+    /// This can be called on its own, the black/white plane keeps its last content.
+    async fn update_chromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        chromatic: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::DataStartTransmission2, chromatic)
+            .await
+    }
+
+    async fn clear_achromatic_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+
+        self.command(spi, Command::DataStartTransmission1).await?;
+        self.interface
+            .data_x_times(spi, CLEAR_ACHROMATIC, NUM_DISPLAY_BITS)
+            .await
+    }
+
+    async fn clear_chromatic_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+
+        self.command(spi, Command::DataStartTransmission2).await?;
+        self.interface
+            .data_x_times(spi, CLEAR_CHROMATIC, NUM_DISPLAY_BITS)
+            .await
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd10in2b<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    type DisplayColor = Color;
+    async fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        delay: DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Self::Error> {
+        let interface =
+            DisplayInterface::new(Some(busy), dc, rst, delay, RESET_TIMING, delay_us, None);
+        let color = DEFAULT_BACKGROUND_COLOR;
+
+        let mut epd = Epd10in2b { interface, color };
+
+        epd.init(spi).await?;
+
+        Ok(epd)
+    }
+
+    async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.command(spi, Command::PowerOff).await?;
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5]).await?;
+        self.interface.mark_asleep();
+        Ok(())
+    }
+
+    async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.require_reset_pin()?;
+        self.init(spi).await
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
+
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    /// Transmits the black/white frame and blanks the chromatic plane
+    async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        self.update_achromatic_frame(spi, buffer).await?;
+        self.command(spi, Command::DataStartTransmission2).await?;
+        self.interface
+            .data_x_times(spi, CLEAR_CHROMATIC, NUM_DISPLAY_BITS)
+            .await
+    }
+
+    /// The panel doesn't support partial updates, this always returns [ErrorKind::Unsupported]
+    async fn update_partial_frame(
+        &mut self,
+        _spi: &mut SPI,
+        _buffer: &[u8],
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+    ) -> Result<(), Self::Error> {
+        Err(ErrorKind::Unsupported)
+    }
+
+    async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.command(spi, Command::DisplayRefresh).await?;
+        self.interface.delay(100_000).await;
+        self.wait_until_idle(spi).await
+    }
+
+    async fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_frame(spi, buffer).await?;
+        self.display_frame(spi).await
+    }
+
+    async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.clear_achromatic_frame(spi).await?;
+        self.clear_chromatic_frame(spi).await
+    }
+
+    /// The panel has no selectable LUTs, this always returns [ErrorKind::Unsupported]
+    async fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        _refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Self::Error> {
+        Err(ErrorKind::Unsupported)
+    }
+
+    async fn wait_until_idle(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.wait_until_idle(BUSY_LEVEL).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd10in2b<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    async fn command(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.cmd(spi, command).await
+    }
+
+    async fn send_data(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.data(spi, data).await
+    }
+
+    async fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    async fn send_resolution(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let w = self.width();
+        let h = self.height();
+
+        self.command(spi, Command::TconResolution).await?;
+        self.send_data(spi, &[(w >> 8) as u8]).await?;
+        self.send_data(spi, &[w as u8]).await?;
+        self.send_data(spi, &[(h >> 8) as u8]).await?;
+        self.send_data(spi, &[h as u8]).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 960);
+        assert_eq!(HEIGHT, 640);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+        assert_eq!(
+            NUM_DISPLAY_BITS as usize,
+            buffer_len(WIDTH as usize, HEIGHT as usize)
+        );
+    }
+}
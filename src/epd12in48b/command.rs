@@ -0,0 +1,103 @@
+//! SPI Commands for the Waveshare 12.48" (B) E-Ink Display
+
+use crate::traits;
+
+/// Epd12in48b commands
+///
+/// The same command set is understood by all four controllers of the panel.
+///
+/// Should rarely (never?) be needed directly.
+///
+/// For more infos about the addresses and what they are doing look into the PDFs.
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub(crate) enum Command {
+    /// Set Resolution, LUT selection, BWR pixels, gate scan direction, source shift
+    /// direction, booster switch, soft reset.
+    PanelSetting = 0x00,
+
+    /// Selecting internal and external power
+    PowerSetting = 0x01,
+
+    /// After the Power Off command, the driver will power off following the Power Off
+    /// Sequence; BUSY signal will become "0".
+    PowerOff = 0x02,
+
+    /// Turning On the Power
+    ///
+    /// After the Power ON command, the driver will power on following the Power ON
+    /// sequence. Once complete, the BUSY signal will become "1".
+    PowerOn = 0x04,
+
+    /// Starting data transmission
+    BoosterSoftStart = 0x06,
+
+    /// This command makes the chip enter the deep-sleep mode to save power.
+    ///
+    /// The deep sleep mode would return to stand-by by hardware reset.
+    ///
+    /// The only one parameter is a check code, the command would be excuted if check code = 0xA5.
+    DeepSleep = 0x07,
+
+    /// This command starts transmitting the black/white data and writes them into SRAM.
+    DataStartTransmission1 = 0x10,
+
+    /// After this command is issued, driver will refresh display (data/VCOM) according to
+    /// SRAM data and LUT.
+    ///
+    /// After Display Refresh command, BUSY signal will become "0" until the display
+    /// update is finished.
+    DisplayRefresh = 0x12,
+
+    /// This command starts transmitting the red data and writes them into SRAM.
+    DataStartTransmission2 = 0x13,
+
+    /// Dual SPI - what for?
+    DualSpi = 0x15,
+
+    /// The command controls the PLL clock frequency.
+    PllControl = 0x30,
+
+    /// This command indicates the interval of Vcom and data output. When setting the
+    /// vertical back porch, the total blanking will be kept (20 Hsync).
+    VcomAndDataIntervalSetting = 0x50,
+
+    /// This command defines non-overlap period of Gate and Source.
+    TconSetting = 0x60,
+
+    /// This command defines alternative resolution and this setting is of higher priority
+    /// than the RES\[1:0\] in R00H (PSR).
+    TconResolution = 0x61,
+
+    /// This command sets `VCOM_DC` value.
+    VcmDcSetting = 0x82,
+
+    /// Cascade setting, shares the temperature and the clock of the master controllers
+    CascadeSetting = 0xE0,
+
+    /// Power saving
+    PowerSaving = 0xE3,
+
+    /// Force temperature setting for the cascaded controllers
+    ForceTemperature = 0xE5,
+}
+
+impl traits::Command for Command {
+    /// Returns the address of the command
+    fn address(self) -> u8 {
+        self as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn command_addr() {
+        assert_eq!(Command::PanelSetting.address(), 0x00);
+        assert_eq!(Command::DataStartTransmission2.address(), 0x13);
+        assert_eq!(Command::CascadeSetting.address(), 0xE0);
+    }
+}
@@ -0,0 +1,677 @@
+//! A simple Driver for the Waveshare 12.48" (B) E-Ink Display via SPI
+//!
+//! The panel is driven by four cascaded controllers, every one of them with its
+//! own chip select and BUSY line, while DC and RST are shared. Looking at the front
+//! of the panel, the controllers drive the following quadrants:
+//!
+//! ```text
+//!         648px   656px
+//!       +-------+-------+
+//! 492px |  S2   |  M2   |
+//!       +-------+-------+
+//! 492px |  M1   |  S1   |
+//!       +-------+-------+
+//! ```
+//!
+//! As this doesn't fit the single SPI device model of [WaveshareDisplay](crate::traits::WaveshareDisplay),
+//! [Epd12in48b] owns its four [SpiDevice]s and offers the familiar functions on top
+//! of one logical 1304x984 buffer, which is split into the four quadrants internally.
+//!
+//! # References
+//!
+//! - [Waveshare product page](https://www.waveshare.com/wiki/12.48inch_e-Paper_Module_(B))
+//! - [Waveshare C driver](https://github.com/waveshareteam/12.48inch-e-paper/blob/master/RaspberryPi/c/lib/e-Paper/EPD_12in48b.c)
+use core::fmt::{Debug, Display};
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+
+use crate::buffer_len;
+use crate::color::TriColor;
+use crate::error::ErrorKind;
+use crate::interface::{BusyGroup, BusyLevel, DisplayDelay, ResetTiming};
+use crate::traits::{Command as _, ErrorType};
+
+pub(crate) mod command;
+use self::command::Command;
+
+/// Full size buffer for use with the 12in48b EPD
+///
+/// This needs 320kB of RAM, the driver functions take plain byte slices so any
+/// other buffer with the same layout works as well.
+#[cfg(feature = "graphics")]
+pub type Display12in48b = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize, HEIGHT as usize * 2) },
+    TriColor,
+>;
+
+/// Width of the display
+pub const WIDTH: u32 = 1304;
+/// Height of the display
+pub const HEIGHT: u32 = 984;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: TriColor = TriColor::White;
+const BUSY_LEVEL: BusyLevel = BusyLevel::Low;
+const RESET_TIMING: ResetTiming = ResetTiming::new(200_000, 10_000);
+/// Bytes of one row of the logical buffer
+const ROW_BYTES: usize = WIDTH as usize / 8;
+/// Bytes of one color plane of the logical buffer
+const PLANE_SIZE: usize = buffer_len(WIDTH as usize, HEIGHT as usize);
+
+/// Controller indices of the masters, the slaves S1 and S2 follow them
+const M1: usize = 0;
+const M2: usize = 2;
+/// The masters take care of the power supply of their slaves
+const MASTERS: [usize; 2] = [M1, M2];
+
+/// The part of the logical buffer driven by one controller
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Quadrant {
+    /// first column in bytes
+    x: usize,
+    /// width in bytes
+    width: usize,
+    /// first row
+    y: usize,
+    /// height in rows
+    height: usize,
+    /// Panel setting, the upper controllers scan mirrored
+    panel_setting: u8,
+}
+
+/// Quadrants in controller order (M1, S1, M2, S2)
+const QUADRANTS: [Quadrant; 4] = [
+    Quadrant {
+        x: 0,
+        width: 81,
+        y: 492,
+        height: 492,
+        panel_setting: 0x0F,
+    },
+    Quadrant {
+        x: 81,
+        width: 82,
+        y: 492,
+        height: 492,
+        panel_setting: 0x0F,
+    },
+    Quadrant {
+        x: 81,
+        width: 82,
+        y: 0,
+        height: 492,
+        panel_setting: 0x03,
+    },
+    Quadrant {
+        x: 0,
+        width: 81,
+        y: 0,
+        height: 492,
+        panel_setting: 0x03,
+    },
+];
+
+/// Rows of `quadrant` inside of a full plane of the logical buffer
+fn quadrant_rows(buffer: &[u8], quadrant: Quadrant) -> impl Iterator<Item = &[u8]> {
+    buffer[quadrant.y * ROW_BYTES..(quadrant.y + quadrant.height) * ROW_BYTES]
+        .chunks(ROW_BYTES)
+        .map(move |row| &row[quadrant.x..quadrant.x + quadrant.width])
+}
+
+/// Epd12in48b driver
+///
+pub struct Epd12in48b<SPI, BUSY, DC, RST, DELAY> {
+    /// SPI devices of the controllers M1, S1, M2 and S2
+    spi: [SPI; 4],
+    /// BUSY pins of the controllers M1, S1, M2 and S2, low while busy
+    busy: BusyGroup<BUSY, 4>,
+    /// Shared Data/Command Control Pin (High for data, Low for command)
+    dc: DC,
+    /// Shared Pin for Resetting
+    rst: RST,
+    /// Sleeps for the reset timing and the waits of the refresh
+    delay: DELAY,
+    /// Timing of the shared reset
+    reset_timing: ResetTiming,
+    /// Longest write, longer data is split up, `None` for no limit
+    max_transfer_size: Option<usize>,
+    /// Background Color
+    color: TriColor,
+    /// The controllers are in deep sleep and BUSY doesn't answer until the next reset
+    asleep: bool,
+    /// Duration of the last wait for BUSY in µs, `None` without a clock
+    last_busy_us: Option<u32>,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST>
+    for Epd12in48b<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    type Error = ErrorKind<SPI, BUSY, DC, RST>;
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd12in48b<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    /// Creates a new driver and initialises all four controllers
+    ///
+    /// `spi` and `busy` are expected in the order M1, S1, M2, S2.
+    pub async fn new(
+        spi: [SPI; 4],
+        busy: [BUSY; 4],
+        dc: DC,
+        rst: RST,
+        delay: DELAY,
+    ) -> Result<Self, <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let mut epd = Epd12in48b {
+            spi,
+            busy: BusyGroup::new(busy, BUSY_LEVEL),
+            dc,
+            rst,
+            delay,
+            reset_timing: RESET_TIMING,
+            max_transfer_size: None,
+            color: DEFAULT_BACKGROUND_COLOR,
+            asleep: false,
+            last_busy_us: None,
+        };
+
+        epd.init().await?;
+
+        Ok(epd)
+    }
+
+    async fn init(&mut self) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.reset().await?;
+
+        for (controller, quadrant) in QUADRANTS.iter().enumerate() {
+            self.cmd_with_data(controller, Command::PanelSetting, &[quadrant.panel_setting])
+                .await?;
+        }
+
+        for controller in MASTERS {
+            self.cmd_with_data(
+                controller,
+                Command::PowerSetting,
+                &[0x07, 0x17, 0x3F, 0x3F, 0x0D],
+            )
+            .await?;
+        }
+
+        self.cmd_with_data_all(Command::BoosterSoftStart, &[0x17, 0x17, 0x39, 0x17])
+            .await?;
+
+        for (controller, quadrant) in QUADRANTS.iter().enumerate() {
+            let w = (quadrant.width * 8) as u16;
+            let h = quadrant.height as u16;
+            self.cmd_with_data(
+                controller,
+                Command::TconResolution,
+                &[(w >> 8) as u8, w as u8, (h >> 8) as u8, h as u8],
+            )
+            .await?;
+        }
+
+        self.cmd_with_data_all(Command::DualSpi, &[0x20]).await?;
+        self.cmd_with_data_all(Command::PllControl, &[0x08]).await?;
+        self.cmd_with_data_all(Command::VcomAndDataIntervalSetting, &[0x11, 0x07])
+            .await?;
+        self.cmd_with_data_all(Command::TconSetting, &[0x22])
+            .await?;
+
+        for controller in MASTERS {
+            // share temperature and clock with the slaves
+            self.cmd_with_data(controller, Command::CascadeSetting, &[0x01])
+                .await?;
+        }
+        self.cmd_with_data_all(Command::PowerSaving, &[0x00])
+            .await?;
+        for controller in MASTERS {
+            self.cmd_with_data(controller, Command::VcmDcSetting, &[0x1C])
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Wakes all controllers up from deep sleep
+    pub async fn wake_up(&mut self) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.init().await
+    }
+
+    /// Powers all controllers off and lets them enter deep sleep
+    pub async fn sleep(&mut self) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.wait_until_idle().await?;
+        for controller in MASTERS {
+            self.command(controller, Command::PowerOff).await?;
+        }
+        self.wait_until_idle().await?;
+        self.cmd_with_data_all(Command::DeepSleep, &[0xA5]).await?;
+        self.asleep = true;
+        Ok(())
+    }
+
+    /// Replaces the timing of the shared reset, it takes effect with the next
+    /// [wake_up](Epd12in48b::wake_up)
+    pub fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.reset_timing = timing;
+    }
+
+    /// Splits long SPI writes like the rows of a frame into chunks of at most `size` bytes
+    ///
+    /// `None`, the default, sends every row in one write. Use `Some(4096)` with Linux'
+    /// spidev, it rejects longer transfers by default.
+    pub fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.max_transfer_size = size;
+    }
+
+    /// Returns how long the last wait for the busy pins took in µs
+    ///
+    /// Only measured with a clock, pass a [TimedDelay](crate::TimedDelay) as `delay` to
+    /// [Epd12in48b::new]. Otherwise it's `None`.
+    pub fn last_busy_duration(&self) -> Option<u32> {
+        self.last_busy_us
+    }
+
+    /// Sets the background color used by [clear_frame](Epd12in48b::clear_frame)
+    pub fn set_background_color(&mut self, color: TriColor) {
+        self.color = color;
+    }
+
+    /// Get current background color
+    pub fn background_color(&self) -> &TriColor {
+        &self.color
+    }
+
+    /// Get the width of the display
+    pub fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    /// Get the height of the display
+    pub fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    /// Transmits a full black/white frame and blanks the chromatic plane
+    pub async fn update_frame(
+        &mut self,
+        buffer: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.update_achromatic_frame(buffer).await?;
+        for (controller, quadrant) in QUADRANTS.iter().enumerate() {
+            self.command(controller, Command::DataStartTransmission2)
+                .await?;
+            self.data_x_times(controller, 0x00, quadrant.width * quadrant.height)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Transmits the black/white and the chromatic plane
+    pub async fn update_color_frame(
+        &mut self,
+        black: &[u8],
+        chromatic: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.update_achromatic_frame(black).await?;
+        self.update_chromatic_frame(chromatic).await
+    }
+
+    /// Transmits only the black/white plane, a cleared bit is a black pixel
+    pub async fn update_achromatic_frame(
+        &mut self,
+        black: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.write_plane(Command::DataStartTransmission1, black)
+            .await
+    }
+
+    /// Transmits only the chromatic plane, a set bit is a red pixel
+    pub async fn update_chromatic_frame(
+        &mut self,
+        chromatic: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.write_plane(Command::DataStartTransmission2, chromatic)
+            .await
+    }
+
+    /// Refreshes all four quadrants at once
+    pub async fn display_frame(
+        &mut self,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.wait_until_idle().await?;
+        for controller in MASTERS {
+            self.command(controller, Command::PowerOn).await?;
+        }
+        self.delay(300_000).await;
+        self.wait_until_idle().await?;
+
+        for controller in 0..QUADRANTS.len() {
+            self.command(controller, Command::DisplayRefresh).await?;
+        }
+        self.delay(100_000).await;
+        self.wait_until_idle().await
+    }
+
+    /// Transmits the black/white frame and refreshes the display
+    pub async fn update_and_display_frame(
+        &mut self,
+        buffer: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.update_frame(buffer).await?;
+        self.display_frame().await
+    }
+
+    /// Clears both planes of all controllers to the background color
+    ///
+    /// The display isn't refreshed, call [display_frame](Epd12in48b::display_frame) afterwards.
+    pub async fn clear_frame(
+        &mut self,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let (black, chromatic) = match self.color {
+            TriColor::White => (0xFF, 0x00),
+            TriColor::Black => (0x00, 0x00),
+            TriColor::Chromatic => (0xFF, 0xFF),
+        };
+        self.wait_until_idle().await?;
+        for (controller, quadrant) in QUADRANTS.iter().enumerate() {
+            let len = quadrant.width * quadrant.height;
+            self.command(controller, Command::DataStartTransmission1)
+                .await?;
+            self.data_x_times(controller, black, len).await?;
+            self.command(controller, Command::DataStartTransmission2)
+                .await?;
+            self.data_x_times(controller, chromatic, len).await?;
+        }
+        Ok(())
+    }
+
+    /// Waits until none of the four controllers is busy anymore
+    ///
+    /// Returns [ErrorKind::Asleep] in deep sleep instead of waiting for the busy pins
+    pub async fn wait_until_idle(
+        &mut self,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if self.asleep {
+            return Err(ErrorKind::Asleep);
+        }
+        let start = self.delay.now_us();
+        let result = self
+            .busy
+            .wait_until_idle()
+            .await
+            .map_err(ErrorKind::BusyError);
+        self.last_busy_us = start
+            .zip(self.delay.now_us())
+            .map(|(start, now)| now.wrapping_sub(start));
+        result
+    }
+
+    /// Sends every controller its quadrant of `buffer`
+    async fn write_plane(
+        &mut self,
+        command: Command,
+        buffer: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        assert!(buffer.len() == PLANE_SIZE);
+        self.wait_until_idle().await?;
+        for (controller, quadrant) in QUADRANTS.iter().enumerate() {
+            self.command(controller, command).await?;
+            self.dc.set_high().map_err(ErrorKind::DcError)?;
+            for row in quadrant_rows(buffer, *quadrant) {
+                self.write(controller, row).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn reset(&mut self) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let timing = self.reset_timing;
+        self.rst.set_high().map_err(ErrorKind::RstError)?;
+        self.delay(timing.deassert_us).await;
+        self.rst.set_low().map_err(ErrorKind::RstError)?;
+        self.delay(timing.assert_us).await;
+        self.rst.set_high().map_err(ErrorKind::RstError)?;
+        self.asleep = false;
+        self.delay(timing.post_us).await;
+        Ok(())
+    }
+
+    async fn command(
+        &mut self,
+        controller: usize,
+        command: Command,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        // low for commands
+        self.dc.set_low().map_err(ErrorKind::DcError)?;
+        self.write(controller, &[command.address()]).await
+    }
+
+    async fn send_data(
+        &mut self,
+        controller: usize,
+        data: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        // high for data
+        self.dc.set_high().map_err(ErrorKind::DcError)?;
+        self.write(controller, data).await
+    }
+
+    async fn cmd_with_data(
+        &mut self,
+        controller: usize,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.command(controller, command).await?;
+        self.send_data(controller, data).await
+    }
+
+    /// Sends the same command to all four controllers
+    async fn cmd_with_data_all(
+        &mut self,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        for controller in 0..QUADRANTS.len() {
+            self.cmd_with_data(controller, command, data).await?;
+        }
+        Ok(())
+    }
+
+    async fn data_x_times(
+        &mut self,
+        controller: usize,
+        val: u8,
+        repetitions: usize,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let chunk = [val; 64];
+        self.dc.set_high().map_err(ErrorKind::DcError)?;
+        let mut remaining = repetitions;
+        while remaining > 0 {
+            let len = remaining.min(chunk.len());
+            self.write(controller, &chunk[..len]).await?;
+            remaining -= len;
+        }
+        Ok(())
+    }
+
+    async fn write(
+        &mut self,
+        controller: usize,
+        data: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        // split up for limited drivers like Linux' spidev
+        let size = self.max_transfer_size.unwrap_or(usize::MAX).max(1);
+        for data_chunk in data.chunks(size) {
+            self.spi[controller]
+                .write(data_chunk)
+                .await
+                .map_err(ErrorKind::SpiError)?;
+        }
+        Ok(())
+    }
+
+    /// Sleeps for `duration_us` µs
+    async fn delay(&mut self, duration_us: u32) {
+        self.delay.sleep_us(duration_us).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::mock::{block_on, mocks, Busy, Dc, Delay, Log, Rst, Spi};
+
+    const S1: usize = 1;
+    const S2: usize = 3;
+
+    /// Creates the driver on four sets of mocks, returns the log of M1, which also
+    /// records the shared delay
+    fn epd() -> (Rc<Log>, Epd12in48b<Spi, Busy, Dc, Rst, Delay>) {
+        let (log, m1, m1_busy, dc, rst, delay) = mocks();
+        let (_, s1, s1_busy, _, _, _) = mocks();
+        let (_, m2, m2_busy, _, _, _) = mocks();
+        let (_, s2, s2_busy, _, _, _) = mocks();
+        let epd = block_on(Epd12in48b::new(
+            [m1, s1, m2, s2],
+            [m1_busy, s1_busy, m2_busy, s2_busy],
+            dc,
+            rst,
+            delay,
+        ))
+        .unwrap();
+        (log, epd)
+    }
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 1304);
+        assert_eq!(HEIGHT, 984);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, TriColor::White);
+        assert_eq!(ROW_BYTES, 163);
+        assert_eq!(PLANE_SIZE, 160_392);
+    }
+
+    #[test]
+    fn quadrants_cover_the_panel() {
+        assert_eq!(QUADRANTS[S2].width + QUADRANTS[M2].width, ROW_BYTES);
+        assert_eq!(QUADRANTS[M1].width + QUADRANTS[S1].width, ROW_BYTES);
+        assert_eq!(QUADRANTS[S1].y, QUADRANTS[M1].y);
+        assert_eq!(QUADRANTS[S2].width * 8, 648);
+        assert_eq!(QUADRANTS[M2].width * 8, 656);
+        assert_eq!(QUADRANTS[S2].height + QUADRANTS[M1].height, HEIGHT as usize);
+    }
+
+    #[test]
+    fn quadrant_slicing() {
+        let mut buffer = [0u8; PLANE_SIZE];
+        for (i, byte) in buffer.iter_mut().enumerate() {
+            // encode the column in the lower bits and the row parity in the top bit
+            let (row, column) = (i / ROW_BYTES, i % ROW_BYTES);
+            *byte = column as u8 | (((row % 2) as u8) << 7);
+        }
+
+        let mut covered = [0u8; PLANE_SIZE];
+        for quadrant in QUADRANTS {
+            let mut rows = 0;
+            for (r, row) in quadrant_rows(&buffer, quadrant).enumerate() {
+                assert_eq!(row.len(), quadrant.width);
+                let row_parity = (((quadrant.y + r) % 2) as u8) << 7;
+                for (c, &byte) in row.iter().enumerate() {
+                    assert_eq!(byte, (quadrant.x + c) as u8 | row_parity);
+                }
+                let offset = row.as_ptr() as usize - buffer.as_ptr() as usize;
+                for seen in &mut covered[offset..offset + row.len()] {
+                    *seen += 1;
+                }
+                rows += 1;
+            }
+            assert_eq!(rows, quadrant.height);
+        }
+
+        assert!(covered.iter().all(|&seen| seen == 1));
+    }
+
+    #[test]
+    fn quadrant_borders() {
+        let buffer: [u8; PLANE_SIZE] = core::array::from_fn(|i| (i % ROW_BYTES) as u8);
+
+        // the left quadrants end with column 80, the right ones start with 81
+        let s2_row = quadrant_rows(&buffer, QUADRANTS[S2]).next().unwrap();
+        let m2_row = quadrant_rows(&buffer, QUADRANTS[M2]).next().unwrap();
+        assert_eq!(*s2_row.last().unwrap(), 80);
+        assert_eq!(m2_row[0], 81);
+        assert_eq!(*m2_row.last().unwrap(), 162);
+
+        // the lower quadrants start with row 492
+        let m1_first = quadrant_rows(&buffer, QUADRANTS[M1]).next().unwrap();
+        assert_eq!(
+            m1_first.as_ptr() as usize - buffer.as_ptr() as usize,
+            492 * ROW_BYTES
+        );
+    }
+
+    #[test]
+    fn long_reset() {
+        let (log, mut epd) = epd();
+        epd.set_reset_timing(ResetTiming::new(0, 5_000_000));
+        log.clear();
+        block_on(epd.wake_up()).unwrap();
+        // longer than a single DelayNs of 4.29 s
+        let total: u64 = log.delays().iter().map(|&ns| u64::from(ns)).sum();
+        assert_eq!(total, 5_200_000_000);
+    }
+
+    #[test]
+    fn max_transfer_size() {
+        let (log, mut epd) = epd();
+        let buffer = [0xFF; PLANE_SIZE];
+
+        log.clear();
+        block_on(epd.update_achromatic_frame(&buffer)).unwrap();
+        // the command, then one write per row of M1
+        assert_eq!(log.transfers()[..3], [1, 81, 81]);
+
+        epd.set_max_transfer_size(Some(64));
+        log.clear();
+        block_on(epd.update_achromatic_frame(&buffer)).unwrap();
+        assert_eq!(log.transfers()[..5], [1, 64, 17, 64, 17]);
+    }
+
+    #[test]
+    fn asleep_until_wake_up() {
+        let (_, mut epd) = epd();
+        block_on(epd.sleep()).unwrap();
+        // the busy pins don't change in deep sleep
+        let result = block_on(epd.display_frame());
+        assert!(matches!(result, Err(ErrorKind::Asleep)));
+
+        block_on(epd.wake_up()).unwrap();
+        block_on(epd.display_frame()).unwrap();
+    }
+}
@@ -0,0 +1,444 @@
+//! A simple Driver for the Waveshare 13.3" (K) E-Ink Display via SPI
+//!
+//! The panel is driven by a SSD1677 controller, which addresses its RAM in pixels
+//! in x direction and allows arbitrary RAM windows.
+//!
+//! A full frame needs `960 / 8 * 680 = 81_600` bytes, so [Display13in3k] is only
+//! an option for targets with enough RAM. All driver functions take plain byte
+//! slices, so a user provided buffer (e.g. through [VarDisplay](crate::graphics::VarDisplay)
+//! or a buffer rendered in bands) works as well.
+//!
+//! # References
+//!
+//! - [Waveshare product page](https://www.waveshare.com/wiki/13.3inch_e-Paper_HAT_(K)_Manual)
+//! - [Waveshare C driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_13in3k.c)
+//! - [Waveshare Python driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd13in3k.py)
+use core::fmt::{Debug, Display};
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+
+use crate::buffer_len;
+use crate::color::Color;
+use crate::error::ErrorKind;
+use crate::interface::{BusyLevel, DisplayDelay, DisplayInterface, ResetTiming};
+use crate::traits::{ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay};
+
+pub(crate) mod command;
+use self::command::Command;
+
+/// Full size buffer for use with the 13in3k EPD
+///
+/// This needs 81.6kB of RAM, use a [VarDisplay](crate::graphics::VarDisplay)
+/// if you want to provide your own buffer.
+#[cfg(feature = "graphics")]
+pub type Display13in3k = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize, HEIGHT as usize) },
+    Color,
+>;
+
+/// Width of the display
+pub const WIDTH: u32 = 960;
+/// Height of the display
+pub const HEIGHT: u32 = 680;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+const BUSY_LEVEL: BusyLevel = BusyLevel::High;
+const RESET_TIMING: ResetTiming = ResetTiming::new(10_000, 2_000);
+const SINGLE_BYTE_WRITE: bool = false;
+
+/// Epd13in3k driver
+///
+pub struct Epd13in3k<SPI, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    color: Color,
+    /// Refresh LUT
+    refresh: RefreshLut,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST>
+    for Epd13in3k<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    type Error = ErrorKind<SPI, BUSY, DC, RST>;
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Epd13in3k<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.reset().await?;
+        self.wait_until_idle(spi).await?;
+
+        self.command(spi, Command::SwReset).await?;
+        self.wait_until_idle(spi).await?;
+
+        self.cmd_with_data(
+            spi,
+            Command::BoosterSoftStartControl,
+            &[0xAE, 0xC7, 0xC3, 0xC0, 0x80],
+        )
+        .await?;
+
+        // gate count (HEIGHT - 1) and the default scanning order
+        self.cmd_with_data(
+            spi,
+            Command::DriverOutputControl,
+            &[(HEIGHT - 1) as u8, ((HEIGHT - 1) >> 8) as u8, 0x02],
+        )
+        .await?;
+
+        self.cmd_with_data(spi, Command::BorderWaveformControl, &[0x01])
+            .await?;
+
+        // x increment, y increment, address counter is updated in x direction
+        self.cmd_with_data(spi, Command::DataEntryModeSetting, &[0x03])
+            .await?;
+
+        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1).await?;
+
+        // use the internal temperature sensor
+        self.cmd_with_data(spi, Command::TemperatureSensorSelection, &[0x80])
+            .await?;
+
+        self.set_ram_counter(spi, 0, 0).await?;
+        self.wait_until_idle(spi).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd13in3k<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    type DisplayColor = Color;
+    async fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        delay: DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Self::Error> {
+        let interface =
+            DisplayInterface::new(Some(busy), dc, rst, delay, RESET_TIMING, delay_us, None);
+
+        let mut epd = Epd13in3k {
+            interface,
+            color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+        };
+
+        epd.init(spi).await?;
+
+        Ok(epd)
+    }
+
+    async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        // Deep Sleep Mode 2, a hardware reset is needed to wake up
+        self.cmd_with_data(spi, Command::DeepSleepMode, &[0x03])
+            .await?;
+        self.interface.mark_asleep();
+        Ok(())
+    }
+
+    async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.require_reset_pin()?;
+        self.init(spi).await
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
+
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        assert!(buffer.len() == buffer_len(WIDTH as usize, HEIGHT as usize));
+        self.wait_until_idle(spi).await?;
+        self.use_full_frame(spi).await?;
+        self.cmd_with_data(spi, Command::WriteRam, buffer).await
+    }
+
+    /// Writes `buffer` into the RAM window starting at (x, y)
+    ///
+    /// x and width need to be multiples of 8
+    async fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        assert!(x % 8 == 0 && width % 8 == 0);
+        assert!(x + width <= WIDTH && y + height <= HEIGHT);
+        assert!(buffer.len() == buffer_len(width as usize, height as usize));
+
+        self.wait_until_idle(spi).await?;
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)
+            .await?;
+        self.set_ram_counter(spi, x, y).await?;
+        self.cmd_with_data(spi, Command::WriteRam, buffer).await?;
+
+        // restore the full window for the next frame update
+        self.use_full_frame(spi).await
+    }
+
+    async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        let update = match self.refresh {
+            // Load temperature and waveform, DISPLAY with DISPLAY Mode 1
+            RefreshLut::Full => 0xF7,
+            // Same as above with DISPLAY Mode 2, only changed pixels are driven
+            RefreshLut::Quick => 0xFF,
+        };
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[update])
+            .await?;
+        self.command(spi, Command::MasterActivation).await?;
+        self.wait_until_idle(spi).await
+    }
+
+    async fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_frame(spi, buffer).await?;
+        self.display_frame(spi).await
+    }
+
+    async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        let color = self.color.get_byte_value();
+        self.wait_until_idle(spi).await?;
+
+        self.use_full_frame(spi).await?;
+        self.command(spi, Command::WriteRam).await?;
+        self.interface
+            .data_x_times(spi, color, WIDTH / 8 * HEIGHT)
+            .await?;
+
+        self.use_full_frame(spi).await?;
+        self.command(spi, Command::WriteRam2).await?;
+        self.interface
+            .data_x_times(spi, color, WIDTH / 8 * HEIGHT)
+            .await
+    }
+
+    /// The waveforms are always taken from the OTP, `RefreshLut::Quick` selects
+    /// the differential display mode for the next refreshes.
+    async fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Self::Error> {
+        if let Some(refresh_lut) = refresh_rate {
+            self.refresh = refresh_lut;
+        }
+        Ok(())
+    }
+
+    async fn wait_until_idle(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.wait_until_idle(BUSY_LEVEL).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd13in3k<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    async fn command(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.cmd(spi, command).await
+    }
+
+    async fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    async fn use_full_frame(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        // choose full frame/ram
+        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1).await?;
+
+        // start from the beginning
+        self.set_ram_counter(spi, 0, 0).await
+    }
+
+    /// Sets both X and Y pixels ranges
+    ///
+    /// The SSD1677 uses pixel positions for x as well, so no shifting is needed here
+    async fn set_ram_area(
+        &mut self,
+        spi: &mut SPI,
+        start_x: u32,
+        start_y: u32,
+        end_x: u32,
+        end_y: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.cmd_with_data(
+            spi,
+            Command::SetRamXAddressStartEndPosition,
+            &[
+                start_x as u8,
+                (start_x >> 8) as u8,
+                end_x as u8,
+                (end_x >> 8) as u8,
+            ],
+        )
+        .await?;
+
+        self.cmd_with_data(
+            spi,
+            Command::SetRamYAddressStartEndPosition,
+            &[
+                start_y as u8,
+                (start_y >> 8) as u8,
+                end_y as u8,
+                (end_y >> 8) as u8,
+            ],
+        )
+        .await
+    }
+
+    /// Sets both X and Y pixels counters when writing data to RAM
+    async fn set_ram_counter(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.cmd_with_data(
+            spi,
+            Command::SetRamXAddressCounter,
+            &[x as u8, (x >> 8) as u8],
+        )
+        .await?;
+
+        self.cmd_with_data(
+            spi,
+            Command::SetRamYAddressCounter,
+            &[y as u8, (y >> 8) as u8],
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{block_on, mocks};
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 960);
+        assert_eq!(HEIGHT, 680);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+        assert_eq!(buffer_len(WIDTH as usize, HEIGHT as usize), 81_600);
+    }
+
+    #[test]
+    fn max_transfer_size() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd13in3k::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        let frame = [0x0F; 81_600];
+
+        epd.set_max_transfer_size(Some(4096));
+        log.clear();
+        block_on(epd.update_frame(&mut spi, &frame)).unwrap();
+        assert_eq!(log.transfers().iter().max(), Some(&4096));
+        assert_eq!(log.data_of(0x24, 0), frame);
+    }
+}
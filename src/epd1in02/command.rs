@@ -0,0 +1,105 @@
+//! SPI Commands for the Waveshare 1.02" E-Ink Display
+
+use crate::traits;
+
+/// Epd1in02 commands for the UC8175 controller
+///
+/// Should rarely (never?) be needed directly.
+///
+/// For more infos about the addresses and what they are doing look into the PDFs.
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub(crate) enum Command {
+    /// Set Resolution, LUT selection, gate scan direction, source shift direction,
+    /// booster switch, soft reset.
+    PanelSetting = 0x00,
+
+    /// Selecting internal and external power
+    PowerSetting = 0x01,
+
+    /// After the Power Off command, the driver will power off following the Power Off
+    /// Sequence; BUSY signal will become "0".
+    PowerOff = 0x02,
+
+    /// Turning On the Power
+    PowerOn = 0x04,
+
+    /// Starting data transmission
+    BoosterSoftStart = 0x06,
+
+    /// This command makes the chip enter the deep-sleep mode to save power.
+    ///
+    /// The only one parameter is a check code, the command would be excuted if check code = 0xA5.
+    DeepSleep = 0x07,
+
+    /// Writes the "OLD" data to SRAM.
+    DataStartTransmission1 = 0x10,
+
+    /// Refreshes the display with the data in SRAM and the current LUT.
+    DisplayRefresh = 0x12,
+
+    /// Writes the "NEW" data to SRAM.
+    DataStartTransmission2 = 0x13,
+
+    /// Look-Up Table for pixels turning white
+    LutWhite = 0x23,
+    /// Look-Up Table for pixels turning black
+    LutBlack = 0x24,
+
+    /// Selects how the LUTs treat pixels which don't change
+    LutOption = 0x2A,
+
+    /// The command controls the PLL clock frequency.
+    PllControl = 0x30,
+
+    /// This command indicates the interval of Vcom and data output.
+    VcomAndDataIntervalSetting = 0x50,
+
+    /// This command defines non-overlap period of Gate and Source.
+    TconSetting = 0x60,
+
+    /// This command defines alternative resolution and this setting is of higher priority
+    /// than the RES\[1:0\] in R00H (PSR).
+    ResolutionSetting = 0x61,
+
+    /// Read the status flags of the controller
+    GetStatus = 0x71,
+
+    /// Set VCM_DC
+    VcmDcSetting = 0x82,
+
+    /// Sets the window of the partial mode, x in steps of 8 pixels.
+    PartialWindow = 0x90,
+
+    /// Enters the partial mode, only the partial window is written and refreshed.
+    PartialIn = 0x91,
+
+    /// Leaves the partial mode.
+    PartialOut = 0x92,
+
+    /// Analog setting of the vendor init sequence, not described in the datasheet
+    AnalogSetting = 0xD2,
+
+    /// This command is set for saving power during refresh period.
+    PowerSaving = 0xE3,
+}
+
+impl traits::Command for Command {
+    /// Returns the address of the command
+    fn address(self) -> u8 {
+        self as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn command_addr() {
+        assert_eq!(Command::PanelSetting.address(), 0x00);
+        assert_eq!(Command::LutBlack.address(), 0x24);
+        assert_eq!(Command::PartialWindow.address(), 0x90);
+    }
+}
@@ -0,0 +1,470 @@
+//! A simple Driver for the Waveshare 1.02" E-Ink Display via SPI
+//!
+//! The panel is driven by a UC8175 controller. The waveforms are uploaded by the
+//! driver, [WaveshareDisplay::set_lut] switches between the full (`RefreshLut::Full`)
+//! and the partial (`RefreshLut::Quick`) ones of the vendor driver.
+//!
+//! [WaveshareDisplay::update_partial_frame] writes a window of the display with the
+//! `PartialIn`/`PartialWindow` commands, the next [WaveshareDisplay::display_frame]
+//! refreshes it and sends `PartialOut`. The controller addresses the sources in
+//! steps of 8, so the window is widened to the byte boundaries around `x` and
+//! `x + width`.
+//!
+//! # References
+//!
+//! - [Waveshare product page](https://www.waveshare.com/wiki/1.02inch_e-Paper_Module_Manual)
+//! - [Waveshare C driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_1in02d.c)
+//! - [Waveshare Python driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd1in02.py)
+use core::fmt::{Debug, Display};
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+
+use crate::buffer_len;
+use crate::color::Color;
+use crate::error::ErrorKind;
+use crate::interface::{BusyLevel, DisplayDelay, DisplayInterface, ResetTiming};
+use crate::traits::{ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay};
+
+//The Lookup Tables for the Display
+mod constants;
+use self::constants::*;
+
+pub(crate) mod command;
+use self::command::Command;
+
+/// Full size buffer for use with the 1in02 EPD
+#[cfg(feature = "graphics")]
+pub type Display1in02 = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize, HEIGHT as usize) },
+    Color,
+>;
+
+/// Width of the display
+pub const WIDTH: u32 = 80;
+/// Height of the display
+pub const HEIGHT: u32 = 128;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+const BUSY_LEVEL: BusyLevel = BusyLevel::Low;
+const RESET_TIMING: ResetTiming = ResetTiming::new(20_000, 2_000);
+const SINGLE_BYTE_WRITE: bool = true;
+
+/// Epd1in02 driver
+///
+pub struct Epd1in02<SPI, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    color: Color,
+    /// Refresh LUT
+    refresh: RefreshLut,
+    /// The controller is in partial mode until the next refresh
+    is_partial_refresh: bool,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST>
+    for Epd1in02<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    type Error = ErrorKind<SPI, BUSY, DC, RST>;
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Epd1in02<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.reset().await?;
+        self.is_partial_refresh = false;
+
+        self.cmd_with_data(spi, Command::AnalogSetting, &[0x3F])
+            .await?;
+
+        // LUT from registers, B/W mode, scan up, shift right, booster on
+        self.cmd_with_data(spi, Command::PanelSetting, &[0x6F])
+            .await?;
+
+        self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x3F])
+            .await?;
+
+        self.cmd_with_data(spi, Command::LutOption, &[0x00, 0x00])
+            .await?;
+
+        self.cmd_with_data(spi, Command::PllControl, &[0x13])
+            .await?;
+
+        self.cmd_with_data(spi, Command::TconSetting, &[0x22])
+            .await?;
+
+        self.send_resolution(spi).await?;
+
+        self.cmd_with_data(spi, Command::VcmDcSetting, &[0x12])
+            .await?;
+
+        self.cmd_with_data(spi, Command::PowerSaving, &[0x33])
+            .await?;
+
+        // also sets the power and the VCOM interval belonging to the waveform
+        self.set_lut(spi, None).await?;
+
+        self.command(spi, Command::PowerOn).await?;
+        self.wait_until_idle(spi).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd1in02<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    type DisplayColor = Color;
+    async fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        delay: DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Self::Error> {
+        let interface =
+            DisplayInterface::new(Some(busy), dc, rst, delay, RESET_TIMING, delay_us, None);
+
+        let mut epd = Epd1in02 {
+            interface,
+            color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+            is_partial_refresh: false,
+        };
+
+        epd.init(spi).await?;
+
+        Ok(epd)
+    }
+
+    async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        // float the border while sleeping
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0xF7])
+            .await?;
+        self.command(spi, Command::PowerOff).await?;
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5]).await?;
+        self.interface.mark_asleep();
+        Ok(())
+    }
+
+    async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.require_reset_pin()?;
+        self.init(spi).await
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
+
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        assert!(buffer.len() == buffer_len(WIDTH as usize, HEIGHT as usize));
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)
+            .await
+    }
+
+    /// Writes `buffer` into the window starting at (x, y)
+    ///
+    /// The window is widened to full bytes, so `buffer` has to hold the pixels from
+    /// `x & !7` up to the end of the byte containing `x + width - 1` for every row.
+    async fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        assert!(width > 0 && height > 0);
+        assert!(x + width <= WIDTH && y + height <= HEIGHT);
+        let window = partial_window(x, y, width, height);
+        let row_bytes = (window[1] - window[0]) as usize / 8 + 1;
+        assert!(buffer.len() == row_bytes * height as usize);
+
+        self.wait_until_idle(spi).await?;
+        self.command(spi, Command::PartialIn).await?;
+        self.cmd_with_data(spi, Command::PartialWindow, &window)
+            .await?;
+        self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)
+            .await?;
+        self.is_partial_refresh = true;
+        Ok(())
+    }
+
+    async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.command(spi, Command::DisplayRefresh).await?;
+        self.wait_until_idle(spi).await?;
+
+        if self.is_partial_refresh {
+            self.command(spi, Command::PartialOut).await?;
+            self.is_partial_refresh = false;
+        }
+        Ok(())
+    }
+
+    async fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_frame(spi, buffer).await?;
+        self.display_frame(spi).await
+    }
+
+    async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        let color = self.color.get_byte_value();
+        self.wait_until_idle(spi).await?;
+
+        self.command(spi, Command::DataStartTransmission1).await?;
+        self.interface
+            .data_x_times(spi, color, WIDTH / 8 * HEIGHT)
+            .await?;
+
+        self.command(spi, Command::DataStartTransmission2).await?;
+        self.interface
+            .data_x_times(spi, color, WIDTH / 8 * HEIGHT)
+            .await
+    }
+
+    /// Uploads the full (`RefreshLut::Full`) or the partial (`RefreshLut::Quick`) waveform
+    ///
+    /// The vendor driver uses a lower source voltage and a different VCOM interval with
+    /// the partial waveform, both are set here as well.
+    async fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Self::Error> {
+        if let Some(refresh_lut) = refresh_rate {
+            self.refresh = refresh_lut;
+        }
+        self.wait_until_idle(spi).await?;
+        match self.refresh {
+            RefreshLut::Full => self.set_lut_helper(spi, 0x2B, 0x57, &LUT_W, &LUT_B).await,
+            RefreshLut::Quick => {
+                self.set_lut_helper(spi, 0x25, 0xF2, &LUT_W_QUICK, &LUT_B_QUICK)
+                    .await
+            }
+        }
+    }
+
+    async fn wait_until_idle(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.wait_until_idle(BUSY_LEVEL).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd1in02<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    async fn command(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.cmd(spi, command).await
+    }
+
+    async fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    async fn send_resolution(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let w = self.width();
+        let h = self.height();
+
+        // both resolutions are a single byte on the UC8175
+        self.cmd_with_data(spi, Command::ResolutionSetting, &[w as u8, h as u8])
+            .await
+    }
+
+    async fn set_lut_helper(
+        &mut self,
+        spi: &mut SPI,
+        source_voltage: u8,
+        vcom_interval: u8,
+        lut_w: &[u8],
+        lut_b: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.cmd_with_data(
+            spi,
+            Command::PowerSetting,
+            &[0x03, 0x00, source_voltage, source_voltage],
+        )
+        .await?;
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[vcom_interval])
+            .await?;
+        self.cmd_with_data(spi, Command::LutWhite, lut_w).await?;
+        self.cmd_with_data(spi, Command::LutBlack, lut_b).await
+    }
+}
+
+/// Data of the `PartialWindow` command for the given pixel window
+///
+/// The UC8175 takes the horizontal start in steps of 8 sources and needs the lower
+/// 3 bits of the horizontal end set, so the window is widened to full bytes. The
+/// vertical range is given in gates, the last byte scans only inside the window.
+fn partial_window(x: u32, y: u32, width: u32, height: u32) -> [u8; 5] {
+    let x_start = x & !0x07;
+    let x_end = (x + width - 1) | 0x07;
+    let y_end = y + height - 1;
+    [x_start as u8, x_end as u8, y as u8, y_end as u8, 0x01]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{block_on, mocks};
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 80);
+        assert_eq!(HEIGHT, 128);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+        // 80 pixels fill 10 bytes without padding
+        assert_eq!(buffer_len(WIDTH as usize, HEIGHT as usize), 10 * 128);
+    }
+
+    #[test]
+    fn window_is_byte_aligned() {
+        assert_eq!(partial_window(0, 0, WIDTH, HEIGHT), [0, 79, 0, 127, 0x01]);
+        assert_eq!(partial_window(16, 8, 16, 16), [16, 31, 8, 23, 0x01]);
+        // a window inside a byte takes the whole byte
+        assert_eq!(partial_window(10, 0, 4, 1), [8, 15, 0, 0, 0x01]);
+    }
+
+    #[test]
+    fn lut_selection() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd1in02::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert_eq!(log.data_of(0x01, 0), [0x03, 0x00, 0x2B, 0x2B]);
+        assert_eq!(log.data_of(0x23, 0), LUT_W);
+        assert_eq!(log.data_of(0x24, 0), LUT_B);
+
+        log.clear();
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Quick))).unwrap();
+        assert_eq!(log.commands(), [0x01, 0x50, 0x23, 0x24]);
+        assert_eq!(log.data_of(0x01, 0), [0x03, 0x00, 0x25, 0x25]);
+        assert_eq!(log.data_of(0x50, 0), [0xF2]);
+        assert_eq!(log.data_of(0x23, 0), LUT_W_QUICK);
+        assert_eq!(log.data_of(0x24, 0), LUT_B_QUICK);
+    }
+
+    #[test]
+    fn partial_refresh() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd1in02::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        let digit = [0x00; 2 * 16];
+        block_on(epd.update_partial_frame(&mut spi, &digit, 16, 8, 16, 16)).unwrap();
+        assert_eq!(log.commands(), [0x91, 0x90, 0x13]);
+        assert_eq!(log.data_of(0x90, 0), [16, 31, 8, 23, 0x01]);
+        assert_eq!(log.data_of(0x13, 0), digit);
+
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(log.commands(), [0x12, 0x92]);
+
+        // the next refresh is a full one again
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(log.commands(), [0x12]);
+    }
+}
@@ -1,5 +1,16 @@
 //! A simple Driver for the Waveshare 1.54" E-Ink Display via SPI
 //!
+//! Besides full refreshes the panel does partial ones with [QuickRefresh]: write the
+//! frame currently shown with [QuickRefresh::update_old_frame] into the second RAM bank
+//! and the new one with [QuickRefresh::update_new_frame], then
+//! [QuickRefresh::display_new_frame] drives the pixels with the partial waveform. The
+//! `update_partial_*` variants do the same for a window, e.g. a 48x48 icon.
+//!
+//! The partial waveform relies on the panel state left by a full refresh, so after
+//! [WaveshareDisplay::new] or [WaveshareDisplay::wake_up] a full refresh with
+//! [WaveshareDisplay::display_frame] has to be done first. Until then
+//! [QuickRefresh::display_new_frame] returns [ErrorKind::NoBaseFrame].
+//!
 //! # Example for the 1.54 in E-Ink Display
 //!
 //!```rust, no_run
@@ -17,9 +28,10 @@
 //!# let busy_in = pin::Mock::new(&expectations);
 //!# let dc = pin::Mock::new(&expectations);
 //!# let rst = pin::Mock::new(&expectations);
+//!# let mut delay = delay::NoopDelay::new();
 //!
 //!// Setup EPD
-//!let mut epd = Epd1in54::new(&mut spi, busy_in, dc, rst, None)?;
+//!let mut epd = Epd1in54::new(&mut spi, busy_in, dc, Some(rst), delay, None)?;
 //!
 //!// Use display graphics from embedded-graphics
 //!let mut display = Display1in54::default();
@@ -50,26 +62,32 @@ pub const HEIGHT: u32 = 200;
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 //const DPI: u16 = 184;
-const IS_BUSY_LOW: bool = false;
+const BUSY_LEVEL: BusyLevel = BusyLevel::High;
+const RESET_TIMING: ResetTiming = ResetTiming::new(10_000, 10_000);
 const SINGLE_BYTE_WRITE: bool = true;
 
 use core::fmt::{Debug, Display};
+use core::num::NonZeroU32;
 use embedded_hal::digital::{InputPin, OutputPin};
 use embedded_hal_async::{digital::Wait, spi::SpiDevice};
 
 use crate::{
-    traits::{ErrorType, InternalWiAdditions},
+    traits::{ErrorType, InternalWiAdditions, QuickRefresh, UpdateMode},
     type_a::{
+        border_waveform,
         command::Command,
         constants::{LUT_FULL_UPDATE, LUT_PARTIAL_UPDATE},
+        data_entry_mode, display_update_control, gate_scan, ram_x, BORDER_VCOM,
     },
 };
 
 use crate::buffer_len;
 use crate::color::Color;
 use crate::error::ErrorKind;
-use crate::interface::DisplayInterface;
-use crate::traits::{RefreshLut, WaveshareDisplay};
+use crate::interface::{BusyLevel, DisplayDelay, DisplayInterface, ResetTiming};
+use crate::traits::{PartialRefreshes, RefreshLut, WaveshareDisplay};
+
+pub use crate::type_a::command::DeepSleepMode;
 
 /// Full size buffer for use with the 1in54b EPD
 #[cfg(feature = "graphics")]
@@ -82,16 +100,35 @@ pub type Display1in54 = crate::graphics::Display<
 >;
 
 /// Epd1in54 driver
-pub struct Epd1in54<SPI, BUSY, DC, RST> {
+pub struct Epd1in54<SPI, BUSY, DC, RST, DELAY> {
     /// SPI
-    interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
     /// Color
     background_color: Color,
-    /// Refresh LUT
-    refresh: RefreshLut,
+    /// Refresh LUT, `None` for the waveforms of the OTP
+    refresh: Option<RefreshLut>,
+    /// Register LUT loaded into the controller, `None` if unknown
+    lut: Option<RefreshLut>,
+    /// A full refresh was done since the last init
+    base_frame: bool,
+    /// Border color set with [Epd1in54::set_border_color]
+    border: Option<Color>,
+    /// Holds the border at VCOM during full refreshes
+    suppress_border_flash: bool,
+    /// Quick refreshes since the last full refresh, and when the next one is a full one
+    partial_refreshes: PartialRefreshes,
+    /// Update sequence set with [Epd1in54::set_update_mode]
+    update_mode: Option<UpdateMode>,
+    /// Mode of [WaveshareDisplay::sleep]
+    sleep_mode: DeepSleepMode,
+    /// Mirrors the columns, set with [Epd1in54::set_scan_mirroring]
+    x_flip: bool,
+    /// Mirrors the rows, set with [Epd1in54::set_scan_mirroring]
+    y_flip: bool,
 }
 
-impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd1in54<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST>
+    for Epd1in54<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -101,11 +138,13 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
 }
 
-impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd1in54<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Epd1in54<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -115,60 +154,21 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.interface.reset(spi, 10_000, 10_000).await?;
-
-        // 3 Databytes:
-        // A[7:0]
-        // 0.. A[8]
-        // 0.. B[2:0]
-        // Default Values: A = Height of Screen (0x127), B = 0x00 (GD, SM and TB=0?)
-        self.interface
-            .cmd_with_data(
-                spi,
-                Command::DriverOutputControl,
-                &[HEIGHT as u8, (HEIGHT >> 8) as u8, 0x00],
-            )
-            .await?;
-
-        // 3 Databytes: (and default values from datasheet and arduino)
-        // 1 .. A[6:0]  = 0xCF | 0xD7
-        // 1 .. B[6:0]  = 0xCE | 0xD6
-        // 1 .. C[6:0]  = 0x8D | 0x9D
-        //TODO: test
-        self.interface
-            .cmd_with_data(spi, Command::BoosterSoftStartControl, &[0xD7, 0xD6, 0x9D])
-            .await?;
-
-        // One Databyte with value 0xA8 for 7V VCOM
-        self.interface
-            .cmd_with_data(spi, Command::WriteVcomRegister, &[0xA8])
-            .await?;
-
-        // One Databyte with default value 0x1A for 4 dummy lines per gate
-        self.interface
-            .cmd_with_data(spi, Command::SetDummyLinePeriod, &[0x1A])
-            .await?;
-
-        // One Databyte with default value 0x08 for 2us per line
-        self.interface
-            .cmd_with_data(spi, Command::SetGateLineWidth, &[0x08])
-            .await?;
-
-        // One Databyte with default value 0x03
-        //  -> address: x increment, y increment, address counter is updated in x direction
-        self.interface
-            .cmd_with_data(spi, Command::DataEntryModeSetting, &[0x03])
-            .await?;
-
-        self.set_lut(spi, None).await?;
-
-        self.wait_until_idle(spi).await
+        self.interface.reset().await?;
+        if !self.interface.has_reset_pin() {
+            self.interface
+                .sw_reset(spi, Command::SwReset, BUSY_LEVEL)
+                .await?;
+        }
+        self.configure(spi).await
     }
 }
 
-impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd1in54<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd1in54<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -178,6 +178,7 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type DisplayColor = Color;
     fn width(&self) -> u32 {
@@ -188,19 +189,61 @@ where
         HEIGHT
     }
 
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
+
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
     async fn new(
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        delay: DELAY,
         delay_us: Option<u32>,
     ) -> Result<Self, Self::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        let interface =
+            DisplayInterface::new(Some(busy), dc, rst, delay, RESET_TIMING, delay_us, None);
 
         let mut epd = Epd1in54 {
             interface,
             background_color: DEFAULT_BACKGROUND_COLOR,
-            refresh: RefreshLut::Full,
+            refresh: Some(RefreshLut::Full),
+            lut: None,
+            base_frame: false,
+            border: None,
+            suppress_border_flash: false,
+            partial_refreshes: PartialRefreshes::default(),
+            update_mode: None,
+            sleep_mode: DeepSleepMode::Normal,
+            x_flip: false,
+            y_flip: false,
         };
 
         epd.init(spi).await?;
@@ -208,28 +251,48 @@ where
         Ok(epd)
     }
 
+    /// Resets and initializes the controller again
+    ///
+    /// The RAM survives both [DeepSleepMode]s, so the base of the quick refreshes is
+    /// kept. In [DeepSleepMode::Normal] the controller is still awake, so a software
+    /// reset replaces the slower hardware reset.
     async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.init(spi).await
+        let (base_frame, partial_refreshes) = (self.base_frame, self.partial_refreshes);
+        if matches!(self.sleep_mode, DeepSleepMode::Normal) {
+            self.wait_until_idle(spi).await?;
+            self.interface
+                .sw_reset(spi, Command::SwReset, BUSY_LEVEL)
+                .await?;
+            self.configure(spi).await?;
+        } else {
+            self.interface.require_reset_pin()?;
+            self.init(spi).await?;
+        }
+        self.base_frame = base_frame;
+        self.partial_refreshes = partial_refreshes;
+        Ok(())
     }
 
     async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
-        // 0x00 for Normal mode (Power on Reset), 0x01 for Deep Sleep Mode
-        //TODO: is 0x00 needed here or would 0x01 be even more efficient?
         self.interface
-            .cmd_with_data(spi, Command::DeepSleepMode, &[0x00])
-            .await
+            .cmd_with_data(spi, Command::DeepSleepMode, &[self.sleep_mode as u8])
+            .await?;
+        if !matches!(self.sleep_mode, DeepSleepMode::Normal) {
+            self.interface.mark_asleep();
+        }
+        Ok(())
     }
 
     async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
         self.use_full_frame(spi).await?;
-        self.interface
-            .cmd_with_data(spi, Command::WriteRam, buffer)
-            .await
+        self.send_ram(spi, Command::WriteRam, buffer).await
     }
 
-    //TODO: update description: last 3 bits will be ignored for width and x_pos
+    /// Writes `buffer` into the RAM window starting at (x, y)
+    ///
+    /// x and width need to be multiples of 8.
     async fn update_partial_frame(
         &mut self,
         spi: &mut SPI,
@@ -239,27 +302,35 @@ where
         width: u32,
         height: u32,
     ) -> Result<(), Self::Error> {
+        assert!(buffer.len() == buffer_len(width as usize, height as usize));
         self.wait_until_idle(spi).await?;
-        self.set_ram_area(spi, x, y, x + width, y + height).await?;
-        self.set_ram_counter(spi, x, y).await?;
-
-        self.interface
-            .cmd_with_data(spi, Command::WriteRam, buffer)
-            .await
+        self.set_partial_window(spi, x, y, width, height).await?;
+        self.send_ram(spi, Command::WriteRam, buffer).await?;
+        self.use_full_frame(spi).await
     }
 
+    /// Refreshes the display with the LUT selected with [WaveshareDisplay::set_lut]
+    ///
+    /// A refresh with `RefreshLut::Full` is the base for the partial refreshes of
+    /// [QuickRefresh].
     async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
-        // enable clock signal, enable cp, display pattern -> 0xC4 (tested with the arduino version)
-        //TODO: test control_1 or control_2 with default value 0xFF (from the datasheet)
-        self.interface
-            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xC4])
-            .await?;
-
-        self.interface.cmd(spi, Command::MasterActivation).await?;
-        // MASTER Activation should not be interupted to avoid currption of panel images
-        // therefore a terminate command is send
-        self.interface.cmd(spi, Command::Nop).await
+        self.load_lut(spi, self.refresh).await?;
+        let hold_border = self.suppress_border_flash && self.refresh != Some(RefreshLut::Quick);
+        if hold_border {
+            self.interface
+                .cmd_with_data(spi, Command::BorderWaveformControl, &[BORDER_VCOM])
+                .await?;
+        }
+        self.turn_on_display(spi, self.update_mode).await?;
+        if hold_border {
+            self.restore_border(spi).await?;
+        }
+        if self.refresh != Some(RefreshLut::Quick) {
+            self.base_frame = true;
+            self.partial_refreshes.full_done();
+        }
+        Ok(())
     }
 
     async fn update_and_display_frame(
@@ -292,26 +363,27 @@ where
         &self.background_color
     }
 
+    /// Selects the register LUT used by [WaveshareDisplay::display_frame]
+    ///
+    /// Unlike the other drivers `None` doesn't load the old LUT once more, it switches
+    /// to the waveforms stored in the OTP of the panel. The selection is kept across
+    /// [WaveshareDisplay::wake_up].
     async fn set_lut(
         &mut self,
         spi: &mut SPI,
         refresh_rate: Option<RefreshLut>,
     ) -> Result<(), Self::Error> {
-        if let Some(refresh_lut) = refresh_rate {
-            self.refresh = refresh_lut;
-        }
-        match self.refresh {
-            RefreshLut::Full => self.set_lut_helper(spi, &LUT_FULL_UPDATE).await,
-            RefreshLut::Quick => self.set_lut_helper(spi, &LUT_PARTIAL_UPDATE).await,
-        }
+        self.refresh = refresh_rate;
+        self.lut = None;
+        self.load_lut(spi, refresh_rate).await
     }
 
-    async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
+    async fn wait_until_idle(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.wait_until_idle(BUSY_LEVEL).await
     }
 }
 
-impl<SPI, BUSY, DC, RST> Epd1in54<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> Epd1in54<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -321,7 +393,286 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
+    /// Replaces every `refreshes`th quick refresh with a full one, `None` disables it
+    pub fn set_full_refresh_after(&mut self, refreshes: Option<NonZeroU32>) {
+        self.partial_refreshes.set_full_after(refreshes);
+    }
+
+    /// Quick refreshes since the last full refresh
+    pub fn partial_refreshes(&self) -> u32 {
+        self.partial_refreshes.count()
+    }
+
+    /// Holds the border at VCOM during full refreshes, so it doesn't flash
+    ///
+    /// [WaveshareDisplay::display_frame] then waits for the refresh to finish and sends
+    /// the border color of [Epd1in54::set_border_color] again, or the one of the
+    /// background color if none is set. It is off by default.
+    pub fn set_suppress_border_flash(&mut self, suppress: bool) {
+        self.suppress_border_flash = suppress;
+    }
+
+    /// Selects the mode of [WaveshareDisplay::sleep]
+    ///
+    /// `DeepSleepMode::Normal`, the default, keeps the controller awake, while
+    /// `DeepSleepMode::Mode1` draws less current until the next
+    /// [WaveshareDisplay::wake_up]. Both keep the RAM.
+    pub fn set_deep_sleep_mode(&mut self, mode: DeepSleepMode) {
+        self.sleep_mode = mode;
+    }
+
+    /// Selects the update sequence of [WaveshareDisplay::display_frame]
+    ///
+    /// With `None`, the default, it follows the LUT selected with [WaveshareDisplay::set_lut].
+    pub fn set_update_mode(&mut self, mode: Option<UpdateMode>) {
+        self.update_mode = mode;
+    }
+
+    /// Mirrors the image on the panel, e.g. for a panel mounted upside down
+    ///
+    /// `x_flip` mirrors the columns and `y_flip` the rows, both together rotate by 180°.
+    /// The controller does it while the frame is written, so it costs nothing on the
+    /// MCU and still works with the rotation of the graphics. It takes effect with the
+    /// next [WaveshareDisplay::update_frame] and is kept across
+    /// [WaveshareDisplay::wake_up]. The base of the quick refreshes isn't mirrored, so
+    /// do a full refresh before the next [QuickRefresh::display_new_frame].
+    pub async fn set_scan_mirroring(
+        &mut self,
+        spi: &mut SPI,
+        x_flip: bool,
+        y_flip: bool,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.x_flip = x_flip;
+        self.y_flip = y_flip;
+        self.wait_until_idle(spi).await?;
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::DriverOutputControl,
+                &[HEIGHT as u8, (HEIGHT >> 8) as u8, gate_scan(self.y_flip)],
+            )
+            .await?;
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::DataEntryModeSetting,
+                &[data_entry_mode(self.x_flip)],
+            )
+            .await
+    }
+
+    /// Sets the color of the border around the active area
+    ///
+    /// It takes effect with the next refresh and is kept across
+    /// [WaveshareDisplay::wake_up].
+    pub async fn set_border_color(
+        &mut self,
+        spi: &mut SPI,
+        color: Color,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.border = Some(color);
+        self.wait_until_idle(spi).await?;
+        self.send_border_waveform(spi).await
+    }
+
+    /// Sends the border again after a refresh with the border held at VCOM
+    async fn restore_border(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.wait_until_idle(spi).await?;
+        let color = self.border.unwrap_or(self.background_color);
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::BorderWaveformControl,
+                &[border_waveform(color)],
+            )
+            .await
+    }
+
+    /// Sets up the registers after a reset
+    async fn configure(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        // 3 Databytes:
+        // A[7:0]
+        // 0.. A[8]
+        // 0.. B[2:0]
+        // Default Values: A = Height of Screen (0x127), B = 0x00 (GD, SM and TB=0?)
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::DriverOutputControl,
+                &[HEIGHT as u8, (HEIGHT >> 8) as u8, gate_scan(self.y_flip)],
+            )
+            .await?;
+
+        // 3 Databytes: (and default values from datasheet and arduino)
+        // 1 .. A[6:0]  = 0xCF | 0xD7
+        // 1 .. B[6:0]  = 0xCE | 0xD6
+        // 1 .. C[6:0]  = 0x8D | 0x9D
+        //TODO: test
+        self.interface
+            .cmd_with_data(spi, Command::BoosterSoftStartControl, &[0xD7, 0xD6, 0x9D])
+            .await?;
+
+        // One Databyte with value 0xA8 for 7V VCOM
+        self.interface
+            .cmd_with_data(spi, Command::WriteVcomRegister, &[0xA8])
+            .await?;
+
+        // One Databyte with default value 0x1A for 4 dummy lines per gate
+        self.interface
+            .cmd_with_data(spi, Command::SetDummyLinePeriod, &[0x1A])
+            .await?;
+
+        // One Databyte with default value 0x08 for 2us per line
+        self.interface
+            .cmd_with_data(spi, Command::SetGateLineWidth, &[0x08])
+            .await?;
+
+        // One Databyte with default value 0x03
+        //  -> address: x increment, y increment, address counter is updated in x direction
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::DataEntryModeSetting,
+                &[data_entry_mode(self.x_flip)],
+            )
+            .await?;
+
+        self.send_border_waveform(spi).await?;
+
+        self.set_lut(spi, self.refresh).await?;
+        self.base_frame = false;
+        self.partial_refreshes.full_done();
+
+        self.wait_until_idle(spi).await
+    }
+
+    /// Sends the border of [Epd1in54::set_border_color], the reset value is kept without one
+    async fn send_border_waveform(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if let Some(color) = self.border {
+            self.interface
+                .cmd_with_data(
+                    spi,
+                    Command::BorderWaveformControl,
+                    &[border_waveform(color)],
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn turn_on_display(
+        &mut self,
+        spi: &mut SPI,
+        mode: Option<UpdateMode>,
+    ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        // enable clock signal, enable cp, display pattern -> 0xC4 (tested with the arduino version)
+        // the OTP waveforms additionally need load temperature and load LUT -> 0xF4
+        //TODO: test control_1 or control_2 with default value 0xFF (from the datasheet)
+        let control = match mode {
+            Some(mode) => display_update_control(mode),
+            None if self.lut.is_some() => 0xC4,
+            None => 0xF4,
+        };
+        if mode == Some(UpdateMode::FullWithLoadLut) {
+            self.lut = None;
+        }
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[control])
+            .await?;
+
+        self.interface.cmd(spi, Command::MasterActivation).await?;
+        // MASTER Activation should not be interupted to avoid currption of panel images
+        // therefore a terminate command is send
+        self.interface.cmd(spi, Command::Nop).await
+    }
+
+    /// Uploads the LUT of `refresh` unless it is loaded already
+    ///
+    /// With `None` the next refresh loads the OTP waveforms, which overwrite the register LUT.
+    async fn load_lut(
+        &mut self,
+        spi: &mut SPI,
+        refresh: Option<RefreshLut>,
+    ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        if self.lut != refresh {
+            match refresh {
+                Some(RefreshLut::Full) => self.set_lut_helper(spi, &LUT_FULL_UPDATE).await?,
+                Some(RefreshLut::Quick) => self.set_lut_helper(spi, &LUT_PARTIAL_UPDATE).await?,
+                None => {}
+            }
+            self.lut = refresh;
+        }
+        Ok(())
+    }
+
+    async fn set_partial_window(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        assert!(x % 8 == 0 && width % 8 == 0);
+        assert!(width > 0 && height > 0);
+        assert!(x + width <= WIDTH && y + height <= HEIGHT);
+
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)
+            .await?;
+        self.set_ram_counter(spi, x, y).await
+    }
+
+    /// Writes `buffer` into a RAM bank, `None` writes the full frame
+    async fn write_ram(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        buffer: &[u8],
+        window: Option<(u32, u32, u32, u32)>,
+    ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        self.wait_until_idle(spi).await?;
+        match window {
+            Some((x, y, width, height)) => {
+                assert!(buffer.len() == buffer_len(width as usize, height as usize));
+                self.set_partial_window(spi, x, y, width, height).await?;
+            }
+            None => {
+                assert!(buffer.len() == buffer_len(WIDTH as usize, HEIGHT as usize));
+                self.use_full_frame(spi).await?;
+            }
+        }
+        self.send_ram(spi, command, buffer).await?;
+        self.use_full_frame(spi).await
+    }
+
+    /// Sends `buffer` to a RAM bank, with reversed bits if the columns are mirrored
+    async fn send_ram(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        buffer: &[u8],
+    ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        if !self.x_flip {
+            return self.interface.cmd_with_data(spi, command, buffer).await;
+        }
+        self.interface.cmd(spi, command).await?;
+        for byte in buffer {
+            self.interface.data(spi, &[byte.reverse_bits()]).await?;
+        }
+        Ok(())
+    }
+
     pub(crate) async fn use_full_frame(
         &mut self,
         spi: &mut SPI,
@@ -343,7 +694,7 @@ where
     ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
         self.wait_until_idle(spi).await?;
         assert!(start_x < end_x);
-        assert!(start_y < end_y);
+        assert!(start_y <= end_y);
 
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
@@ -351,7 +702,10 @@ where
             .cmd_with_data(
                 spi,
                 Command::SetRamXAddressStartEndPosition,
-                &[(start_x >> 3) as u8, (end_x >> 3) as u8],
+                &[
+                    ram_x(start_x, WIDTH, self.x_flip),
+                    ram_x(end_x, WIDTH, self.x_flip),
+                ],
             )
             .await?;
 
@@ -380,7 +734,11 @@ where
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
         self.interface
-            .cmd_with_data(spi, Command::SetRamXAddressCounter, &[(x >> 3) as u8])
+            .cmd_with_data(
+                spi,
+                Command::SetRamXAddressCounter,
+                &[ram_x(x, WIDTH, self.x_flip)],
+            )
             .await?;
 
         // 2 Databytes: A[7:0] & 0..A[8]
@@ -407,9 +765,125 @@ where
     }
 }
 
+impl<SPI, BUSY, DC, RST, DELAY> QuickRefresh<SPI, BUSY, DC, RST, DELAY>
+    for Epd1in54<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    /// Writes the frame currently shown into the second RAM bank
+    ///
+    /// The controller compares it with the new frame to find the pixels to drive.
+    async fn update_old_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        self.write_ram(spi, Command::WriteRam2, buffer, None).await
+    }
+
+    /// Writes the new frame, to be shown with [QuickRefresh::display_new_frame]
+    async fn update_new_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        self.write_ram(spi, Command::WriteRam, buffer, None).await
+    }
+
+    /// Partial refresh with the partial waveform
+    ///
+    /// Returns [ErrorKind::NoBaseFrame] if no full refresh was done since the last init.
+    /// The LUT selected with [WaveshareDisplay::set_lut] is loaded again by the next
+    /// [WaveshareDisplay::display_frame].
+    ///
+    /// Once the limit set with [Epd1in54::set_full_refresh_after] is reached, this is a
+    /// full refresh with the full waveform instead.
+    async fn display_new_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        if !self.base_frame {
+            return Err(ErrorKind::NoBaseFrame);
+        }
+        self.wait_until_idle(spi).await?;
+        if self.partial_refreshes.full_due() {
+            // the OTP waveforms stay in use if they are selected
+            let full = self.refresh.map(|_| RefreshLut::Full);
+            self.load_lut(spi, full).await?;
+            self.turn_on_display(spi, None).await?;
+            self.partial_refreshes.full_done();
+            return Ok(());
+        }
+        self.load_lut(spi, Some(RefreshLut::Quick)).await?;
+        self.turn_on_display(spi, None).await?;
+        self.partial_refreshes.partial_done();
+        Ok(())
+    }
+
+    async fn update_and_display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_new_frame(spi, buffer).await?;
+        self.display_new_frame(spi).await
+    }
+
+    /// Writes a part of the frame currently shown into the second RAM bank
+    ///
+    /// x and width need to be multiples of 8.
+    async fn update_partial_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        self.write_ram(spi, Command::WriteRam2, buffer, Some((x, y, width, height)))
+            .await
+    }
+
+    /// Writes a part of the new frame, to be shown with [QuickRefresh::display_new_frame]
+    ///
+    /// x and width need to be multiples of 8.
+    async fn update_partial_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        self.write_ram(spi, Command::WriteRam, buffer, Some((x, y, width, height)))
+            .await
+    }
+
+    /// Fills a window of both RAM banks with the background color
+    async fn clear_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        let color = self.background_color.get_byte_value();
+        let bytes = buffer_len(width as usize, height as usize) as u32;
+
+        for command in [Command::WriteRam, Command::WriteRam2] {
+            self.set_partial_window(spi, x, y, width, height).await?;
+            self.interface.cmd(spi, command).await?;
+            self.interface.data_x_times(spi, color, bytes).await?;
+        }
+        self.use_full_frame(spi).await
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::{block_on, mocks};
 
     #[test]
     fn epd_size() {
@@ -417,4 +891,237 @@ mod tests {
         assert_eq!(HEIGHT, 200);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn partial_refresh_needs_full_refresh() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd1in54::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        let frame = [0xFF; WIDTH as usize / 8 * HEIGHT as usize];
+        block_on(epd.update_old_frame(&mut spi, &frame)).unwrap();
+        let result = block_on(epd.update_and_display_new_frame(&mut spi, &frame));
+        assert!(matches!(result, Err(ErrorKind::NoBaseFrame)));
+
+        block_on(epd.update_and_display_frame(&mut spi, &frame)).unwrap();
+        log.clear();
+        block_on(epd.update_and_display_new_frame(&mut spi, &frame)).unwrap();
+        assert_eq!(log.data_of(0x32, 0), LUT_PARTIAL_UPDATE);
+        assert_eq!(log.data_of(0x22, 0), [0xC4]);
+
+        // the full LUT is loaded again for the next full refresh
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x32, 0), LUT_FULL_UPDATE);
+
+        // the RAM survives the sleep, so the base frame is kept
+        epd.set_deep_sleep_mode(DeepSleepMode::Mode1);
+        block_on(epd.sleep(&mut spi)).unwrap();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        block_on(epd.display_new_frame(&mut spi)).unwrap();
+    }
+
+    #[test]
+    fn partial_icon() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd1in54::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        let icon = [0x00; 6 * 48];
+        log.clear();
+        block_on(epd.update_partial_old_frame(&mut spi, &icon, 24, 100, 48, 48)).unwrap();
+        assert_eq!(log.data_of(0x44, 0), [3, 8]);
+        assert_eq!(log.data_of(0x45, 0), [100, 0, 147, 0]);
+        assert_eq!(log.data_of(0x4E, 0), [3]);
+        assert_eq!(log.data_of(0x26, 0), icon);
+        // the window is reset to the full frame afterwards
+        assert_eq!(log.data_of(0x44, 1), [0, 24]);
+
+        log.clear();
+        block_on(epd.update_partial_new_frame(&mut spi, &icon, 24, 100, 48, 48)).unwrap();
+        assert_eq!(log.data_of(0x24, 0), icon);
+        assert!(!log.commands().contains(&0x26));
+    }
+
+    #[test]
+    fn deep_sleep_mode() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd1in54::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        block_on(epd.sleep(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x10, 0), [0x00]);
+
+        // awake, a software reset without the delays of the hardware one
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.commands()[0], 0x12);
+        assert!(log.delays().is_empty());
+
+        epd.set_deep_sleep_mode(DeepSleepMode::Mode1);
+        log.clear();
+        block_on(epd.sleep(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x10, 0), [0x01]);
+
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert!(!log.delays().is_empty());
+    }
+
+    #[test]
+    fn scan_mirroring() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd1in54::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert_eq!(log.data_of(0x01, 0), [0xC8, 0x00, 0x00]);
+        assert_eq!(log.data_of(0x11, 0), [0x03]);
+
+        log.clear();
+        block_on(epd.set_scan_mirroring(&mut spi, true, true)).unwrap();
+        assert_eq!(log.data_of(0x01, 0), [0xC8, 0x00, 0x01]);
+        assert_eq!(log.data_of(0x11, 0), [0x02]);
+
+        // the columns are counted from the right and the bits are reversed
+        log.clear();
+        block_on(epd.update_partial_frame(&mut spi, &[0x01, 0x03], 8, 0, 8, 2)).unwrap();
+        assert_eq!(log.data_of(0x44, 0), [23, 23]);
+        assert_eq!(log.data_of(0x45, 0), [0, 0, 1, 0]);
+        assert_eq!(log.data_of(0x4E, 0), [23]);
+        assert_eq!(log.data_of(0x24, 0), [0x80, 0xC0]);
+
+        // kept across the reset of the wake up
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x01, 0), [0xC8, 0x00, 0x01]);
+        assert_eq!(log.data_of(0x11, 0), [0x02]);
+    }
+
+    #[test]
+    fn border_flash_suppressed() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd1in54::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert!(!log.commands().contains(&0x3C));
+
+        // the border is held at VCOM around the activation and restored afterwards
+        epd.set_suppress_border_flash(true);
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        let commands = log.commands();
+        let position = |command, n| {
+            commands
+                .iter()
+                .enumerate()
+                .filter(|&(_, &sent)| sent == command)
+                .nth(n)
+                .unwrap()
+                .0
+        };
+        assert!(position(0x3C, 0) < position(0x20, 0));
+        assert!(position(0x20, 0) < position(0x3C, 1));
+        assert_eq!(log.data_of(0x3C, 0), [0x80]);
+        assert_eq!(log.data_of(0x3C, 1), [0x03]);
+
+        // the quick refresh leaves the border alone
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Quick))).unwrap();
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert!(!log.commands().contains(&0x3C));
+    }
+
+    #[test]
+    fn border_color() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd1in54::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert!(!log.commands().contains(&0x3C));
+
+        log.clear();
+        block_on(epd.set_border_color(&mut spi, Color::Black)).unwrap();
+        assert_eq!(log.commands(), [0x3C]);
+        assert_eq!(log.data_of(0x3C, 0), [0x00]);
+
+        // kept across the reset of the wake up
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x3C, 0), [0x00]);
+
+        log.clear();
+        block_on(epd.set_border_color(&mut spi, Color::White)).unwrap();
+        assert_eq!(log.data_of(0x3C, 0), [0x03]);
+    }
+
+    #[test]
+    fn full_refresh_after_partials() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd1in54::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        epd.set_full_refresh_after(NonZeroU32::new(2));
+
+        log.clear();
+        block_on(epd.display_new_frame(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x32, 0), LUT_PARTIAL_UPDATE);
+        assert_eq!(epd.partial_refreshes(), 1);
+
+        log.clear();
+        block_on(epd.display_new_frame(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x32, 0), LUT_FULL_UPDATE);
+        assert_eq!(epd.partial_refreshes(), 0);
+    }
+
+    #[test]
+    fn update_mode() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd1in54::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        epd.set_update_mode(Some(UpdateMode::Full));
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x22, 0), [0xC8]);
+
+        // quick refreshes keep their own sequence
+        log.clear();
+        block_on(epd.display_new_frame(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x22, 0), [0xC4]);
+
+        // the LUT of the OTP replaces the uploaded one
+        epd.set_update_mode(Some(UpdateMode::FullWithLoadLut));
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x22, 0), [0xF8]);
+        log.clear();
+        epd.set_update_mode(None);
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x32, 0), LUT_FULL_UPDATE);
+        assert_eq!(log.data_of(0x22, 0), [0xC4]);
+    }
+
+    #[test]
+    fn otp_lut() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd1in54::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert_eq!(log.data_of(0x32, 0), LUT_FULL_UPDATE);
+
+        log.clear();
+        block_on(epd.set_lut(&mut spi, None)).unwrap();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert!(!log.commands().contains(&0x32));
+        assert_eq!(log.data_of(0x22, 0), [0xF4]);
+
+        // kept across the reset of the wake up
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert!(!log.commands().contains(&0x32));
+        assert_eq!(log.data_of(0x22, 0), [0xF4]);
+
+        // quick refreshes still use the register LUT, which has to be uploaded again
+        log.clear();
+        block_on(epd.display_new_frame(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x32, 0), LUT_PARTIAL_UPDATE);
+        assert_eq!(log.data_of(0x22, 0), [0xC4]);
+
+        log.clear();
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Full))).unwrap();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x32, 0), LUT_FULL_UPDATE);
+        assert_eq!(log.data_of(0x22, 0), [0xC4]);
+    }
 }
@@ -10,7 +10,8 @@ pub const WIDTH: u32 = 200;
 pub const HEIGHT: u32 = 200;
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
-const IS_BUSY_LOW: bool = false;
+const BUSY_LEVEL: BusyLevel = BusyLevel::High;
+const RESET_TIMING: ResetTiming = ResetTiming::new(10_000, 10_000);
 const SINGLE_BYTE_WRITE: bool = true;
 
 use core::fmt::{Debug, Display};
@@ -24,21 +25,20 @@ use crate::{
     color::Color,
     error::ErrorKind,
     traits::{ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay},
-    type_a::{
-        command::Command,
-        constants::{LUT_FULL_UPDATE, LUT_PARTIAL_UPDATE},
-    },
+    type_a::command::Command,
 };
 
-use crate::interface::DisplayInterface;
+use self::constants::{LUT_FULL_UPDATE, LUT_PARTIAL_UPDATE};
+
+use crate::interface::{BusyLevel, DisplayDelay, DisplayInterface, ResetTiming};
 
 #[cfg(feature = "graphics")]
 pub use crate::epd1in54::Display1in54;
 
 /// Epd1in54 driver
-pub struct Epd1in54<SPI, BUSY, DC, RST> {
+pub struct Epd1in54<SPI, BUSY, DC, RST, DELAY> {
     /// SPI
-    interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
     /// Color
     background_color: Color,
 
@@ -46,7 +46,8 @@ pub struct Epd1in54<SPI, BUSY, DC, RST> {
     refresh: RefreshLut,
 }
 
-impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd1in54<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST>
+    for Epd1in54<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -56,11 +57,13 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
 }
 
-impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd1in54<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Epd1in54<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -70,9 +73,10 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.interface.reset(spi, 10_000, 10_000).await?;
+        self.interface.reset().await?;
         self.wait_until_idle(spi).await?;
         self.interface.cmd(spi, Command::SwReset).await?;
         self.wait_until_idle(spi).await?;
@@ -129,7 +133,8 @@ where
     }
 }
 
-impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd1in54<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd1in54<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -139,6 +144,7 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type DisplayColor = Color;
     fn width(&self) -> u32 {
@@ -149,14 +155,47 @@ where
         HEIGHT
     }
 
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
+
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
     async fn new(
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        delay: DELAY,
         delay_us: Option<u32>,
     ) -> Result<Self, Self::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        let interface =
+            DisplayInterface::new(Some(busy), dc, rst, delay, RESET_TIMING, delay_us, None);
 
         let mut epd = Epd1in54 {
             interface,
@@ -170,6 +209,7 @@ where
     }
 
     async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.require_reset_pin()?;
         self.init(spi).await
     }
 
@@ -177,7 +217,9 @@ where
         self.wait_until_idle(spi).await?;
         self.interface
             .cmd_with_data(spi, Command::DeepSleepMode, &[0x01])
-            .await
+            .await?;
+        self.interface.mark_asleep();
+        Ok(())
     }
 
     async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
@@ -295,12 +337,12 @@ where
         Ok(())
     }
 
-    async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
+    async fn wait_until_idle(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.wait_until_idle(BUSY_LEVEL).await
     }
 }
 
-impl<SPI, BUSY, DC, RST> Epd1in54<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> Epd1in54<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -310,7 +352,36 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
+    /// Measures the temperature of the panel with the sensor of the controller, in °C
+    ///
+    /// The controller answers on the data line, so it has to be connected to the MISO
+    /// of the SPI bus as well. The loaded waveform is kept.
+    pub async fn read_temperature(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<i8, ErrorKind<SPI, BUSY, DC, RST>> {
+        self.wait_until_idle(spi).await?;
+        // internal sensor
+        self.interface
+            .cmd_with_data(spi, Command::TemperatureSensorSelection, &[0x80])
+            .await?;
+        // Enable clock signal, load temperature value, Disable OSC
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xA1])
+            .await?;
+        self.interface.cmd(spi, Command::MasterActivation).await?;
+        self.wait_until_idle(spi).await?;
+
+        // 12 bit two's complement in 1/16 °C, the first byte holds the whole degrees
+        let mut temperature = [0u8; 2];
+        self.interface
+            .cmd_then_read(spi, Command::TemperatureSensorRead, 0, &mut temperature)
+            .await?;
+        Ok(temperature[0] as i8)
+    }
+
     pub(crate) async fn use_full_frame(
         &mut self,
         spi: &mut SPI,
@@ -420,6 +491,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::{block_on, mocks};
 
     #[test]
     fn epd_size() {
@@ -427,4 +499,16 @@ mod tests {
         assert_eq!(HEIGHT, 200);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn read_temperature() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd1in54::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        log.queue_reads(&[0x17, 0x40]);
+        assert_eq!(block_on(epd.read_temperature(&mut spi)).unwrap(), 23);
+        assert_eq!(log.commands(), [0x18, 0x22, 0x20, 0x1B]);
+        assert_eq!(log.data_of(0x22, 0), [0xA1]);
+    }
 }
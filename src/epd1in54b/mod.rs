@@ -7,7 +7,7 @@ use embedded_hal::{
 use embedded_hal_async::{digital::Wait, spi::SpiDevice};
 
 use crate::error::ErrorKind;
-use crate::interface::DisplayInterface;
+use crate::interface::{BusyLevel, DisplayDelay, DisplayInterface, ResetTiming};
 use crate::traits::{
     ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
 };
@@ -22,33 +22,38 @@ pub const WIDTH: u32 = 200;
 pub const HEIGHT: u32 = 200;
 /// Default Background Color (white)
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
-const IS_BUSY_LOW: bool = true;
+const BUSY_LEVEL: BusyLevel = BusyLevel::Low;
+const RESET_TIMING: ResetTiming = ResetTiming::new(10_000, 10_000);
 const SINGLE_BYTE_WRITE: bool = true;
+/// Red plane byte of the controller without any red pixel
+const NO_RED: u8 = 0xFF;
 
 use crate::color::Color;
+#[cfg(feature = "graphics")]
+use crate::color::TriColor;
 
 pub(crate) mod command;
 use self::command::Command;
 use crate::buffer_len;
 
 /// Full size buffer for use with the 1in54b EPD
-/// TODO this should be a TriColor, but let's keep it as is at first
 #[cfg(feature = "graphics")]
 pub type Display1in54b = crate::graphics::Display<
     WIDTH,
     HEIGHT,
-    false,
-    { buffer_len(WIDTH as usize, HEIGHT as usize) },
-    Color,
+    true,
+    { buffer_len(WIDTH as usize, HEIGHT as usize * 2) },
+    TriColor,
 >;
 
 /// Epd1in54b driver
-pub struct Epd1in54b<SPI, BUSY, DC, RST> {
-    interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
+pub struct Epd1in54b<SPI, BUSY, DC, RST, DELAY> {
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
     color: Color,
 }
 
-impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd1in54b<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST>
+    for Epd1in54b<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -58,11 +63,13 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
 }
 
-impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd1in54b<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Epd1in54b<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -72,9 +79,10 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.interface.reset(spi, 10_000, 10_000).await?;
+        self.interface.reset().await?;
 
         // set the power settings
         self.interface
@@ -88,7 +96,7 @@ where
 
         // power on
         self.command(spi, Command::PowerOn).await?;
-        self.interface.delay(spi, 5000).await?;
+        self.interface.delay(5000).await;
         self.wait_until_idle(spi).await?;
 
         // set the panel settings
@@ -116,8 +124,8 @@ where
     }
 }
 
-impl<SPI, BUSY, DC, RST> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST>
-    for Epd1in54b<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd1in54b<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -127,6 +135,7 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn update_color_frame(
         &mut self,
@@ -165,12 +174,43 @@ where
         self.interface
             .cmd(spi, Command::DataStartTransmission2)
             .await?;
-        self.interface.data(spi, chromatic).await?;
+        // The controller shows cleared bits as red
+        for byte in chromatic {
+            self.interface.data(spi, &[!byte]).await?;
+        }
         Ok(())
     }
+
+    async fn clear_achromatic_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.send_resolution(spi).await?;
+
+        let color = DEFAULT_BACKGROUND_COLOR.get_byte_value();
+        self.interface
+            .cmd(spi, Command::DataStartTransmission1)
+            .await?;
+        // Uses 2 bits per pixel
+        self.interface
+            .data_x_times(spi, color, 2 * (WIDTH / 8 * HEIGHT))
+            .await
+    }
+
+    async fn clear_chromatic_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.send_resolution(spi).await?;
+
+        // Cleared bits are red
+        self.interface
+            .cmd(spi, Command::DataStartTransmission2)
+            .await?;
+        self.interface
+            .data_x_times(spi, NO_RED, WIDTH / 8 * HEIGHT)
+            .await
+    }
 }
 
-impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd1in54b<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd1in54b<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -180,16 +220,19 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type DisplayColor = Color;
     async fn new(
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        delay: DELAY,
         delay_us: Option<u32>,
     ) -> Result<Self, Self::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        let interface =
+            DisplayInterface::new(Some(busy), dc, rst, delay, RESET_TIMING, delay_us, None);
         let color = DEFAULT_BACKGROUND_COLOR;
 
         let mut epd = Epd1in54b { interface, color };
@@ -240,6 +283,37 @@ where
         HEIGHT
     }
 
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
+
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
     async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
         self.send_resolution(spi).await?;
@@ -256,14 +330,13 @@ where
 
         //NOTE: Example code has a delay here
 
-        // Clear the read layer
-        let color = self.color.get_byte_value();
+        // Clear the red layer, cleared bits are red
         let nbits = WIDTH * (HEIGHT / 8);
 
         self.interface
             .cmd(spi, Command::DataStartTransmission2)
             .await?;
-        self.interface.data_x_times(spi, color, nbits).await
+        self.interface.data_x_times(spi, NO_RED, nbits).await
 
         //NOTE: Example code has a delay here
     }
@@ -296,26 +369,8 @@ where
     }
 
     async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.wait_until_idle(spi).await?;
-        self.send_resolution(spi).await?;
-
-        let color = DEFAULT_BACKGROUND_COLOR.get_byte_value();
-
-        // Clear the black
-        self.interface
-            .cmd(spi, Command::DataStartTransmission1)
-            .await?;
-
-        // Uses 2 bits per pixel
-        self.interface
-            .data_x_times(spi, color, 2 * (WIDTH / 8 * HEIGHT))
-            .await?;
-
-        // Clear the red
-        self.interface
-            .data_x_times(spi, color, WIDTH / 8 * HEIGHT)
-            .await?;
-        Ok(())
+        self.clear_achromatic_frame(spi).await?;
+        self.clear_chromatic_frame(spi).await
     }
 
     async fn set_lut(
@@ -349,12 +404,12 @@ where
             .await
     }
 
-    async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
+    async fn wait_until_idle(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.wait_until_idle(BUSY_LEVEL).await
     }
 }
 
-impl<SPI, BUSY, DC, RST> Epd1in54b<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> Epd1in54b<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -364,6 +419,7 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn command(
         &mut self,
@@ -0,0 +1,555 @@
+//! A simple Driver for the Waveshare 1.54" (B) V2 E-Ink Display via SPI
+//!
+//! The V2 revision uses a SSD1681 controller with waveforms from the OTP, so no LUT
+//! is uploaded. The black/white plane goes into RAM 0x24 and the red plane into
+//! RAM 0x26, both planes can be written on their own. Text-only updates can use
+//! [WaveshareThreeColorDisplay::update_achromatic_frame] without sending the red
+//! plane again.
+//!
+//! Like the vendor driver, the RAM is written with y decrementing: the first row of a
+//! buffer goes to the highest RAM row.
+//!
+//! After [WaveshareDisplay::sleep] the controller needs a hardware reset, which
+//! [WaveshareDisplay::wake_up] does before running the init again.
+//!
+//! The red RAM uses 0x00 for "no red", while the Waveshare examples draw red with
+//! cleared bits and invert the image while sending it. The chromatic buffer of a
+//! [Display1in54b] already has the bits of red pixels set and is sent unchanged,
+//! [WaveshareDisplay::clear_frame] takes care of the inverted meaning of the red RAM.
+//!
+//! # References
+//!
+//! - [Waveshare product page](https://www.waveshare.com/wiki/1.54inch_e-Paper_Module_(B)_Manual)
+//! - [Waveshare C driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_1in54b_V2.c)
+//! - [Waveshare Python driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd1in54b_V2.py)
+use core::fmt::{Debug, Display};
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+
+use crate::buffer_len;
+use crate::color::TriColor;
+use crate::error::ErrorKind;
+use crate::interface::{BusyLevel, DisplayDelay, DisplayInterface, ResetTiming};
+use crate::traits::{
+    ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+};
+
+use crate::type_a::command::Command;
+
+/// Full size buffer for use with the 1in54b V2 EPD
+///
+/// Holds the black/white plane followed by the chromatic plane.
+#[cfg(feature = "graphics")]
+pub type Display1in54b = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize, HEIGHT as usize * 2) },
+    TriColor,
+>;
+
+/// Width of the display
+pub const WIDTH: u32 = 200;
+/// Height of the display
+pub const HEIGHT: u32 = 200;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: TriColor = TriColor::White;
+const BUSY_LEVEL: BusyLevel = BusyLevel::High;
+const RESET_TIMING: ResetTiming = ResetTiming::new(10_000, 2_000);
+const SINGLE_BYTE_WRITE: bool = false;
+/// Size of one color plane in bytes
+const PLANE_SIZE: u32 = WIDTH / 8 * HEIGHT;
+
+/// Epd1in54b V2 driver
+///
+pub struct Epd1in54b<SPI, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    color: TriColor,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST>
+    for Epd1in54b<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    type Error = ErrorKind<SPI, BUSY, DC, RST>;
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Epd1in54b<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.reset().await?;
+        self.wait_until_idle(spi).await?;
+
+        self.command(spi, Command::SwReset).await?;
+        self.wait_until_idle(spi).await?;
+
+        // 200 gate lines
+        self.cmd_with_data(
+            spi,
+            Command::DriverOutputControl,
+            &[(HEIGHT - 1) as u8, ((HEIGHT - 1) >> 8) as u8, 0x01],
+        )
+        .await?;
+
+        // x increment, y decrement, address counter is updated in x direction
+        self.cmd_with_data(spi, Command::DataEntryModeSetting, &[0x01])
+            .await?;
+
+        // follow the red RAM with the border
+        self.cmd_with_data(spi, Command::BorderWaveformControl, &[0x05])
+            .await?;
+
+        // use the internal temperature sensor
+        self.cmd_with_data(spi, Command::TemperatureSensorSelection, &[0x80])
+            .await?;
+
+        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1).await?;
+        self.set_ram_counter(spi, 0, 0).await?;
+        self.wait_until_idle(spi).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd1in54b<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    async fn update_color_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+        chromatic: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_achromatic_frame(spi, black).await?;
+        self.update_chromatic_frame(spi, chromatic).await
+    }
+
+    /// Writes the black/white plane, a cleared bit is a black pixel
+    async fn update_achromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+    ) -> Result<(), Self::Error> {
+        assert!(black.len() as u32 == PLANE_SIZE);
+        self.wait_until_idle(spi).await?;
+        self.set_ram_counter(spi, 0, 0).await?;
+        self.cmd_with_data(spi, Command::WriteRam, black).await
+    }
+
+    /// Writes the chromatic plane, a set bit is a red pixel
+    ///
+    /// The black/white plane is left as it is.
+    async fn update_chromatic_frame(
+        &mut self,
+        spi: &mut SPI,
+        chromatic: &[u8],
+    ) -> Result<(), Self::Error> {
+        assert!(chromatic.len() as u32 == PLANE_SIZE);
+        self.wait_until_idle(spi).await?;
+        self.set_ram_counter(spi, 0, 0).await?;
+        self.cmd_with_data(spi, Command::WriteRam2, chromatic).await
+    }
+
+    async fn clear_achromatic_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        let (black, _) = clear_bytes(self.color);
+        self.wait_until_idle(spi).await?;
+
+        self.set_ram_counter(spi, 0, 0).await?;
+        self.command(spi, Command::WriteRam).await?;
+        self.interface.data_x_times(spi, black, PLANE_SIZE).await
+    }
+
+    async fn clear_chromatic_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        let (_, red) = clear_bytes(self.color);
+        self.wait_until_idle(spi).await?;
+
+        self.set_ram_counter(spi, 0, 0).await?;
+        self.command(spi, Command::WriteRam2).await?;
+        self.interface.data_x_times(spi, red, PLANE_SIZE).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd1in54b<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    type DisplayColor = TriColor;
+    async fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        delay: DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Self::Error> {
+        let interface =
+            DisplayInterface::new(Some(busy), dc, rst, delay, RESET_TIMING, delay_us, None);
+        let color = DEFAULT_BACKGROUND_COLOR;
+
+        let mut epd = Epd1in54b { interface, color };
+
+        epd.init(spi).await?;
+
+        Ok(epd)
+    }
+
+    async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        // Deep Sleep Mode 1, a hardware reset is needed to wake up
+        self.cmd_with_data(spi, Command::DeepSleepMode, &[0x01])
+            .await?;
+        self.interface.mark_asleep();
+        Ok(())
+    }
+
+    /// Resets the controller and runs the init again, there are no LUTs to upload
+    async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.require_reset_pin()?;
+        self.init(spi).await
+    }
+
+    fn set_background_color(&mut self, color: TriColor) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &TriColor {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
+
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    /// Writes both planes from the combined buffer of a [Display1in54b]
+    ///
+    /// The buffer holds the black/white plane followed by the chromatic plane.
+    async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        assert!(buffer.len() as u32 == 2 * PLANE_SIZE);
+        let (black, chromatic) = buffer.split_at(PLANE_SIZE as usize);
+        self.update_color_frame(spi, black, chromatic).await
+    }
+
+    /// Writes `buffer` into the black/white RAM window starting at (x, y)
+    ///
+    /// The red RAM is left untouched, x and width need to be multiples of 8.
+    async fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        assert!(x % 8 == 0 && width % 8 == 0);
+        assert!(x + width <= WIDTH && y + height <= HEIGHT);
+        assert!(buffer.len() == buffer_len(width as usize, height as usize));
+
+        self.wait_until_idle(spi).await?;
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)
+            .await?;
+        self.set_ram_counter(spi, x, y).await?;
+        self.cmd_with_data(spi, Command::WriteRam, buffer).await?;
+
+        // restore the full window for the next frame update
+        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1).await
+    }
+
+    async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        // Load temperature and waveform, DISPLAY with DISPLAY Mode 1
+        self.turn_on_display(spi, 0xF7).await
+    }
+
+    /// Writes and refreshes the combined buffer of a [Display1in54b]
+    async fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_frame(spi, buffer).await?;
+        self.display_frame(spi).await
+    }
+
+    async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.clear_achromatic_frame(spi).await?;
+        self.clear_chromatic_frame(spi).await
+    }
+
+    async fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        _refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    async fn wait_until_idle(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.wait_until_idle(BUSY_LEVEL).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd1in54b<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    async fn turn_on_display(
+        &mut self,
+        spi: &mut SPI,
+        update: u8,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[update])
+            .await?;
+        self.command(spi, Command::MasterActivation).await?;
+        self.wait_until_idle(spi).await
+    }
+
+    async fn command(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.cmd(spi, command).await
+    }
+
+    async fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    /// Sets both X and Y pixels ranges
+    ///
+    /// The SSD1681 addresses x in bytes, so x positions are divided by 8. y is counted
+    /// down in RAM, so the y range starts at the RAM address of `start_y`.
+    async fn set_ram_area(
+        &mut self,
+        spi: &mut SPI,
+        start_x: u32,
+        start_y: u32,
+        end_x: u32,
+        end_y: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let (start_y, end_y) = (ram_y(start_y), ram_y(end_y));
+        self.cmd_with_data(
+            spi,
+            Command::SetRamXAddressStartEndPosition,
+            &[(start_x >> 3) as u8, (end_x >> 3) as u8],
+        )
+        .await?;
+
+        self.cmd_with_data(
+            spi,
+            Command::SetRamYAddressStartEndPosition,
+            &[
+                start_y as u8,
+                (start_y >> 8) as u8,
+                end_y as u8,
+                (end_y >> 8) as u8,
+            ],
+        )
+        .await
+    }
+
+    /// Sets both X and Y pixels counters when writing data to RAM
+    async fn set_ram_counter(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let y = ram_y(y);
+        self.cmd_with_data(spi, Command::SetRamXAddressCounter, &[(x >> 3) as u8])
+            .await?;
+
+        self.cmd_with_data(
+            spi,
+            Command::SetRamYAddressCounter,
+            &[y as u8, (y >> 8) as u8],
+        )
+        .await
+    }
+}
+
+/// RAM address of the row `y`, the RAM is written with y decrementing
+fn ram_y(y: u32) -> u32 {
+    HEIGHT - 1 - y
+}
+
+/// Bytes to fill the black/white and the red RAM with to show `color` everywhere
+///
+/// The red RAM uses set bits for colored pixels, the opposite of the black/white RAM.
+fn clear_bytes(color: TriColor) -> (u8, u8) {
+    match color {
+        TriColor::White => (0xFF, 0x00),
+        TriColor::Black => (0x00, 0x00),
+        TriColor::Chromatic => (0xFF, 0xFF),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{block_on, mocks};
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 200);
+        assert_eq!(HEIGHT, 200);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, TriColor::White);
+        assert_eq!(
+            PLANE_SIZE as usize,
+            buffer_len(WIDTH as usize, HEIGHT as usize)
+        );
+    }
+
+    #[test]
+    fn clear_planes() {
+        // 0x00 is "no red" in the red RAM
+        assert_eq!(clear_bytes(DEFAULT_BACKGROUND_COLOR), (0xFF, 0x00));
+        assert_eq!(clear_bytes(TriColor::Black), (0x00, 0x00));
+        assert_eq!(clear_bytes(TriColor::Chromatic), (0xFF, 0xFF));
+    }
+
+    #[test]
+    fn planes_are_sent_unchanged() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd1in54b::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        let black = [0xF0; PLANE_SIZE as usize];
+        let red = [0x0F; PLANE_SIZE as usize];
+        block_on(epd.update_color_frame(&mut spi, &black, &red)).unwrap();
+        assert_eq!(log.data_of(0x24, 0), black);
+        assert_eq!(log.data_of(0x26, 0), red);
+
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(log.commands(), [0x22, 0x20]);
+        assert_eq!(log.data_of(0x22, 0), [0xF7]);
+    }
+
+    #[test]
+    fn init_counts_y_down() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let _epd = block_on(Epd1in54b::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert_eq!(log.data_of(0x01, 0), [0xC7, 0x00, 0x01]);
+        assert_eq!(log.data_of(0x11, 0), [0x01]);
+        assert_eq!(log.data_of(0x44, 0), [0x00, 0x18]);
+        assert_eq!(log.data_of(0x45, 0), [0xC7, 0x00, 0x00, 0x00]);
+        assert_eq!(log.data_of(0x4F, 0), [0xC7, 0x00]);
+    }
+
+    #[test]
+    fn achromatic_update_keeps_red_plane() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd1in54b::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        let black = [0x00; PLANE_SIZE as usize];
+        block_on(epd.update_achromatic_frame(&mut spi, &black)).unwrap();
+        assert_eq!(log.data_of(0x24, 0), black);
+        assert!(!log.commands().contains(&0x26));
+    }
+
+    #[test]
+    fn sleep_and_wake_up() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd1in54b::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        let init = log.writes();
+
+        log.clear();
+        block_on(epd.sleep(&mut spi)).unwrap();
+        assert_eq!(log.commands(), [0x10]);
+        assert_eq!(log.data_of(0x10, 0), [0x01]);
+
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.writes(), init);
+    }
+}
@@ -1,10 +1,18 @@
 //! A simple Driver for the Waveshare 1.54" (C) E-Ink Display via SPI
+//!
+//! The panel shows black, white and yellow. The crate's [TriColor] is used for it,
+//! `TriColor::Chromatic` is the yellow of this panel.
+//!
+//! `DataStartTransmission1` receives the black plane and `DataStartTransmission2` the
+//! yellow plane. The controller expects cleared bits for yellow pixels, while the
+//! chromatic half of a [Display1in54c] uses set bits for them, so the driver inverts
+//! the yellow plane while sending it.
 use core::fmt::{Debug, Display};
 use embedded_hal::digital::{InputPin, OutputPin};
 use embedded_hal_async::{digital::Wait, spi::SpiDevice};
 
 use crate::error::ErrorKind;
-use crate::interface::DisplayInterface;
+use crate::interface::{BusyLevel, DisplayDelay, DisplayInterface, ResetTiming};
 use crate::traits::{
     ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
 };
@@ -14,35 +22,39 @@ pub const WIDTH: u32 = 152;
 /// Height of epd1in54 in pixels
 pub const HEIGHT: u32 = 152;
 /// Default Background Color (white)
-pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
-const IS_BUSY_LOW: bool = true;
+pub const DEFAULT_BACKGROUND_COLOR: TriColor = TriColor::White;
+const BUSY_LEVEL: BusyLevel = BusyLevel::Low;
+const RESET_TIMING: ResetTiming = ResetTiming::new(10_000, 2_000);
 const NUM_DISPLAY_BITS: u32 = WIDTH / 8 * HEIGHT;
 const SINGLE_BYTE_WRITE: bool = true;
 
-use crate::color::Color;
+use crate::color::TriColor;
 
 pub(crate) mod command;
 use self::command::Command;
 use crate::buffer_len;
 
 /// Full size buffer for use with the 1in54c EPD
-/// TODO this should be a TriColor, but let's keep it as is at first
+///
+/// The black plane is followed by the chromatic plane, `TriColor::Chromatic` pixels
+/// are shown in yellow.
 #[cfg(feature = "graphics")]
 pub type Display1in54c = crate::graphics::Display<
     WIDTH,
     HEIGHT,
     false,
-    { buffer_len(WIDTH as usize, HEIGHT as usize) },
-    Color,
+    { buffer_len(WIDTH as usize, HEIGHT as usize * 2) },
+    TriColor,
 >;
 
 /// Epd1in54c driver
-pub struct Epd1in54c<SPI, BUSY, DC, RST> {
-    interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
-    color: Color,
+pub struct Epd1in54c<SPI, BUSY, DC, RST, DELAY> {
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    color: TriColor,
 }
 
-impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd1in54c<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST>
+    for Epd1in54c<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -52,11 +64,13 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
 }
 
-impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd1in54c<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Epd1in54c<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -66,13 +80,14 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         // Based on Reference Program Code from:
         // https://www.waveshare.com/w/upload/a/ac/1.54inch_e-Paper_Module_C_Specification.pdf
         // and:
         // https://github.com/waveshare/e-Paper/blob/master/STM32/STM32-F103ZET6/User/e-Paper/EPD_1in54c.c
-        self.interface.reset(spi, 10_000, 2_000).await?;
+        self.interface.reset().await?;
 
         // start the booster
         self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x17])
@@ -80,7 +95,7 @@ where
 
         // power on
         self.command(spi, Command::PowerOn).await?;
-        self.interface.delay(spi, 5000).await?;
+        self.interface.delay(5000).await;
         self.wait_until_idle(spi).await?;
 
         // set the panel settings
@@ -97,8 +112,8 @@ where
     }
 }
 
-impl<SPI, BUSY, DC, RST> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST>
-    for Epd1in54c<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd1in54c<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -108,6 +123,7 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn update_color_frame(
         &mut self,
@@ -119,28 +135,56 @@ where
         self.update_chromatic_frame(spi, chromatic).await
     }
 
+    /// Writes the black plane, a cleared bit is a black pixel
     async fn update_achromatic_frame(
         &mut self,
         spi: &mut SPI,
         black: &[u8],
     ) -> Result<(), Self::Error> {
+        assert!(black.len() == NUM_DISPLAY_BITS as usize);
         self.wait_until_idle(spi).await?;
         self.cmd_with_data(spi, Command::DataStartTransmission1, black)
             .await
     }
 
+    /// Writes the yellow plane, a set bit is a yellow pixel
     async fn update_chromatic_frame(
         &mut self,
         spi: &mut SPI,
         chromatic: &[u8],
     ) -> Result<(), Self::Error> {
+        assert!(chromatic.len() == NUM_DISPLAY_BITS as usize);
         self.wait_until_idle(spi).await?;
-        self.cmd_with_data(spi, Command::DataStartTransmission2, chromatic)
+        self.command(spi, Command::DataStartTransmission2).await?;
+        for byte in chromatic {
+            self.send_data(spi, &[!byte]).await?;
+        }
+        Ok(())
+    }
+
+    async fn clear_achromatic_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+
+        // Set bits are white
+        self.command(spi, Command::DataStartTransmission1).await?;
+        self.interface
+            .data_x_times(spi, 0xFF, NUM_DISPLAY_BITS)
+            .await
+    }
+
+    async fn clear_chromatic_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+
+        // Cleared bits are yellow
+        self.command(spi, Command::DataStartTransmission2).await?;
+        self.interface
+            .data_x_times(spi, 0xFF, NUM_DISPLAY_BITS)
             .await
     }
 }
 
-impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd1in54c<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd1in54c<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -150,16 +194,19 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
-    type DisplayColor = Color;
+    type DisplayColor = TriColor;
     async fn new(
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        delay: DELAY,
         delay_us: Option<u32>,
     ) -> Result<Self, Self::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        let interface =
+            DisplayInterface::new(Some(busy), dc, rst, delay, RESET_TIMING, delay_us, None);
         let color = DEFAULT_BACKGROUND_COLOR;
 
         let mut epd = Epd1in54c { interface, color };
@@ -174,18 +221,21 @@ where
 
         self.command(spi, Command::PowerOff).await?;
         self.wait_until_idle(spi).await?;
-        self.cmd_with_data(spi, Command::DeepSleep, &[0xa5]).await
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xa5]).await?;
+        self.interface.mark_asleep();
+        Ok(())
     }
 
     async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.require_reset_pin()?;
         self.init(spi).await
     }
 
-    fn set_background_color(&mut self, color: Color) {
+    fn set_background_color(&mut self, color: TriColor) {
         self.color = color;
     }
 
-    fn background_color(&self) -> &Color {
+    fn background_color(&self) -> &TriColor {
         &self.color
     }
 
@@ -197,16 +247,44 @@ where
         HEIGHT
     }
 
-    async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
-        self.update_achromatic_frame(spi, buffer).await?;
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
 
-        // Clear the chromatic layer
-        let color = self.color.get_byte_value();
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
 
-        self.command(spi, Command::DataStartTransmission2).await?;
-        self.interface
-            .data_x_times(spi, color, NUM_DISPLAY_BITS)
-            .await
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    /// Writes both planes from the combined buffer of a [Display1in54c]
+    ///
+    /// The buffer holds the black plane followed by the yellow plane.
+    async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        assert!(buffer.len() == 2 * NUM_DISPLAY_BITS as usize);
+        let (black, chromatic) = buffer.split_at(NUM_DISPLAY_BITS as usize);
+        self.update_color_frame(spi, black, chromatic).await
     }
 
     #[allow(unused)]
@@ -236,21 +314,10 @@ where
         self.display_frame(spi).await
     }
 
+    /// Makes the whole panel white, regardless of the background color
     async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.wait_until_idle(spi).await?;
-        let color = DEFAULT_BACKGROUND_COLOR.get_byte_value();
-
-        // Clear the black
-        self.command(spi, Command::DataStartTransmission1).await?;
-        self.interface
-            .data_x_times(spi, color, NUM_DISPLAY_BITS)
-            .await?;
-
-        // Clear the chromatic
-        self.command(spi, Command::DataStartTransmission2).await?;
-        self.interface
-            .data_x_times(spi, color, NUM_DISPLAY_BITS)
-            .await
+        self.clear_achromatic_frame(spi).await?;
+        self.clear_chromatic_frame(spi).await
     }
 
     async fn set_lut(
@@ -261,12 +328,12 @@ where
         Ok(())
     }
 
-    async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
+    async fn wait_until_idle(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.wait_until_idle(BUSY_LEVEL).await
     }
 }
 
-impl<SPI, BUSY, DC, RST> Epd1in54c<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> Epd1in54c<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -276,6 +343,7 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn command(
         &mut self,
@@ -316,7 +384,7 @@ where
         self.send_data(spi, &[(w as u8) & 0b1111_1000]).await?;
         // | D7 | D6 | D5 | D4 | D3 | D2 | D1 |      D0 |
         // |  - |  - |  - |  - |  - |  - |  - | VRES[8] |
-        self.send_data(spi, &[(w >> 8) as u8]).await?;
+        self.send_data(spi, &[(h >> 8) as u8]).await?;
         // | D7 | D6 | D5 | D4 | D3 | D2 | D1 |      D0 |
         // |                  VRES[7:0]                 |
         // Specification shows C/D is zero while sending the last byte,
@@ -325,3 +393,64 @@ where
         self.send_data(spi, &[h as u8]).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{block_on, mocks};
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 152);
+        assert_eq!(HEIGHT, 152);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, TriColor::White);
+        assert_eq!(NUM_DISPLAY_BITS, 19 * 152);
+    }
+
+    #[test]
+    fn yellow_plane_is_inverted() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd1in54c::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        let mut buffer = [0x00; 2 * NUM_DISPLAY_BITS as usize];
+        // the first pixel of the chromatic plane is yellow
+        buffer[NUM_DISPLAY_BITS as usize] = 0x80;
+        block_on(epd.update_frame(&mut spi, &buffer)).unwrap();
+        assert_eq!(log.commands(), [0x10, 0x13]);
+        assert!(log.data_of(0x10, 0).iter().all(|&byte| byte == 0x00));
+        let yellow = log.data_of(0x13, 0);
+        assert_eq!(yellow.len(), NUM_DISPLAY_BITS as usize);
+        assert_eq!(yellow[0], 0x7F);
+        assert!(yellow[1..].iter().all(|&byte| byte == 0xFF));
+    }
+
+    #[test]
+    fn clear_frame_is_white() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd1in54c::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        epd.set_background_color(TriColor::Chromatic);
+
+        log.clear();
+        block_on(epd.clear_frame(&mut spi)).unwrap();
+        assert!(log.data_of(0x10, 0).iter().all(|&byte| byte == 0xFF));
+        assert!(log.data_of(0x13, 0).iter().all(|&byte| byte == 0xFF));
+        assert_eq!(log.data_of(0x13, 0).len(), NUM_DISPLAY_BITS as usize);
+    }
+
+    #[test]
+    fn asleep_until_wake_up() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd1in54c::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        // the busy pin doesn't change in deep sleep
+        block_on(epd.sleep(&mut spi)).unwrap();
+        let result = block_on(epd.display_frame(&mut spi));
+        assert!(matches!(result, Err(ErrorKind::Asleep)));
+
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(log.commands(), [0x12]);
+    }
+}
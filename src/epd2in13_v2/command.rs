@@ -21,6 +21,7 @@ pub(crate) enum Command {
     SwReset = 0x12,
     HvReadyDetection = 0x14,
     VciDetection = 0x15,
+    TemperatureSensorSelection = 0x18,
     TemperatureSensorControlWrite = 0x1A,
     TemperatureSensorControlRead = 0x1B,
     TemperatureSensorExtControlWrite = 0x1C,
@@ -83,8 +84,7 @@ impl DriverOutput {
 ///  | | | | | | | `--- disable clock
 ///  | | | | | | `----- disable analog
 ///  | | | | | `------- display
-///  | | | | `--------- undocumented and unknown use,
-///  | | | |            but used in waveshare reference code
+///  | | | | `--------- display mode 2, only drives the pixels which changed
 ///  | | | `----------- load LUT
 ///  | | `------------- load temp
 ///  | `--------------- enable clock
@@ -112,6 +112,11 @@ impl DisplayUpdateControl2 {
         self
     }
 
+    pub fn display_mode_2(mut self) -> Self {
+        self.0.set_bit(3, true);
+        self
+    }
+
     pub fn load_lut(mut self) -> Self {
         self.0.set_bit(4, true);
         self
@@ -187,16 +192,16 @@ impl BorderWaveForm {
     }
 }
 
-#[allow(dead_code)]
-#[derive(Copy, Clone)]
+/// Mode of [Command::DeepSleepMode]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum DeepSleepMode {
-    // Sleeps and keeps access to RAM and controller
+    /// Sleeps and keeps access to RAM and controller
     Normal = 0x00,
 
-    // Sleeps without access to RAM/controller but keeps RAM content
+    /// Sleeps without access to RAM/controller but keeps RAM content
     Mode1 = 0x01,
 
-    // Same as MODE_1 but RAM content is not kept
+    /// Same as MODE_1 but RAM content is not kept, which draws the least current
     Mode2 = 0x11,
 }
 
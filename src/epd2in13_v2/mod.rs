@@ -1,5 +1,9 @@
 //! A Driver for the Waveshare 2.13" E-Ink Display (V2 and V3) via SPI
 //!
+//! The panel is 122 pixels wide, every row of the RAM and of [Display2in13] takes
+//! 16 bytes. The last 6 bits of a row lie past the panel, the driver sets them to the
+//! background color while sending a frame.
+//!
 //! # References V2
 //!
 //! - [Waveshare product page](https://www.waveshare.com/wiki/2.13inch_e-Paper_HAT)
@@ -21,14 +25,15 @@ use embedded_hal_async::{digital::Wait, spi::SpiDevice};
 use crate::buffer_len;
 use crate::color::Color;
 use crate::error::ErrorKind;
-use crate::interface::DisplayInterface;
-use crate::traits::{ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::interface::{BusyLevel, DisplayDelay, DisplayInterface, ResetTiming};
+use crate::traits::{ErrorType, InternalWiAdditions, RefreshLut, UpdateMode, WaveshareDisplay};
 
 pub(crate) mod command;
+pub use self::command::DeepSleepMode;
 use self::command::{
     BorderWaveForm, BorderWaveFormFixLevel, BorderWaveFormGs, BorderWaveFormVbd, Command,
-    DataEntryModeDir, DataEntryModeIncr, DeepSleepMode, DisplayUpdateControl2, DriverOutput,
-    GateDrivingVoltage, I32Ext, SourceDrivingVoltage, Vcom,
+    DataEntryModeDir, DataEntryModeIncr, DisplayUpdateControl2, DriverOutput, GateDrivingVoltage,
+    I32Ext, SourceDrivingVoltage, Vcom,
 };
 
 pub(crate) mod constants;
@@ -45,13 +50,8 @@ compile_error!(
 
 /// Full size buffer for use with the 2in13 v2 and v3 EPD
 #[cfg(feature = "graphics")]
-pub type Display2in13 = crate::graphics::Display<
-    WIDTH,
-    HEIGHT,
-    false,
-    { buffer_len(WIDTH as usize, HEIGHT as usize) },
-    Color,
->;
+pub type Display2in13 =
+    crate::graphics::Display<WIDTH, HEIGHT, false, { ROW_BYTES * HEIGHT as usize }, Color>;
 
 /// Width of the display.
 pub const WIDTH: u32 = 122;
@@ -61,24 +61,45 @@ pub const HEIGHT: u32 = 250;
 
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
-const IS_BUSY_LOW: bool = false;
+/// Bytes of a row, in the buffer and in the controller RAM
+const ROW_BYTES: usize = buffer_len(WIDTH as usize, 1);
+/// Bits of the last byte of a row which lie past the last column
+const PADDING_BITS: u8 = 0xFF >> (WIDTH % 8);
+const BUSY_LEVEL: BusyLevel = BusyLevel::High;
+const RESET_TIMING: ResetTiming = ResetTiming::new(10_000, 10_000);
 const SINGLE_BYTE_WRITE: bool = true;
 
+/// Length of a waveform for [Epd2in13::set_custom_lut]
+#[cfg(feature = "epd2in13_v2")]
+pub const LUT_LEN: usize = 70;
+/// Length of a waveform for [Epd2in13::set_custom_lut]
+#[cfg(feature = "epd2in13_v3")]
+pub const LUT_LEN: usize = 159;
+
 /// Epd2in13 (V2 & V3) driver
 ///
 /// To use this driver for V2 of the display, feature \"epd2in13_v3\" needs to be disabled and feature \"epd2in13_v2\" enabled.
-pub struct Epd2in13<SPI, BUSY, DC, RST> {
+pub struct Epd2in13<SPI, BUSY, DC, RST, DELAY> {
     /// Connection Interface
-    interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
 
     sleep_mode: DeepSleepMode,
 
     /// Background Color
     background_color: Color,
     refresh: RefreshLut,
+    /// Waveform uploaded instead of the built-in ones
+    custom_lut: Option<[u8; LUT_LEN]>,
+    /// Color of the border around the active area
+    border: Color,
+    /// Holds the border at VCOM during full refreshes
+    suppress_border_flash: bool,
+    /// Update sequence set with [Epd2in13::set_update_mode]
+    update_mode: Option<UpdateMode>,
 }
 
-impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd2in13<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST>
+    for Epd2in13<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -88,12 +109,13 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
 }
 
-impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST>
-    for Epd2in13<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Epd2in13<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -103,16 +125,17 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         // HW reset
-        self.interface.reset(spi, 10_000, 10_000).await?;
+        self.interface.reset().await?;
 
         if self.refresh == RefreshLut::Quick {
             self.set_vcom_register(spi, (-9).vcom()).await?;
             self.wait_until_idle(spi).await?;
 
-            self.set_lut(spi, Some(self.refresh)).await?;
+            self.load_lut(spi).await?;
 
             // Python code does this, not sure why
             // self.cmd_with_data(spi, Command::WriteOtpSelection, &[0, 0, 0, 0, 0x40, 0, 0])?;
@@ -127,15 +150,8 @@ where
             self.command(spi, Command::MasterActivation).await?;
             self.wait_until_idle(spi).await?;
 
-            self.set_border_waveform(
-                spi,
-                BorderWaveForm {
-                    vbd: BorderWaveFormVbd::Gs,
-                    fix_level: BorderWaveFormFixLevel::Vss,
-                    gs_trans: BorderWaveFormGs::Lut1,
-                },
-            )
-            .await?;
+            self.set_border_waveform(spi, self.border_waveform())
+                .await?;
         } else {
             self.wait_until_idle(spi).await?;
             self.command(spi, Command::SwReset).await?;
@@ -163,15 +179,8 @@ where
             self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1).await?;
             self.set_ram_address_counters(spi, 0, 0).await?;
 
-            self.set_border_waveform(
-                spi,
-                BorderWaveForm {
-                    vbd: BorderWaveFormVbd::Gs,
-                    fix_level: BorderWaveFormFixLevel::Vss,
-                    gs_trans: BorderWaveFormGs::Lut3,
-                },
-            )
-            .await?;
+            self.set_border_waveform(spi, self.border_waveform())
+                .await?;
 
             self.set_vcom_register(spi, (-21).vcom()).await?;
 
@@ -187,7 +196,7 @@ where
 
             self.set_gate_line_width(spi, 10).await?;
 
-            self.set_lut(spi, Some(self.refresh)).await?;
+            self.load_lut(spi).await?;
         }
 
         self.wait_until_idle(spi).await?;
@@ -195,7 +204,8 @@ where
     }
 }
 
-impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd2in13<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in13<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -205,20 +215,34 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type DisplayColor = Color;
     async fn new(
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        delay: DELAY,
         delay_us: Option<u32>,
     ) -> Result<Self, Self::Error> {
         let mut epd = Epd2in13 {
-            interface: DisplayInterface::new(busy, dc, rst, delay_us),
+            interface: DisplayInterface::new(
+                Some(busy),
+                dc,
+                rst,
+                delay,
+                RESET_TIMING,
+                delay_us,
+                None,
+            ),
             sleep_mode: DeepSleepMode::Mode1,
             background_color: DEFAULT_BACKGROUND_COLOR,
             refresh: RefreshLut::Full,
+            custom_lut: None,
+            border: DEFAULT_BACKGROUND_COLOR,
+            suppress_border_flash: false,
+            update_mode: None,
         };
 
         epd.init(spi).await?;
@@ -226,6 +250,9 @@ where
     }
 
     async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        if !matches!(self.sleep_mode, DeepSleepMode::Normal) {
+            self.interface.require_reset_pin()?;
+        }
         self.init(spi).await
     }
 
@@ -245,30 +272,40 @@ where
         self.command(spi, Command::MasterActivation).await?;
 
         self.set_sleep_mode(spi, self.sleep_mode).await?;
+        if !matches!(self.sleep_mode, DeepSleepMode::Normal) {
+            self.interface.mark_asleep();
+        }
         Ok(())
     }
 
     async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
-        assert!(buffer.len() == buffer_len(WIDTH as usize, HEIGHT as usize));
+        assert!(buffer.len() == ROW_BYTES * HEIGHT as usize);
         self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1).await?;
         self.set_ram_address_counters(spi, 0, 0).await?;
 
-        self.cmd_with_data(spi, Command::WriteRam, buffer).await?;
+        self.write_rows(spi, Command::WriteRam, buffer, ROW_BYTES, true)
+            .await?;
 
         if self.refresh == RefreshLut::Full {
             // Always keep the base buffer equal to current if not doing partial refresh.
             self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1).await?;
             self.set_ram_address_counters(spi, 0, 0).await?;
 
-            self.cmd_with_data(spi, Command::WriteRamRed, buffer)
+            self.write_rows(spi, Command::WriteRamRed, buffer, ROW_BYTES, true)
                 .await?;
         }
         Ok(())
     }
 
+    /// Writes `buffer` into a window of the frame
+    ///
+    /// `x` and `width` are rounded out to byte boundaries, `buffer` has to cover the
+    /// rounded window. Returns [ErrorKind::InvalidWindow] if the window doesn't lie
+    /// inside the display or the buffer has a different size.
+    ///
     /// Updating only a part of the frame is not supported when using the
-    /// partial refresh feature. The function will panic if called when set to
-    /// use partial refresh.
+    /// partial refresh feature, this returns [ErrorKind::Unsupported] while
+    /// [RefreshLut::Quick] is set.
     async fn update_partial_frame(
         &mut self,
         spi: &mut SPI,
@@ -278,29 +315,38 @@ where
         width: u32,
         height: u32,
     ) -> Result<(), Self::Error> {
-        assert!((width * height / 8) as usize == buffer.len());
-
         // This should not be used when doing partial refresh. The RAM_RED must
         // be updated with the last buffer having been displayed. Doing partial
         // update directly in RAM makes this update impossible (we can't read
         // RAM content). Using this function will most probably make the actual
         // display incorrect as the controler will compare with something
         // incorrect.
-        assert!(self.refresh == RefreshLut::Full);
+        if self.refresh != RefreshLut::Full {
+            return Err(ErrorKind::Unsupported);
+        }
 
-        self.set_ram_area(spi, x, y, x + width, y + height).await?;
-        self.set_ram_address_counters(spi, x, y).await?;
+        let (start_x, end_x) =
+            byte_aligned_window(x, y, width, height).ok_or(ErrorKind::InvalidWindow)?;
+        if buffer.len() != buffer_len((end_x - start_x) as usize, height as usize) {
+            return Err(ErrorKind::InvalidWindow);
+        }
 
-        self.cmd_with_data(spi, Command::WriteRam, buffer).await?;
+        // the window ends with the padding if it reaches the last column
+        let row_bytes = ((end_x - start_x) / 8) as usize;
+        let padded = end_x > WIDTH;
+        let (end_x, end_y) = (end_x - 1, y + height - 1);
+        self.set_ram_area(spi, start_x, y, end_x, end_y).await?;
+        self.set_ram_address_counters(spi, start_x, y).await?;
 
-        if self.refresh == RefreshLut::Full {
-            // Always keep the base buffer equals to current if not doing partial refresh.
-            self.set_ram_area(spi, x, y, x + width, y + height).await?;
-            self.set_ram_address_counters(spi, x, y).await?;
+        self.write_rows(spi, Command::WriteRam, buffer, row_bytes, padded)
+            .await?;
 
-            self.cmd_with_data(spi, Command::WriteRamRed, buffer)
-                .await?;
-        }
+        // Always keep the base buffer equals to current if not doing partial refresh.
+        self.set_ram_area(spi, start_x, y, end_x, end_y).await?;
+        self.set_ram_address_counters(spi, start_x, y).await?;
+
+        self.write_rows(spi, Command::WriteRamRed, buffer, row_bytes, padded)
+            .await?;
 
         Ok(())
     }
@@ -308,7 +354,10 @@ where
     /// Never use directly this function when using partial refresh, or also
     /// keep the base buffer in syncd using `set_partial_base_buffer` function.
     async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        if self.refresh == RefreshLut::Full {
+        if let Some(mode) = self.update_mode {
+            self.set_display_update_control_2(spi, update_control(mode))
+                .await?;
+        } else if self.refresh == RefreshLut::Full {
             self.set_display_update_control_2(
                 spi,
                 DisplayUpdateControl2::new()
@@ -323,8 +372,25 @@ where
             self.set_display_update_control_2(spi, DisplayUpdateControl2::new().display())
                 .await?;
         }
+
+        let hold_border = self.suppress_border_flash && self.refresh == RefreshLut::Full;
+        if hold_border {
+            self.set_border_waveform(
+                spi,
+                BorderWaveForm {
+                    vbd: BorderWaveFormVbd::Vcom,
+                    fix_level: BorderWaveFormFixLevel::Vss,
+                    gs_trans: BorderWaveFormGs::Lut0,
+                },
+            )
+            .await?;
+        }
         self.command(spi, Command::MasterActivation).await?;
         self.wait_until_idle(spi).await?;
+        if hold_border {
+            self.set_border_waveform(spi, self.border_waveform())
+                .await?;
+        }
 
         Ok(())
     }
@@ -351,11 +417,7 @@ where
 
         self.command(spi, Command::WriteRam).await?;
         self.interface
-            .data_x_times(
-                spi,
-                color,
-                buffer_len(WIDTH as usize, HEIGHT as usize) as u32,
-            )
+            .data_x_times(spi, color, (ROW_BYTES * HEIGHT as usize) as u32)
             .await?;
 
         // Always keep the base buffer equals to current if not doing partial refresh.
@@ -365,11 +427,7 @@ where
 
             self.command(spi, Command::WriteRamRed).await?;
             self.interface
-                .data_x_times(
-                    spi,
-                    color,
-                    buffer_len(WIDTH as usize, HEIGHT as usize) as u32,
-                )
+                .data_x_times(spi, color, (ROW_BYTES * HEIGHT as usize) as u32)
                 .await?;
         }
         Ok(())
@@ -391,11 +449,46 @@ where
         HEIGHT
     }
 
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
+
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    /// Uploads one of the built-in waveforms
+    ///
+    /// This replaces a waveform set with [Epd2in13::set_custom_lut].
     async fn set_lut(
         &mut self,
         spi: &mut SPI,
         refresh_rate: Option<RefreshLut>,
     ) -> Result<(), Self::Error> {
+        self.custom_lut = None;
         let buffer = match refresh_rate {
             Some(RefreshLut::Full) | None => &LUT_FULL_UPDATE,
             Some(RefreshLut::Quick) => &LUT_PARTIAL_UPDATE,
@@ -405,13 +498,13 @@ where
             .await
     }
 
-    async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.interface.wait_until_idle(spi, IS_BUSY_LOW).await?;
+    async fn wait_until_idle(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.wait_until_idle(BUSY_LEVEL).await?;
         Ok(())
     }
 }
 
-impl<SPI, BUSY, DC, RST> Epd2in13<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> Epd2in13<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -421,6 +514,7 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     /// When using partial refresh, the controller uses the provided buffer for
     /// comparison with new buffer.
@@ -429,16 +523,94 @@ where
         spi: &mut SPI,
         buffer: &[u8],
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        assert!(buffer_len(WIDTH as usize, HEIGHT as usize) == buffer.len());
+        assert!(ROW_BYTES * HEIGHT as usize == buffer.len());
         self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1).await?;
         self.set_ram_address_counters(spi, 0, 0).await?;
 
-        self.cmd_with_data(spi, Command::WriteRamRed, buffer)
+        self.write_rows(spi, Command::WriteRamRed, buffer, ROW_BYTES, true)
             .await?;
         Ok(())
     }
 
+    /// Uploads a waveform of the caller instead of the built-in ones
+    ///
+    /// The driver keeps a copy, so it is uploaded again by [WaveshareDisplay::wake_up]
+    /// and [Epd2in13::set_refresh]. [WaveshareDisplay::set_lut] goes back to the built-in
+    /// waveforms.
+    pub async fn set_custom_lut(
+        &mut self,
+        spi: &mut SPI,
+        lut: &[u8; LUT_LEN],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.custom_lut = Some(*lut);
+        self.load_lut(spi).await
+    }
+
+    /// Uploads the custom waveform, or the built-in one of the refresh mode
+    async fn load_lut(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        match self.custom_lut {
+            Some(lut) => {
+                self.cmd_with_data(spi, Command::WriteLutRegister, &lut)
+                    .await
+            }
+            None => self.set_lut(spi, Some(self.refresh)).await,
+        }
+    }
+
+    /// Measures the temperature of the panel with the sensor of the controller, in °C
+    ///
+    /// The controller answers on the data line, so it has to be connected to the MISO
+    /// of the SPI bus as well.
+    pub async fn read_temperature(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<i8, <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.wait_until_idle(spi).await?;
+        // internal sensor
+        self.cmd_with_data(spi, Command::TemperatureSensorSelection, &[0x80])
+            .await?;
+        self.set_display_update_control_2(
+            spi,
+            DisplayUpdateControl2::new()
+                .enable_clock()
+                .enable_analog()
+                .load_temp()
+                .disable_analog()
+                .disable_clock(),
+        )
+        .await?;
+        self.command(spi, Command::MasterActivation).await?;
+        self.wait_until_idle(spi).await?;
+
+        // 12 bit two's complement in 1/16 °C, the first byte holds the whole degrees
+        let mut temperature = [0u8; 2];
+        self.interface
+            .cmd_then_read(
+                spi,
+                Command::TemperatureSensorControlRead,
+                0,
+                &mut temperature,
+            )
+            .await?;
+        Ok(temperature[0] as i8)
+    }
+
+    /// Holds the border at VCOM during full refreshes, so it doesn't flash
+    ///
+    /// [WaveshareDisplay::display_frame] sends the border color of
+    /// [Epd2in13::set_border_color] again after the refresh. It is off by default.
+    pub fn set_suppress_border_flash(&mut self, suppress: bool) {
+        self.suppress_border_flash = suppress;
+    }
+
     /// Selects which sleep mode will be used when triggering the deep sleep.
+    ///
+    /// `DeepSleepMode::Mode1`, the default, keeps the RAM, so the quick refreshes can
+    /// go on after [WaveshareDisplay::wake_up]. After `DeepSleepMode::Mode2` the frame
+    /// has to be written again with [WaveshareDisplay::update_frame].
     pub fn set_deep_sleep_mode(&mut self, mode: DeepSleepMode) {
         self.sleep_mode = mode;
     }
@@ -457,6 +629,41 @@ where
         Ok(())
     }
 
+    /// Selects the update sequence of [WaveshareDisplay::display_frame]
+    ///
+    /// With `None`, the default, it follows the refresh mode.
+    pub fn set_update_mode(&mut self, mode: Option<UpdateMode>) {
+        self.update_mode = mode;
+    }
+
+    /// Sets the color of the border around the active area
+    ///
+    /// It takes effect with the next refresh and is kept across
+    /// [WaveshareDisplay::wake_up] and [Epd2in13::set_refresh].
+    pub async fn set_border_color(
+        &mut self,
+        spi: &mut SPI,
+        color: Color,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.border = color;
+        self.wait_until_idle(spi).await?;
+        self.set_border_waveform(spi, self.border_waveform()).await
+    }
+
+    /// The border follows the LUT of a pixel staying in the border color
+    fn border_waveform(&self) -> BorderWaveForm {
+        let gs_trans = match (self.border, self.refresh) {
+            (Color::Black, _) => BorderWaveFormGs::Lut0,
+            (Color::White, RefreshLut::Full) => BorderWaveFormGs::Lut3,
+            (Color::White, RefreshLut::Quick) => BorderWaveFormGs::Lut1,
+        };
+        BorderWaveForm {
+            vbd: BorderWaveFormVbd::Gs,
+            fix_level: BorderWaveFormFixLevel::Vss,
+            gs_trans,
+        }
+    }
+
     async fn set_gate_scan_start_position(
         &mut self,
         spi: &mut SPI,
@@ -629,6 +836,33 @@ where
         Ok(())
     }
 
+    /// Sends `buffer` to a RAM bank in rows of `row_bytes`
+    ///
+    /// If the rows are `padded`, the bits of their last byte past the last column are
+    /// set to the background color, whatever the buffer holds there.
+    async fn write_rows(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        buffer: &[u8],
+        row_bytes: usize,
+        padded: bool,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if !padded {
+            return self.cmd_with_data(spi, command, buffer).await;
+        }
+        let padding = self.background_color.get_byte_value() & PADDING_BITS;
+        self.command(spi, command).await?;
+        for row in buffer.chunks(row_bytes) {
+            let last = row.len() - 1;
+            self.interface.data(spi, &row[..last]).await?;
+            self.interface
+                .data(spi, &[row[last] & !PADDING_BITS | padding])
+                .await?;
+        }
+        Ok(())
+    }
+
     async fn command(
         &mut self,
         spi: &mut SPI,
@@ -647,14 +881,242 @@ where
     }
 }
 
+/// Rounds the window out to byte boundaries, returns its first and one past its last column
+///
+/// Returns `None` if the window is empty or doesn't lie inside the display.
+fn byte_aligned_window(x: u32, y: u32, width: u32, height: u32) -> Option<(u32, u32)> {
+    if width == 0 || height == 0 {
+        return None;
+    }
+    // checked, the window may come from anywhere in `u32`
+    let end_x = x.checked_add(width).filter(|&end| end <= WIDTH)?;
+    y.checked_add(height).filter(|&end| end <= HEIGHT)?;
+    Some((x / 8 * 8, end_x.div_ceil(8) * 8))
+}
+
+/// Display update control for the update sequence of `mode`, clock and analog are
+/// disabled afterwards like in the full refresh
+fn update_control(mode: UpdateMode) -> DisplayUpdateControl2 {
+    let control = DisplayUpdateControl2::new()
+        .enable_clock()
+        .enable_analog()
+        .display()
+        .disable_analog()
+        .disable_clock();
+    match mode {
+        UpdateMode::Full => control,
+        UpdateMode::Partial => control.display_mode_2(),
+        UpdateMode::FullWithLoadLut => control.load_temp().load_lut(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::{block_on, mocks};
 
     #[test]
     fn epd_size() {
         assert_eq!(WIDTH, 122);
         assert_eq!(HEIGHT, 250);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+        assert_eq!(ROW_BYTES, 16);
+        assert_eq!(PADDING_BITS, 0b0011_1111);
+    }
+
+    #[test]
+    fn rows_are_padded() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in13::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        // a black line over the full width of row 10, including the padding bits
+        let mut buffer = [0xFF; ROW_BYTES * HEIGHT as usize];
+        buffer[10 * ROW_BYTES..11 * ROW_BYTES].fill(0x00);
+
+        log.clear();
+        block_on(epd.update_frame(&mut spi, &buffer)).unwrap();
+        let sent = log.data_of(0x24, 0);
+        assert_eq!(sent.len(), buffer.len());
+        for (y, row) in sent.chunks(ROW_BYTES).enumerate() {
+            let last = if y == 10 { 0b0011_1111 } else { 0xFF };
+            assert_eq!(row[ROW_BYTES - 1], last, "row {}", y);
+        }
+
+        // a black background pads with black
+        epd.set_background_color(Color::Black);
+        log.clear();
+        block_on(epd.update_frame(&mut spi, &buffer)).unwrap();
+        let sent = log.data_of(0x24, 0);
+        assert_eq!(sent[ROW_BYTES - 1], 0b1100_0000);
+        assert_eq!(sent[11 * ROW_BYTES - 1], 0x00);
+
+        // a window up to the last column is padded as well
+        log.clear();
+        let region = [0x00; 2 * 4];
+        block_on(epd.update_partial_frame(&mut spi, &region, 112, 0, 10, 4)).unwrap();
+        assert_eq!(log.data_of(0x24, 0), [0x00; 8]);
+        epd.set_background_color(Color::White);
+        log.clear();
+        block_on(epd.update_partial_frame(&mut spi, &region, 112, 0, 10, 4)).unwrap();
+        assert_eq!(
+            log.data_of(0x24, 0),
+            [0x00, 0x3F, 0x00, 0x3F, 0x00, 0x3F, 0x00, 0x3F]
+        );
+    }
+
+    #[test]
+    fn unaligned_partial_window() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in13::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        // x = 13..23 is rounded out to 8..24, two bytes per row
+        log.clear();
+        let region = [0x00; 2 * 20];
+        block_on(epd.update_partial_frame(&mut spi, &region, 13, 40, 10, 20)).unwrap();
+        assert_eq!(log.data_of(0x44, 0), [1, 2]);
+        assert_eq!(log.data_of(0x45, 0), [40, 0, 59, 0]);
+        assert_eq!(log.data_of(0x4E, 0), [1]);
+        assert_eq!(log.data_of(0x24, 0), region);
+        assert_eq!(log.data_of(0x26, 0), region);
+
+        let wrong_size = block_on(epd.update_partial_frame(&mut spi, &[0x00; 20], 13, 40, 10, 20));
+        assert!(matches!(wrong_size, Err(ErrorKind::InvalidWindow)));
+        let outside = block_on(epd.update_partial_frame(&mut spi, &region, 120, 40, 10, 20));
+        assert!(matches!(outside, Err(ErrorKind::InvalidWindow)));
+        let overflow = block_on(epd.update_partial_frame(&mut spi, &region, 8, u32::MAX, 8, 2));
+        assert!(matches!(overflow, Err(ErrorKind::InvalidWindow)));
+        let overflow = block_on(epd.update_partial_frame(&mut spi, &region, u32::MAX, 0, 8, 2));
+        assert!(matches!(overflow, Err(ErrorKind::InvalidWindow)));
+
+        block_on(epd.set_refresh(&mut spi, RefreshLut::Quick)).unwrap();
+        let quick = block_on(epd.update_partial_frame(&mut spi, &region, 13, 40, 10, 20));
+        assert!(matches!(quick, Err(ErrorKind::Unsupported)));
+    }
+
+    #[test]
+    fn update_mode() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in13::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x22, 0), [0xC7]);
+
+        epd.set_update_mode(Some(UpdateMode::Partial));
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x22, 0), [0xCF]);
+
+        epd.set_update_mode(Some(UpdateMode::FullWithLoadLut));
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x22, 0), [0xF7]);
+    }
+
+    #[test]
+    fn custom_lut() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in13::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        let lut = [0x11; LUT_LEN];
+        log.clear();
+        block_on(epd.set_custom_lut(&mut spi, &lut)).unwrap();
+        assert_eq!(log.data_of(0x32, 0), lut);
+
+        // kept over a deep sleep
+        block_on(epd.sleep(&mut spi)).unwrap();
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x32, 0), lut);
+
+        // set_lut goes back to the built-in waveforms
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Full))).unwrap();
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x32, 0), LUT_FULL_UPDATE);
+    }
+
+    #[test]
+    fn read_temperature() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in13::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        log.queue_reads(&[0xFB, 0x00]);
+        assert_eq!(block_on(epd.read_temperature(&mut spi)).unwrap(), -5);
+        assert_eq!(log.commands(), [0x18, 0x22, 0x20, 0x1B]);
+        assert_eq!(log.data_of(0x18, 0), [0x80]);
+        assert_eq!(log.data_of(0x22, 0), [0xE3]);
+    }
+
+    #[test]
+    fn deep_sleep_mode() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in13::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        block_on(epd.sleep(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x10, 0), [0x01]);
+
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        epd.set_deep_sleep_mode(DeepSleepMode::Mode2);
+        log.clear();
+        block_on(epd.sleep(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x10, 0), [0x11]);
+    }
+
+    #[test]
+    fn border_flash_suppressed() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in13::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert!(!log.commands().contains(&0x3C));
+
+        // the border is held at VCOM around the activation and restored afterwards
+        epd.set_suppress_border_flash(true);
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        let commands = log.commands();
+        let position = |command, n| {
+            commands
+                .iter()
+                .enumerate()
+                .filter(|&(_, &sent)| sent == command)
+                .nth(n)
+                .unwrap()
+                .0
+        };
+        assert!(position(0x3C, 0) < position(0x20, 0));
+        assert!(position(0x20, 0) < position(0x3C, 1));
+        assert_eq!(log.data_of(0x3C, 0), [0x80]);
+        assert_eq!(log.data_of(0x3C, 1), [0x03]);
+
+        // the quick refresh leaves the border alone
+        block_on(epd.set_refresh(&mut spi, RefreshLut::Quick)).unwrap();
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert!(!log.commands().contains(&0x3C));
+    }
+
+    #[test]
+    fn border_color() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in13::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert_eq!(log.data_of(0x3C, 0), [0x03]);
+
+        log.clear();
+        block_on(epd.set_border_color(&mut spi, Color::Black)).unwrap();
+        assert_eq!(log.commands(), [0x3C]);
+        assert_eq!(log.data_of(0x3C, 0), [0x00]);
+
+        // kept when switching to the quick refresh
+        log.clear();
+        block_on(epd.set_refresh(&mut spi, RefreshLut::Quick)).unwrap();
+        assert_eq!(log.data_of(0x3C, 0), [0x00]);
+
+        log.clear();
+        block_on(epd.set_border_color(&mut spi, Color::White)).unwrap();
+        assert_eq!(log.data_of(0x3C, 0), [0x01]);
     }
 }
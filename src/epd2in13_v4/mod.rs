@@ -0,0 +1,711 @@
+//! A simple Driver for the Waveshare 2.13" E-Ink Display V4 via SPI
+//!
+//! The panel is driven by a SSD1680A controller with the waveforms in its OTP. Three
+//! refresh flavors are supported:
+//!
+//! - full refresh with [WaveshareDisplay::display_frame] and `RefreshLut::Full`
+//! - fast refresh with [WaveshareDisplay::display_frame] after switching to
+//!   `RefreshLut::Quick` with [WaveshareDisplay::set_lut], which loads the waveform
+//!   for a fixed temperature
+//! - partial refresh with [QuickRefresh]: write the base image with
+//!   [QuickRefresh::update_old_frame] and show it with a full refresh, afterwards
+//!   [QuickRefresh::update_partial_new_frame] (or [WaveshareDisplay::update_partial_frame])
+//!   and [QuickRefresh::display_new_frame] only drive the changed pixels
+//!
+//! The 122 pixels of a row take 16 bytes, the last 6 bits of every row are padding.
+//!
+//! # References
+//!
+//! - [Waveshare product page](https://www.waveshare.com/wiki/2.13inch_e-Paper_HAT_Manual)
+//! - [Waveshare C driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_2in13_V4.c)
+//! - [Waveshare Python driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd2in13_V4.py)
+use core::fmt::{Debug, Display};
+use core::num::NonZeroU32;
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+
+use crate::buffer_len;
+use crate::color::Color;
+use crate::error::ErrorKind;
+use crate::interface::{BusyLevel, DisplayDelay, DisplayInterface, ResetTiming};
+use crate::traits::{
+    ErrorType, InternalWiAdditions, PartialRefreshes, QuickRefresh, RefreshLut, WaveshareDisplay,
+};
+use crate::type_a::command::Command;
+
+/// Full size buffer for use with the 2in13 v4 EPD
+#[cfg(feature = "graphics")]
+pub type Display2in13 = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize, HEIGHT as usize) },
+    Color,
+>;
+
+/// Width of the display
+pub const WIDTH: u32 = 122;
+/// Height of the display
+pub const HEIGHT: u32 = 250;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+const BUSY_LEVEL: BusyLevel = BusyLevel::High;
+const RESET_TIMING: ResetTiming = ResetTiming::new(10_000, 2_000);
+const SINGLE_BYTE_WRITE: bool = false;
+
+/// Bytes of a full frame, including the padding bits of every row
+const FRAME_SIZE: u32 = buffer_len(WIDTH as usize, HEIGHT as usize) as u32;
+
+/// Temperature the waveform of the fast refresh is loaded for
+const FAST_TEMPERATURE: u8 = 0x5A;
+
+/// Epd2in13 v4 driver
+///
+pub struct Epd2in13<SPI, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    background_color: Color,
+    /// Refresh LUT
+    refresh: RefreshLut,
+    /// Partial refreshes since the last full refresh, and when the next one is a full one
+    partial_refreshes: PartialRefreshes,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST>
+    for Epd2in13<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    type Error = ErrorKind<SPI, BUSY, DC, RST>;
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Epd2in13<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.reset().await?;
+        self.partial_refreshes.full_done();
+        self.wait_until_idle(spi).await?;
+
+        self.interface.cmd(spi, Command::SwReset).await?;
+        self.wait_until_idle(spi).await?;
+
+        self.set_driver_output(spi).await?;
+
+        // x increment, y increment, address counter is updated in x direction
+        self.interface
+            .cmd_with_data(spi, Command::DataEntryModeSetting, &[0x03])
+            .await?;
+
+        self.use_full_frame(spi).await?;
+
+        self.interface
+            .cmd_with_data(spi, Command::BorderWaveformControl, &[0x05])
+            .await?;
+
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl1, &[0x00, 0x80])
+            .await?;
+
+        // use the internal temperature sensor
+        self.interface
+            .cmd_with_data(spi, Command::TemperatureSensorSelection, &[0x80])
+            .await?;
+
+        self.wait_until_idle(spi).await?;
+
+        if let RefreshLut::Quick = self.refresh {
+            self.load_temperature(spi, FAST_TEMPERATURE).await?;
+        }
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in13<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    type DisplayColor = Color;
+    async fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        delay: DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Self::Error> {
+        let interface =
+            DisplayInterface::new(Some(busy), dc, rst, delay, RESET_TIMING, delay_us, None);
+
+        let mut epd = Epd2in13 {
+            interface,
+            background_color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+            partial_refreshes: PartialRefreshes::default(),
+        };
+
+        epd.init(spi).await?;
+
+        Ok(epd)
+    }
+
+    async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        // Deep Sleep Mode 1, a hardware reset is needed to wake up
+        self.interface
+            .cmd_with_data(spi, Command::DeepSleepMode, &[0x01])
+            .await?;
+        self.interface.mark_asleep();
+        Ok(())
+    }
+
+    async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.require_reset_pin()?;
+        self.init(spi).await
+    }
+
+    fn set_background_color(&mut self, background_color: Color) {
+        self.background_color = background_color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.background_color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
+
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        assert!(buffer.len() == FRAME_SIZE as usize);
+        self.wait_until_idle(spi).await?;
+        self.use_full_frame(spi).await?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam, buffer)
+            .await
+    }
+
+    /// Writes `buffer` into the RAM window starting at (x, y)
+    ///
+    /// Use [QuickRefresh::display_new_frame] to only drive the changed pixels.
+    /// x and width need to be multiples of 8, unless the window ends at the right edge.
+    async fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        self.update_partial_new_frame(spi, buffer, x, y, width, height)
+            .await
+    }
+
+    async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        let update = match self.refresh {
+            // Load temperature and waveform, DISPLAY with DISPLAY Mode 1
+            RefreshLut::Full => 0xF7,
+            // Same as above, but the waveform of the fast temperature is already loaded
+            RefreshLut::Quick => 0xC7,
+        };
+        self.wait_until_idle(spi).await?;
+        self.turn_on_display(spi, update).await?;
+        self.partial_refreshes.full_done();
+        Ok(())
+    }
+
+    async fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_frame(spi, buffer).await?;
+        self.display_frame(spi).await
+    }
+
+    async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        let color = self.background_color.get_byte_value();
+        self.wait_until_idle(spi).await?;
+
+        self.use_full_frame(spi).await?;
+        self.interface.cmd(spi, Command::WriteRam).await?;
+        self.interface.data_x_times(spi, color, FRAME_SIZE).await?;
+
+        self.use_full_frame(spi).await?;
+        self.interface.cmd(spi, Command::WriteRam2).await?;
+        self.interface.data_x_times(spi, color, FRAME_SIZE).await
+    }
+
+    /// Selects the refresh used by [WaveshareDisplay::display_frame]
+    ///
+    /// Both refreshes use the waveforms of the OTP, `RefreshLut::Quick` loads the
+    /// waveform of a fixed temperature which is shorter than the measured one.
+    async fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Self::Error> {
+        if let Some(refresh_lut) = refresh_rate {
+            self.refresh = refresh_lut;
+        }
+        self.wait_until_idle(spi).await?;
+        match self.refresh {
+            RefreshLut::Full => {
+                // measure the temperature again with the next refresh
+                self.interface
+                    .cmd_with_data(spi, Command::TemperatureSensorSelection, &[0x80])
+                    .await
+            }
+            RefreshLut::Quick => self.load_temperature(spi, FAST_TEMPERATURE).await,
+        }
+    }
+
+    async fn wait_until_idle(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.wait_until_idle(BUSY_LEVEL).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> QuickRefresh<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in13<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    /// Writes the base image into both RAM banks
+    ///
+    /// Show it with a full refresh before doing any partial ones.
+    async fn update_old_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        assert!(buffer.len() == FRAME_SIZE as usize);
+        self.wait_until_idle(spi).await?;
+        self.write_both_rams(spi, buffer).await
+    }
+
+    /// Writes the new image, to be shown with [QuickRefresh::display_new_frame]
+    async fn update_new_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        self.update_partial_new_frame(spi, buffer, 0, 0, WIDTH, HEIGHT)
+            .await
+    }
+
+    /// Partial refresh, only the pixels differing from the old frame are driven
+    ///
+    /// The controller takes the new frame as old frame for the next partial refresh.
+    /// Once the limit set with [Epd2in13::set_full_refresh_after] is reached, this is
+    /// a refresh like [WaveshareDisplay::display_frame] instead.
+    async fn display_new_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        if self.partial_refreshes.full_due() {
+            return self.display_frame(spi).await;
+        }
+        self.wait_until_idle(spi).await?;
+        // DISPLAY with DISPLAY Mode 2
+        self.turn_on_display(spi, 0xFF).await?;
+        self.partial_refreshes.partial_done();
+        Ok(())
+    }
+
+    async fn update_and_display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_new_frame(spi, buffer).await?;
+        self.display_new_frame(spi).await
+    }
+
+    /// Writes a part of the base image into both RAM banks
+    async fn update_partial_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        assert!(buffer.len() == buffer_len(width as usize, height as usize));
+        self.wait_until_idle(spi).await?;
+        self.set_partial_window(spi, x, y, width, height).await?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam, buffer)
+            .await?;
+        self.set_partial_window(spi, x, y, width, height).await?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam2, buffer)
+            .await?;
+        self.use_full_frame(spi).await
+    }
+
+    /// Writes a part of the new image, to be shown with [QuickRefresh::display_new_frame]
+    async fn update_partial_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        assert!(buffer.len() == buffer_len(width as usize, height as usize));
+        self.wait_until_idle(spi).await?;
+
+        // keep the border as it is during partial refreshes
+        self.interface
+            .cmd_with_data(spi, Command::BorderWaveformControl, &[0x80])
+            .await?;
+
+        self.set_partial_window(spi, x, y, width, height).await?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam, buffer)
+            .await?;
+        if self.partial_refreshes.full_due() {
+            self.set_partial_window(spi, x, y, width, height).await?;
+            self.interface
+                .cmd_with_data(spi, Command::WriteRam2, buffer)
+                .await?;
+        }
+        self.use_full_frame(spi).await
+    }
+
+    /// Fills a part of the new image with the background color
+    async fn clear_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        let color = self.background_color.get_byte_value();
+        self.wait_until_idle(spi).await?;
+        let bytes = buffer_len(width as usize, height as usize) as u32;
+        self.set_partial_window(spi, x, y, width, height).await?;
+        self.interface.cmd(spi, Command::WriteRam).await?;
+        self.interface.data_x_times(spi, color, bytes).await?;
+        if self.partial_refreshes.full_due() {
+            self.set_partial_window(spi, x, y, width, height).await?;
+            self.interface.cmd(spi, Command::WriteRam2).await?;
+            self.interface.data_x_times(spi, color, bytes).await?;
+        }
+        self.use_full_frame(spi).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd2in13<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    /// Replaces every `refreshes`th partial refresh with a full one, `None` disables it
+    ///
+    /// The frame written for that refresh goes into both RAM banks, so it is the base
+    /// for the following partial refreshes.
+    pub fn set_full_refresh_after(&mut self, refreshes: Option<NonZeroU32>) {
+        self.partial_refreshes.set_full_after(refreshes);
+    }
+
+    /// Partial refreshes since the last full or fast refresh
+    pub fn partial_refreshes(&self) -> u32 {
+        self.partial_refreshes.count()
+    }
+
+    async fn set_driver_output(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::DriverOutputControl,
+                &[(HEIGHT - 1) as u8, ((HEIGHT - 1) >> 8) as u8, 0x00],
+            )
+            .await
+    }
+
+    async fn write_both_rams(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.use_full_frame(spi).await?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam, buffer)
+            .await?;
+        self.use_full_frame(spi).await?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam2, buffer)
+            .await
+    }
+
+    /// Loads the waveform for a fixed temperature instead of the measured one
+    async fn load_temperature(
+        &mut self,
+        spi: &mut SPI,
+        temperature: u8,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface
+            .cmd_with_data(spi, Command::TemperatureSensorSelection, &[0x80])
+            .await?;
+        // Enable clock signal, load temperature value
+        self.turn_on_display(spi, 0xB1).await?;
+
+        self.interface
+            .cmd_with_data(spi, Command::TemperatureSensorControl, &[temperature, 0x00])
+            .await?;
+        // Enable clock signal, load the waveform for the written temperature
+        self.turn_on_display(spi, 0x91).await
+    }
+
+    async fn turn_on_display(
+        &mut self,
+        spi: &mut SPI,
+        update: u8,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[update])
+            .await?;
+        self.interface.cmd(spi, Command::MasterActivation).await?;
+        self.wait_until_idle(spi).await
+    }
+
+    async fn use_full_frame(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        // choose full frame/ram
+        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1).await?;
+
+        // start from the beginning
+        self.set_ram_counter(spi, 0, 0).await
+    }
+
+    async fn set_partial_window(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        assert!(x % 8 == 0 && (width % 8 == 0 || x + width == WIDTH));
+        assert!(width > 0 && height > 0);
+        assert!(x + width <= WIDTH && y + height <= HEIGHT);
+
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)
+            .await?;
+        self.set_ram_counter(spi, x, y).await
+    }
+
+    /// Sets both X and Y pixels ranges
+    ///
+    /// The SSD1680A addresses x in bytes, so x positions are divided by 8
+    async fn set_ram_area(
+        &mut self,
+        spi: &mut SPI,
+        start_x: u32,
+        start_y: u32,
+        end_x: u32,
+        end_y: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::SetRamXAddressStartEndPosition,
+                &[(start_x >> 3) as u8, (end_x >> 3) as u8],
+            )
+            .await?;
+
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::SetRamYAddressStartEndPosition,
+                &[
+                    start_y as u8,
+                    (start_y >> 8) as u8,
+                    end_y as u8,
+                    (end_y >> 8) as u8,
+                ],
+            )
+            .await
+    }
+
+    /// Sets both X and Y pixels counters when writing data to RAM
+    async fn set_ram_counter(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface
+            .cmd_with_data(spi, Command::SetRamXAddressCounter, &[(x >> 3) as u8])
+            .await?;
+
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::SetRamYAddressCounter,
+                &[y as u8, (y >> 8) as u8],
+            )
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{block_on, mocks};
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 122);
+        assert_eq!(HEIGHT, 250);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+        // 122 pixels are padded to 16 bytes per row
+        assert_eq!(FRAME_SIZE, 16 * 250);
+    }
+
+    #[test]
+    fn fast_refresh() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in13::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x22, 0), [0xF7]);
+
+        log.clear();
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Quick))).unwrap();
+        assert_eq!(log.data_of(0x22, 0), [0xB1]);
+        assert_eq!(log.data_of(0x1A, 0), [FAST_TEMPERATURE, 0x00]);
+        assert_eq!(log.data_of(0x22, 1), [0x91]);
+
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(log.commands(), [0x22, 0x20]);
+        assert_eq!(log.data_of(0x22, 0), [0xC7]);
+    }
+
+    #[test]
+    fn partial_refresh() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in13::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        let base = [0xFF; FRAME_SIZE as usize];
+        block_on(epd.update_old_frame(&mut spi, &base)).unwrap();
+        assert_eq!(log.data_of(0x24, 0).len(), FRAME_SIZE as usize);
+        assert_eq!(log.data_of(0x26, 0).len(), FRAME_SIZE as usize);
+
+        log.clear();
+        let digit = [0x00; 2 * 16];
+        block_on(epd.update_partial_frame(&mut spi, &digit, 16, 100, 16, 16)).unwrap();
+        assert_eq!(log.data_of(0x3C, 0), [0x80]);
+        assert_eq!(log.data_of(0x44, 0), [2, 3]);
+        assert_eq!(log.data_of(0x45, 0), [100, 0, 115, 0]);
+        assert_eq!(log.data_of(0x4E, 0), [2]);
+        assert_eq!(log.data_of(0x24, 0).len(), digit.len());
+        assert!(!log.commands().contains(&0x26));
+
+        log.clear();
+        block_on(epd.display_new_frame(&mut spi)).unwrap();
+        assert_eq!(log.commands(), [0x22, 0x20]);
+        assert_eq!(log.data_of(0x22, 0), [0xFF]);
+    }
+
+    #[test]
+    fn full_refresh_after_partials() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in13::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        epd.set_full_refresh_after(NonZeroU32::new(2));
+
+        let icon = [0x00; 2 * 16];
+        log.clear();
+        block_on(epd.update_partial_new_frame(&mut spi, &icon, 8, 8, 16, 16)).unwrap();
+        block_on(epd.display_new_frame(&mut spi)).unwrap();
+        assert!(!log.commands().contains(&0x26));
+        assert_eq!(log.data_of(0x22, 0), [0xFF]);
+        assert_eq!(epd.partial_refreshes(), 1);
+
+        // the second one is a full refresh, the new frame is its base as well
+        log.clear();
+        block_on(epd.update_partial_new_frame(&mut spi, &icon, 8, 8, 16, 16)).unwrap();
+        block_on(epd.display_new_frame(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x26, 0), icon);
+        assert_eq!(log.data_of(0x22, 0), [0xF7]);
+        assert_eq!(epd.partial_refreshes(), 0);
+    }
+}
@@ -0,0 +1,426 @@
+//! A simple Driver for the Waveshare 2.13" (G) E-Ink Display via SPI
+//!
+//! The panel shows black, white, yellow and red. Every pixel takes 2 bits, four
+//! pixels are packed into one byte, see [QuadColor].
+//!
+//! The panel is 122 pixels wide, which takes 30.5 bytes at 2 bits per pixel. The
+//! [Display2in13g] buffer rounds every row up to 31 bytes, while the controller is
+//! set up for 128 pixels wide rows of 32 bytes. The driver sends the 2 padding pixels
+//! of the last buffer byte of every row as white, whatever the buffer holds, and adds
+//! the missing byte of white padding.
+//!
+//! # References
+//!
+//! - [Waveshare product page](https://www.waveshare.com/wiki/2.13inch_e-Paper_HAT_(G)_Manual)
+//! - [Waveshare C driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_2in13g.c)
+//! - [Waveshare Python driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd2in13g.py)
+use core::fmt::{Debug, Display};
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+
+use crate::color::QuadColor;
+use crate::error::ErrorKind;
+use crate::interface::{BusyLevel, DisplayDelay, DisplayInterface, ResetTiming};
+use crate::quad_buffer_len;
+use crate::traits::{ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay};
+
+pub(crate) mod command;
+use self::command::Command;
+
+/// Full size buffer for use with the 2in13g EPD
+///
+/// Every pixel takes 2 bits, so the buffer holds four pixels per byte.
+#[cfg(feature = "graphics")]
+pub type Display2in13g = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { quad_buffer_len(WIDTH as usize, HEIGHT as usize) },
+    QuadColor,
+>;
+
+/// Width of the display
+pub const WIDTH: u32 = 122;
+/// Height of the display
+pub const HEIGHT: u32 = 250;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: QuadColor = QuadColor::White;
+const BUSY_LEVEL: BusyLevel = BusyLevel::Low;
+const RESET_TIMING: ResetTiming = ResetTiming::new(20_000, 2_000);
+const SINGLE_BYTE_WRITE: bool = false;
+
+/// Bytes of a full frame with 2 bits per pixel
+const FRAME_SIZE: usize = quad_buffer_len(WIDTH as usize, HEIGHT as usize);
+/// Bytes of a row in the buffer
+const ROW_BYTES: usize = quad_buffer_len(WIDTH as usize, 1);
+/// Width of a row in the controller RAM, including the padding
+const RAM_WIDTH: u32 = 128;
+/// Bytes of a row in the controller RAM
+const RAM_ROW_BYTES: usize = quad_buffer_len(RAM_WIDTH as usize, 1);
+/// Bits of the last buffer byte of a row that are padding, 2 pixels
+const PADDING_MASK: u8 = 0x0F;
+
+/// Epd2in13g driver
+///
+pub struct Epd2in13g<SPI, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    color: QuadColor,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST>
+    for Epd2in13g<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    type Error = ErrorKind<SPI, BUSY, DC, RST>;
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Epd2in13g<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.reset().await?;
+        self.wait_until_idle(spi).await?;
+
+        // The analog settings are locked until this register is written
+        self.cmd_with_data(spi, Command::Unlock, &[0x78]).await?;
+
+        self.cmd_with_data(spi, Command::PanelSetting, &[0x0F, 0x29])
+            .await?;
+        self.cmd_with_data(spi, Command::PowerSetting, &[0x07, 0x00])
+            .await?;
+        self.cmd_with_data(spi, Command::PowerOffSequenceSetting, &[0x10, 0x54, 0x44])
+            .await?;
+        self.cmd_with_data(
+            spi,
+            Command::BoosterSoftStart,
+            &[0x05, 0x00, 0x3F, 0x0A, 0x25, 0x12, 0x1A],
+        )
+        .await?;
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x37])
+            .await?;
+        self.cmd_with_data(spi, Command::TconSetting, &[0x02, 0x02])
+            .await?;
+        self.send_resolution(spi).await?;
+        self.cmd_with_data(spi, Command::PowerSaving, &[0x22])
+            .await?;
+        self.cmd_with_data(spi, Command::PllControl, &[0x08])
+            .await?;
+
+        self.command(spi, Command::PowerOn).await?;
+        self.wait_until_idle(spi).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in13g<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    type DisplayColor = QuadColor;
+    async fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        delay: DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Self::Error> {
+        let interface =
+            DisplayInterface::new(Some(busy), dc, rst, delay, RESET_TIMING, delay_us, None);
+        let color = DEFAULT_BACKGROUND_COLOR;
+
+        let mut epd = Epd2in13g { interface, color };
+
+        epd.init(spi).await?;
+
+        Ok(epd)
+    }
+
+    async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::PowerOff, &[0x00]).await?;
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5]).await?;
+        self.interface.mark_asleep();
+        Ok(())
+    }
+
+    async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.require_reset_pin()?;
+        self.init(spi).await
+    }
+
+    fn set_background_color(&mut self, color: QuadColor) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &QuadColor {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
+
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    /// Transmits a full frame with 2 bits per pixel
+    ///
+    /// The buffer needs to hold 250 rows of 31 bytes, like the one of [Display2in13g].
+    /// Every row is padded to the 32 bytes of the controller RAM.
+    async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        assert!(buffer.len() == FRAME_SIZE);
+        let white = QuadColor::White.get_byte_value();
+        let mut padding = [white; RAM_ROW_BYTES - ROW_BYTES + 1];
+        self.wait_until_idle(spi).await?;
+        self.command(spi, Command::DataStartTransmission).await?;
+        for row in buffer.chunks(ROW_BYTES) {
+            let (last, pixels) = row.split_last().unwrap();
+            padding[0] = (last & !PADDING_MASK) | (white & PADDING_MASK);
+            self.interface.data(spi, pixels).await?;
+            self.interface.data(spi, &padding).await?;
+        }
+        Ok(())
+    }
+
+    /// The panel doesn't support partial updates, this always returns [ErrorKind::Unsupported]
+    async fn update_partial_frame(
+        &mut self,
+        _spi: &mut SPI,
+        _buffer: &[u8],
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+    ) -> Result<(), Self::Error> {
+        Err(ErrorKind::Unsupported)
+    }
+
+    async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.command(spi, Command::PowerOn).await?;
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::DisplayRefresh, &[0x00])
+            .await?;
+        self.wait_until_idle(spi).await
+    }
+
+    async fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_frame(spi, buffer).await?;
+        self.display_frame(spi).await
+    }
+
+    async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        let color = self.color.get_byte_value();
+        self.wait_until_idle(spi).await?;
+
+        self.command(spi, Command::DataStartTransmission).await?;
+        self.interface
+            .data_x_times(spi, color, (RAM_ROW_BYTES * HEIGHT as usize) as u32)
+            .await
+    }
+
+    /// The panel has no selectable LUTs, this always returns [ErrorKind::Unsupported]
+    async fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        _refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Self::Error> {
+        Err(ErrorKind::Unsupported)
+    }
+
+    async fn wait_until_idle(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.wait_until_idle(BUSY_LEVEL).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd2in13g<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    async fn command(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.cmd(spi, command).await
+    }
+
+    async fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    async fn send_resolution(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let w = RAM_WIDTH;
+        let h = self.height();
+
+        self.cmd_with_data(
+            spi,
+            Command::TconResolution,
+            &[(w >> 8) as u8, w as u8, (h >> 8) as u8, h as u8],
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{block_on, mocks};
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 122);
+        assert_eq!(HEIGHT, 250);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, QuadColor::White);
+        assert_eq!(ROW_BYTES, 31);
+        assert_eq!(RAM_ROW_BYTES, 32);
+        assert_eq!(FRAME_SIZE, 31 * 250);
+    }
+
+    #[test]
+    fn resolution_bytes() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        block_on(Epd2in13g::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert_eq!(log.data_of(0x61, 0), [0x00, 0x80, 0x00, 0xFA]);
+    }
+
+    #[test]
+    fn rows_are_padded() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in13g::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        let mut buffer = [0x00; FRAME_SIZE];
+        buffer[ROW_BYTES] = 0xFF;
+
+        log.clear();
+        block_on(epd.update_frame(&mut spi, &buffer)).unwrap();
+        let sent = log.data_of(0x10, 0);
+        assert_eq!(sent.len(), RAM_ROW_BYTES * HEIGHT as usize);
+        // the last byte of every row is white padding
+        assert_eq!(sent[RAM_ROW_BYTES - 1], 0x55);
+        assert_eq!(sent[RAM_ROW_BYTES], 0xFF);
+        assert_eq!(sent[2 * RAM_ROW_BYTES - 1], 0x55);
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn last_column() {
+        use embedded_graphics_core::{draw_target::DrawTarget, prelude::Point, Pixel};
+
+        let mut display = Display2in13g::default();
+        display.clear(QuadColor::White).unwrap();
+        display.set_pixel(Pixel(Point::new(WIDTH as i32 - 1, 1), QuadColor::Red));
+
+        // x = 121 is the second pixel of the last byte of the second row, the last
+        // 4 bits of that byte are the padding, which clear doesn't draw
+        let byte = ROW_BYTES + 30;
+        assert_eq!(display.buffer()[byte], 0b01_11_00_00);
+        for (i, &value) in display.buffer().iter().enumerate() {
+            if i % ROW_BYTES == ROW_BYTES - 1 {
+                assert_eq!(value & PADDING_MASK, 0b00_00);
+            } else {
+                assert_eq!(value, 0x55);
+            }
+        }
+
+        // the padding is sent as white
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in13g::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        log.clear();
+        block_on(epd.update_frame(&mut spi, display.buffer())).unwrap();
+        let sent = log.data_of(0x10, 0);
+        assert_eq!(sent[RAM_ROW_BYTES + 30], 0b01_11_01_01);
+        assert_eq!(sent[30], 0x55);
+    }
+
+    #[test]
+    fn partial_update_is_unsupported() {
+        let (_log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in13g::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        let result = block_on(epd.update_partial_frame(&mut spi, &[0; 4], 0, 0, 8, 2));
+        assert!(matches!(result, Err(ErrorKind::Unsupported)));
+    }
+}
@@ -0,0 +1,101 @@
+//! SPI Commands for the Waveshare 2.36" (G) E-Ink Display
+
+use crate::traits;
+
+/// Epd2in36g commands
+///
+/// Should rarely (never?) be needed directly.
+///
+/// For more infos about the addresses and what they are doing look into the PDFs.
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub(crate) enum Command {
+    /// Set Resolution, LUT selection, gate scan direction, source shift
+    /// direction, booster switch, soft reset.
+    PanelSetting = 0x00,
+
+    /// Selecting internal and external power
+    PowerSetting = 0x01,
+
+    /// After the Power Off command, the driver will power off following the Power Off
+    /// Sequence; BUSY signal will become "0".
+    PowerOff = 0x02,
+
+    /// Setting Power OFF sequence
+    PowerOffSequenceSetting = 0x03,
+
+    /// Turning On the Power
+    ///
+    /// After the Power ON command, the driver will power on following the Power ON
+    /// sequence. Once complete, the BUSY signal will become "1".
+    PowerOn = 0x04,
+
+    /// Starting data transmission
+    BoosterSoftStart = 0x06,
+
+    /// This command makes the chip enter the deep-sleep mode to save power.
+    ///
+    /// The deep sleep mode would return to stand-by by hardware reset.
+    ///
+    /// The only one parameter is a check code, the command would be excuted if check code = 0xA5.
+    DeepSleep = 0x07,
+
+    /// This command starts transmitting the 2 bit per pixel data and writes it into SRAM.
+    DataStartTransmission = 0x10,
+
+    /// After this command is issued, driver will refresh display (data/VCOM) according to
+    /// SRAM data and LUT.
+    ///
+    /// The only parameter has to be 0x00.
+    DisplayRefresh = 0x12,
+
+    /// The command controls the PLL clock frequency.
+    PllControl = 0x30,
+
+    /// This command indicates the interval of Vcom and data output. When setting the
+    /// vertical back porch, the total blanking will be kept (20 Hsync).
+    VcomAndDataIntervalSetting = 0x50,
+
+    /// This command defines non-overlap period of Gate and Source.
+    TconSetting = 0x60,
+
+    /// This command defines alternative resolution and this setting is of higher priority
+    /// than the RES\[1:0\] in R00H (PSR).
+    TconResolution = 0x61,
+
+    /// Unlocks the vendor registers, has to be written first after a reset
+    Unlock = 0x66,
+
+    /// Undocumented analog setting of the vendor init sequence
+    AnalogSetting2 = 0x84,
+
+    /// Undocumented analog setting of the vendor init sequence
+    AnalogSetting = 0xB0,
+
+    /// Power saving of the source and gate drivers
+    PowerSaving = 0xE3,
+
+    /// Undocumented setting of the vendor init sequence, written right before the power on
+    AnalogSetting3 = 0xE9,
+}
+
+impl traits::Command for Command {
+    /// Returns the address of the command
+    fn address(self) -> u8 {
+        self as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn command_addr() {
+        assert_eq!(Command::PanelSetting.address(), 0x00);
+        assert_eq!(Command::Unlock.address(), 0x66);
+        assert_eq!(Command::AnalogSetting2.address(), 0x84);
+        assert_eq!(Command::AnalogSetting3.address(), 0xE9);
+    }
+}
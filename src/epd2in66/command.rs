@@ -0,0 +1,93 @@
+//! SPI Commands for the Waveshare 2.66" E-Ink Display
+
+use crate::traits;
+
+/// Epd2in66 commands for the SSD1680 controller
+///
+/// Should rarely (never?) be needed directly.
+///
+/// For more infos about the addresses and what they are doing look into the PDFs.
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub(crate) enum Command {
+    /// Driver Output control
+    ///     3 Databytes:
+    ///     A[7:0]
+    ///     0.. A[8]
+    ///     0.. B[2:0]
+    DriverOutputControl = 0x01,
+    /// Gate driving voltage
+    GateDrivingVoltage = 0x03,
+    /// Source driving voltage
+    SourceDrivingVoltage = 0x04,
+    /// Booster Soft start control
+    ///     5 Databytes: driving strength and minimum off time of the three phases
+    ///     and the phase durations
+    BoosterSoftStartControl = 0x0C,
+    /// Deep Sleep Mode Control
+    ///     1 Databyte:
+    ///         0x00: Normal Mode (POR)
+    ///         0x01: Deep Sleep Mode 1, RAM content is kept
+    ///         0x03: Deep Sleep Mode 2, RAM content is lost
+    DeepSleepMode = 0x10,
+    /// Data Entry mode setting
+    DataEntryModeSetting = 0x11,
+    /// Resets the commands and parameters to their default values, BUSY is high during the reset
+    SwReset = 0x12,
+    /// Selects the internal or an external temperature sensor
+    TemperatureSensorSelection = 0x18,
+    /// Writes to the temperature register
+    TemperatureSensorControlWrite = 0x1A,
+    /// Reads from the temperature register
+    TemperatureSensorControlRead = 0x1B,
+    /// Activates the display update sequence set with `DisplayUpdateControl2`
+    MasterActivation = 0x20,
+    /// RAM content option for the display update
+    DisplayUpdateControl1 = 0x21,
+    /// Display update sequence option
+    DisplayUpdateControl2 = 0x22,
+    /// Write to the black/white RAM
+    WriteRam = 0x24,
+    /// Write to the red RAM, used as the previous image for differential updates
+    WriteRam2 = 0x26,
+    /// Writes the VCOM register
+    WriteVcomRegister = 0x2C,
+    /// Writes the waveform look up table
+    WriteLutRegister = 0x32,
+    /// Border waveform control
+    BorderWaveformControl = 0x3C,
+    /// Start and end position of the RAM window in x direction, in bytes (8 pixels)
+    SetRamXAddressStartEndPosition = 0x44,
+    /// Start and end position of the RAM window in y direction
+    SetRamYAddressStartEndPosition = 0x45,
+    /// Fills the red RAM with a regular pattern
+    AutoWriteRedRamRegularPattern = 0x46,
+    /// Fills the black/white RAM with a regular pattern
+    AutoWriteBwRamRegularPattern = 0x47,
+    /// Initial x position of the RAM address counter, in bytes (8 pixels)
+    SetRamXAddressCounter = 0x4E,
+    /// Initial y position of the RAM address counter
+    SetRamYAddressCounter = 0x4F,
+    /// No operation
+    Nop = 0x7F,
+}
+
+impl traits::Command for Command {
+    /// Returns the address of the command
+    fn address(self) -> u8 {
+        self as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Command;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn command_addr() {
+        assert_eq!(Command::DriverOutputControl.address(), 0x01);
+        assert_eq!(Command::SetRamXAddressCounter.address(), 0x4E);
+        assert_eq!(Command::Nop.address(), 0x7F);
+    }
+}
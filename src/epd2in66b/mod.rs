@@ -11,6 +11,11 @@
 //! Information on this display/hat can be found at the [Waveshare Wiki](https://www.waveshare.com/wiki/Pico-ePaper-2.66-B).
 //! Do read this documentation, in particular to understand how often this display both should and should not be updated.
 //!
+//! The black and white plane is written to RAM 0x24 and the red plane to RAM 0x26, and both
+//! can be updated on their own with [WaveshareThreeColorDisplay]. A set bit in the red RAM
+//! shows red. This is inverted compared to the vendor images, but matches the chromatic
+//! buffer of [Display2in66b], so the planes are sent without any further conversion.
+//!
 //! # Example for the 'Pico-ePaper-2.66-B' B/W/R Pi Pico Hat E-Ink Display
 //! This example was created in an environment using the [Knurling](https://github.com/knurling-rs) ```flip-link```, ```defmt``` and ```probe-run``` tools - you will
 //! need to adjust for your preferred setup.
@@ -171,7 +176,7 @@ use embedded_hal::digital::{InputPin, OutputPin};
 use embedded_hal_async::{digital::Wait, spi::SpiDevice};
 
 use crate::color::TriColor;
-use crate::interface::DisplayInterface;
+use crate::interface::{BusyLevel, DisplayDelay, DisplayInterface, ResetTiming};
 use crate::prelude::ErrorKind;
 use crate::traits::{
     ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
@@ -187,6 +192,8 @@ pub const WIDTH: u32 = 152;
 pub const HEIGHT: u32 = 296;
 
 const SINGLE_BYTE_WRITE: bool = true;
+const BUSY_LEVEL: BusyLevel = BusyLevel::High;
+const RESET_TIMING: ResetTiming = ResetTiming::new(20_000, 2_000);
 
 /// White, display this during long-term storage
 pub const DEFAULT_BACKGROUND_COLOR: TriColor = TriColor::White;
@@ -202,12 +209,13 @@ pub type Display2in66b = crate::graphics::Display<
 >;
 
 /// The EPD 2in66-B driver.
-pub struct Epd2in66b<SPI, BUSY, DC, RST> {
-    interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
+pub struct Epd2in66b<SPI, BUSY, DC, RST, DELAY> {
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
     background: TriColor,
 }
 
-impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd2in66b<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST>
+    for Epd2in66b<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -217,11 +225,13 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
 }
 
-impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd2in66b<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Epd2in66b<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -231,6 +241,7 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         // We follow the sequence of the Pi-Pico hat example code.
@@ -253,8 +264,8 @@ where
     }
 }
 
-impl<SPI, BUSY, DC, RST> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST>
-    for Epd2in66b<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in66b<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -264,6 +275,7 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn update_color_frame(
         &mut self,
@@ -294,9 +306,27 @@ where
         self.interface.cmd(spi, Command::WriteRedRAM).await?;
         self.interface.data(spi, chromatic).await
     }
+
+    async fn clear_achromatic_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        let white = match self.background {
+            TriColor::Black | TriColor::Chromatic => StartWith::Zero,
+            TriColor::White => StartWith::One,
+        };
+        self.black_white_pattern(spi, PatW::W160, PatH::H296, white)
+            .await
+    }
+
+    async fn clear_chromatic_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        let red = match self.background {
+            TriColor::Black | TriColor::White => StartWith::Zero,
+            TriColor::Chromatic => StartWith::One,
+        };
+        self.red_pattern(spi, PatW::W160, PatH::H296, red).await
+    }
 }
 
-impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd2in66b<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in66b<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -306,6 +336,7 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type DisplayColor = TriColor;
 
@@ -313,14 +344,23 @@ where
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        delay: DELAY,
         delay_us: Option<u32>,
     ) -> Result<Self, Self::Error>
     where
         Self: Sized,
     {
         let mut epd = Self {
-            interface: DisplayInterface::new(busy, dc, rst, delay_us),
+            interface: DisplayInterface::new(
+                Some(busy),
+                dc,
+                rst,
+                delay,
+                RESET_TIMING,
+                delay_us,
+                None,
+            ),
             background: DEFAULT_BACKGROUND_COLOR,
         };
         epd.init(spi).await?;
@@ -334,10 +374,13 @@ where
                 Command::DeepSleepMode,
                 &[DeepSleep::SleepLosingRAM as u8],
             )
-            .await
+            .await?;
+        self.interface.mark_asleep();
+        Ok(())
     }
 
     async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.require_reset_pin()?;
         self.init(spi).await
     }
 
@@ -357,6 +400,37 @@ where
         HEIGHT
     }
 
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
+
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
     async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
         self.set_cursor(spi, 0, 0).await?;
         self.update_achromatic_frame(spi, buffer).await?;
@@ -381,6 +455,10 @@ where
     }
 
     async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        // Load temperature and waveform, DISPLAY with DISPLAY Mode 1
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xF7])
+            .await?;
         self.interface.cmd(spi, Command::MasterActivation).await?;
         self.wait_until_idle(spi).await
     }
@@ -395,14 +473,8 @@ where
     }
 
     async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        let (white, red) = match self.background {
-            TriColor::Black => (StartWith::Zero, StartWith::Zero),
-            TriColor::White => (StartWith::One, StartWith::Zero),
-            TriColor::Chromatic => (StartWith::Zero, StartWith::One),
-        };
-        self.black_white_pattern(spi, PatW::W160, PatH::H296, white)
-            .await?;
-        self.red_pattern(spi, PatW::W160, PatH::H296, red).await
+        self.clear_achromatic_frame(spi).await?;
+        self.clear_chromatic_frame(spi).await
     }
 
     async fn set_lut(
@@ -419,7 +491,7 @@ where
 }
 
 // Helper functions that enforce some type and value constraints. Meant to help with code readability. They caught some of my silly errors -> yay rust!.
-impl<SPI, BUSY, DC, RST> Epd2in66b<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> Epd2in66b<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -429,12 +501,13 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn wait_until_idle(
         &mut self,
-        spi: &mut SPI,
+        _spi: &mut SPI,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        self.interface.wait_until_idle(spi, false).await
+        self.interface.wait_until_idle(BUSY_LEVEL).await
     }
 
     async fn hw_reset(
@@ -442,7 +515,7 @@ where
         spi: &mut SPI,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
         // The initial delay is taken from other code here, the 2 ms comes from the SSD1675B datasheet.
-        self.interface.reset(spi, 20_000, 2_000).await?;
+        self.interface.reset().await?;
         self.wait_until_idle(spi).await
     }
     async fn sw_reset(
@@ -559,3 +632,43 @@ where
         self.wait_until_idle(spi).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{block_on, mocks};
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 152);
+        assert_eq!(HEIGHT, 296);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, TriColor::White);
+    }
+
+    #[test]
+    fn planes_are_sent_unchanged() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in66b::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        block_on(epd.update_color_frame(&mut spi, &[0xF0, 0x0F], &[0x81, 0x18])).unwrap();
+        assert_eq!(log.data_of(0x24, 0), [0xF0, 0x0F]);
+        assert_eq!(log.data_of(0x26, 0), [0x81, 0x18]);
+
+        log.clear();
+        block_on(epd.update_chromatic_frame(&mut spi, &[0x42])).unwrap();
+        assert_eq!(log.data_of(0x26, 0), [0x42]);
+        assert!(!log.commands().contains(&0x24));
+    }
+
+    #[test]
+    fn display_frame_uses_full_update() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in66b::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(log.commands(), [0x22, 0x20]);
+        assert_eq!(log.data_of(0x22, 0), [0xF7]);
+    }
+}
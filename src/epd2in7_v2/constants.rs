@@ -0,0 +1,30 @@
+//! This file contains the look-up-table of the 4 level grayscale refresh.
+//!
+//! The first 153 bytes are written with `WriteLutRegister`: the voltage select of
+//! the five LUTs (12 bytes each) followed by twelve groups of 7 bytes with the frame
+//! counts and repeats of the phases. The last six bytes are the frame rates, the
+//! end option and the gate, source and VCOM voltages.
+
+#[rustfmt::skip]
+pub(crate) const LUT_4GRAY: [u8; 159] = [
+    0x40, 0x48, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x08, 0x48, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x02, 0x48, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x20, 0x48, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x0A, 0x19, 0x00, 0x03, 0x08, 0x00, 0x00,
+    0x14, 0x01, 0x00, 0x14, 0x01, 0x00, 0x03,
+    0x0A, 0x03, 0x00, 0x08, 0x19, 0x00, 0x00,
+    0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x22, 0x22, 0x22, 0x22, 0x22, 0x22, 0x00, 0x00, 0x00,
+    // end option, gate voltage, source voltages, VCOM
+    0x22, 0x17, 0x41, 0x00, 0x32, 0x1C,
+];
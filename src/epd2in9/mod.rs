@@ -21,7 +21,7 @@
 //!# let mut delay = delay::NoopDelay::new();
 //!
 //!// Setup EPD
-//!let mut epd = Epd2in9::new(&mut spi, busy_in, dc, rst, &mut None)?;
+//!let mut epd = Epd2in9::new(&mut spi, busy_in, dc, Some(rst), delay, None)?;
 //!
 //!// Use display graphics from embedded-graphics
 //!let mut display = Display2in9::default();
@@ -47,7 +47,8 @@ pub const WIDTH: u32 = 128;
 pub const HEIGHT: u32 = 296;
 /// Default Background Color (white)
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
-const IS_BUSY_LOW: bool = false;
+const BUSY_LEVEL: BusyLevel = BusyLevel::High;
+const RESET_TIMING: ResetTiming = ResetTiming::new(10_000, 10_000);
 const SINGLE_BYTE_WRITE: bool = true;
 
 use core::fmt::{Debug, Display};
@@ -55,17 +56,21 @@ use embedded_hal::digital::{InputPin, OutputPin};
 use embedded_hal_async::{digital::Wait, spi::SpiDevice};
 
 use crate::{
-    traits::{ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay},
+    traits::{ErrorType, InternalWiAdditions, RefreshLut, UpdateMode, WaveshareDisplay},
     type_a::{
+        border_waveform,
         command::Command,
         constants::{LUT_FULL_UPDATE, LUT_PARTIAL_UPDATE},
+        data_entry_mode, display_update_control, gate_scan, ram_x, BORDER_VCOM,
     },
 };
 
 use crate::buffer_len;
 use crate::color::Color;
 use crate::error::ErrorKind;
-use crate::interface::DisplayInterface;
+use crate::interface::{BusyLevel, DisplayDelay, DisplayInterface, ResetTiming};
+
+pub use crate::type_a::command::DeepSleepMode;
 
 /// Display with Fullsize buffer for use with the 2in9 EPD
 #[cfg(feature = "graphics")]
@@ -79,16 +84,28 @@ pub type Display2in9 = crate::graphics::Display<
 
 /// Epd2in9 driver
 ///
-pub struct Epd2in9<SPI, BUSY, DC, RST> {
+pub struct Epd2in9<SPI, BUSY, DC, RST, DELAY> {
     /// SPI
-    interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
     /// Color
     background_color: Color,
     /// Refresh LUT
     refresh: RefreshLut,
+    /// Border color set with [Epd2in9::set_border_color]
+    border: Option<Color>,
+    /// Holds the border at VCOM during full refreshes
+    suppress_border_flash: bool,
+    /// Update sequence set with [Epd2in9::set_update_mode]
+    update_mode: Option<UpdateMode>,
+    /// Mode of [WaveshareDisplay::sleep]
+    sleep_mode: DeepSleepMode,
+    /// Mirrors the columns, set with [Epd2in9::set_scan_mirroring]
+    x_flip: bool,
+    /// Mirrors the rows, set with [Epd2in9::set_scan_mirroring]
+    y_flip: bool,
 }
 
-impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd2in9<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST> for Epd2in9<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -98,11 +115,13 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
 }
 
-impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd2in9<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Epd2in9<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -112,56 +131,23 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.interface.reset(spi, 10_000, 10_000).await?;
-
-        self.wait_until_idle(spi).await?;
-
-        // 3 Databytes:
-        // A[7:0]
-        // 0.. A[8]
-        // 0.. B[2:0]
-        // Default Values: A = Height of Screen (0x127), B = 0x00 (GD, SM and TB=0?)
-        self.interface
-            .cmd_with_data(spi, Command::DriverOutputControl, &[0x27, 0x01, 0x00])
-            .await?;
-
-        // 3 Databytes: (and default values from datasheet and arduino)
-        // 1 .. A[6:0]  = 0xCF | 0xD7
-        // 1 .. B[6:0]  = 0xCE | 0xD6
-        // 1 .. C[6:0]  = 0x8D | 0x9D
-        //TODO: test
-        self.interface
-            .cmd_with_data(spi, Command::BoosterSoftStartControl, &[0xD7, 0xD6, 0x9D])
-            .await?;
-
-        // One Databyte with value 0xA8 for 7V VCOM
-        self.interface
-            .cmd_with_data(spi, Command::WriteVcomRegister, &[0xA8])
-            .await?;
-
-        // One Databyte with default value 0x1A for 4 dummy lines per gate
-        self.interface
-            .cmd_with_data(spi, Command::SetDummyLinePeriod, &[0x1A])
-            .await?;
-
-        // One Databyte with default value 0x08 for 2us per line
-        self.interface
-            .cmd_with_data(spi, Command::SetGateLineWidth, &[0x08])
-            .await?;
-
-        // One Databyte with default value 0x03
-        //  -> address: x increment, y increment, address counter is updated in x direction
-        self.interface
-            .cmd_with_data(spi, Command::DataEntryModeSetting, &[0x03])
-            .await?;
-
-        self.set_lut(spi, None).await
+        self.interface.reset().await?;
+        if self.interface.has_reset_pin() {
+            self.wait_until_idle(spi).await?;
+        } else {
+            self.interface
+                .sw_reset(spi, Command::SwReset, BUSY_LEVEL)
+                .await?;
+        }
+        self.configure(spi).await
     }
 }
 
-impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd2in9<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in9<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -171,6 +157,7 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type DisplayColor = Color;
     fn width(&self) -> u32 {
@@ -181,19 +168,58 @@ where
         HEIGHT
     }
 
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
+
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
     async fn new(
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        delay: DELAY,
         delay_us: Option<u32>,
     ) -> Result<Self, Self::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        let interface =
+            DisplayInterface::new(Some(busy), dc, rst, delay, RESET_TIMING, delay_us, None);
 
         let mut epd = Epd2in9 {
             interface,
             background_color: DEFAULT_BACKGROUND_COLOR,
             refresh: RefreshLut::Full,
+            border: None,
+            suppress_border_flash: false,
+            update_mode: None,
+            sleep_mode: DeepSleepMode::Normal,
+            x_flip: false,
+            y_flip: false,
         };
 
         epd.init(spi).await?;
@@ -203,28 +229,35 @@ where
 
     async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
-        // 0x00 for Normal mode (Power on Reset), 0x01 for Deep Sleep Mode
-        //TODO: is 0x00 needed here? (see also epd1in54)
         self.interface
-            .cmd_with_data(spi, Command::DeepSleepMode, &[0x00])
+            .cmd_with_data(spi, Command::DeepSleepMode, &[self.sleep_mode as u8])
             .await?;
+        if !matches!(self.sleep_mode, DeepSleepMode::Normal) {
+            self.interface.mark_asleep();
+        }
         Ok(())
     }
 
+    /// Resets and initializes the controller again
+    ///
+    /// In [DeepSleepMode::Normal] the controller is still awake, so a software reset
+    /// replaces the slower hardware reset.
     async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.wait_until_idle(spi).await?;
-        self.init(spi).await?;
-        Ok(())
+        if matches!(self.sleep_mode, DeepSleepMode::Normal) {
+            self.wait_until_idle(spi).await?;
+            self.interface
+                .sw_reset(spi, Command::SwReset, BUSY_LEVEL)
+                .await?;
+            return self.configure(spi).await;
+        }
+        self.interface.require_reset_pin()?;
+        self.init(spi).await
     }
 
     async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
         self.use_full_frame(spi).await?;
-
-        self.interface
-            .cmd_with_data(spi, Command::WriteRam, buffer)
-            .await?;
-        Ok(())
+        self.write_ram(spi, buffer).await
     }
 
     //TODO: update description: last 3 bits will be ignored for width and x_pos
@@ -238,27 +271,36 @@ where
         height: u32,
     ) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
-        self.set_ram_area(spi, x, y, x + width, y + height).await?;
-        self.set_ram_counter(spi, x, y).await?;
-
-        self.interface
-            .cmd_with_data(spi, Command::WriteRam, buffer)
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)
             .await?;
-        Ok(())
+        self.set_ram_counter(spi, x, y).await?;
+        self.write_ram(spi, buffer).await
     }
 
     async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
         // enable clock signal, enable cp, display pattern -> 0xC4 (tested with the arduino version)
         //TODO: test control_1 or control_2 with default value 0xFF (from the datasheet)
+        let control = self.update_mode.map_or(0xC4, display_update_control);
         self.interface
-            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xC4])
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[control])
             .await?;
 
+        let hold_border = self.suppress_border_flash && self.refresh == RefreshLut::Full;
+        if hold_border {
+            self.interface
+                .cmd_with_data(spi, Command::BorderWaveformControl, &[BORDER_VCOM])
+                .await?;
+        }
+
         self.interface.cmd(spi, Command::MasterActivation).await?;
         // MASTER Activation should not be interupted to avoid currption of panel images
         // therefore a terminate command is send
         self.interface.cmd(spi, Command::Nop).await?;
+
+        if hold_border {
+            self.restore_border(spi).await?;
+        }
         Ok(())
     }
 
@@ -308,12 +350,12 @@ where
         }
     }
 
-    async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
+    async fn wait_until_idle(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.wait_until_idle(BUSY_LEVEL).await
     }
 }
 
-impl<SPI, BUSY, DC, RST> Epd2in9<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> Epd2in9<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -323,7 +365,190 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
+    /// Holds the border at VCOM during full refreshes, so it doesn't flash
+    ///
+    /// [WaveshareDisplay::display_frame] then waits for the refresh to finish and sends
+    /// the border color of [Epd2in9::set_border_color] again, or the one of the
+    /// background color if none is set. It is off by default.
+    pub fn set_suppress_border_flash(&mut self, suppress: bool) {
+        self.suppress_border_flash = suppress;
+    }
+
+    /// Selects the mode of [WaveshareDisplay::sleep]
+    ///
+    /// `DeepSleepMode::Normal`, the default, keeps the controller awake, while
+    /// `DeepSleepMode::Mode1` draws less current until the next
+    /// [WaveshareDisplay::wake_up]. Both keep the RAM.
+    pub fn set_deep_sleep_mode(&mut self, mode: DeepSleepMode) {
+        self.sleep_mode = mode;
+    }
+
+    /// Selects the update sequence of [WaveshareDisplay::display_frame]
+    ///
+    /// `None`, the default, is the pattern display of `UpdateMode::Partial`, which
+    /// the LUTs of this driver are made for.
+    pub fn set_update_mode(&mut self, mode: Option<UpdateMode>) {
+        self.update_mode = mode;
+    }
+
+    /// Sets the color of the border around the active area
+    ///
+    /// It takes effect with the next refresh and is kept across
+    /// [WaveshareDisplay::wake_up].
+    pub async fn set_border_color(
+        &mut self,
+        spi: &mut SPI,
+        color: Color,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.border = Some(color);
+        self.wait_until_idle(spi).await?;
+        self.send_border_waveform(spi).await
+    }
+
+    /// Sends the border again after a refresh with the border held at VCOM
+    async fn restore_border(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.wait_until_idle(spi).await?;
+        let color = self.border.unwrap_or(self.background_color);
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::BorderWaveformControl,
+                &[border_waveform(color)],
+            )
+            .await
+    }
+
+    /// Sets up the registers after a reset
+    async fn configure(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        // 3 Databytes:
+        // A[7:0]
+        // 0.. A[8]
+        // 0.. B[2:0]
+        // Default Values: A = Height of Screen (0x127), B = 0x00 (GD, SM and TB=0?)
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::DriverOutputControl,
+                &[0x27, 0x01, gate_scan(self.y_flip)],
+            )
+            .await?;
+
+        // 3 Databytes: (and default values from datasheet and arduino)
+        // 1 .. A[6:0]  = 0xCF | 0xD7
+        // 1 .. B[6:0]  = 0xCE | 0xD6
+        // 1 .. C[6:0]  = 0x8D | 0x9D
+        //TODO: test
+        self.interface
+            .cmd_with_data(spi, Command::BoosterSoftStartControl, &[0xD7, 0xD6, 0x9D])
+            .await?;
+
+        // One Databyte with value 0xA8 for 7V VCOM
+        self.interface
+            .cmd_with_data(spi, Command::WriteVcomRegister, &[0xA8])
+            .await?;
+
+        // One Databyte with default value 0x1A for 4 dummy lines per gate
+        self.interface
+            .cmd_with_data(spi, Command::SetDummyLinePeriod, &[0x1A])
+            .await?;
+
+        // One Databyte with default value 0x08 for 2us per line
+        self.interface
+            .cmd_with_data(spi, Command::SetGateLineWidth, &[0x08])
+            .await?;
+
+        // One Databyte with default value 0x03
+        //  -> address: x increment, y increment, address counter is updated in x direction
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::DataEntryModeSetting,
+                &[data_entry_mode(self.x_flip)],
+            )
+            .await?;
+
+        self.send_border_waveform(spi).await?;
+
+        self.set_lut(spi, None).await
+    }
+
+    /// Sends the border of [Epd2in9::set_border_color], the reset value is kept without one
+    async fn send_border_waveform(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if let Some(color) = self.border {
+            self.interface
+                .cmd_with_data(
+                    spi,
+                    Command::BorderWaveformControl,
+                    &[border_waveform(color)],
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Mirrors the image on the panel, e.g. for a panel mounted upside down
+    ///
+    /// `x_flip` mirrors the columns and `y_flip` the rows, both together rotate by 180°.
+    /// The controller does it while the frame is written, so it costs nothing on the
+    /// MCU and still works with the rotation of the graphics. It takes effect with the
+    /// next [WaveshareDisplay::update_frame] and is kept across
+    /// [WaveshareDisplay::wake_up].
+    pub async fn set_scan_mirroring(
+        &mut self,
+        spi: &mut SPI,
+        x_flip: bool,
+        y_flip: bool,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.x_flip = x_flip;
+        self.y_flip = y_flip;
+        self.wait_until_idle(spi).await?;
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::DriverOutputControl,
+                &[0x27, 0x01, gate_scan(self.y_flip)],
+            )
+            .await?;
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::DataEntryModeSetting,
+                &[data_entry_mode(self.x_flip)],
+            )
+            .await
+    }
+
+    /// Writes `buffer` from the RAM counter on, with reversed bits if the columns are
+    /// mirrored
+    async fn write_ram(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if !self.x_flip {
+            return self
+                .interface
+                .cmd_with_data(spi, Command::WriteRam, buffer)
+                .await;
+        }
+        self.interface.cmd(spi, Command::WriteRam).await?;
+        for byte in buffer {
+            self.interface.data(spi, &[byte.reverse_bits()]).await?;
+        }
+        Ok(())
+    }
+
     async fn use_full_frame(
         &mut self,
         spi: &mut SPI,
@@ -344,7 +569,7 @@ where
         end_y: u32,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
         assert!(start_x < end_x);
-        assert!(start_y < end_y);
+        assert!(start_y <= end_y);
 
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
@@ -352,7 +577,10 @@ where
             .cmd_with_data(
                 spi,
                 Command::SetRamXAddressStartEndPosition,
-                &[(start_x >> 3) as u8, (end_x >> 3) as u8],
+                &[
+                    ram_x(start_x, WIDTH, self.x_flip),
+                    ram_x(end_x, WIDTH, self.x_flip),
+                ],
             )
             .await?;
 
@@ -381,7 +609,11 @@ where
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
         self.interface
-            .cmd_with_data(spi, Command::SetRamXAddressCounter, &[(x >> 3) as u8])
+            .cmd_with_data(
+                spi,
+                Command::SetRamXAddressCounter,
+                &[ram_x(x, WIDTH, self.x_flip)],
+            )
             .await?;
 
         // 2 Databytes: A[7:0] & 0..A[8]
@@ -413,6 +645,8 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::{block_on, mocks};
+    use crate::NoResetPin;
 
     #[test]
     fn epd_size() {
@@ -420,4 +654,158 @@ mod tests {
         assert_eq!(HEIGHT, 296);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn deep_sleep_mode() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in9::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        block_on(epd.sleep(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x10, 0), [0x00]);
+
+        // awake, a software reset without the delays of the hardware one
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.commands()[0], 0x12);
+        assert!(log.delays().is_empty());
+
+        epd.set_deep_sleep_mode(DeepSleepMode::Mode1);
+        log.clear();
+        block_on(epd.sleep(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x10, 0), [0x01]);
+
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert!(!log.delays().is_empty());
+    }
+
+    #[test]
+    fn no_reset_pin() {
+        let (log, mut spi, busy, dc, _, delay) = mocks();
+        let mut epd = block_on(Epd2in9::new(
+            &mut spi,
+            busy,
+            dc,
+            None::<NoResetPin>,
+            delay,
+            None,
+        ))
+        .unwrap();
+        // software reset instead
+        assert_eq!(log.commands()[0], 0x12);
+
+        // the normal mode doesn't need a reset
+        block_on(epd.sleep(&mut spi)).unwrap();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+
+        epd.set_deep_sleep_mode(DeepSleepMode::Mode1);
+        block_on(epd.sleep(&mut spi)).unwrap();
+        assert!(matches!(
+            block_on(epd.wake_up(&mut spi)),
+            Err(ErrorKind::ResetRequired)
+        ));
+    }
+
+    #[test]
+    fn scan_mirroring() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in9::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert_eq!(log.data_of(0x01, 0), [0x27, 0x01, 0x00]);
+        assert_eq!(log.data_of(0x11, 0), [0x03]);
+
+        log.clear();
+        block_on(epd.set_scan_mirroring(&mut spi, true, true)).unwrap();
+        assert_eq!(log.data_of(0x01, 0), [0x27, 0x01, 0x01]);
+        assert_eq!(log.data_of(0x11, 0), [0x02]);
+
+        // the columns are counted from the right and the bits are reversed
+        log.clear();
+        block_on(epd.update_partial_frame(&mut spi, &[0x01, 0x03], 8, 0, 8, 2)).unwrap();
+        assert_eq!(log.data_of(0x44, 0), [14, 14]);
+        assert_eq!(log.data_of(0x45, 0), [0, 0, 1, 0]);
+        assert_eq!(log.data_of(0x4E, 0), [14]);
+        assert_eq!(log.data_of(0x24, 0), [0x80, 0xC0]);
+
+        // kept across the reset of the wake up
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x01, 0), [0x27, 0x01, 0x01]);
+        assert_eq!(log.data_of(0x11, 0), [0x02]);
+    }
+
+    #[test]
+    fn border_flash_suppressed() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in9::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert!(!log.commands().contains(&0x3C));
+
+        // the border is held at VCOM around the activation and restored afterwards
+        epd.set_suppress_border_flash(true);
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        let commands = log.commands();
+        let position = |command, n| {
+            commands
+                .iter()
+                .enumerate()
+                .filter(|&(_, &sent)| sent == command)
+                .nth(n)
+                .unwrap()
+                .0
+        };
+        assert!(position(0x3C, 0) < position(0x20, 0));
+        assert!(position(0x20, 0) < position(0x3C, 1));
+        assert_eq!(log.data_of(0x3C, 0), [0x80]);
+        assert_eq!(log.data_of(0x3C, 1), [0x03]);
+
+        // the quick refresh leaves the border alone
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Quick))).unwrap();
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert!(!log.commands().contains(&0x3C));
+    }
+
+    #[test]
+    fn border_color() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in9::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert!(!log.commands().contains(&0x3C));
+
+        log.clear();
+        block_on(epd.set_border_color(&mut spi, Color::Black)).unwrap();
+        assert_eq!(log.commands(), [0x3C]);
+        assert_eq!(log.data_of(0x3C, 0), [0x00]);
+
+        // kept across the reset of the wake up
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x3C, 0), [0x00]);
+
+        log.clear();
+        block_on(epd.set_border_color(&mut spi, Color::White)).unwrap();
+        assert_eq!(log.data_of(0x3C, 0), [0x03]);
+    }
+
+    #[test]
+    fn update_mode() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in9::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x22, 0), [0xC4]);
+
+        epd.set_update_mode(Some(UpdateMode::Full));
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x22, 0), [0xC8]);
+
+        epd.set_update_mode(Some(UpdateMode::FullWithLoadLut));
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x22, 0), [0xF8]);
+    }
 }
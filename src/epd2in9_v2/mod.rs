@@ -1,5 +1,29 @@
 //! A simple Driver for the Waveshare 2.9" E-Ink Display V2 via SPI
 //!
+//! Three refresh flavors are supported:
+//!
+//! - full refresh with [WaveshareDisplay::display_frame] and `RefreshLut::Full`, using
+//!   the waveform uploaded by the host
+//! - fast refresh with [Epd2in9::display_fast], or with [WaveshareDisplay::display_frame]
+//!   after switching to `RefreshLut::Quick`. Both load the waveform of the OTP for a
+//!   fixed temperature instead of the measured one
+//! - partial refresh of about 0.3s with [QuickRefresh]: write the base image with
+//!   [QuickRefresh::update_old_frame] and show it with a full refresh, afterwards
+//!   [QuickRefresh::update_partial_new_frame] (or [WaveshareDisplay::update_partial_frame])
+//!   and [QuickRefresh::display_new_frame] only drive the changed pixels
+//!
+//! 4 level grayscale frames are drawn into a [Display2in9Gray], written with
+//! [Epd2in9::update_frame_gray4] and shown with [WaveshareDisplay::display_frame]. The
+//! driver keeps track of the loaded waveform, switching between grayscale, partial and
+//! black/white frames uploads the matching one again.
+//!
+//! # Ghosting
+//!
+//! The partial waveform doesn't shake the particles like a full refresh does, so
+//! traces of earlier images build up with every partial refresh. Do a full refresh
+//! every now and then, [Epd2in9::set_full_refresh_after] does this automatically
+//! after a number of partial refreshes.
+//!
 //! Specification: <https://www.waveshare.com/w/upload/7/79/2.9inch-e-paper-v2-specification.pdf>
 //!
 //! # Example for the 2.9 in E-Ink Display V2
@@ -22,7 +46,7 @@
 //!# let mut delay = delay::NoopDelay::new();
 //!
 //!// Setup EPD
-//!let mut epd = Epd2in9::new(&mut spi, busy_in, dc, rst, &mut None)?;
+//!let mut epd = Epd2in9::new(&mut spi, busy_in, dc, Some(rst), delay, None)?;
 //!
 //!// Use display graphics from embedded-graphics
 //!let mut display = Display2in9::default();
@@ -60,7 +84,8 @@ pub const WIDTH: u32 = 128;
 pub const HEIGHT: u32 = 296;
 /// Default Background Color (white)
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
-const IS_BUSY_LOW: bool = false;
+const BUSY_LEVEL: BusyLevel = BusyLevel::High;
+const RESET_TIMING: ResetTiming = ResetTiming::new(10_000, 2_000);
 const SINGLE_BYTE_WRITE: bool = true;
 
 const LUT_PARTIAL_2IN9: [u8; 159] = [
@@ -87,18 +112,44 @@ const WS_20_30: [u8; 159] = [
     0x44, 0x44, 0x0, 0x0, 0x0, 0x22, 0x17, 0x41, 0x0, 0x32, 0x36,
 ];
 
+const LUT_GRAY4_2IN9: [u8; 159] = [
+    0x00, 0x60, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // VS L0
+    0x20, 0x60, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // VS L1
+    0x28, 0x60, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // VS L2
+    0x2A, 0x60, 0x15, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // VS L3
+    0x00, 0x90, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // VS L4
+    0x00, 0x02, 0x00, 0x05, 0x14, 0x00, 0x00, // TP, SR, RP of Group0
+    0x1E, 0x1E, 0x00, 0x00, 0x00, 0x00, 0x01, // TP, SR, RP of Group1
+    0x00, 0x02, 0x00, 0x05, 0x14, 0x00, 0x00, // TP, SR, RP of Group2
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // TP, SR, RP of Group3
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // TP, SR, RP of Group4
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // TP, SR, RP of Group5
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // TP, SR, RP of Group6
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // TP, SR, RP of Group7
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // TP, SR, RP of Group8
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // TP, SR, RP of Group9
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // TP, SR, RP of Group10
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // TP, SR, RP of Group11
+    0x24, 0x22, 0x22, 0x22, 0x23, 0x32, 0x00, 0x00, 0x00, // FR, XON
+    0x22, 0x17, 0x41, 0xAE, 0x32, 0x28, // EOPT, VGH, VSH1, VSH2, VSL, VCOM
+];
+
 use core::fmt::{Debug, Display};
+use core::num::NonZeroU32;
 use embedded_hal::digital::{InputPin, OutputPin};
 use embedded_hal_async::{digital::Wait, spi::SpiDevice};
 
 use crate::error::ErrorKind;
-use crate::interface::DisplayInterface;
-use crate::traits::{ErrorType, InternalWiAdditions, QuickRefresh, RefreshLut, WaveshareDisplay};
+use crate::interface::{BusyLevel, DisplayDelay, DisplayInterface, ResetTiming};
+use crate::traits::{
+    ErrorType, InternalWiAdditions, PartialRefreshes, QuickRefresh, RefreshLut, WaveshareDisplay,
+};
 
 use crate::type_a::command::Command;
 
-use crate::buffer_len;
 use crate::color::Color;
+use crate::split_gray4;
+use crate::{buffer_len, quad_buffer_len};
 
 /// Display with Fullsize buffer for use with the 2in9 EPD V2
 #[cfg(feature = "graphics")]
@@ -110,18 +161,56 @@ pub type Display2in9 = crate::graphics::Display<
     Color,
 >;
 
+/// Display with a 4 level grayscale buffer for use with [Epd2in9::update_frame_gray4]
+#[cfg(feature = "graphics")]
+pub type Display2in9Gray = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { quad_buffer_len(WIDTH as usize, HEIGHT as usize) },
+    embedded_graphics_core::pixelcolor::Gray2,
+>;
+
+/// Length of a 4 level grayscale buffer, 2 bits per pixel
+pub const GRAY4_BUFFER_LEN: usize = quad_buffer_len(WIDTH as usize, HEIGHT as usize);
+
+/// Temperature the waveform of the fast refresh is loaded for
+const FAST_TEMPERATURE: u8 = 0x64;
+
+/// Bytes of a full frame
+const FRAME_SIZE: u32 = buffer_len(WIDTH as usize, HEIGHT as usize) as u32;
+/// Bytes of one row of a RAM bank
+const ROW_BYTES: usize = WIDTH as usize / 8;
+
+/// Waveform currently loaded into the controller
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Waveform {
+    /// [WS_20_30], uploaded by the host
+    Full,
+    /// The OTP waveform for [FAST_TEMPERATURE]
+    Fast,
+    /// [LUT_PARTIAL_2IN9], uploaded by the host
+    Partial,
+    /// [LUT_GRAY4_2IN9], uploaded by the host, the RAM holds a grayscale frame
+    Gray4,
+}
+
 /// Epd2in9 driver
 ///
-pub struct Epd2in9<SPI, BUSY, DC, RST> {
+pub struct Epd2in9<SPI, BUSY, DC, RST, DELAY> {
     /// SPI
-    interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
     /// Color
     background_color: Color,
     /// Refresh LUT
     refresh: RefreshLut,
+    /// Loaded waveform
+    waveform: Waveform,
+    /// Partial refreshes since the last full refresh, and when the next one is a full one
+    partial_refreshes: PartialRefreshes,
 }
 
-impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd2in9<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST> for Epd2in9<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -131,11 +220,13 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
 }
 
-impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd2in9<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Epd2in9<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -145,9 +236,10 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.interface.reset(spi, 10_000, 2_000).await?;
+        self.interface.reset().await?;
 
         self.wait_until_idle(spi).await?;
         self.interface.cmd(spi, Command::SwReset).await?;
@@ -179,25 +271,15 @@ where
         self.wait_until_idle(spi).await?;
 
         // set LUT by host
-        self.set_lut_helper(spi, &WS_20_30[0..153]).await?;
-        self.interface
-            .cmd_with_data(spi, Command::WriteLutRegisterEnd, &WS_20_30[153..154])
-            .await?;
-        self.interface
-            .cmd_with_data(spi, Command::GateDrivingVoltage, &WS_20_30[154..155])
-            .await?;
-        self.interface
-            .cmd_with_data(spi, Command::SourceDrivingVoltage, &WS_20_30[155..158])
-            .await?;
-        self.interface
-            .cmd_with_data(spi, Command::WriteVcomRegister, &WS_20_30[158..159])
-            .await?;
+        self.load_full_lut(spi).await?;
+        self.partial_refreshes.full_done();
 
         Ok(())
     }
 }
 
-impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd2in9<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in9<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -207,6 +289,7 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type DisplayColor = Color;
     fn width(&self) -> u32 {
@@ -217,19 +300,54 @@ where
         HEIGHT
     }
 
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
+
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
     async fn new(
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        delay: DELAY,
         delay_us: Option<u32>,
     ) -> Result<Self, Self::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        let interface =
+            DisplayInterface::new(Some(busy), dc, rst, delay, RESET_TIMING, delay_us, None);
 
         let mut epd = Epd2in9 {
             interface,
             background_color: DEFAULT_BACKGROUND_COLOR,
             refresh: RefreshLut::Full,
+            waveform: Waveform::Full,
+            partial_refreshes: PartialRefreshes::default(),
         };
 
         epd.init(spi).await?;
@@ -243,21 +361,29 @@ where
         self.interface
             .cmd_with_data(spi, Command::DeepSleepMode, &[0x01])
             .await?;
+        self.interface.mark_asleep();
         Ok(())
     }
 
     async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.require_reset_pin()?;
         self.init(spi).await?;
         Ok(())
     }
 
     async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        assert!(buffer.len() == FRAME_SIZE as usize);
         self.wait_until_idle(spi).await?;
+        self.leave_gray4(spi).await?;
         self.interface
             .cmd_with_data(spi, Command::WriteRam, buffer)
             .await
     }
 
+    /// Writes a part of the new frame for a partial refresh
+    ///
+    /// Same as [QuickRefresh::update_partial_new_frame], show it with
+    /// [QuickRefresh::display_new_frame].
     async fn update_partial_frame(
         &mut self,
         spi: &mut SPI,
@@ -267,26 +393,23 @@ where
         width: u32,
         height: u32,
     ) -> Result<(), Self::Error> {
-        //TODO This is copied from epd2in9 but it seems not working. Partial refresh supported by version 2?
-        self.wait_until_idle(spi).await?;
-        self.set_ram_area(spi, x, y, x + width, y + height).await?;
-        self.set_ram_counter(spi, x, y).await?;
-
-        self.interface
-            .cmd_with_data(spi, Command::WriteRam, buffer)
-            .await?;
-        Ok(())
+        self.update_partial_new_frame(spi, buffer, x, y, width, height)
+            .await
     }
 
     /// actually is the "Turn on Display" sequence
+    ///
+    /// `RefreshLut::Full` uses the waveform uploaded by the host, `RefreshLut::Quick`
+    /// the fast one of the OTP, see [Epd2in9::display_fast]. A grayscale frame is
+    /// shown with the grayscale waveform.
     async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
-        // Enable clock signal, Enable Analog, Load temperature value, DISPLAY with DISPLAY Mode 1, Disable Analog, Disable OSC
-        self.interface
-            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xC7])
-            .await?;
-        self.interface.cmd(spi, Command::MasterActivation).await?;
-        self.wait_until_idle(spi).await?;
+        if self.waveform != Waveform::Gray4 {
+            self.load_selected_lut(spi).await?;
+        }
+        // Enable clock signal, Enable Analog, DISPLAY with DISPLAY Mode 1, Disable Analog, Disable OSC
+        self.turn_on_display(spi, 0xC7).await?;
+        self.partial_refreshes.full_done();
         Ok(())
     }
 
@@ -302,18 +425,15 @@ where
 
     async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
+        self.leave_gray4(spi).await?;
 
         // clear the ram with the background color
         let color = self.background_color.get_byte_value();
 
         self.interface.cmd(spi, Command::WriteRam).await?;
-        self.interface
-            .data_x_times(spi, color, WIDTH / 8 * HEIGHT)
-            .await?;
+        self.interface.data_x_times(spi, color, FRAME_SIZE).await?;
         self.interface.cmd(spi, Command::WriteRam2).await?;
-        self.interface
-            .data_x_times(spi, color, WIDTH / 8 * HEIGHT)
-            .await
+        self.interface.data_x_times(spi, color, FRAME_SIZE).await
     }
 
     fn set_background_color(&mut self, background_color: Color) {
@@ -324,6 +444,9 @@ where
         &self.background_color
     }
 
+    /// Selects the waveform of [WaveshareDisplay::display_frame]
+    ///
+    /// The waveform is loaded with the next refresh.
     async fn set_lut(
         &mut self,
         _spi: &mut SPI,
@@ -335,12 +458,12 @@ where
         Ok(())
     }
 
-    async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
+    async fn wait_until_idle(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.wait_until_idle(BUSY_LEVEL).await
     }
 }
 
-impl<SPI, BUSY, DC, RST> Epd2in9<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> Epd2in9<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -350,7 +473,267 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
+    /// Writes `buffer` and shows it with the fast refresh
+    ///
+    /// The waveform of the OTP is loaded for a fixed temperature, which takes about
+    /// half the time of the full refresh. This doesn't change the refresh selected
+    /// with [WaveshareDisplay::set_lut].
+    pub async fn display_fast(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.wait_until_idle(spi).await?;
+        if self.waveform != Waveform::Fast {
+            self.load_temperature(spi, FAST_TEMPERATURE).await?;
+        }
+        self.update_frame(spi, buffer).await?;
+        self.turn_on_display(spi, 0xC7).await?;
+        self.partial_refreshes.full_done();
+        Ok(())
+    }
+
+    /// Writes a frame with 4 gray levels, shown by the next [WaveshareDisplay::display_frame]
+    ///
+    /// `buffer` holds 2 bits per pixel, MSB first, with `0b00` black, `0b01` dark gray,
+    /// `0b10` light gray and `0b11` white, like a [Display2in9Gray]. It has to be
+    /// [GRAY4_BUFFER_LEN] bytes long.
+    ///
+    /// The grayscale waveform is uploaded if it isn't loaded yet, the next black/white
+    /// frame loads the waveform selected with [WaveshareDisplay::set_lut] again.
+    pub async fn update_frame_gray4(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        assert!(buffer.len() == GRAY4_BUFFER_LEN);
+        self.wait_until_idle(spi).await?;
+        if self.waveform != Waveform::Gray4 {
+            self.load_gray4_lut(spi).await?;
+        }
+
+        self.write_gray4_plane(spi, Command::WriteRam, buffer, true)
+            .await?;
+        self.write_gray4_plane(spi, Command::WriteRam2, buffer, false)
+            .await
+    }
+
+    /// Measures the temperature of the panel with the sensor of the controller, in °C
+    ///
+    /// The controller answers on the data line, so it has to be connected to the MISO
+    /// of the SPI bus as well. The loaded waveform is kept.
+    pub async fn read_temperature(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<i8, <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.wait_until_idle(spi).await?;
+        // internal sensor
+        self.interface
+            .cmd_with_data(spi, Command::TemperatureSensorSelection, &[0x80])
+            .await?;
+        // Enable clock signal, load temperature value, Disable OSC
+        self.turn_on_display(spi, 0xA1).await?;
+
+        // 12 bit two's complement in 1/16 °C, the first byte holds the whole degrees
+        let mut temperature = [0u8; 2];
+        self.interface
+            .cmd_then_read(spi, Command::TemperatureSensorRead, 0, &mut temperature)
+            .await?;
+        Ok(temperature[0] as i8)
+    }
+
+    /// Replaces every `refreshes`th partial refresh with a full one, `None` disables it
+    ///
+    /// The frame written for that refresh goes into both RAM banks, so it is the base
+    /// for the following partial refreshes.
+    pub fn set_full_refresh_after(&mut self, refreshes: Option<NonZeroU32>) {
+        self.partial_refreshes.set_full_after(refreshes);
+    }
+
+    /// Partial refreshes since the last full or fast refresh
+    pub fn partial_refreshes(&self) -> u32 {
+        self.partial_refreshes.count()
+    }
+
+    /// Prepares writing a new frame, returns whether it has to go into both RAM banks
+    ///
+    /// A new frame for a partial refresh only goes into the black/white RAM and needs
+    /// the partial waveform. A forced full refresh updates the base frame as well.
+    async fn prepare_new_frame(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<bool, <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.wait_until_idle(spi).await?;
+        if self.partial_refreshes.full_due() {
+            return Ok(true);
+        }
+        if self.waveform != Waveform::Partial {
+            self.load_partial_lut(spi).await?;
+        }
+        Ok(false)
+    }
+
+    /// Writes one bit of every pixel of a grayscale frame into a RAM bank
+    ///
+    /// With the vendor waveform the black/white RAM takes the high bit and the red
+    /// RAM the low bit of a pixel.
+    async fn write_gray4_plane(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        buffer: &[u8],
+        high: bool,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.use_full_frame(spi).await?;
+        self.interface.cmd(spi, command).await?;
+
+        let mut row = [0u8; ROW_BYTES];
+        for pixels in buffer.chunks(ROW_BYTES * 2) {
+            for (byte, pixels) in row.iter_mut().zip(pixels.chunks(2)) {
+                let (high_bits, low_bits) = split_gray4(pixels);
+                *byte = if high { high_bits } else { low_bits };
+            }
+            self.interface.data(spi, &row).await?;
+        }
+        Ok(())
+    }
+
+    /// Loads the waveform selected with [WaveshareDisplay::set_lut] if it isn't loaded
+    async fn load_selected_lut(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        match self.refresh {
+            RefreshLut::Full if self.waveform != Waveform::Full => self.load_full_lut(spi).await,
+            RefreshLut::Quick if self.waveform != Waveform::Fast => {
+                self.load_temperature(spi, FAST_TEMPERATURE).await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Goes back to the selected black/white waveform after a grayscale frame
+    async fn leave_gray4(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if self.waveform == Waveform::Gray4 {
+            self.load_selected_lut(spi).await?;
+        }
+        Ok(())
+    }
+
+    async fn load_full_lut(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.set_lut_helper(spi, &WS_20_30[0..153]).await?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteLutRegisterEnd, &WS_20_30[153..154])
+            .await?;
+        self.interface
+            .cmd_with_data(spi, Command::GateDrivingVoltage, &WS_20_30[154..155])
+            .await?;
+        self.interface
+            .cmd_with_data(spi, Command::SourceDrivingVoltage, &WS_20_30[155..158])
+            .await?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteVcomRegister, &WS_20_30[158..159])
+            .await?;
+        self.waveform = Waveform::Full;
+        Ok(())
+    }
+
+    /// Uploads the grayscale waveform like the vendor driver
+    async fn load_gray4_lut(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.set_lut_helper(spi, &LUT_GRAY4_2IN9[0..153]).await?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteLutRegisterEnd, &LUT_GRAY4_2IN9[153..154])
+            .await?;
+        self.interface
+            .cmd_with_data(spi, Command::GateDrivingVoltage, &LUT_GRAY4_2IN9[154..155])
+            .await?;
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::SourceDrivingVoltage,
+                &LUT_GRAY4_2IN9[155..158],
+            )
+            .await?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteVcomRegister, &LUT_GRAY4_2IN9[158..159])
+            .await?;
+        // the border follows LUT 1
+        self.interface
+            .cmd_with_data(spi, Command::BorderWaveformControl, &[0x04])
+            .await?;
+        self.waveform = Waveform::Gray4;
+        Ok(())
+    }
+
+    /// Uploads the partial waveform like the vendor driver
+    async fn load_partial_lut(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.reset().await?;
+
+        self.set_lut_helper(spi, &LUT_PARTIAL_2IN9[0..153]).await?;
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::WriteOtpSelection,
+                &[0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00],
+            )
+            .await?;
+        // keep the border as it is during partial refreshes
+        self.interface
+            .cmd_with_data(spi, Command::BorderWaveformControl, &[0x80])
+            .await?;
+        // Enable clock signal, Enable Analog
+        self.turn_on_display(spi, 0xC0).await?;
+        self.waveform = Waveform::Partial;
+        Ok(())
+    }
+
+    /// Loads the waveform of the OTP for `temperature` instead of the measured one
+    async fn load_temperature(
+        &mut self,
+        spi: &mut SPI,
+        temperature: u8,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface
+            .cmd_with_data(spi, Command::TemperatureSensorSelection, &[0x80])
+            .await?;
+        // Enable clock signal, load temperature value
+        self.turn_on_display(spi, 0xB1).await?;
+
+        self.interface
+            .cmd_with_data(spi, Command::TemperatureSensorControl, &[temperature, 0x00])
+            .await?;
+        // Enable clock signal, load the waveform for the written temperature
+        self.turn_on_display(spi, 0x91).await?;
+        self.waveform = Waveform::Fast;
+        Ok(())
+    }
+
+    async fn turn_on_display(
+        &mut self,
+        spi: &mut SPI,
+        update: u8,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface
+            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[update])
+            .await?;
+        self.interface.cmd(spi, Command::MasterActivation).await?;
+        self.wait_until_idle(spi).await
+    }
+
     async fn use_full_frame(
         &mut self,
         spi: &mut SPI,
@@ -362,6 +745,23 @@ where
         self.set_ram_counter(spi, 0, 0).await
     }
 
+    async fn set_partial_window(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        assert!(x % 8 == 0 && width % 8 == 0);
+        assert!(width > 0 && height > 0);
+        assert!(x + width <= WIDTH && y + height <= HEIGHT);
+
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)
+            .await?;
+        self.set_ram_counter(spi, x, y).await
+    }
+
     async fn set_ram_area(
         &mut self,
         spi: &mut SPI,
@@ -371,7 +771,7 @@ where
         end_y: u32,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
         assert!(start_x < end_x);
-        assert!(start_y < end_y);
+        assert!(start_y <= end_y);
 
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
@@ -408,7 +808,7 @@ where
         // x is positioned in bytes, so the last 3 bits which show the position inside a byte in the ram
         // aren't relevant
         self.interface
-            .cmd_with_data(spi, Command::SetRamXAddressCounter, &[x as u8])
+            .cmd_with_data(spi, Command::SetRamXAddressCounter, &[(x >> 3) as u8])
             .await?;
 
         // 2 Databytes: A[7:0] & 0..A[8]
@@ -437,7 +837,8 @@ where
     }
 }
 
-impl<SPI, BUSY, DC, RST> QuickRefresh<SPI, BUSY, DC, RST> for Epd2in9<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> QuickRefresh<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in9<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -447,65 +848,62 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
-    /// To be followed immediately by `update_new_frame`.
+    /// Writes the base image into both RAM banks
+    ///
+    /// Show it with a full refresh before doing any partial ones.
     async fn update_old_frame(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        assert!(buffer.len() == FRAME_SIZE as usize);
         self.wait_until_idle(spi).await?;
+        self.leave_gray4(spi).await?;
+        self.use_full_frame(spi).await?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam, buffer)
+            .await?;
+        self.use_full_frame(spi).await?;
         self.interface
             .cmd_with_data(spi, Command::WriteRam2, buffer)
             .await
     }
 
-    /// To be used immediately after `update_old_frame`.
+    /// Writes the new image, to be shown with [QuickRefresh::display_new_frame]
     async fn update_new_frame(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        self.wait_until_idle(spi).await?;
-        self.interface.reset(spi, 10_000, 2_000).await?;
-
-        self.set_lut_helper(spi, &LUT_PARTIAL_2IN9).await?;
-        self.interface
-            .cmd_with_data(
-                spi,
-                Command::WriteOtpSelection,
-                &[0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00],
-            )
-            .await?;
-        self.interface
-            .cmd_with_data(spi, Command::BorderWaveformControl, &[0x80])
-            .await?;
-        self.interface
-            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xC0])
-            .await?;
-        self.interface.cmd(spi, Command::MasterActivation).await?;
-
-        self.wait_until_idle(spi).await?;
-
-        self.use_full_frame(spi).await?;
-
-        self.interface
-            .cmd_with_data(spi, Command::WriteRam, buffer)
-            .await?;
-        Ok(())
+        self.update_partial_new_frame(spi, buffer, 0, 0, WIDTH, HEIGHT)
+            .await
     }
 
-    /// For a quick refresh of the new updated frame. To be used immediately after `update_new_frame`
+    /// Partial refresh, only the pixels differing from the old frame are driven
+    ///
+    /// The controller takes the new frame as old frame for the next partial refresh.
+    /// Once the limit set with [Epd2in9::set_full_refresh_after] is reached, this is a
+    /// full refresh instead.
     async fn display_new_frame(
         &mut self,
         spi: &mut SPI,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if self.partial_refreshes.full_due() {
+            self.wait_until_idle(spi).await?;
+            if self.waveform != Waveform::Full {
+                self.load_full_lut(spi).await?;
+            }
+            self.turn_on_display(spi, 0xC7).await?;
+            self.partial_refreshes.full_done();
+            return Ok(());
+        }
+
         self.wait_until_idle(spi).await?;
-        self.interface
-            .cmd_with_data(spi, Command::DisplayUpdateControl2, &[0x0F])
-            .await?;
-        self.interface.cmd(spi, Command::MasterActivation).await?;
-        self.wait_until_idle(spi).await?;
+        // Enable clock signal, Enable Analog, DISPLAY with DISPLAY Mode 2, Disable Analog, Disable OSC
+        self.turn_on_display(spi, 0x0F).await?;
+        self.partial_refreshes.partial_done();
         Ok(())
     }
 
@@ -520,8 +918,7 @@ where
         Ok(())
     }
 
-    /// Partial quick refresh not supported yet
-    #[allow(unused)]
+    /// Writes a part of the base image into both RAM banks
     async fn update_partial_old_frame(
         &mut self,
         spi: &mut SPI,
@@ -531,12 +928,22 @@ where
         width: u32,
         height: u32,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        //TODO supported by display?
-        unimplemented!()
+        assert!(buffer.len() == buffer_len(width as usize, height as usize));
+        self.wait_until_idle(spi).await?;
+        self.leave_gray4(spi).await?;
+
+        self.set_partial_window(spi, x, y, width, height).await?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam, buffer)
+            .await?;
+        self.set_partial_window(spi, x, y, width, height).await?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam2, buffer)
+            .await?;
+        self.use_full_frame(spi).await
     }
 
-    /// Partial quick refresh not supported yet
-    #[allow(unused)]
+    /// Writes a part of the new image, to be shown with [QuickRefresh::display_new_frame]
     async fn update_partial_new_frame(
         &mut self,
         spi: &mut SPI,
@@ -546,12 +953,23 @@ where
         width: u32,
         height: u32,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        //TODO supported by display?
-        unimplemented!()
+        assert!(buffer.len() == buffer_len(width as usize, height as usize));
+        let both_rams = self.prepare_new_frame(spi).await?;
+
+        self.set_partial_window(spi, x, y, width, height).await?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam, buffer)
+            .await?;
+        if both_rams {
+            self.set_partial_window(spi, x, y, width, height).await?;
+            self.interface
+                .cmd_with_data(spi, Command::WriteRam2, buffer)
+                .await?;
+        }
+        self.use_full_frame(spi).await
     }
 
-    /// Partial quick refresh not supported yet
-    #[allow(unused)]
+    /// Fills a part of the new image with the background color
     async fn clear_partial_frame(
         &mut self,
         spi: &mut SPI,
@@ -560,19 +978,182 @@ where
         width: u32,
         height: u32,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        //TODO supported by display?
-        unimplemented!()
+        let color = self.background_color.get_byte_value();
+        let both_rams = self.prepare_new_frame(spi).await?;
+
+        let bytes = buffer_len(width as usize, height as usize) as u32;
+
+        self.set_partial_window(spi, x, y, width, height).await?;
+        self.interface.cmd(spi, Command::WriteRam).await?;
+        self.interface.data_x_times(spi, color, bytes).await?;
+        if both_rams {
+            self.set_partial_window(spi, x, y, width, height).await?;
+            self.interface.cmd(spi, Command::WriteRam2).await?;
+            self.interface.data_x_times(spi, color, bytes).await?;
+        }
+        self.use_full_frame(spi).await
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::{block_on, mocks};
 
     #[test]
     fn epd_size() {
         assert_eq!(WIDTH, 128);
         assert_eq!(HEIGHT, 296);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+        assert_eq!(FRAME_SIZE, 16 * 296);
+    }
+
+    #[test]
+    fn fast_refresh() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in9::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        let frame = [0xFF; FRAME_SIZE as usize];
+        block_on(epd.display_fast(&mut spi, &frame)).unwrap();
+        assert_eq!(log.data_of(0x18, 0), [0x80]);
+        assert_eq!(log.data_of(0x1A, 0), [FAST_TEMPERATURE, 0x00]);
+        assert_eq!(log.data_of(0x22, 1), [0x91]);
+        assert_eq!(log.data_of(0x24, 0).len(), FRAME_SIZE as usize);
+        assert_eq!(log.data_of(0x22, 2), [0xC7]);
+
+        // the next full refresh uploads the host waveform again
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x32, 0), WS_20_30[..153]);
+        assert_eq!(log.data_of(0x22, 0), [0xC7]);
+    }
+
+    #[test]
+    fn partial_refresh() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in9::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        let digit = [0x00; 2 * 16];
+        block_on(epd.update_partial_frame(&mut spi, &digit, 16, 100, 16, 16)).unwrap();
+        assert_eq!(log.data_of(0x32, 0), LUT_PARTIAL_2IN9[..153]);
+        assert_eq!(log.data_of(0x3C, 0), [0x80]);
+        assert_eq!(log.data_of(0x44, 0), [2, 3]);
+        assert_eq!(log.data_of(0x45, 0), [100, 0, 115, 0]);
+        assert_eq!(log.data_of(0x4E, 0), [2]);
+        assert_eq!(log.data_of(0x24, 0).len(), digit.len());
+        assert!(!log.commands().contains(&0x26));
+        // the window is reset to the full frame afterwards
+        assert_eq!(log.data_of(0x44, 1), [0, 15]);
+
+        log.clear();
+        block_on(epd.display_new_frame(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x22, 0), [0x0F]);
+        assert_eq!(epd.partial_refreshes(), 1);
+
+        // the partial waveform stays loaded
+        log.clear();
+        block_on(epd.update_partial_frame(&mut spi, &digit, 16, 100, 16, 16)).unwrap();
+        assert!(!log.commands().contains(&0x32));
+    }
+
+    #[test]
+    fn full_refresh_after_partials() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in9::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        epd.set_full_refresh_after(NonZeroU32::new(3));
+
+        let frame = [0xFF; FRAME_SIZE as usize];
+        for _ in 0..2 {
+            block_on(epd.update_and_display_new_frame(&mut spi, &frame)).unwrap();
+        }
+        assert_eq!(epd.partial_refreshes(), 2);
+
+        // the third one is a full refresh, which updates the base frame as well
+        log.clear();
+        block_on(epd.update_and_display_new_frame(&mut spi, &frame)).unwrap();
+        assert_eq!(log.data_of(0x24, 0).len(), FRAME_SIZE as usize);
+        assert_eq!(log.data_of(0x26, 0).len(), FRAME_SIZE as usize);
+        assert_eq!(log.data_of(0x32, 0), WS_20_30[..153]);
+        assert_eq!(log.data_of(0x22, 0), [0xC7]);
+        assert_eq!(epd.partial_refreshes(), 0);
+
+        log.clear();
+        block_on(epd.update_and_display_new_frame(&mut spi, &frame)).unwrap();
+        assert_eq!(log.data_of(0x22, 1), [0x0F]);
+        assert_eq!(epd.partial_refreshes(), 1);
+    }
+
+    #[test]
+    fn gray4_planes() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in9::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert_eq!(GRAY4_BUFFER_LEN, 9472);
+
+        // black, dark gray, light gray and white at the start of the first row
+        let mut buffer = [0xFF; GRAY4_BUFFER_LEN];
+        buffer[0] = 0b00_01_10_11;
+
+        log.clear();
+        block_on(epd.update_frame_gray4(&mut spi, &buffer)).unwrap();
+        assert_eq!(log.data_of(0x32, 0), LUT_GRAY4_2IN9[..153]);
+        assert_eq!(log.data_of(0x2C, 0), [0x28]);
+
+        let bw = log.data_of(0x24, 0);
+        assert_eq!(bw.len(), FRAME_SIZE as usize);
+        assert_eq!(bw[0], 0b0011_1111);
+        let red = log.data_of(0x26, 0);
+        assert_eq!(red.len(), FRAME_SIZE as usize);
+        assert_eq!(red[0], 0b0101_1111);
+
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert!(!log.commands().contains(&0x32));
+        assert_eq!(log.data_of(0x22, 0), [0xC7]);
+
+        // the next black/white frame uploads the full waveform again
+        log.clear();
+        let frame = [0xFF; FRAME_SIZE as usize];
+        block_on(epd.update_frame(&mut spi, &frame)).unwrap();
+        assert_eq!(log.data_of(0x32, 0), WS_20_30[..153]);
+    }
+
+    #[test]
+    fn gray4_and_partial_waveforms() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in9::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        let digit = [0x00; 2 * 16];
+        block_on(epd.update_partial_frame(&mut spi, &digit, 16, 100, 16, 16)).unwrap();
+        block_on(epd.display_new_frame(&mut spi)).unwrap();
+
+        log.clear();
+        block_on(epd.update_frame_gray4(&mut spi, &[0xFF; GRAY4_BUFFER_LEN])).unwrap();
+        assert_eq!(log.data_of(0x32, 0), LUT_GRAY4_2IN9[..153]);
+        block_on(epd.display_frame(&mut spi)).unwrap();
+
+        log.clear();
+        block_on(epd.update_partial_frame(&mut spi, &digit, 16, 100, 16, 16)).unwrap();
+        assert_eq!(log.data_of(0x32, 0), LUT_PARTIAL_2IN9[..153]);
+
+        // the grayscale waveform stays loaded for the next grayscale frame
+        block_on(epd.update_frame_gray4(&mut spi, &[0xFF; GRAY4_BUFFER_LEN])).unwrap();
+        log.clear();
+        block_on(epd.update_frame_gray4(&mut spi, &[0xFF; GRAY4_BUFFER_LEN])).unwrap();
+        assert!(!log.commands().contains(&0x32));
+    }
+
+    #[test]
+    fn read_temperature() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in9::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        log.queue_reads(&[0x19, 0x80]);
+        assert_eq!(block_on(epd.read_temperature(&mut spi)).unwrap(), 25);
+        assert_eq!(log.commands(), [0x18, 0x22, 0x20, 0x1B]);
+        // the host waveform isn't replaced by the one of the OTP
+        assert_eq!(log.data_of(0x22, 0), [0xA1]);
     }
 }
@@ -5,9 +5,7 @@
 //!```rust, no_run
 //!# use embedded_hal_mock::eh1::*;
 //!# fn main() -> Result<(), embedded_hal::spi::ErrorKind> {
-//!use embedded_graphics::{
-//!    pixelcolor::BinaryColor::On as Black, prelude::*, primitives::{Line, PrimitiveStyle},
-//!};
+//!use embedded_graphics::{prelude::*, primitives::{Line, PrimitiveStyle}};
 //!use epd_waveshare::{epd2in9bc::*, prelude::*};
 //!#
 //!# let expectations = [];
@@ -20,32 +18,28 @@
 //!# let mut delay = delay::NoopDelay::new();
 //!
 //!// Setup EPD
-//!let mut epd = Epd2in9bc::new(&mut spi, busy_in, dc, rst, &mut None)?;
+//!let mut epd = Epd2in9bc::new(&mut spi, busy_in, dc, Some(rst), delay, None)?;
 //!
 //!// Use display graphics from embedded-graphics
-//!// This display is for the black/white pixels
-//!let mut mono_display = Display2in9bc::default();
+//!// This display is for the black/white/chromatic pixels
+//!let mut tricolor_display = Display2in9bc::default();
 //!
-//!// Use embedded graphics for drawing
-//!// A black line
+//!// Use embedded graphics for drawing a black line
 //!let _ = Line::new(Point::new(0, 120), Point::new(0, 200))
-//!    .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
-//!    .draw(&mut mono_display);
+//!    .into_styled(PrimitiveStyle::with_stroke(TriColor::Black, 1))
+//!    .draw(&mut tricolor_display);
 //!
-//!// Use a second display for red/yellow
-//!let mut chromatic_display = Display2in9bc::default();
-//!
-//!// We use `Black` but it will be shown as red/yellow
+//!// We use `Chromatic` but it will be shown as red/yellow
 //!let _ = Line::new(Point::new(15, 120), Point::new(15, 200))
-//!    .into_styled(PrimitiveStyle::with_stroke(Color::Black, 1))
-//!    .draw(&mut chromatic_display);
+//!    .into_styled(PrimitiveStyle::with_stroke(TriColor::Chromatic, 1))
+//!    .draw(&mut tricolor_display);
 //!
 //!// Display updated frame
 //!epd.update_color_frame(
 //!    &mut spi,
 //!    &mut delay,
-//!    &mono_display.buffer(),
-//!    &chromatic_display.buffer()
+//!    &tricolor_display.bw_buffer(),
+//!    &tricolor_display.chromatic_buffer()
 //!)?;
 //!epd.display_frame(&mut spi, &mut delay)?;
 //!
@@ -54,12 +48,20 @@
 //!# Ok(())
 //!# }
 //!```
+//!
+//! # Partial updates
+//!
+//! [WaveshareDisplay::update_partial_frame] writes a window of the black/white plane
+//! and [Epd2in9bc::display_partial_frame] also refreshes only that window, the
+//! chromatic plane is left as it is. Partial refreshes don't drive the chromatic
+//! particles, so do a full refresh with [WaveshareDisplay::display_frame] every now
+//! and then.
 use core::fmt::{Debug, Display};
 use embedded_hal::digital::{InputPin, OutputPin};
 use embedded_hal_async::{digital::Wait, spi::SpiDevice};
 
 use crate::error::ErrorKind;
-use crate::interface::DisplayInterface;
+use crate::interface::{BusyLevel, DisplayDelay, DisplayInterface, ResetTiming};
 use crate::traits::{
     ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
 };
@@ -73,13 +75,16 @@ pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 
 const NUM_DISPLAY_BITS: u32 = WIDTH / 8 * HEIGHT;
 
-const IS_BUSY_LOW: bool = true;
+const BUSY_LEVEL: BusyLevel = BusyLevel::Low;
+const RESET_TIMING: ResetTiming = ResetTiming::new(10_000, 10_000);
 const VCOM_DATA_INTERVAL: u8 = 0x07;
 const WHITE_BORDER: u8 = 0x70;
 const BLACK_BORDER: u8 = 0x30;
 const CHROMATIC_BORDER: u8 = 0xb0;
 const FLOATING_BORDER: u8 = 0xF0;
 const SINGLE_BYTE_WRITE: bool = true;
+/// Chromatic plane byte of the controller without any chromatic pixel
+const NO_CHROMATIC: u8 = 0xFF;
 
 use crate::color::{Color, TriColor};
 
@@ -88,23 +93,23 @@ use self::command::Command;
 use crate::buffer_len;
 
 /// Full size buffer for use with the 2in9b/c EPD
-/// TODO this should be a TriColor, but let's keep it as is at first
 #[cfg(feature = "graphics")]
 pub type Display2in9bc = crate::graphics::Display<
     WIDTH,
     HEIGHT,
-    false,
-    { buffer_len(WIDTH as usize, HEIGHT as usize) },
-    Color,
+    true,
+    { buffer_len(WIDTH as usize, HEIGHT as usize * 2) },
+    TriColor,
 >;
 
 /// Epd2in9bc driver
-pub struct Epd2in9bc<SPI, BUSY, DC, RST> {
-    interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
+pub struct Epd2in9bc<SPI, BUSY, DC, RST, DELAY> {
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
     color: Color,
 }
 
-impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd2in9bc<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST>
+    for Epd2in9bc<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -114,11 +119,13 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
 }
 
-impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd2in9bc<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Epd2in9bc<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -128,11 +135,12 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         // Values taken from datasheet and sample code
 
-        self.interface.reset(spi, 10_000, 10_000).await?;
+        self.interface.reset().await?;
 
         // start the booster
         self.interface
@@ -141,7 +149,7 @@ where
 
         // power on
         self.command(spi, Command::PowerOn).await?;
-        self.interface.delay(spi, 5000).await?;
+        self.interface.delay(5000).await;
         self.wait_until_idle(spi).await?;
 
         // set the panel settings
@@ -167,8 +175,8 @@ where
     }
 }
 
-impl<SPI, BUSY, DC, RST> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST>
-    for Epd2in9bc<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in9bc<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -178,6 +186,7 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn update_color_frame(
         &mut self,
@@ -206,7 +215,7 @@ where
 
     /// Update only chromatic data of the display.
     ///
-    /// This data takes precedence over the black/white data.
+    /// This data takes precedence over the black/white data, set bits are chromatic.
     async fn update_chromatic_frame(
         &mut self,
         spi: &mut SPI,
@@ -215,14 +224,46 @@ where
         self.interface
             .cmd(spi, Command::DataStartTransmission2)
             .await?;
-        self.interface.data(spi, chromatic).await?;
+        // The controller shows cleared bits as chromatic
+        for byte in chromatic {
+            self.interface.data(spi, &[!byte]).await?;
+        }
 
         self.wait_until_idle(spi).await?;
         Ok(())
     }
+
+    async fn clear_achromatic_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.send_resolution(spi).await?;
+
+        let color = DEFAULT_BACKGROUND_COLOR.get_byte_value();
+        self.interface
+            .cmd(spi, Command::DataStartTransmission1)
+            .await?;
+        self.interface
+            .data_x_times(spi, color, NUM_DISPLAY_BITS)
+            .await?;
+
+        self.wait_until_idle(spi).await
+    }
+
+    async fn clear_chromatic_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.send_resolution(spi).await?;
+
+        // Cleared bits are chromatic
+        self.interface
+            .cmd(spi, Command::DataStartTransmission2)
+            .await?;
+        self.interface
+            .data_x_times(spi, NO_CHROMATIC, NUM_DISPLAY_BITS)
+            .await?;
+
+        self.wait_until_idle(spi).await
+    }
 }
 
-impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd2in9bc<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in9bc<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -232,16 +273,19 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type DisplayColor = Color;
     async fn new(
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        delay: DELAY,
         delay_us: Option<u32>,
     ) -> Result<Self, Self::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        let interface =
+            DisplayInterface::new(Some(busy), dc, rst, delay, RESET_TIMING, delay_us, None);
         let color = DEFAULT_BACKGROUND_COLOR;
 
         let mut epd = Epd2in9bc { interface, color };
@@ -265,10 +309,13 @@ where
         // The example STM code from Github has a wait after PowerOff
         self.wait_until_idle(spi).await?;
 
-        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5]).await
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5]).await?;
+        self.interface.mark_asleep();
+        Ok(())
     }
 
     async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.require_reset_pin()?;
         self.init(spi).await
     }
 
@@ -288,6 +335,37 @@ where
         HEIGHT
     }
 
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
+
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
     async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
         self.interface
             .cmd(spi, Command::DataStartTransmission1)
@@ -295,20 +373,23 @@ where
 
         self.interface.data(spi, buffer).await?;
 
-        // Clear the chromatic layer
-        let color = self.color.get_byte_value();
-
+        // Clear the chromatic layer, cleared bits are chromatic
         self.interface
             .cmd(spi, Command::DataStartTransmission2)
             .await?;
         self.interface
-            .data_x_times(spi, color, NUM_DISPLAY_BITS)
+            .data_x_times(spi, NO_CHROMATIC, NUM_DISPLAY_BITS)
             .await?;
 
         self.wait_until_idle(spi).await
     }
 
-    #[allow(unused)]
+    /// Writes `buffer` into a window of the black/white plane
+    ///
+    /// `x` and `width` are rounded out to byte boundaries, `buffer` has to cover the
+    /// rounded window. Returns [ErrorKind::InvalidWindow] if the window doesn't lie
+    /// inside the display or the buffer has a different size. Unlike
+    /// [WaveshareDisplay::update_frame] the chromatic plane is left as it is.
     async fn update_partial_frame(
         &mut self,
         spi: &mut SPI,
@@ -318,7 +399,18 @@ where
         width: u32,
         height: u32,
     ) -> Result<(), Self::Error> {
-        Ok(())
+        let (start_x, end_x) =
+            byte_aligned_window(x, y, width, height).ok_or(ErrorKind::InvalidWindow)?;
+        if buffer.len() != buffer_len((end_x - start_x) as usize, height as usize) {
+            return Err(ErrorKind::InvalidWindow);
+        }
+
+        self.command(spi, Command::PartialIn).await?;
+        self.set_partial_window(spi, start_x, end_x, y, height)
+            .await?;
+        self.cmd_with_data(spi, Command::DataStartTransmission1, buffer)
+            .await?;
+        self.command(spi, Command::PartialOut).await
     }
 
     async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
@@ -337,28 +429,8 @@ where
     }
 
     async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.send_resolution(spi).await?;
-
-        let color = DEFAULT_BACKGROUND_COLOR.get_byte_value();
-
-        // Clear the black
-        self.interface
-            .cmd(spi, Command::DataStartTransmission1)
-            .await?;
-
-        self.interface
-            .data_x_times(spi, color, NUM_DISPLAY_BITS)
-            .await?;
-
-        // Clear the chromatic
-        self.interface
-            .cmd(spi, Command::DataStartTransmission2)
-            .await?;
-        self.interface
-            .data_x_times(spi, color, NUM_DISPLAY_BITS)
-            .await?;
-
-        self.wait_until_idle(spi).await
+        self.clear_achromatic_frame(spi).await?;
+        self.clear_chromatic_frame(spi).await
     }
 
     async fn set_lut(
@@ -369,12 +441,12 @@ where
         Ok(())
     }
 
-    async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
+    async fn wait_until_idle(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.wait_until_idle(BUSY_LEVEL).await
     }
 }
 
-impl<SPI, BUSY, DC, RST> Epd2in9bc<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> Epd2in9bc<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -384,6 +456,7 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn command(
         &mut self,
@@ -424,6 +497,65 @@ where
         self.send_data(spi, &[h as u8]).await
     }
 
+    /// Writes a window of the black/white plane and only refreshes that window
+    ///
+    /// The window is handled like in [WaveshareDisplay::update_partial_frame], the
+    /// chromatic plane is left as it is.
+    pub async fn display_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let (start_x, end_x) =
+            byte_aligned_window(x, y, width, height).ok_or(ErrorKind::InvalidWindow)?;
+        if buffer.len() != buffer_len((end_x - start_x) as usize, height as usize) {
+            return Err(ErrorKind::InvalidWindow);
+        }
+
+        self.command(spi, Command::PartialIn).await?;
+        self.set_partial_window(spi, start_x, end_x, y, height)
+            .await?;
+        self.cmd_with_data(spi, Command::DataStartTransmission1, buffer)
+            .await?;
+        self.command(spi, Command::DisplayRefresh).await?;
+        self.wait_until_idle(spi).await?;
+        self.command(spi, Command::PartialOut).await
+    }
+
+    async fn set_partial_window(
+        &mut self,
+        spi: &mut SPI,
+        start_x: u32,
+        end_x: u32,
+        y: u32,
+        height: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        // the lower 3 bits of the horizontal start are ignored, the ones of the end have to be set
+        let last_x = end_x - 1;
+        let last_y = y + height - 1;
+        // Gates scan both inside and outside of the partial window (default)
+        let pt_scan = 0x01;
+
+        self.cmd_with_data(
+            spi,
+            Command::PartialWindow,
+            &[
+                start_x as u8 & 0xF8,
+                last_x as u8 | 0x07,
+                (y >> 8) as u8,
+                y as u8,
+                (last_y >> 8) as u8,
+                last_y as u8,
+                pt_scan,
+            ],
+        )
+        .await
+    }
+
     /// Set the outer border of the display to the chosen color.
     pub async fn set_border_color(
         &mut self,
@@ -443,3 +575,44 @@ where
         .await
     }
 }
+
+/// Rounds the window out to byte boundaries, returns its first and one past its last column
+///
+/// Returns `None` if the window is empty or doesn't lie inside the display.
+fn byte_aligned_window(x: u32, y: u32, width: u32, height: u32) -> Option<(u32, u32)> {
+    if width == 0 || height == 0 || x + width > WIDTH || y + height > HEIGHT {
+        return None;
+    }
+    Some((x / 8 * 8, (x + width).div_ceil(8) * 8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{block_on, mocks};
+
+    #[test]
+    fn partial_black_plane() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in9bc::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        // x = 4..20 is rounded out to 0..24, y = 256..271 needs the high byte
+        log.clear();
+        let readout = [0x00; 3 * 16];
+        block_on(epd.update_partial_frame(&mut spi, &readout, 4, 256, 16, 16)).unwrap();
+        assert_eq!(log.commands(), [0x91, 0x90, 0x10, 0x92]);
+        assert_eq!(
+            log.data_of(0x90, 0),
+            [0x00, 0x17, 0x01, 0x00, 0x01, 0x0F, 0x01]
+        );
+        assert_eq!(log.data_of(0x10, 0), readout);
+
+        // the chromatic plane isn't written
+        log.clear();
+        block_on(epd.display_partial_frame(&mut spi, &readout, 4, 256, 16, 16)).unwrap();
+        assert_eq!(log.commands(), [0x91, 0x90, 0x10, 0x12, 0x92]);
+
+        let wrong_size = block_on(epd.display_partial_frame(&mut spi, &readout, 8, 256, 16, 16));
+        assert!(matches!(wrong_size, Err(ErrorKind::InvalidWindow)));
+    }
+}
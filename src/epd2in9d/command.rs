@@ -22,8 +22,7 @@ pub(crate) enum Command {
     PowerOn = 0x04,
     /// This command enables the internal bandgap, which will be cleared by the next POF.
     PowerOnMeasure = 0x05,
-    /// Starting data transmission
-    ///     3-times: self.send_data(0x17)?; //07 0f 17 1f 27 2F 37 2f
+    /// Sets the soft start period, driving strength and minimum off time of the booster phases
     BoosterSoftStart = 0x06,
     /// After this command is transmitted, the chip would enter the deep-sleep mode to save power.
     ///
@@ -101,12 +100,12 @@ pub(crate) enum Command {
     // GsstSetting = 0x65,
     /// The LUT_REV / Chip Revision is read from OTP address = 0x001.
     ///
-    /// Doesn't work! Waveshare doesn't connect the read pin
-    // Revision = 0x70,
+    /// Needs the data line on the MISO, Waveshare doesn't connect the read pin
+    Revision = 0x70,
     /// Read Flags. This command reads the IC status
     /// PTL, I2C_ERR, I2C_BUSY, DATA, PON, POF, BUSY
     ///
-    /// Doesn't work! Waveshare doesn't connect the read pin
+    /// Needs the data line on the MISO, Waveshare doesn't connect the read pin
     GetStatus = 0x71,
     /// Automatically measure VCOM. This command reads the IC status
     AutoMeasurementVcom = 0x80,
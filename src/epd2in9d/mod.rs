@@ -4,6 +4,15 @@
 //! 参考[Waveshare](https://www.waveshare.net/wiki/2.9inch_e-Paper_HAT_%28D%29)的文档/例程进行构建
 //!
 //! Specification: https://www.waveshare.net/w/upload/b/b5/2.9inch_e-Paper_%28D%29_Specification.pdf
+//!
+//! The UC8151D uses the waveform of its OTP until [WaveshareDisplay::set_lut] is called,
+//! which uploads the full (`RefreshLut::Full`) or the partial (`RefreshLut::Quick`)
+//! waveform tables of the Waveshare examples.
+//!
+//! [WaveshareDisplay::update_partial_frame] writes a window of the display with the
+//! `PartialIn`/`PartialWindow` commands, the next [WaveshareDisplay::display_frame]
+//! refreshes it and sends `PartialOut`. The controller addresses x in bytes, so the
+//! window is widened to the byte boundaries around `x` and `x + width`.
 
 use core::fmt::{Debug, Display};
 use core::slice::from_raw_parts;
@@ -11,8 +20,14 @@ use core::slice::from_raw_parts;
 use embedded_hal::digital::{InputPin, OutputPin};
 use embedded_hal_async::{digital::Wait, spi::SpiDevice};
 
+use crate::booster::BoosterSoftStart;
+use crate::pll::FrameRate;
 use crate::traits::{InternalWiAdditions, RefreshLut, WaveshareDisplay};
-use crate::{interface::DisplayInterface, prelude::ErrorKind, traits::ErrorType};
+use crate::{
+    interface::{BusyLevel, DisplayDelay, DisplayInterface, ResetTiming},
+    prelude::ErrorKind,
+    traits::ErrorType,
+};
 
 //The Lookup Tables for the Display
 mod constants;
@@ -27,7 +42,15 @@ pub const HEIGHT: u32 = 296;
 pub const EPD_ARRAY: u32 = 4736;
 /// Default Background Color (white)
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::Black;
-const IS_BUSY_LOW: bool = false;
+/// Booster soft start of the partial refresh setup if none is set, phase C2 isn't sent
+pub const DEFAULT_BOOSTER_SOFT_START: BoosterSoftStart =
+    BoosterSoftStart::from_bytes(0x17, 0x17, 0x17, 0x17);
+/// Frame rate of the partial refresh setup if none is set
+pub const DEFAULT_FRAME_RATE: FrameRate = FrameRate::Hz50;
+const BUSY_LEVEL: BusyLevel = BusyLevel::High;
+const RESET_TIMING: ResetTiming = ResetTiming::new(10_000, 2_000);
+/// S2G and G2S non-overlap periods of 12 (reset value)
+const DEFAULT_TCON: u8 = 0x22;
 const SINGLE_BYTE_WRITE: bool = true;
 
 use crate::color::Color;
@@ -48,9 +71,9 @@ pub type Display2in9d = crate::graphics::Display<
 
 /// Epd2in9d driver
 ///
-pub struct Epd2in9d<'a, SPI, BUSY, DC, RST> {
+pub struct Epd2in9d<'a, SPI, BUSY, DC, RST, DELAY> {
     /// SPI
-    interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
     /// Color
     // background_color: Color,
     color: Color,
@@ -60,9 +83,16 @@ pub struct Epd2in9d<'a, SPI, BUSY, DC, RST> {
     old_data: &'a [u8],
     // 标记是否局刷的状态
     is_partial_refresh: bool,
+    /// Booster soft start set with [Epd2in9d::set_booster_soft_start]
+    booster: Option<BoosterSoftStart>,
+    /// Non-overlap periods set with [Epd2in9d::set_tcon]
+    tcon: u8,
+    /// Frame rate set with [Epd2in9d::set_frame_rate]
+    frame_rate: Option<FrameRate>,
 }
 
-impl<'a, SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd2in9d<'a, SPI, BUSY, DC, RST>
+impl<'a, SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST>
+    for Epd2in9d<'a, SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -72,12 +102,13 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
 }
 
-impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST>
-    for Epd2in9d<'_, SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Epd2in9d<'_, SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -87,9 +118,10 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.interface.reset(spi, 10_000, 2_000).await?;
+        self.interface.reset().await?;
 
         //panel setting
         //LUT from OTP，KW-BF   KWR-AF	BWROTP 0f	BWOTP 1f
@@ -97,6 +129,21 @@ where
             .cmd_with_data(spi, Command::PanelSetting, &[0x1f, 0x0D])
             .await?;
 
+        // the reset value is used until one is set
+        if let Some(booster) = self.booster {
+            self.send_booster_soft_start(spi, booster).await?;
+        }
+        if self.tcon != DEFAULT_TCON {
+            self.interface
+                .cmd_with_data(spi, Command::TconSetting, &[self.tcon])
+                .await?;
+        }
+        if let Some(frame_rate) = self.frame_rate {
+            self.interface
+                .cmd_with_data(spi, Command::PllControl, &[frame_rate.uc8151_byte()])
+                .await?;
+        }
+
         //resolution setting
         self.interface
             .cmd_with_data(spi, Command::ResolutionSetting, &[0x80, 0x01, 0x28])
@@ -114,7 +161,8 @@ where
     }
 }
 
-impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd2in9d<'_, SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd2in9d<'_, SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -124,16 +172,19 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type DisplayColor = Color;
     async fn new(
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        delay: DELAY,
         delay_us: Option<u32>,
     ) -> Result<Self, Self::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        let interface =
+            DisplayInterface::new(Some(busy), dc, rst, delay, RESET_TIMING, delay_us, None);
         let color = DEFAULT_BACKGROUND_COLOR;
         let old_data: &[u8] = &[];
         let is_partial_refresh = false;
@@ -144,6 +195,9 @@ where
             refresh: RefreshLut::Full,
             old_data,
             is_partial_refresh,
+            booster: None,
+            tcon: DEFAULT_TCON,
+            frame_rate: None,
         };
 
         epd.init(spi).await?;
@@ -158,15 +212,17 @@ where
             .await?;
         self.interface.cmd(spi, Command::PowerOff).await?;
         self.wait_until_idle(spi).await?;
-        self.interface.delay(spi, 100_000).await?;
+        self.interface.delay(100_000).await;
         self.interface
             .cmd_with_data(spi, Command::DeepSleep, &[0xA5])
             .await?;
+        self.interface.mark_asleep();
 
         Ok(())
     }
 
     async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.require_reset_pin()?;
         self.init(spi).await?;
         Ok(())
     }
@@ -187,12 +243,44 @@ where
         HEIGHT
     }
 
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
+
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
     // Corresponds to the Display function.
     // Used to write the data to be displayed to the screen SRAM.
     async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
         if self.is_partial_refresh {
-            // Modify local refresh status if full refresh is performed.
+            // The partial registers are still set, start over with the full refresh.
             self.is_partial_refresh = false;
+            self.init(spi).await?;
         }
         self.wait_until_idle(spi).await?;
 
@@ -219,6 +307,13 @@ where
         width: u32,
         height: u32,
     ) -> Result<(), Self::Error> {
+        // the window is addressed in bytes
+        let x_start = x & !0x07;
+        let x_end = (x + width - 1) | 0x07;
+        let y_end = y + height - 1;
+        assert!(x_end < WIDTH && y_end < HEIGHT);
+        assert!(buffer.len() as u32 == (x_end - x_start + 1) / 8 * height);
+
         if !self.is_partial_refresh {
             // Initialize only on first call
             self.set_part_reg(spi).await?;
@@ -226,24 +321,37 @@ where
         }
         self.interface.cmd(spi, Command::PartialIn).await?;
 
-        self.interface.cmd(spi, Command::PartialWindow).await?;
-        self.interface.data(spi, &[(x - x % 8) as u8]).await?;
-        self.interface
-            .data(spi, &[(((x - x % 8) + width - 1) - 1) as u8])
-            .await?;
-        self.interface.data(spi, &[(y / 256) as u8]).await?;
-        self.interface.data(spi, &[(y % 256) as u8]).await?;
+        // the last byte scans the whole gate range, not only the window
         self.interface
-            .data(spi, &[((y + height - 1) / 256) as u8])
+            .cmd_with_data(
+                spi,
+                Command::PartialWindow,
+                &[
+                    x_start as u8,
+                    x_end as u8,
+                    (y >> 8) as u8,
+                    y as u8,
+                    (y_end >> 8) as u8,
+                    y_end as u8,
+                    0x28,
+                ],
+            )
             .await?;
-        self.interface
-            .data(spi, &[((y + height - 1) % 256 - 1) as u8])
-            .await?;
-        self.interface.data(spi, &[0x28]).await?;
 
-        self.interface
-            .cmd_with_data(spi, Command::DataStartTransmission1, self.old_data)
-            .await?;
+        // The old data decides which pixels have to change. If the last frame doesn't
+        // match the window, drive every pixel by sending the inverse of the new data.
+        if self.old_data.len() == buffer.len() {
+            self.interface
+                .cmd_with_data(spi, Command::DataStartTransmission1, self.old_data)
+                .await?;
+        } else {
+            self.interface
+                .cmd(spi, Command::DataStartTransmission1)
+                .await?;
+            for byte in buffer {
+                self.interface.data(spi, &[!byte]).await?;
+            }
+        }
 
         self.interface
             .cmd_with_data(spi, Command::DataStartTransmission2, buffer)
@@ -256,8 +364,11 @@ where
     /// actually is the "Turn on Display" sequence
     async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.interface.cmd(spi, Command::DisplayRefresh).await?;
-        self.interface.delay(spi, 1_000).await?;
+        self.interface.delay(1_000).await;
         self.wait_until_idle(spi).await?;
+        if self.is_partial_refresh {
+            self.interface.cmd(spi, Command::PartialOut).await?;
+        }
         Ok(())
     }
 
@@ -295,18 +406,28 @@ where
         if let Some(refresh_lut) = refresh_rate {
             self.refresh = refresh_lut;
         }
-        self.set_lut_helper(spi, &LUT_VCOM1, &LUT_WW1, &LUT_BW1, &LUT_WB1, &LUT_BB1)
+        // LUT from the registers, B/W mode, scan up, shift right, booster on
+        self.interface
+            .cmd_with_data(spi, Command::PanelSetting, &[0xbf, 0x0D])
             .await?;
-
-        Ok(())
+        match self.refresh {
+            RefreshLut::Full => {
+                self.set_lut_helper(spi, &LUT_VCOM_DC, &LUT_WW, &LUT_BW, &LUT_WB, &LUT_BB)
+                    .await
+            }
+            RefreshLut::Quick => {
+                self.set_lut_helper(spi, &LUT_VCOM1, &LUT_WW1, &LUT_BW1, &LUT_WB1, &LUT_BB1)
+                    .await
+            }
+        }
     }
 
-    async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
+    async fn wait_until_idle(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.wait_until_idle(BUSY_LEVEL).await
     }
 }
 
-impl<SPI, BUSY, DC, RST> Epd2in9d<'_, SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> Epd2in9d<'_, SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -316,6 +437,7 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     /// Wake Up Screen
     ///
@@ -324,7 +446,7 @@ where
     // fn awaken(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
     //     // reset the device
     //     self.interface.reset(spi, 20_000, 2_000)?;
-    //     self.wait_until_idle(spi, delay)?;
+    //     self.wait_until_idle(delay)?;
 
     //     // panel setting
     //     // LUT from OTP，KW-BF   KWR-AF	BWROTP 0f	BWOTP 1f
@@ -341,13 +463,110 @@ where
     //     Ok(())
     // }
 
+    /// Sets the frame rate of the refresh with the PLL control
+    ///
+    /// Until one is set the full refresh uses the reset value and the partial refresh
+    /// setup [DEFAULT_FRAME_RATE]. It takes effect with the next refresh and is kept
+    /// across [WaveshareDisplay::wake_up].
+    pub async fn set_frame_rate(
+        &mut self,
+        spi: &mut SPI,
+        frame_rate: FrameRate,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.frame_rate = Some(frame_rate);
+        self.wait_until_idle(spi).await?;
+        self.interface
+            .cmd_with_data(spi, Command::PllControl, &[frame_rate.uc8151_byte()])
+            .await
+    }
+
+    /// Sets the soft start of the booster, for supplies which brown out while it starts
+    ///
+    /// It takes effect with the next power on and is kept across
+    /// [WaveshareDisplay::wake_up].
+    pub async fn set_booster_soft_start(
+        &mut self,
+        spi: &mut SPI,
+        booster: BoosterSoftStart,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.booster = Some(booster);
+        self.wait_until_idle(spi).await?;
+        self.send_booster_soft_start(spi, booster).await
+    }
+
+    /// Sets the source to gate (`s2g`) and gate to source (`g2s`) non-overlap periods
+    ///
+    /// Both are 4 bit values, the period is 4 × (value + 1), so the default of 2 is 12.
+    /// Longer periods help against faint vertical lines, e.g. with long FPC extenders.
+    /// Returns [ErrorKind::InvalidSetting] if a value doesn't fit into 4 bits. The
+    /// periods are kept across [WaveshareDisplay::wake_up].
+    pub async fn set_tcon(
+        &mut self,
+        spi: &mut SPI,
+        s2g: u8,
+        g2s: u8,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if s2g > 0x0F || g2s > 0x0F {
+            return Err(ErrorKind::InvalidSetting);
+        }
+        self.tcon = s2g << 4 | g2s;
+        self.wait_until_idle(spi).await?;
+        self.interface
+            .cmd_with_data(spi, Command::TconSetting, &[self.tcon])
+            .await
+    }
+
+    /// Reads the status flags of the controller (command 0x71)
+    ///
+    /// From bit 6 down to bit 0 they are PTL, I2C_ERR, I2C_BUSY, DATA, PON, POF and
+    /// BUSY_N, e.g. `status & 0x04 != 0` tells that the panel is powered on. It doesn't
+    /// wait until the controller is idle. The data line has to be connected to the MISO
+    /// of the SPI bus as well, which the Waveshare HATs don't do.
+    pub async fn status(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<u8, <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let mut status = [0u8];
+        self.interface
+            .cmd_then_read(spi, Command::GetStatus, 0, &mut status)
+            .await?;
+        Ok(status[0])
+    }
+
+    /// Reads the revision of the controller (command 0x70) into `buffer`
+    ///
+    /// The LUT revision from the OTP comes first, followed by the chip revision. How
+    /// many bytes it sends depends on the controller, see its datasheet. Like
+    /// [Epd2in9d::status] it needs the data line on the MISO.
+    pub async fn revision(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &mut [u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface
+            .cmd_then_read(spi, Command::Revision, 0, buffer)
+            .await
+    }
+
+    /// Sends the booster soft start, the UC8151D has three phases
+    async fn send_booster_soft_start(
+        &mut self,
+        spi: &mut SPI,
+        booster: BoosterSoftStart,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let bytes = booster.bytes();
+        self.interface
+            .cmd_with_data(spi, Command::BoosterSoftStart, &bytes[..3])
+            .await
+    }
+
     async fn set_part_reg(
         &mut self,
         spi: &mut SPI,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
         // Reset the EPD driver circuit
         //TODO: 这里在微雪的例程中反复刷新了3次，后面有显示问题再进行修改
-        self.interface.reset(spi, 10_000, 2_000).await?;
+        self.interface.reset().await?;
 
         // Power settings
         //TODO: The data in the document is [0x03,0x00,0x2b,0x2b,0x09].
@@ -356,20 +575,18 @@ where
             .await?;
 
         // Soft start
-        self.interface
-            .cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x17])
-            .await?;
+        let booster = self.booster.unwrap_or(DEFAULT_BOOSTER_SOFT_START);
+        self.send_booster_soft_start(spi, booster).await?;
 
         // Panel settings
         self.interface
             .cmd_with_data(spi, Command::PanelSetting, &[0xbf, 0x0D])
             .await?;
 
-        // Setting the refresh rate
-        // 3a 100HZ | 29 150Hz | 39 200HZ | 31 171HZ
-        // 3a is used in the example
+        // Setting the refresh rate, 50 Hz unless one is set
+        let frame_rate = self.frame_rate.unwrap_or(DEFAULT_FRAME_RATE);
         self.interface
-            .cmd_with_data(spi, Command::PllControl, &[0x3C])
+            .cmd_with_data(spi, Command::PllControl, &[frame_rate.uc8151_byte()])
             .await?;
 
         // Resolution Settings
@@ -382,7 +599,8 @@ where
             .cmd_with_data(spi, Command::VcmDcSetting, &[0x12])
             .await?;
 
-        self.set_lut(spi, None).await?;
+        self.set_lut_helper(spi, &LUT_VCOM1, &LUT_WW1, &LUT_BW1, &LUT_WB1, &LUT_BB1)
+            .await?;
 
         // Power on
         // self.interface.cmd_with_data(
@@ -440,3 +658,141 @@ where
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{block_on, mocks, Write};
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 128);
+        assert_eq!(HEIGHT, 296);
+        assert_eq!(EPD_ARRAY, WIDTH / 8 * HEIGHT);
+    }
+
+    #[test]
+    fn init_bytes() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        block_on(Epd2in9d::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert_eq!(
+            log.writes(),
+            [
+                Write::Command(0x00),
+                Write::Data(0x1F),
+                Write::Data(0x0D),
+                Write::Command(0x61),
+                Write::Data(0x80),
+                Write::Data(0x01),
+                Write::Data(0x28),
+                Write::Command(0x04),
+                Write::Command(0x50),
+                Write::Data(0x97),
+            ]
+        );
+    }
+
+    #[test]
+    fn frame_rate() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in9d::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert!(!log.commands().contains(&0x30));
+
+        log.clear();
+        block_on(epd.set_frame_rate(&mut spi, FrameRate::Hz150)).unwrap();
+        assert_eq!(log.data_of(0x30, 0), [0x29]);
+
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x30, 0), [0x29]);
+    }
+
+    #[test]
+    fn booster_soft_start() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in9d::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        let booster = BoosterSoftStart::from_bytes(0xC4, 0xC4, 0x0C, 0x0C);
+        log.clear();
+        block_on(epd.set_booster_soft_start(&mut spi, booster)).unwrap();
+        assert_eq!(log.data_of(0x06, 0), [0xC4, 0xC4, 0x0C]);
+
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x06, 0), [0xC4, 0xC4, 0x0C]);
+    }
+
+    #[test]
+    fn status_and_revision() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in9d::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        log.queue_reads(&[0x05]);
+        assert_eq!(block_on(epd.status(&mut spi)).unwrap(), 0x05);
+        assert_eq!(log.commands(), [0x71]);
+
+        log.clear();
+        log.queue_reads(&[0x0C, 0x01]);
+        let mut revision = [0u8; 2];
+        block_on(epd.revision(&mut spi, &mut revision)).unwrap();
+        assert_eq!(revision, [0x0C, 0x01]);
+        assert_eq!(log.commands(), [0x70]);
+    }
+
+    #[test]
+    fn tcon() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in9d::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        block_on(epd.set_tcon(&mut spi, 0x05, 0x0A)).unwrap();
+        assert_eq!(log.data_of(0x60, 0), [0x5A]);
+
+        let too_long = block_on(epd.set_tcon(&mut spi, 0x10, 0x02));
+        assert!(matches!(too_long, Err(ErrorKind::InvalidSetting)));
+
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x60, 0), [0x5A]);
+    }
+
+    #[test]
+    fn lut_selection() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in9d::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Full))).unwrap();
+        assert_eq!(log.data_of(0x00, 0), [0xBF, 0x0D]);
+        assert_eq!(log.data_of(0x20, 0), LUT_VCOM_DC);
+        assert_eq!(log.data_of(0x22, 0), LUT_BW);
+
+        log.clear();
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Quick))).unwrap();
+        assert_eq!(log.data_of(0x20, 0), LUT_VCOM1);
+        assert_eq!(log.data_of(0x22, 0), LUT_BW1);
+    }
+
+    #[test]
+    fn partial_window() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in9d::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        // x = 3..23 is widened to the bytes 0..24
+        let buffer = [0x00; 3 * 30];
+        log.clear();
+        block_on(epd.update_partial_frame(&mut spi, &buffer, 3, 10, 20, 30)).unwrap();
+        assert_eq!(
+            log.data_of(0x90, 0),
+            [0x00, 0x17, 0x00, 0x0A, 0x00, 0x27, 0x28]
+        );
+        assert_eq!(log.data_of(0x06, 0), [0x17, 0x17, 0x17]);
+        assert!(log.data_of(0x10, 0).iter().all(|&byte| byte == 0xFF));
+        assert_eq!(log.data_of(0x13, 0), buffer);
+
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(log.commands(), [0x12, 0x92]);
+    }
+}
@@ -0,0 +1,101 @@
+//! SPI Commands for the Waveshare 3.52" E-Ink Display
+
+use crate::traits;
+
+/// Epd3in52 commands for the UC8253 controller
+///
+/// Should rarely (never?) be needed directly.
+///
+/// For more infos about the addresses and what they are doing look into the PDFs.
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub(crate) enum Command {
+    /// Set Resolution, LUT selection, gate scan direction, source shift direction,
+    /// booster switch, soft reset.
+    PanelSetting = 0x00,
+
+    /// Selecting internal and external power
+    PowerSetting = 0x01,
+
+    /// After the Power Off command, the driver will power off following the Power Off
+    /// Sequence; BUSY signal will become "0".
+    PowerOff = 0x02,
+
+    /// Setting Power OFF sequence
+    PowerOffSequenceSetting = 0x03,
+
+    /// Turning On the Power
+    PowerOn = 0x04,
+
+    /// Starting data transmission
+    BoosterSoftStart = 0x06,
+
+    /// This command makes the chip enter the deep-sleep mode to save power.
+    ///
+    /// The only one parameter is a check code, the command would be excuted if check code = 0xA5.
+    DeepSleep = 0x07,
+
+    /// Writes the "OLD" data to SRAM.
+    DataStartTransmission1 = 0x10,
+
+    /// Refreshes the display with the data in SRAM and the current LUT.
+    DisplayRefresh = 0x12,
+
+    /// Writes the "NEW" data to SRAM.
+    DataStartTransmission2 = 0x13,
+
+    /// Runs power on, display refresh and power off in one go.
+    ///
+    /// The only one parameter is a check code, the sequence is only started if check code = 0xA5.
+    AutoSequence = 0x17,
+
+    /// VCOM Look-Up Table
+    LutForVcom = 0x20,
+    /// White-to-White Look-Up Table
+    LutWhiteToWhite = 0x21,
+    /// Black-to-White Look-Up Table
+    LutBlackToWhite = 0x22,
+    /// White-to-Black Look-Up Table
+    LutWhiteToBlack = 0x23,
+    /// Black-to-Black Look-Up Table
+    LutBlackToBlack = 0x24,
+
+    /// The command controls the PLL clock frequency.
+    PllControl = 0x30,
+
+    /// This command indicates the interval of Vcom and data output.
+    VcomAndDataIntervalSetting = 0x50,
+
+    /// This command defines non-overlap period of Gate and Source.
+    TconSetting = 0x60,
+
+    /// This command defines alternative resolution and this setting is of higher priority
+    /// than the RES\[1:0\] in R00H (PSR).
+    ResolutionSetting = 0x61,
+
+    /// Set VCM_DC
+    VcmDcSetting = 0x82,
+
+    /// This command is set for saving power during refresh period.
+    PowerSaving = 0xE3,
+}
+
+impl traits::Command for Command {
+    /// Returns the address of the command
+    fn address(self) -> u8 {
+        self as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn command_addr() {
+        assert_eq!(Command::PanelSetting.address(), 0x00);
+        assert_eq!(Command::AutoSequence.address(), 0x17);
+        assert_eq!(Command::LutBlackToBlack.address(), 0x24);
+    }
+}
@@ -53,6 +53,9 @@ pub(crate) enum Command {
     DisplayUpdateSequenceSetting = 0x22,
     /// This command will transfer its data to B/W RAM, until another command is written
     WriteRam = 0x24,
+    /// This command will transfer its data to the second RAM, which holds the high bit of
+    /// 4 level grayscale frames
+    WriteRam2 = 0x26,
     /// This command writes VCOM register from MCU interface
     WriteVcomRegister = 0x2C,
     /// This command writes LUT register from MCU interface (105 bytes),
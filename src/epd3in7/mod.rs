@@ -1,5 +1,13 @@
 //! A simple Driver for the Waveshare 3.7" E-Ink Display via SPI
 //!
+//! Besides black/white frames the panel shows 4 gray levels. Use a [Display3in7Gray]
+//! and [Epd3in7::update_frame_gray4] for them, the 2 bits of a pixel are split into the
+//! two RAMs of the controller and the grayscale waveform is uploaded.
+//!
+//! `RefreshLut::Quick` loads the fast black/white waveform (A2), e.g. for page turns or
+//! small regions updated with [update_partial_frame](WaveshareDisplay::update_partial_frame).
+//! It doesn't clear the display, so ghosting builds up until the next refresh with
+//! `RefreshLut::Full` (GC). Grayscale frames are refused while A2 is loaded.
 //!
 //! Build with the help of documentation/code from [Waveshare](https://www.waveshare.com/wiki/3.7inch_e-Paper_HAT),
 use core::fmt::{Debug, Display};
@@ -15,7 +23,9 @@ use self::constants::*;
 use crate::buffer_len;
 use crate::color::Color;
 use crate::error::ErrorKind;
-use crate::interface::DisplayInterface;
+use crate::interface::{BusyLevel, DisplayDelay, DisplayInterface, ResetTiming};
+use crate::quad_buffer_len;
+use crate::split_gray4;
 use crate::traits::{ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay};
 
 /// Width of the display.
@@ -27,7 +37,8 @@ pub const HEIGHT: u32 = 480;
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
 
-const IS_BUSY_LOW: bool = false;
+const BUSY_LEVEL: BusyLevel = BusyLevel::High;
+const RESET_TIMING: ResetTiming = ResetTiming::new(30, 10);
 
 const SINGLE_BYTE_WRITE: bool = true;
 
@@ -41,15 +52,33 @@ pub type Display3in7 = crate::graphics::Display<
     Color,
 >;
 
+/// Display with a 4 level grayscale buffer for use with [Epd3in7::update_frame_gray4]
+#[cfg(feature = "graphics")]
+pub type Display3in7Gray = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { quad_buffer_len(WIDTH as usize, HEIGHT as usize) },
+    embedded_graphics_core::pixelcolor::Gray2,
+>;
+
+/// Length of a 4 level grayscale buffer, 2 bits per pixel
+pub const GRAY4_BUFFER_LEN: usize = quad_buffer_len(WIDTH as usize, HEIGHT as usize);
+
+/// Bytes of one row in RAM
+const ROW_BYTES: usize = WIDTH as usize / 8;
+
 /// Epd3in7 driver
-pub struct Epd3in7<SPI, BUSY, DC, RST> {
+pub struct Epd3in7<SPI, BUSY, DC, RST, DELAY> {
     /// Connection Interface
-    interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
     /// Background Color
     background_color: Color,
+    /// Black/white waveform loaded into the LUT register
+    lut: RefreshLut,
 }
 
-impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd3in7<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST> for Epd3in7<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -59,11 +88,13 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
 }
 
-impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd3in7<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Epd3in7<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -73,22 +104,23 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         // reset the device
-        self.interface.reset(spi, 30, 10).await?;
+        self.interface.reset().await?;
 
         self.interface.cmd(spi, Command::SwReset).await?;
-        self.interface.delay(spi, 300000u32).await?;
+        self.interface.delay(300000u32).await;
 
         self.interface
             .cmd_with_data(spi, Command::AutoWriteRedRamRegularPattern, &[0xF7])
             .await?;
-        self.interface.wait_until_idle(spi, IS_BUSY_LOW).await?;
+        self.interface.wait_until_idle(BUSY_LEVEL).await?;
         self.interface
             .cmd_with_data(spi, Command::AutoWriteBwRamRegularPattern, &[0xF7])
             .await?;
-        self.interface.wait_until_idle(spi, IS_BUSY_LOW).await?;
+        self.interface.wait_until_idle(BUSY_LEVEL).await?;
 
         self.interface
             .cmd_with_data(spi, Command::GateSetting, &[0xDF, 0x01, 0x00])
@@ -155,7 +187,8 @@ where
     }
 }
 
-impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd3in7<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd3in7<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -165,6 +198,7 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type DisplayColor = Color;
 
@@ -172,12 +206,22 @@ where
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        delay: DELAY,
         delay_us: Option<u32>,
     ) -> Result<Self, <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
         let mut epd = Epd3in7 {
-            interface: DisplayInterface::new(busy, dc, rst, delay_us),
+            interface: DisplayInterface::new(
+                Some(busy),
+                dc,
+                rst,
+                delay,
+                RESET_TIMING,
+                delay_us,
+                None,
+            ),
             background_color: DEFAULT_BACKGROUND_COLOR,
+            lut: RefreshLut::Full,
         };
 
         epd.init(spi).await?;
@@ -188,6 +232,7 @@ where
         &mut self,
         spi: &mut SPI,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.require_reset_pin()?;
         self.init(spi).await
     }
 
@@ -201,7 +246,9 @@ where
         self.interface.cmd(spi, Command::PowerOff).await?;
         self.interface
             .cmd_with_data(spi, Command::Sleep2, &[0xA5])
-            .await
+            .await?;
+        self.interface.mark_asleep();
+        Ok(())
     }
 
     fn set_background_color(&mut self, color: Self::DisplayColor) {
@@ -220,25 +267,54 @@ where
         HEIGHT
     }
 
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
+
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
     async fn update_frame(
         &mut self,
         spi: &mut SPI,
         buffer: &[u8],
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
         assert!(buffer.len() == buffer_len(WIDTH as usize, HEIGHT as usize));
-        self.interface
-            .cmd_with_data(spi, Command::SetRamXAddressCounter, &[0x00, 0x00])
-            .await?;
-        self.interface
-            .cmd_with_data(spi, Command::SetRamYAddressCounter, &[0x00, 0x00])
-            .await?;
+        self.use_full_frame(spi).await?;
 
         self.interface
             .cmd_with_data(spi, Command::WriteRam, buffer)
             .await
     }
 
-    #[allow(unused)]
+    /// Writes `buffer` into the RAM window starting at (x, y)
+    ///
+    /// x and width need to be multiples of 8. Select `RefreshLut::Quick` with
+    /// [set_lut](WaveshareDisplay::set_lut) first for a fast refresh with the A2 waveform.
     async fn update_partial_frame(
         &mut self,
         spi: &mut SPI,
@@ -248,19 +324,34 @@ where
         width: u32,
         height: u32,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        todo!()
+        assert!(x % 8 == 0 && width % 8 == 0);
+        assert!(x + width <= WIDTH && y + height <= HEIGHT);
+        assert!(buffer.len() == buffer_len(width as usize, height as usize));
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)
+            .await?;
+        self.set_ram_counter(spi, x, y).await?;
+        self.interface
+            .cmd_with_data(spi, Command::WriteRam, buffer)
+            .await?;
+
+        // restore the full window for the next frame update
+        self.use_full_frame(spi).await
     }
 
     async fn display_frame(
         &mut self,
         spi: &mut SPI,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        //self.interface
-        //    .cmd_with_data(spi, Command::WRITE_LUT_REGISTER, &LUT_1GRAY_GC)?;
+        // all waveforms are uploaded to the LUT register, the display update sequence
+        // setting of init (0xCF) activates each of them
         self.interface
             .cmd(spi, Command::DisplayUpdateSequence)
             .await?;
-        self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
+        self.interface.wait_until_idle(BUSY_LEVEL).await
     }
 
     async fn update_and_display_frame(
@@ -276,17 +367,12 @@ where
         &mut self,
         spi: &mut SPI,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        self.interface
-            .cmd_with_data(spi, Command::SetRamXAddressCounter, &[0x00, 0x00])
-            .await?;
-        self.interface
-            .cmd_with_data(spi, Command::SetRamYAddressCounter, &[0x00, 0x00])
-            .await?;
+        self.use_full_frame(spi).await?;
 
         let color = self.background_color.get_byte_value();
         self.interface.cmd(spi, Command::WriteRam).await?;
         self.interface
-            .data_x_times(spi, color, WIDTH * HEIGHT)
+            .data_x_times(spi, color, WIDTH / 8 * HEIGHT)
             .await
     }
 
@@ -295,20 +381,258 @@ where
         spi: &mut SPI,
         refresh_rate: Option<RefreshLut>,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        let buffer = match refresh_rate {
-            Some(RefreshLut::Full) | None => &LUT_1GRAY_GC,
-            Some(RefreshLut::Quick) => &LUT_1GRAY_DU,
+        let lut = refresh_rate.unwrap_or_default();
+        let buffer = match lut {
+            RefreshLut::Full => &LUT_1GRAY_GC,
+            RefreshLut::Quick => &LUT_1GRAY_A2,
         };
 
         self.interface
             .cmd_with_data(spi, Command::WriteLutRegister, buffer)
-            .await
+            .await?;
+        self.lut = lut;
+        Ok(())
     }
 
     async fn wait_until_idle(
+        &mut self,
+        _spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.wait_until_idle(BUSY_LEVEL).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd3in7<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    /// Transmits a 4 level grayscale frame and uploads the grayscale waveform
+    ///
+    /// `buffer` holds 2 bits per pixel, MSB first, with `0b00` black, `0b01` dark gray,
+    /// `0b10` light gray and `0b11` white, like a [Display3in7Gray]. It has to be
+    /// [GRAY4_BUFFER_LEN] bytes long.
+    ///
+    /// The low bits go into the black/white RAM and the high bits into the second RAM.
+    /// Call [set_lut](WaveshareDisplay::set_lut) before the next black/white frame to
+    /// load a black/white waveform again.
+    ///
+    /// Returns [ErrorKind::Unsupported] while `RefreshLut::Quick` is selected, the A2
+    /// waveform can't show gray levels. Select `RefreshLut::Full` first.
+    pub async fn update_frame_gray4(
         &mut self,
         spi: &mut SPI,
+        buffer: &[u8],
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
+        assert!(buffer.len() == GRAY4_BUFFER_LEN);
+        if self.lut == RefreshLut::Quick {
+            return Err(ErrorKind::Unsupported);
+        }
+        self.write_gray4_plane(spi, Command::WriteRam, buffer, false)
+            .await?;
+        self.write_gray4_plane(spi, Command::WriteRam2, buffer, true)
+            .await?;
+
+        self.interface
+            .cmd_with_data(spi, Command::WriteLutRegister, &LUT_4GRAY_GC)
+            .await
+    }
+
+    async fn write_gray4_plane(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        buffer: &[u8],
+        high: bool,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.use_full_frame(spi).await?;
+        self.interface.cmd(spi, command).await?;
+
+        let mut row = [0u8; ROW_BYTES];
+        for pixels in buffer.chunks(ROW_BYTES * 2) {
+            for (byte, pixels) in row.iter_mut().zip(pixels.chunks(2)) {
+                let (high_bits, low_bits) = split_gray4(pixels);
+                *byte = if high { high_bits } else { low_bits };
+            }
+            self.interface.data(spi, &row).await?;
+        }
+        Ok(())
+    }
+
+    async fn use_full_frame(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        // choose full frame/ram
+        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1).await?;
+
+        // start from the beginning
+        self.set_ram_counter(spi, 0, 0).await
+    }
+
+    /// Sets both X and Y pixels ranges, both are addressed in pixels
+    async fn set_ram_area(
+        &mut self,
+        spi: &mut SPI,
+        start_x: u32,
+        start_y: u32,
+        end_x: u32,
+        end_y: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::SetRamXAddressStartEndPosition,
+                &[
+                    start_x as u8,
+                    (start_x >> 8) as u8,
+                    end_x as u8,
+                    (end_x >> 8) as u8,
+                ],
+            )
+            .await?;
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::SetRamYAddressStartEndPosition,
+                &[
+                    start_y as u8,
+                    (start_y >> 8) as u8,
+                    end_y as u8,
+                    (end_y >> 8) as u8,
+                ],
+            )
+            .await
+    }
+
+    /// Sets both X and Y pixels counters when writing data to RAM
+    async fn set_ram_counter(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::SetRamXAddressCounter,
+                &[x as u8, (x >> 8) as u8],
+            )
+            .await?;
+        self.interface
+            .cmd_with_data(
+                spi,
+                Command::SetRamYAddressCounter,
+                &[y as u8, (y >> 8) as u8],
+            )
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{block_on, mocks};
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 280);
+        assert_eq!(HEIGHT, 480);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+        assert_eq!(ROW_BYTES, 35);
+        assert_eq!(GRAY4_BUFFER_LEN, 2 * ROW_BYTES * HEIGHT as usize);
+    }
+
+    #[test]
+    fn gray4_split() {
+        // black, dark gray, light gray, white, then white, light gray, dark gray, black
+        let pixels = [0b00_01_10_11, 0b11_10_01_00];
+        let (high, low) = split_gray4(&pixels);
+        // the second RAM takes the high bits, light gray and white are set
+        assert_eq!(high, 0b0011_1100);
+        // the black/white RAM takes the low bits, dark gray and white are set
+        assert_eq!(low, 0b0101_1010);
+    }
+
+    #[test]
+    fn gray4_planes() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd3in7::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        let mut buffer = [0xFF; GRAY4_BUFFER_LEN];
+        buffer[0] = 0b00_01_10_11;
+        buffer[1] = 0b11_10_01_00;
+        // first byte of the second row, all light gray
+        buffer[2 * ROW_BYTES] = 0b10_10_10_10;
+        buffer[2 * ROW_BYTES + 1] = 0b10_10_10_10;
+        block_on(epd.update_frame_gray4(&mut spi, &buffer)).unwrap();
+
+        let low = log.data_of(0x24, 0);
+        let high = log.data_of(0x26, 0);
+        assert_eq!(low.len(), ROW_BYTES * HEIGHT as usize);
+        assert_eq!(high.len(), ROW_BYTES * HEIGHT as usize);
+        assert_eq!((high[0], low[0]), (0b0011_1100, 0b0101_1010));
+        assert_eq!((high[ROW_BYTES], low[ROW_BYTES]), (0xFF, 0x00));
+        assert_eq!(low[1], 0xFF);
+        assert_eq!(log.data_of(0x32, 0), LUT_4GRAY_GC);
+    }
+
+    #[test]
+    fn a2_refuses_gray4() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd3in7::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert_eq!(log.data_of(0x32, 0), LUT_1GRAY_GC);
+
+        log.clear();
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Quick))).unwrap();
+        assert_eq!(log.data_of(0x32, 0), LUT_1GRAY_A2);
+
+        log.clear();
+        let buffer = [0xFF; GRAY4_BUFFER_LEN];
+        let result = block_on(epd.update_frame_gray4(&mut spi, &buffer));
+        assert!(matches!(result, Err(ErrorKind::Unsupported)));
+        assert!(log.commands().is_empty());
+
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Full))).unwrap();
+        block_on(epd.update_frame_gray4(&mut spi, &buffer)).unwrap();
+        assert_eq!(log.data_of(0x32, 1), LUT_4GRAY_GC);
+    }
+
+    #[test]
+    fn partial_window() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd3in7::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        let buffer = [0x0F; 3 * 20];
+        block_on(epd.update_partial_frame(&mut spi, &buffer, 256, 300, 24, 20)).unwrap();
+        // the window ends on the last pixel of the row, both axes are addressed in pixels
+        assert_eq!(log.data_of(0x44, 0), [0x00, 0x01, 0x17, 0x01]);
+        assert_eq!(log.data_of(0x45, 0), [0x2C, 0x01, 0x3F, 0x01]);
+        assert_eq!(log.data_of(0x4E, 0), [0x00, 0x01]);
+        assert_eq!(log.data_of(0x4F, 0), [0x2C, 0x01]);
+        assert_eq!(log.data_of(0x24, 0), buffer);
+        // the full window is restored afterwards
+        assert_eq!(log.data_of(0x44, 1), [0x00, 0x00, 0x17, 0x01]);
+    }
+
+    #[test]
+    fn clear_frame_fills_the_ram() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd3in7::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        block_on(epd.clear_frame(&mut spi)).unwrap();
+        let ram = log.data_of(0x24, 0);
+        assert_eq!(ram.len(), ROW_BYTES * HEIGHT as usize);
+        assert!(ram.iter().all(|&byte| byte == 0xFF));
     }
 }
@@ -34,8 +34,7 @@ pub(crate) enum Command {
     PowerOn = 0x04,
     /// This command enables the internal bandgap, which will be cleared by the next POF.
     PowerOnMeasure = 0x05,
-    /// Starting data transmission
-    ///     3-times: self.send_data(0x17)?; //07 0f 17 1f 27 2F 37 2f
+    /// Sets the soft start period, driving strength and minimum off time of the booster phases
     BoosterSoftStart = 0x06,
     /// After this command is transmitted, the chip would enter the deep-sleep mode to save power.
     ///
@@ -148,10 +147,16 @@ pub(crate) enum Command {
     ///
     /// The value of (n) is depending on the amount of programmed data, tha max address = 0xFFF.
     ReadOtp = 0xA2,
+    /// Cascade setting, bit 1 (TSFIX) makes the controller use the temperature of
+    /// [Command::ForceTemperature] instead of the measured one.
+    CascadeSetting = 0xE0,
     /// This command is set for saving power during fresh period. If the output voltage of VCOM / Source is from negative to positive or
     /// from positive to negative, the power saving mechanism will be activated. The active period width is defined by the following two
     /// parameters.
     PowerSaving = 0xE3,
+    /// Sets the temperature the waveform is selected for, used with
+    /// [Command::CascadeSetting].
+    ForceTemperature = 0xE5,
 }
 
 impl traits::Command for Command {
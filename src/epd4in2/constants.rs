@@ -1,20 +1,6 @@
 //! This file contains look-up-tables used to set voltages used during
 //! various categories of pixel refreshes.
 
-#[rustfmt::skip]
-pub(crate) const LUT_VCOM0: [u8; 44] = [
-// The commented-out line below was used in a Ben Krasnow video explaining
-// partial refreshes.
-// 0x40, 0x17, 0x00, 0x00, 0x00, 0x02,
-    0x00, 0x17, 0x00, 0x00, 0x00, 0x02,
-    0x00, 0x17, 0x17, 0x00, 0x00, 0x02,
-    0x00, 0x0A, 0x01, 0x00, 0x00, 0x01,
-    0x00, 0x0E, 0x0E, 0x00, 0x00, 0x02,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-];
-
 #[rustfmt::skip]
 pub(crate) const LUT_VCOM0_QUICK: [u8; 44] = [
     0x00, 0x0E, 0x00, 0x00, 0x00, 0x01,
@@ -27,18 +13,18 @@ pub(crate) const LUT_VCOM0_QUICK: [u8; 44] = [
 ];
 
 #[rustfmt::skip]
-pub(crate) const LUT_WW: [u8; 42] =[
-    0x40, 0x17, 0x00, 0x00, 0x00, 0x02,
-    0x90, 0x17, 0x17, 0x00, 0x00, 0x02,
-    0x40, 0x0A, 0x01, 0x00, 0x00, 0x01,
-    0xA0, 0x0E, 0x0E, 0x00, 0x00, 0x02,
+pub(crate) const LUT_WW_QUICK: [u8; 42] =[
+    0xA0, 0x0E, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
 ];
 
 #[rustfmt::skip]
-pub(crate) const LUT_WW_QUICK: [u8; 42] =[
+pub(crate) const LUT_BW_QUICK: [u8; 42] =[
     0xA0, 0x0E, 0x00, 0x00, 0x00, 0x01,
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
@@ -49,19 +35,19 @@ pub(crate) const LUT_WW_QUICK: [u8; 42] =[
 ];
 
 #[rustfmt::skip]
-pub(crate) const LUT_BW: [u8; 42] =[
-    0x40, 0x17, 0x00, 0x00, 0x00, 0x02,
-    0x90, 0x17, 0x17, 0x00, 0x00, 0x02,
-    0x40, 0x0A, 0x01, 0x00, 0x00, 0x01,
-    0xA0, 0x0E, 0x0E, 0x00, 0x00, 0x02,
+pub(crate) const LUT_BB_QUICK: [u8; 42] =[
+    0x50, 0x0E, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
 ];
 
 #[rustfmt::skip]
-pub(crate) const LUT_BW_QUICK: [u8; 42] =[
-    0xA0, 0x0E, 0x00, 0x00, 0x00, 0x01,
+pub(crate) const LUT_WB_QUICK: [u8; 42] =[
+    0x50, 0x0E, 0x00, 0x00, 0x00, 0x01,
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
@@ -70,45 +56,58 @@ pub(crate) const LUT_BW_QUICK: [u8; 42] =[
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
 ];
 
+// 4 level grayscale waveforms of the vendor driver, the old/new data of a pixel
+// selects one of the four tables
 #[rustfmt::skip]
-pub(crate) const LUT_BB: [u8; 42] =[
-    0x80, 0x17, 0x00, 0x00, 0x00, 0x02,
-    0x90, 0x17, 0x17, 0x00, 0x00, 0x02,
-    0x80, 0x0A, 0x01, 0x00, 0x00, 0x01,
-    0x50, 0x0E, 0x0E, 0x00, 0x00, 0x02,
+pub(crate) const LUT_VCOM_GRAY4: [u8; 42] = [
+    0x00, 0x0A, 0x00, 0x00, 0x00, 0x01,
+    0x60, 0x14, 0x14, 0x00, 0x00, 0x01,
+    0x00, 0x14, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x13, 0x0A, 0x01, 0x00, 0x01,
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
 ];
 
 #[rustfmt::skip]
-pub(crate) const LUT_BB_QUICK: [u8; 42] =[
-    0x50, 0x0E, 0x00, 0x00, 0x00, 0x01,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+pub(crate) const LUT_WW_GRAY4: [u8; 42] = [
+    0x40, 0x0A, 0x00, 0x00, 0x00, 0x01,
+    0x90, 0x14, 0x14, 0x00, 0x00, 0x01,
+    0x10, 0x14, 0x0A, 0x00, 0x00, 0x01,
+    0xA0, 0x13, 0x01, 0x00, 0x00, 0x01,
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
 ];
 
 #[rustfmt::skip]
-pub(crate) const LUT_WB: [u8; 42] =[
-    0x80, 0x17, 0x00, 0x00, 0x00, 0x02,
-    0x90, 0x17, 0x17, 0x00, 0x00, 0x02,
-    0x80, 0x0A, 0x01, 0x00, 0x00, 0x01,
-    0x50, 0x0E, 0x0E, 0x00, 0x00, 0x02,
+pub(crate) const LUT_BW_GRAY4: [u8; 42] = [
+    0x40, 0x0A, 0x00, 0x00, 0x00, 0x01,
+    0x90, 0x14, 0x14, 0x00, 0x00, 0x01,
+    0x00, 0x14, 0x0A, 0x00, 0x00, 0x01,
+    0x99, 0x0C, 0x01, 0x03, 0x04, 0x01,
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
 ];
 
 #[rustfmt::skip]
-pub(crate) const LUT_WB_QUICK: [u8; 42] =[
-    0x50, 0x0E, 0x00, 0x00, 0x00, 0x01,
+pub(crate) const LUT_WB_GRAY4: [u8; 42] = [
+    0x40, 0x0A, 0x00, 0x00, 0x00, 0x01,
+    0x90, 0x14, 0x14, 0x00, 0x00, 0x01,
+    0x00, 0x14, 0x0A, 0x00, 0x00, 0x01,
+    0x99, 0x0B, 0x04, 0x04, 0x01, 0x01,
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const LUT_BB_GRAY4: [u8; 42] = [
+    0x80, 0x0A, 0x00, 0x00, 0x00, 0x01,
+    0x90, 0x14, 0x14, 0x00, 0x00, 0x01,
+    0x20, 0x14, 0x0A, 0x00, 0x00, 0x01,
+    0x50, 0x13, 0x01, 0x00, 0x00, 0x01,
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
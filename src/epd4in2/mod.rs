@@ -25,7 +25,7 @@
 //!# let mut delay = delay::NoopDelay::new();
 //!
 //!// Setup EPD
-//!let mut epd = Epd4in2::new(&mut spi, busy_in, dc, rst, None)?;
+//!let mut epd = Epd4in2::new(&mut spi, busy_in, dc, Some(rst), delay, None)?;
 //!
 //!// Use display graphics from embedded-graphics
 //!let mut display = Display4in2::default();
@@ -48,13 +48,27 @@
 //!
 //!
 //! BE CAREFUL! The screen can get ghosting/burn-ins through the Partial Fast Update Drawing.
+//!
+//! 4 level grayscale frames are drawn into a [Display4in2Gray], written with
+//! [Epd4in2::update_frame_gray4] and shown with [WaveshareDisplay::display_frame] after
+//! [Epd4in2::set_lut_gray] uploaded the grayscale waveforms.
+//!
+//! Windows of the frame are written with [WaveshareDisplay::update_partial_frame].
+//! [Epd4in2::display_partial_frame] writes a window and only refreshes that part of the
+//! panel, which is fast enough for a clock together with `RefreshLut::Quick`.
 use core::fmt::{Debug, Display};
+use core::num::NonZeroU32;
 use embedded_hal::digital::{InputPin, OutputPin};
 use embedded_hal_async::{digital::Wait, spi::SpiDevice};
 
+use crate::booster::BoosterSoftStart;
 use crate::error::ErrorKind;
-use crate::interface::DisplayInterface;
-use crate::traits::{ErrorType, InternalWiAdditions, QuickRefresh, RefreshLut, WaveshareDisplay};
+use crate::interface::{BusyLevel, DisplayDelay, DisplayInterface, ResetTiming};
+use crate::pll::FrameRate;
+use crate::traits::{
+    ErrorType, InternalWiAdditions, PartialRefreshes, PowerState, QuickRefresh, RefreshLut,
+    WaveshareDisplay,
+};
 
 //The Lookup Tables for the Display
 mod constants;
@@ -66,14 +80,23 @@ pub const WIDTH: u32 = 400;
 pub const HEIGHT: u32 = 300;
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
-const IS_BUSY_LOW: bool = true;
+/// Default booster soft start, phase C2 isn't sent
+pub const DEFAULT_BOOSTER_SOFT_START: BoosterSoftStart =
+    BoosterSoftStart::from_bytes(0x17, 0x17, 0x17, 0x17);
+/// Default frame rate, 200 Hz didn't work on some boards
+pub const DEFAULT_FRAME_RATE: FrameRate = FrameRate::Hz100;
+const BUSY_LEVEL: BusyLevel = BusyLevel::Low;
+const RESET_TIMING: ResetTiming = ResetTiming::new(10_000, 10_000);
 const SINGLE_BYTE_WRITE: bool = true;
+/// S2G and G2S non-overlap periods of 12 (reset value)
+const DEFAULT_TCON: u8 = 0x22;
 
 use crate::color::Color;
 
 pub(crate) mod command;
 use self::command::Command;
-use crate::buffer_len;
+use crate::split_gray4;
+use crate::{buffer_len, quad_buffer_len};
 
 /// Full size buffer for use with the 4in2 EPD
 #[cfg(feature = "graphics")]
@@ -85,18 +108,48 @@ pub type Display4in2 = crate::graphics::Display<
     Color,
 >;
 
+/// Display with a 4 level grayscale buffer for use with [Epd4in2::update_frame_gray4]
+#[cfg(feature = "graphics")]
+pub type Display4in2Gray = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { quad_buffer_len(WIDTH as usize, HEIGHT as usize) },
+    embedded_graphics_core::pixelcolor::Gray2,
+>;
+
+/// Length of a 4 level grayscale buffer, 2 bits per pixel
+pub const GRAY4_BUFFER_LEN: usize = quad_buffer_len(WIDTH as usize, HEIGHT as usize);
+
+/// Bytes of one row in RAM
+const ROW_BYTES: usize = WIDTH as usize / 8;
+
 /// Epd4in2 driver
 ///
-pub struct Epd4in2<SPI, BUSY, DC, RST> {
+pub struct Epd4in2<SPI, BUSY, DC, RST, DELAY> {
     /// Connection Interface
-    interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
     /// Background Color
     color: Color,
     /// Refresh LUT
     refresh: RefreshLut,
+    /// Temperature set with [Epd4in2::set_temperature]
+    temperature: Option<i8>,
+    /// Color of the border around the active area
+    border: Color,
+    /// Booster soft start set with [Epd4in2::set_booster_soft_start]
+    booster: BoosterSoftStart,
+    /// Non-overlap periods set with [Epd4in2::set_tcon]
+    tcon: u8,
+    /// Frame rate set with [Epd4in2::set_frame_rate]
+    frame_rate: FrameRate,
+    /// Power state, to tell the deep sleep apart
+    power: PowerState,
+    /// Quick refreshes since the last full refresh, and when the next one is a full one
+    partial_refreshes: PartialRefreshes,
 }
 
-impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd4in2<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST> for Epd4in2<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -106,11 +159,13 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
 }
 
-impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd4in2<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Epd4in2<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -120,10 +175,12 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         // reset the device
-        self.interface.reset(spi, 10_000, 10_000).await?;
+        self.interface.reset().await?;
+        self.power = PowerState::Active;
 
         // set the power settings
         self.interface
@@ -131,45 +188,47 @@ where
             .await?;
 
         // start the booster
-        self.interface
-            .cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x17])
-            .await?;
+        self.send_booster_soft_start(spi).await?;
 
         // power on
         self.command(spi, Command::PowerOn).await?;
-        self.interface.delay(spi, 5000).await?;
+        self.interface.delay(5000).await;
         self.wait_until_idle(spi).await?;
 
-        // set the panel settings
-        self.cmd_with_data(spi, Command::PanelSetting, &[0x3F])
-            .await?;
-
         // Set Frequency, 200 Hz didn't work on my board
         // 150Hz and 171Hz wasn't tested yet
         // TODO: Test these other frequencies
         // 3A 100HZ   29 150Hz 39 200HZ  31 171HZ DEFAULT: 3c 50Hz
-        self.cmd_with_data(spi, Command::PllControl, &[0x3A])
+        self.cmd_with_data(spi, Command::PllControl, &[self.frame_rate.uc8151_byte()])
             .await?;
 
         self.send_resolution(spi).await?;
 
+        // the reset value is already in place
+        if self.tcon != DEFAULT_TCON {
+            self.cmd_with_data(spi, Command::TconSetting, &[self.tcon])
+                .await?;
+        }
+
         self.interface
             .cmd_with_data(spi, Command::VcmDcSetting, &[0x12])
             .await?;
 
-        //VBDF 17|D7 VBDW 97  VBDB 57  VBDF F7  VBDW 77  VBDB 37  VBDR B7
-        self.interface
-            .cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x97])
-            .await?;
+        self.send_border(spi).await?;
+
+        self.send_temperature(spi).await?;
 
+        // sends the panel setting together with the LUT
         self.set_lut(spi, None).await?;
+        self.partial_refreshes.full_done();
 
         self.wait_until_idle(spi).await?;
         Ok(())
     }
 }
 
-impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd4in2<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd4in2<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -179,22 +238,32 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type DisplayColor = Color;
     async fn new(
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        delay: DELAY,
         delay_us: Option<u32>,
     ) -> Result<Self, Self::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        let interface =
+            DisplayInterface::new(Some(busy), dc, rst, delay, RESET_TIMING, delay_us, None);
         let color = DEFAULT_BACKGROUND_COLOR;
 
         let mut epd = Epd4in2 {
             interface,
             color,
             refresh: RefreshLut::Full,
+            temperature: None,
+            border: DEFAULT_BACKGROUND_COLOR,
+            booster: DEFAULT_BOOSTER_SOFT_START,
+            tcon: DEFAULT_TCON,
+            frame_rate: DEFAULT_FRAME_RATE,
+            power: PowerState::Active,
+            partial_refreshes: PartialRefreshes::default(),
         };
 
         epd.init(spi).await?;
@@ -220,11 +289,20 @@ where
         self.interface
             .cmd_with_data(spi, Command::DeepSleep, &[0xA5])
             .await?;
+        self.power = PowerState::DeepSleep;
         Ok(())
     }
 
     async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.init(spi).await
+        match self.power {
+            PowerState::Active => Ok(()),
+            PowerState::PoweredOff => self.power_on(spi).await,
+            PowerState::DeepSleep => {
+                self.interface.require_reset_pin()?;
+                self.init(spi).await
+            }
+            PowerState::Unknown => self.init(spi).await,
+        }
     }
 
     fn set_background_color(&mut self, color: Color) {
@@ -243,6 +321,37 @@ where
         HEIGHT
     }
 
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
+
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
     async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
         let color_value = self.color.get_byte_value();
@@ -260,6 +369,13 @@ where
         Ok(())
     }
 
+    /// Writes `buffer` into a window of the frame
+    ///
+    /// `x` and `width` are rounded out to byte boundaries, `buffer` has to cover the
+    /// rounded window. Returns [ErrorKind::InvalidWindow] if the window doesn't lie
+    /// inside the display or the buffer has a different size. The rest of the frame
+    /// is kept, [WaveshareDisplay::display_frame] refreshes the whole panel, while
+    /// [Epd4in2::display_partial_frame] only refreshes the window.
     async fn update_partial_frame(
         &mut self,
         spi: &mut SPI,
@@ -269,47 +385,25 @@ where
         width: u32,
         height: u32,
     ) -> Result<(), Self::Error> {
-        self.wait_until_idle(spi).await?;
-        if buffer.len() as u32 != width / 8 * height {
-            //TODO: panic!! or sth like that
-            //return Err("Wrong buffersize");
+        let (start_x, end_x) =
+            byte_aligned_window(x, y, width, height).ok_or(ErrorKind::InvalidWindow)?;
+        if buffer.len() != buffer_len((end_x - start_x) as usize, height as usize) {
+            return Err(ErrorKind::InvalidWindow);
         }
+        self.wait_until_idle(spi).await?;
 
         self.command(spi, Command::PartialIn).await?;
-        self.command(spi, Command::PartialWindow).await?;
-        self.send_data(spi, &[(x >> 8) as u8]).await?;
-        let tmp = x & 0xf8;
-        self.send_data(spi, &[tmp as u8]).await?; // x should be the multiple of 8, the last 3 bit will always be ignored
-        let tmp = tmp + width - 1;
-        self.send_data(spi, &[(tmp >> 8) as u8]).await?;
-        self.send_data(spi, &[(tmp | 0x07) as u8]).await?;
-
-        self.send_data(spi, &[(y >> 8) as u8]).await?;
-        self.send_data(spi, &[y as u8]).await?;
-
-        self.send_data(spi, &[((y + height - 1) >> 8) as u8])
+        self.set_partial_window(spi, start_x, end_x, y, height)
             .await?;
-        self.send_data(spi, &[(y + height - 1) as u8]).await?;
-
-        self.send_data(spi, &[0x01]).await?; // Gates scan both inside and outside of the partial window. (default)
-
-        //TODO: handle dtm somehow
-        let is_dtm1 = false;
-        if is_dtm1 {
-            self.command(spi, Command::DataStartTransmission1).await?; //TODO: check if data_start transmission 1 also needs "old"/background data here
-        } else {
-            self.command(spi, Command::DataStartTransmission2).await?;
-        }
-
-        self.send_data(spi, buffer).await?;
-
-        self.command(spi, Command::PartialOut).await?;
-        Ok(())
+        self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)
+            .await?;
+        self.command(spi, Command::PartialOut).await
     }
 
     async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
-        self.command(spi, Command::DisplayRefresh).await?;
+        self.display_refresh(spi).await?;
+        self.partial_refreshes.full_done();
         Ok(())
     }
 
@@ -319,7 +413,7 @@ where
         buffer: &[u8],
     ) -> Result<(), Self::Error> {
         self.update_frame(spi, buffer).await?;
-        self.command(spi, Command::DisplayRefresh).await?;
+        self.display_refresh(spi).await?;
         Ok(())
     }
 
@@ -353,11 +447,9 @@ where
         if let Some(refresh_lut) = refresh_rate {
             self.refresh = refresh_lut;
         }
+        self.wait_until_idle(spi).await?;
         match self.refresh {
-            RefreshLut::Full => {
-                self.set_lut_helper(spi, &LUT_VCOM0, &LUT_WW, &LUT_BW, &LUT_WB, &LUT_BB)
-                    .await
-            }
+            RefreshLut::Full => self.send_panel_setting(spi, false).await,
             RefreshLut::Quick => {
                 self.set_lut_helper(
                     spi,
@@ -367,17 +459,24 @@ where
                     &LUT_WB_QUICK,
                     &LUT_BB_QUICK,
                 )
-                .await
+                .await?;
+                self.send_panel_setting(spi, true).await
             }
         }
     }
 
-    async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
+    /// Returns [ErrorKind::Asleep] in deep sleep instead of waiting for the busy pin
+    ///
+    /// The busy pin doesn't change until the controller is woken up by a reset.
+    async fn wait_until_idle(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        if self.power == PowerState::DeepSleep {
+            return Err(ErrorKind::Asleep);
+        }
+        self.interface.wait_until_idle(BUSY_LEVEL).await
     }
 }
 
-impl<SPI, BUSY, DC, RST> Epd4in2<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> Epd4in2<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -387,7 +486,227 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
+    /// Replaces every `refreshes`th quick refresh with a full one, `None` disables it
+    ///
+    /// The full refresh uses the waveform of the OTP, afterwards the selected
+    /// waveform is loaded again.
+    pub fn set_full_refresh_after(&mut self, refreshes: Option<NonZeroU32>) {
+        self.partial_refreshes.set_full_after(refreshes);
+    }
+
+    /// Quick refreshes since the last full refresh
+    pub fn partial_refreshes(&self) -> u32 {
+        self.partial_refreshes.count()
+    }
+
+    /// Makes the controller use `celsius` instead of the measured temperature
+    ///
+    /// The temperature is kept across [WaveshareDisplay::wake_up]. Note that it only
+    /// selects between the waveforms of the OTP used by `RefreshLut::Full`, the quick
+    /// and grayscale LUTs are uploaded into the registers and stay the same at every
+    /// temperature.
+    pub async fn set_temperature(
+        &mut self,
+        spi: &mut SPI,
+        celsius: i8,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.temperature = Some(celsius);
+        self.wait_until_idle(spi).await?;
+        self.send_temperature(spi).await
+    }
+
+    /// Turns the high voltages off, but keeps the controller and its RAM awake
+    ///
+    /// Unlike [WaveshareDisplay::sleep] no init is needed afterwards, the next refresh
+    /// turns the power on again, see [Epd4in2::power_on].
+    pub async fn power_off(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.wait_until_idle(spi).await?;
+        self.command(spi, Command::PowerOff).await?;
+        self.wait_until_idle(spi).await?;
+        self.power = PowerState::PoweredOff;
+        Ok(())
+    }
+
+    /// Turns the high voltages on again after [Epd4in2::power_off]
+    pub async fn power_on(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.wait_until_idle(spi).await?;
+        self.command(spi, Command::PowerOn).await?;
+        self.wait_until_idle(spi).await?;
+        self.power = PowerState::Active;
+        Ok(())
+    }
+
+    /// Refreshes the display, turning the power on first if needed
+    async fn display_refresh(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if self.power == PowerState::PoweredOff {
+            self.power_on(spi).await?;
+        }
+        self.command(spi, Command::DisplayRefresh).await
+    }
+
+    /// Sets the color of the border around the active area
+    ///
+    /// It takes effect with the next refresh and is kept across
+    /// [WaveshareDisplay::wake_up].
+    pub async fn set_border_color(
+        &mut self,
+        spi: &mut SPI,
+        color: Color,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.border = color;
+        self.wait_until_idle(spi).await?;
+        self.send_border(spi).await
+    }
+
+    /// Sets the source to gate (`s2g`) and gate to source (`g2s`) non-overlap periods
+    ///
+    /// Both are 4 bit values, the period is 4 × (value + 1), so the default of 2 is 12.
+    /// Longer periods help against faint vertical lines, e.g. with long FPC extenders.
+    /// Returns [ErrorKind::InvalidSetting] if a value doesn't fit into 4 bits. The
+    /// periods are kept across [WaveshareDisplay::wake_up].
+    pub async fn set_tcon(
+        &mut self,
+        spi: &mut SPI,
+        s2g: u8,
+        g2s: u8,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if s2g > 0x0F || g2s > 0x0F {
+            return Err(ErrorKind::InvalidSetting);
+        }
+        self.tcon = s2g << 4 | g2s;
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::TconSetting, &[self.tcon])
+            .await
+    }
+
+    /// Reads the status flags of the controller (command 0x71)
+    ///
+    /// From bit 6 down to bit 0 they are PTL, I2C_ERR, I2C_BUSY, DATA, PON, POF and
+    /// BUSY_N, e.g. `status & 0x04 != 0` tells that the panel is powered on. It doesn't
+    /// wait until the controller is idle. The data line has to be connected to the MISO
+    /// of the SPI bus as well, which the Waveshare HATs don't do.
+    pub async fn status(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<u8, <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if self.power == PowerState::DeepSleep {
+            return Err(ErrorKind::Asleep);
+        }
+        let mut status = [0u8];
+        self.interface
+            .cmd_then_read(spi, Command::GetStatus, 0, &mut status)
+            .await?;
+        Ok(status[0])
+    }
+
+    /// Reads the revision of the controller (command 0x70) into `buffer`
+    ///
+    /// The LUT revision from the OTP comes first, followed by the chip revision. How
+    /// many bytes it sends depends on the controller, see its datasheet. Like
+    /// [Epd4in2::status] it needs the data line on the MISO.
+    pub async fn revision(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &mut [u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if self.power == PowerState::DeepSleep {
+            return Err(ErrorKind::Asleep);
+        }
+        self.interface
+            .cmd_then_read(spi, Command::Revision, 0, buffer)
+            .await
+    }
+
+    /// Sets the frame rate of the refresh with the PLL control
+    ///
+    /// The default is [DEFAULT_FRAME_RATE]. It takes effect with the next refresh and is
+    /// kept across [WaveshareDisplay::wake_up].
+    pub async fn set_frame_rate(
+        &mut self,
+        spi: &mut SPI,
+        frame_rate: FrameRate,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.frame_rate = frame_rate;
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::PllControl, &[frame_rate.uc8151_byte()])
+            .await
+    }
+
+    /// Sets the soft start of the booster, for supplies which brown out while it starts
+    ///
+    /// It takes effect with the next power on and is kept across
+    /// [WaveshareDisplay::wake_up].
+    pub async fn set_booster_soft_start(
+        &mut self,
+        spi: &mut SPI,
+        booster: BoosterSoftStart,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.booster = booster;
+        self.wait_until_idle(spi).await?;
+        self.send_booster_soft_start(spi).await
+    }
+
+    /// Sends the booster soft start, the UC8176 has three phases
+    async fn send_booster_soft_start(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let bytes = self.booster.bytes();
+        self.cmd_with_data(spi, Command::BoosterSoftStart, &bytes[..3])
+            .await
+    }
+
+    /// Sends the panel setting, with the LUTs of the registers or of the OTP
+    async fn send_panel_setting(
+        &mut self,
+        spi: &mut SPI,
+        register_lut: bool,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        // REG_EN on top of black/white mode, scan directions, booster on and no soft reset
+        let reg_en = if register_lut { 0x20 } else { 0x00 };
+        self.cmd_with_data(spi, Command::PanelSetting, &[0x1F | reg_en])
+            .await
+    }
+
+    /// Sends the border color with the VCOM and data interval
+    async fn send_border(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        //VBDF 17|D7 VBDW 97  VBDB 57  VBDF F7  VBDW 77  VBDB 37  VBDR B7
+        let interval = match self.border {
+            Color::Black => 0x57,
+            Color::White => 0x97,
+        };
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[interval])
+            .await
+    }
+
+    /// Forces the temperature of [Epd4in2::set_temperature], if there is one
+    async fn send_temperature(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if let Some(celsius) = self.temperature {
+            self.cmd_with_data(spi, Command::CascadeSetting, &[0x02])
+                .await?;
+            self.cmd_with_data(spi, Command::ForceTemperature, &[celsius as u8])
+                .await?;
+        }
+        Ok(())
+    }
+
     async fn command(
         &mut self,
         spi: &mut SPI,
@@ -488,9 +807,137 @@ where
 
         Ok(())
     }
+
+    /// Writes `buffer` into a window of the frame and only refreshes that window
+    ///
+    /// The window is handled like in [WaveshareDisplay::update_partial_frame]. The
+    /// refresh runs while the controller is in partial mode, so only the pixels inside
+    /// the window are driven. Together with `RefreshLut::Quick` this takes well below
+    /// a second.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn display_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let (start_x, end_x) =
+            byte_aligned_window(x, y, width, height).ok_or(ErrorKind::InvalidWindow)?;
+        if buffer.len() != buffer_len((end_x - start_x) as usize, height as usize) {
+            return Err(ErrorKind::InvalidWindow);
+        }
+        self.wait_until_idle(spi).await?;
+
+        self.command(spi, Command::PartialIn).await?;
+        self.set_partial_window(spi, start_x, end_x, y, height)
+            .await?;
+        self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)
+            .await?;
+        self.display_refresh(spi).await?;
+        self.wait_until_idle(spi).await?;
+        self.command(spi, Command::PartialOut).await
+    }
+
+    async fn set_partial_window(
+        &mut self,
+        spi: &mut SPI,
+        start_x: u32,
+        end_x: u32,
+        y: u32,
+        height: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        // the lower 3 bits of the horizontal start are ignored, the ones of the end have to be set
+        let last_x = end_x - 1;
+        let last_y = y + height - 1;
+        // Gates scan both inside and outside of the partial window (default)
+        let pt_scan = 0x01;
+
+        self.cmd_with_data(
+            spi,
+            Command::PartialWindow,
+            &[
+                (start_x >> 8) as u8,
+                start_x as u8 & 0xF8,
+                (last_x >> 8) as u8,
+                last_x as u8 | 0x07,
+                (y >> 8) as u8,
+                y as u8,
+                (last_y >> 8) as u8,
+                last_y as u8,
+                pt_scan,
+            ],
+        )
+        .await
+    }
+
+    /// Uploads the 4 level grayscale waveforms of the vendor driver
+    ///
+    /// [WaveshareDisplay::set_lut] loads the black/white waveforms again.
+    pub async fn set_lut_gray(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.set_lut_helper(
+            spi,
+            &LUT_VCOM_GRAY4,
+            &LUT_WW_GRAY4,
+            &LUT_BW_GRAY4,
+            &LUT_WB_GRAY4,
+            &LUT_BB_GRAY4,
+        )
+        .await?;
+        self.send_panel_setting(spi, true).await
+    }
+
+    /// Transmits a 4 level grayscale frame
+    ///
+    /// `buffer` holds 2 bits per pixel, MSB first, with `0b00` black, `0b01` dark gray,
+    /// `0b10` light gray and `0b11` white, like a [Display4in2Gray]. It has to be
+    /// [GRAY4_BUFFER_LEN] bytes long.
+    ///
+    /// As in the vendor driver the old data RAM takes the high bit and the new data RAM
+    /// the low bit of a pixel, the grayscale waveforms of [Epd4in2::set_lut_gray]
+    /// expect this.
+    pub async fn update_frame_gray4(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        assert!(buffer.len() == GRAY4_BUFFER_LEN);
+        self.wait_until_idle(spi).await?;
+        self.write_gray4_plane(spi, Command::DataStartTransmission1, buffer, true)
+            .await?;
+        self.write_gray4_plane(spi, Command::DataStartTransmission2, buffer, false)
+            .await
+    }
+
+    /// Writes one bit of every pixel of a grayscale frame into a RAM
+    async fn write_gray4_plane(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        buffer: &[u8],
+        high: bool,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.command(spi, command).await?;
+
+        let mut row = [0u8; ROW_BYTES];
+        for pixels in buffer.chunks(ROW_BYTES * 2) {
+            for (byte, pixels) in row.iter_mut().zip(pixels.chunks(2)) {
+                let (high_bits, low_bits) = split_gray4(pixels);
+                *byte = if high { high_bits } else { low_bits };
+            }
+            self.send_data(spi, &row).await?;
+        }
+        Ok(())
+    }
 }
 
-impl<SPI, BUSY, DC, RST> QuickRefresh<SPI, BUSY, DC, RST> for Epd4in2<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> QuickRefresh<SPI, BUSY, DC, RST, DELAY>
+    for Epd4in2<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -500,6 +947,7 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     /// To be followed immediately after by `update_old_frame`.
     async fn update_old_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
@@ -528,13 +976,24 @@ where
         Ok(())
     }
 
-    /// This is a wrapper around `display_frame` for using this device as a true
-    /// `QuickRefresh` device.
+    /// Refreshes the display with the selected waveform
+    ///
+    /// Once the limit set with [Epd4in2::set_full_refresh_after] is reached, this is a
+    /// full refresh instead.
     async fn display_new_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.display_frame(spi).await
+        self.wait_until_idle(spi).await?;
+        if self.partial_refreshes.full_due() {
+            self.send_panel_setting(spi, false).await?;
+            self.display_refresh(spi).await?;
+            self.partial_refreshes.full_done();
+            return self.set_lut(spi, None).await;
+        }
+        self.display_refresh(spi).await?;
+        self.partial_refreshes.partial_done();
+        Ok(())
     }
 
-    /// This is wrapper around `update_new_frame` and `display_frame` for using
+    /// This is wrapper around `update_new_frame` and `display_new_frame` for using
     /// this device as a true `QuickRefresh` device.
     ///
     /// To be used immediately after `update_old_frame`.
@@ -544,7 +1003,7 @@ where
         buffer: &[u8],
     ) -> Result<(), Self::Error> {
         self.update_new_frame(spi, buffer).await?;
-        self.display_frame(spi).await
+        self.display_new_frame(spi).await
     }
 
     async fn update_partial_old_frame(
@@ -643,14 +1102,309 @@ where
     }
 }
 
+/// Rounds the window out to byte boundaries, returns its first and one past its last column
+///
+/// Returns `None` if the window is empty or doesn't lie inside the display.
+fn byte_aligned_window(x: u32, y: u32, width: u32, height: u32) -> Option<(u32, u32)> {
+    if width == 0 || height == 0 || x + width > WIDTH || y + height > HEIGHT {
+        return None;
+    }
+    Some((x / 8 * 8, (x + width).div_ceil(8) * 8))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::{block_on, mocks};
 
     #[test]
     fn epd_size() {
         assert_eq!(WIDTH, 400);
         assert_eq!(HEIGHT, 300);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+        assert_eq!(GRAY4_BUFFER_LEN, 2 * ROW_BYTES * HEIGHT as usize);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    #[test]
+    fn raw_command() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd4in2::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        block_on(epd.send_raw_command(&mut spi, 0xE5, &[0x5A])).unwrap();
+        assert_eq!(log.commands(), [0xE5]);
+        assert_eq!(log.data_of(0xE5, 0), [0x5A]);
+        assert!(!log.waits().is_empty());
+    }
+
+    #[test]
+    fn frame_rate() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd4in2::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert_eq!(log.data_of(0x30, 0), [0x3A]);
+
+        log.clear();
+        block_on(epd.set_frame_rate(&mut spi, FrameRate::Hz150)).unwrap();
+        assert_eq!(log.data_of(0x30, 0), [0x29]);
+
+        block_on(epd.sleep(&mut spi)).unwrap();
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x30, 0), [0x29]);
+    }
+
+    #[test]
+    fn booster_soft_start() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd4in2::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert_eq!(log.data_of(0x06, 0), [0x17, 0x17, 0x17]);
+
+        let booster = BoosterSoftStart::from_bytes(0xC4, 0xC4, 0x0C, 0x0C);
+        log.clear();
+        block_on(epd.set_booster_soft_start(&mut spi, booster)).unwrap();
+        assert_eq!(log.data_of(0x06, 0), [0xC4, 0xC4, 0x0C]);
+
+        block_on(epd.sleep(&mut spi)).unwrap();
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x06, 0), [0xC4, 0xC4, 0x0C]);
+    }
+
+    #[test]
+    fn status_and_revision() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd4in2::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        log.queue_reads(&[0x05]);
+        assert_eq!(block_on(epd.status(&mut spi)).unwrap(), 0x05);
+        assert_eq!(log.commands(), [0x71]);
+
+        log.clear();
+        log.queue_reads(&[0x0C, 0x01]);
+        let mut revision = [0u8; 2];
+        block_on(epd.revision(&mut spi, &mut revision)).unwrap();
+        assert_eq!(revision, [0x0C, 0x01]);
+        assert_eq!(log.commands(), [0x70]);
+
+        block_on(epd.sleep(&mut spi)).unwrap();
+        let asleep = block_on(epd.status(&mut spi));
+        assert!(matches!(asleep, Err(ErrorKind::Asleep)));
+    }
+
+    #[test]
+    fn tcon() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd4in2::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert!(!log.commands().contains(&0x60));
+
+        log.clear();
+        block_on(epd.set_tcon(&mut spi, 0x05, 0x0A)).unwrap();
+        assert_eq!(log.data_of(0x60, 0), [0x5A]);
+
+        let too_long = block_on(epd.set_tcon(&mut spi, 0x10, 0x10));
+        assert!(matches!(too_long, Err(ErrorKind::InvalidSetting)));
+
+        block_on(epd.sleep(&mut spi)).unwrap();
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x60, 0), [0x5A]);
+    }
+
+    #[test]
+    fn gray4_planes() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd4in2::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        block_on(epd.set_lut_gray(&mut spi)).unwrap();
+        assert_eq!(log.commands(), [0x20, 0x21, 0x22, 0x23, 0x24, 0x00]);
+        assert_eq!(log.data_of(0x00, 0), [0x3F]);
+        assert_eq!(log.data_of(0x20, 0), LUT_VCOM_GRAY4);
+        assert_eq!(log.data_of(0x24, 0), LUT_BB_GRAY4);
+
+        // black, dark gray, light gray and white at the start of the first row
+        let mut buffer = [0xFF; GRAY4_BUFFER_LEN];
+        buffer[0] = 0b00_01_10_11;
+
+        log.clear();
+        block_on(epd.update_frame_gray4(&mut spi, &buffer)).unwrap();
+
+        // vendor mapping of the old data: white 1, light gray 1, dark gray 0, black 0
+        let old = log.data_of(0x10, 0);
+        assert_eq!(old.len(), ROW_BYTES * HEIGHT as usize);
+        assert_eq!(old[0], 0b0011_1111);
+        assert!(old[1..].iter().all(|&byte| byte == 0xFF));
+
+        // vendor mapping of the new data: white 1, light gray 0, dark gray 1, black 0
+        let new = log.data_of(0x13, 0);
+        assert_eq!(new.len(), ROW_BYTES * HEIGHT as usize);
+        assert_eq!(new[0], 0b0101_1111);
+        assert!(new[1..].iter().all(|&byte| byte == 0xFF));
+    }
+
+    #[test]
+    fn partial_window() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd4in2::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        // x = 20..60 is rounded out to 16..64
+        log.clear();
+        let digit = [0x00; 6 * 10];
+        block_on(epd.update_partial_frame(&mut spi, &digit, 20, 280, 40, 10)).unwrap();
+        assert_eq!(log.commands(), [0x91, 0x90, 0x13, 0x92]);
+        assert_eq!(
+            log.data_of(0x90, 0),
+            [0x00, 0x10, 0x00, 0x3F, 0x01, 0x18, 0x01, 0x21, 0x01]
+        );
+        assert_eq!(log.data_of(0x13, 0), digit);
+
+        // only the window is refreshed
+        log.clear();
+        block_on(epd.display_partial_frame(&mut spi, &digit, 20, 280, 40, 10)).unwrap();
+        assert_eq!(log.commands(), [0x91, 0x90, 0x13, 0x12, 0x92]);
+    }
+
+    #[test]
+    fn invalid_partial_window() {
+        let (_log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd4in2::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        let digit = [0x00; 6 * 10];
+        let outside = block_on(epd.update_partial_frame(&mut spi, &digit, 380, 0, 40, 10));
+        assert!(matches!(outside, Err(ErrorKind::InvalidWindow)));
+        let wrong_size = block_on(epd.display_partial_frame(&mut spi, &digit, 16, 0, 40, 10));
+        assert!(matches!(wrong_size, Err(ErrorKind::InvalidWindow)));
+    }
+
+    #[test]
+    fn forced_temperature() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd4in2::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert!(!log.commands().contains(&0xE5));
+
+        log.clear();
+        block_on(epd.set_temperature(&mut spi, 30)).unwrap();
+        assert_eq!(log.commands(), [0xE0, 0xE5]);
+        assert_eq!(log.data_of(0xE5, 0), [30]);
+
+        block_on(epd.sleep(&mut spi)).unwrap();
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0xE0, 0), [0x02]);
+        assert_eq!(log.data_of(0xE5, 0), [30]);
+    }
+
+    #[test]
+    fn border_color() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd4in2::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert_eq!(log.data_of(0x50, 0), [0x97]);
+
+        log.clear();
+        block_on(epd.set_border_color(&mut spi, Color::Black)).unwrap();
+        assert_eq!(log.commands(), [0x50]);
+        assert_eq!(log.data_of(0x50, 0), [0x57]);
+
+        // floating during the deep sleep, restored by the wake up
+        block_on(epd.sleep(&mut spi)).unwrap();
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x50, 0), [0x57]);
+    }
+
+    #[test]
+    fn power_off_and_on() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd4in2::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        block_on(epd.power_off(&mut spi)).unwrap();
+        assert_eq!(log.commands(), [0x02]);
+
+        // no init, the refresh turns the power on again
+        let frame = [0xFF; buffer_len(WIDTH as usize, HEIGHT as usize)];
+        log.clear();
+        block_on(epd.update_and_display_frame(&mut spi, &frame)).unwrap();
+        assert!(!log.commands().contains(&0x00));
+        assert!(log.commands().ends_with(&[0x04, 0x12]));
+
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(log.commands(), [0x12]);
+    }
+
+    #[test]
+    fn power_states() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd4in2::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        // nothing to do while awake
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert!(log.writes().is_empty());
+
+        block_on(epd.power_off(&mut spi)).unwrap();
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.commands(), [0x04]);
+
+        // the busy pin doesn't change in deep sleep
+        block_on(epd.sleep(&mut spi)).unwrap();
+        let frame = [0xFF; buffer_len(WIDTH as usize, HEIGHT as usize)];
+        let result = block_on(epd.update_frame(&mut spi, &frame));
+        assert!(matches!(result, Err(ErrorKind::Asleep)));
+        let result = block_on(epd.power_off(&mut spi));
+        assert!(matches!(result, Err(ErrorKind::Asleep)));
+
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.commands()[0], 0x01);
+        block_on(epd.update_frame(&mut spi, &frame)).unwrap();
+    }
+
+    #[test]
+    fn full_refresh_after_quick_refreshes() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd4in2::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Quick))).unwrap();
+        epd.set_full_refresh_after(NonZeroU32::new(2));
+
+        let frame = [0xFF; buffer_len(WIDTH as usize, HEIGHT as usize)];
+        log.clear();
+        block_on(epd.update_old_frame(&mut spi, &frame)).unwrap();
+        block_on(epd.update_and_display_new_frame(&mut spi, &frame)).unwrap();
+        assert_eq!(log.commands(), [0x10, 0x13, 0x12]);
+        assert_eq!(epd.partial_refreshes(), 1);
+
+        // the OTP waveform for the second one, the quick one again afterwards
+        log.clear();
+        block_on(epd.update_and_display_new_frame(&mut spi, &frame)).unwrap();
+        assert_eq!(log.data_of(0x00, 0), [0x1F]);
+        assert_eq!(log.data_of(0x20, 0), LUT_VCOM0_QUICK);
+        assert_eq!(log.data_of(0x00, 1), [0x3F]);
+        assert_eq!(epd.partial_refreshes(), 0);
+    }
+
+    #[test]
+    fn quick_lut() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd4in2::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        // the full refresh uses the LUT of the OTP
+        assert!(!log.commands().contains(&0x20));
+        assert_eq!(log.data_of(0x00, 0), [0x1F]);
+
+        log.clear();
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Quick))).unwrap();
+        assert_eq!(log.commands(), [0x20, 0x21, 0x22, 0x23, 0x24, 0x00]);
+        assert_eq!(log.data_of(0x20, 0), LUT_VCOM0_QUICK);
+        assert_eq!(log.data_of(0x24, 0), LUT_BB_QUICK);
+        assert_eq!(log.data_of(0x00, 0), [0x3F]);
+
+        log.clear();
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Full))).unwrap();
+        assert_eq!(log.commands(), [0x00]);
+        assert_eq!(log.data_of(0x00, 0), [0x1F]);
     }
 }
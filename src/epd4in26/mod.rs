@@ -0,0 +1,569 @@
+//! A simple Driver for the Waveshare 4.26" E-Ink Display via SPI
+//!
+//! The panel is driven by a SSD1677 controller and supports three refresh modes:
+//!
+//! - the normal full refresh
+//! - a fast refresh (~1.5s): the vendor driver writes a fixed temperature into the
+//!   temperature register, which makes the controller pick a shorter waveform from
+//!   its OTP, see [Epd4in26::display_fast]
+//! - 4 level grayscale, see [Epd4in26::update_frame_gray4]
+//!
+//! [WaveshareDisplay::set_lut] switches between the normal (`RefreshLut::Full`) and
+//! the fast (`RefreshLut::Quick`) refresh for all following frames.
+//!
+//! # References
+//!
+//! - [Waveshare product page](https://www.waveshare.com/wiki/4.26inch_e-Paper_HAT_Manual)
+//! - [Waveshare C driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_4in26.c)
+//! - [Waveshare Python driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd4in26.py)
+use core::fmt::{Debug, Display};
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+
+use crate::buffer_len;
+use crate::color::Color;
+use crate::error::ErrorKind;
+use crate::interface::{BusyLevel, DisplayDelay, DisplayInterface, ResetTiming};
+use crate::split_gray4;
+use crate::traits::{ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay};
+
+pub(crate) mod command;
+use self::command::Command;
+
+/// Full size buffer for use with the 4in26 EPD
+#[cfg(feature = "graphics")]
+pub type Display4in26 = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize, HEIGHT as usize) },
+    Color,
+>;
+
+/// Width of the display
+pub const WIDTH: u32 = 800;
+/// Height of the display
+pub const HEIGHT: u32 = 480;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+/// Size of a buffer for [Epd4in26::update_frame_gray4], 2 bits per pixel
+pub const GRAY4_BUFFER_LEN: usize = buffer_len(WIDTH as usize, HEIGHT as usize * 2);
+const BUSY_LEVEL: BusyLevel = BusyLevel::High;
+const RESET_TIMING: ResetTiming = ResetTiming::new(10_000, 2_000);
+const SINGLE_BYTE_WRITE: bool = false;
+
+/// Bytes of one row of a black/white RAM plane
+const ROW_BYTES: usize = WIDTH as usize / 8;
+/// Temperature written for the fast refresh
+const FAST_TEMPERATURE: u8 = 0x6E;
+/// Temperature written for the 4 level grayscale refresh
+const GRAY4_TEMPERATURE: u8 = 0x5A;
+
+/// Epd4in26 driver
+///
+pub struct Epd4in26<SPI, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    color: Color,
+    /// Refresh LUT
+    refresh: RefreshLut,
+    /// The RAM holds a grayscale frame for the next refresh
+    gray4: bool,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST>
+    for Epd4in26<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    type Error = ErrorKind<SPI, BUSY, DC, RST>;
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Epd4in26<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.reset().await?;
+        self.wait_until_idle(spi).await?;
+
+        self.command(spi, Command::SwReset).await?;
+        self.wait_until_idle(spi).await?;
+
+        // use the internal temperature sensor
+        self.cmd_with_data(spi, Command::TemperatureSensorSelection, &[0x80])
+            .await?;
+
+        self.cmd_with_data(
+            spi,
+            Command::BoosterSoftStartControl,
+            &[0xAE, 0xC7, 0xC3, 0xC0, 0x80],
+        )
+        .await?;
+
+        // gate count (HEIGHT - 1) and the default scanning order
+        self.cmd_with_data(
+            spi,
+            Command::DriverOutputControl,
+            &[(HEIGHT - 1) as u8, ((HEIGHT - 1) >> 8) as u8, 0x02],
+        )
+        .await?;
+
+        self.cmd_with_data(spi, Command::BorderWaveformControl, &[0x01])
+            .await?;
+
+        // x increment, y increment, address counter is updated in x direction
+        self.cmd_with_data(spi, Command::DataEntryModeSetting, &[0x03])
+            .await?;
+
+        self.use_full_frame(spi).await?;
+        self.wait_until_idle(spi).await?;
+
+        // the reset dropped the fast waveform
+        if let RefreshLut::Quick = self.refresh {
+            self.load_temperature(spi, FAST_TEMPERATURE).await?;
+        }
+        Ok(())
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd4in26<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    type DisplayColor = Color;
+    async fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        delay: DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Self::Error> {
+        let interface =
+            DisplayInterface::new(Some(busy), dc, rst, delay, RESET_TIMING, delay_us, None);
+
+        let mut epd = Epd4in26 {
+            interface,
+            color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+            gray4: false,
+        };
+
+        epd.init(spi).await?;
+
+        Ok(epd)
+    }
+
+    async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        // Deep Sleep Mode 1, a hardware reset is needed to wake up
+        self.cmd_with_data(spi, Command::DeepSleepMode, &[0x01])
+            .await?;
+        self.interface.mark_asleep();
+        Ok(())
+    }
+
+    async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.require_reset_pin()?;
+        self.init(spi).await
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
+
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        assert!(buffer.len() == buffer_len(WIDTH as usize, HEIGHT as usize));
+        self.wait_until_idle(spi).await?;
+        self.use_full_frame(spi).await?;
+        self.cmd_with_data(spi, Command::WriteRam, buffer).await?;
+        self.gray4 = false;
+        Ok(())
+    }
+
+    /// Writes `buffer` into the RAM window starting at (x, y)
+    ///
+    /// x and width need to be multiples of 8
+    async fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        assert!(x % 8 == 0 && width % 8 == 0);
+        assert!(x + width <= WIDTH && y + height <= HEIGHT);
+        assert!(buffer.len() == buffer_len(width as usize, height as usize));
+
+        self.wait_until_idle(spi).await?;
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)
+            .await?;
+        self.set_ram_counter(spi, x, y).await?;
+        self.cmd_with_data(spi, Command::WriteRam, buffer).await?;
+
+        // restore the full window for the next frame update
+        self.use_full_frame(spi).await
+    }
+
+    async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        let update = if self.gray4 {
+            // DISPLAY with DISPLAY Mode 2, which uses both RAMs for the gray levels
+            0xCF
+        } else {
+            match self.refresh {
+                // Load temperature and waveform, DISPLAY with DISPLAY Mode 1
+                RefreshLut::Full => 0xF7,
+                // Keep the written temperature, DISPLAY with DISPLAY Mode 1
+                RefreshLut::Quick => 0xC7,
+            }
+        };
+        self.turn_on_display(spi, update).await
+    }
+
+    async fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_frame(spi, buffer).await?;
+        self.display_frame(spi).await
+    }
+
+    async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        let color = self.color.get_byte_value();
+        self.wait_until_idle(spi).await?;
+
+        self.use_full_frame(spi).await?;
+        self.command(spi, Command::WriteRam).await?;
+        self.interface
+            .data_x_times(spi, color, WIDTH / 8 * HEIGHT)
+            .await?;
+
+        self.use_full_frame(spi).await?;
+        self.command(spi, Command::WriteRam2).await?;
+        self.interface
+            .data_x_times(spi, color, WIDTH / 8 * HEIGHT)
+            .await?;
+        self.gray4 = false;
+        Ok(())
+    }
+
+    /// `RefreshLut::Full` selects the normal refresh, `RefreshLut::Quick` the fast one
+    ///
+    /// The waveforms are always taken from the OTP, the fast refresh is selected by
+    /// writing a fixed temperature.
+    async fn set_lut(
+        &mut self,
+        spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Self::Error> {
+        match refresh_rate {
+            Some(RefreshLut::Full) => {
+                // the normal refresh reads the temperature sensor again
+                self.refresh = RefreshLut::Full;
+            }
+            Some(RefreshLut::Quick) => {
+                self.load_temperature(spi, FAST_TEMPERATURE).await?;
+                self.refresh = RefreshLut::Quick;
+            }
+            None => (),
+        }
+        Ok(())
+    }
+
+    async fn wait_until_idle(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.wait_until_idle(BUSY_LEVEL).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd4in26<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    /// Writes `buffer` and shows it with the fast refresh
+    ///
+    /// This doesn't change the refresh selected with [WaveshareDisplay::set_lut].
+    pub async fn display_fast(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.wait_until_idle(spi).await?;
+        self.load_temperature(spi, FAST_TEMPERATURE).await?;
+        self.update_frame(spi, buffer).await?;
+        self.turn_on_display(spi, 0xC7).await
+    }
+
+    /// Writes a frame with 4 gray levels, shown by the next [WaveshareDisplay::display_frame]
+    ///
+    /// `buffer` holds 2 bits per pixel, MSB first, with `0b00` black, `0b01` dark gray,
+    /// `0b10` light gray and `0b11` white. It has to be [GRAY4_BUFFER_LEN] bytes long.
+    ///
+    /// The high bits go into the black/white RAM and the low bits into the red RAM,
+    /// the grayscale waveform is loaded from the OTP.
+    pub async fn update_frame_gray4(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        assert!(buffer.len() == GRAY4_BUFFER_LEN);
+        self.wait_until_idle(spi).await?;
+        self.load_temperature(spi, GRAY4_TEMPERATURE).await?;
+
+        self.write_gray4_plane(spi, Command::WriteRam, buffer, true)
+            .await?;
+        self.write_gray4_plane(spi, Command::WriteRam2, buffer, false)
+            .await?;
+
+        self.gray4 = true;
+        Ok(())
+    }
+
+    async fn write_gray4_plane(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        buffer: &[u8],
+        high: bool,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.use_full_frame(spi).await?;
+        self.command(spi, command).await?;
+
+        let mut row = [0u8; ROW_BYTES];
+        for pixels in buffer.chunks(ROW_BYTES * 2) {
+            for (byte, pixels) in row.iter_mut().zip(pixels.chunks(2)) {
+                let (high_bits, low_bits) = split_gray4(pixels);
+                *byte = if high { high_bits } else { low_bits };
+            }
+            self.interface.data(spi, &row).await?;
+        }
+        Ok(())
+    }
+
+    /// Loads the waveform for a fixed temperature instead of the measured one
+    async fn load_temperature(
+        &mut self,
+        spi: &mut SPI,
+        temperature: u8,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.cmd_with_data(spi, Command::TemperatureSensorSelection, &[0x80])
+            .await?;
+        // Enable clock signal, load temperature value
+        self.turn_on_display(spi, 0xB1).await?;
+
+        self.cmd_with_data(spi, Command::TemperatureSensorControlWrite, &[temperature])
+            .await?;
+        // Enable clock signal, load the waveform for the written temperature
+        self.turn_on_display(spi, 0x91).await
+    }
+
+    async fn turn_on_display(
+        &mut self,
+        spi: &mut SPI,
+        update: u8,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[update])
+            .await?;
+        self.command(spi, Command::MasterActivation).await?;
+        self.wait_until_idle(spi).await
+    }
+
+    async fn command(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.cmd(spi, command).await
+    }
+
+    async fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    async fn use_full_frame(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        // choose full frame/ram
+        self.set_ram_area(spi, 0, 0, WIDTH - 1, HEIGHT - 1).await?;
+
+        // start from the beginning
+        self.set_ram_counter(spi, 0, 0).await
+    }
+
+    /// Sets both X and Y pixels ranges
+    ///
+    /// The SSD1677 uses pixel positions for x as well, so no shifting is needed here
+    async fn set_ram_area(
+        &mut self,
+        spi: &mut SPI,
+        start_x: u32,
+        start_y: u32,
+        end_x: u32,
+        end_y: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.cmd_with_data(
+            spi,
+            Command::SetRamXAddressStartEndPosition,
+            &[
+                start_x as u8,
+                (start_x >> 8) as u8,
+                end_x as u8,
+                (end_x >> 8) as u8,
+            ],
+        )
+        .await?;
+
+        self.cmd_with_data(
+            spi,
+            Command::SetRamYAddressStartEndPosition,
+            &[
+                start_y as u8,
+                (start_y >> 8) as u8,
+                end_y as u8,
+                (end_y >> 8) as u8,
+            ],
+        )
+        .await
+    }
+
+    /// Sets both X and Y pixels counters when writing data to RAM
+    async fn set_ram_counter(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.cmd_with_data(
+            spi,
+            Command::SetRamXAddressCounter,
+            &[x as u8, (x >> 8) as u8],
+        )
+        .await?;
+
+        self.cmd_with_data(
+            spi,
+            Command::SetRamYAddressCounter,
+            &[y as u8, (y >> 8) as u8],
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 800);
+        assert_eq!(HEIGHT, 480);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+        assert_eq!(GRAY4_BUFFER_LEN, 2 * ROW_BYTES * HEIGHT as usize);
+    }
+
+    #[test]
+    fn gray4_levels() {
+        // black, dark gray, light gray, white
+        assert_eq!(
+            split_gray4(&[0b0001_1011, 0b0001_1011]),
+            (0b0011_0011, 0b0101_0101)
+        );
+        assert_eq!(split_gray4(&[0x00, 0x00]), (0x00, 0x00));
+        assert_eq!(split_gray4(&[0xFF, 0xFF]), (0xFF, 0xFF));
+    }
+
+    #[test]
+    fn gray4_pixel_order() {
+        // only the first pixel is white
+        assert_eq!(split_gray4(&[0b1100_0000, 0x00]), (0x80, 0x80));
+        // only the last pixel is light gray
+        assert_eq!(split_gray4(&[0x00, 0b0000_0010]), (0x01, 0x00));
+    }
+}
@@ -11,7 +11,7 @@ use embedded_hal_async::{digital::Wait, spi::SpiDevice};
 
 use crate::color::OctColor;
 use crate::error::ErrorKind;
-use crate::interface::DisplayInterface;
+use crate::interface::{BusyLevel, DisplayDelay, DisplayInterface, ResetTiming};
 use crate::traits::{ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay};
 
 pub(crate) mod command;
@@ -36,17 +36,20 @@ pub const HEIGHT: u32 = 448;
 pub const DEFAULT_BACKGROUND_COLOR: OctColor = OctColor::White;
 /// Default mode of writing data (single byte vs blockwise)
 const SINGLE_BYTE_WRITE: bool = true;
+const BUSY_LEVEL: BusyLevel = BusyLevel::Low;
+const RESET_TIMING: ResetTiming = ResetTiming::new(10_000, 2_000);
 
 /// Epd5in65f driver
 ///
-pub struct Epd5in65f<SPI, BUSY, DC, RST> {
+pub struct Epd5in65f<SPI, BUSY, DC, RST, DELAY> {
     /// Connection Interface
-    interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
     /// Background Color
     color: OctColor,
 }
 
-impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd5in65f<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST>
+    for Epd5in65f<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -56,11 +59,13 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
 }
 
-impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd5in65f<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Epd5in65f<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -70,10 +75,11 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         // Reset the device
-        self.interface.reset(spi, 10_000, 2_000).await?;
+        self.interface.reset().await?;
 
         self.cmd_with_data(spi, Command::PanelSetting, &[0xEF, 0x08])
             .await?;
@@ -94,14 +100,15 @@ where
 
         self.cmd_with_data(spi, Command::FlashMode, &[0xAA]).await?;
 
-        self.interface.delay(spi, 100_000).await?;
+        self.interface.delay(100_000).await;
 
         self.update_vcom(spi).await?;
         Ok(())
     }
 }
 
-impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd5in65f<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd5in65f<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -111,16 +118,19 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type DisplayColor = OctColor;
     async fn new(
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        delay: DELAY,
         delay_us: Option<u32>,
     ) -> Result<Self, Self::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        let interface =
+            DisplayInterface::new(Some(busy), dc, rst, delay, RESET_TIMING, delay_us, None);
         let color = DEFAULT_BACKGROUND_COLOR;
 
         let mut epd = Epd5in65f { interface, color };
@@ -131,11 +141,13 @@ where
     }
 
     async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.require_reset_pin()?;
         self.init(spi).await
     }
 
     async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.cmd_with_data(spi, Command::DeepSleep, &[0xA5]).await?;
+        self.interface.mark_asleep();
         Ok(())
     }
 
@@ -148,16 +160,32 @@ where
         Ok(())
     }
 
+    /// Writes `buffer` into a window of the frame, the rest of the frame is kept
+    ///
+    /// `buffer` holds two pixels per byte, so `x` and `width` have to be even. Returns
+    /// [ErrorKind::InvalidWindow] if they aren't, if the window doesn't lie inside the
+    /// display or if the buffer has a different size. [WaveshareDisplay::display_frame]
+    /// still refreshes the whole display.
     async fn update_partial_frame(
         &mut self,
-        _spi: &mut SPI,
-        _buffer: &[u8],
-        _x: u32,
-        _y: u32,
-        _width: u32,
-        _height: u32,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
     ) -> Result<(), Self::Error> {
-        unimplemented!();
+        if !valid_window(x, y, width, height) || buffer.len() != (width / 2 * height) as usize {
+            return Err(ErrorKind::InvalidWindow);
+        }
+
+        self.wait_until_idle(spi).await?;
+        self.update_vcom(spi).await?;
+        self.command(spi, Command::PartialIn).await?;
+        self.set_partial_window(spi, x, y, width, height).await?;
+        self.cmd_with_data(spi, Command::DataStartTransmission1, buffer)
+            .await?;
+        self.command(spi, Command::PartialOut).await
     }
 
     async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
@@ -208,6 +236,37 @@ where
         HEIGHT
     }
 
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
+
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
     async fn set_lut(
         &mut self,
         _spi: &mut SPI,
@@ -216,12 +275,12 @@ where
         unimplemented!();
     }
 
-    async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.interface.wait_until_idle(spi, true).await
+    async fn wait_until_idle(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.wait_until_idle(BUSY_LEVEL).await
     }
 }
 
-impl<SPI, BUSY, DC, RST> Epd5in65f<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> Epd5in65f<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -231,6 +290,7 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn command(
         &mut self,
@@ -257,8 +317,10 @@ where
         self.interface.cmd_with_data(spi, command, data).await
     }
 
-    async fn wait_busy_low(&mut self, spi: &mut SPI) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        self.interface.wait_until_idle(spi, false).await
+    async fn wait_busy_low(&mut self, _spi: &mut SPI) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface
+            .wait_until_idle(BUSY_LEVEL.inverted())
+            .await
     }
 
     async fn send_resolution(
@@ -275,6 +337,37 @@ where
         self.send_data(spi, &[h as u8]).await
     }
 
+    async fn set_partial_window(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let last_x = x + width - 1;
+        let last_y = y + height - 1;
+        // Gates scan both inside and outside of the partial window (default)
+        let pt_scan = 0x01;
+
+        self.cmd_with_data(
+            spi,
+            Command::PartialWindow,
+            &[
+                (x >> 8) as u8,
+                x as u8,
+                (last_x >> 8) as u8,
+                last_x as u8,
+                (y >> 8) as u8,
+                y as u8,
+                (last_y >> 8) as u8,
+                last_y as u8,
+                pt_scan,
+            ],
+        )
+        .await
+    }
+
     async fn update_vcom(
         &mut self,
         spi: &mut SPI,
@@ -286,9 +379,20 @@ where
     }
 }
 
+/// Checks that a window lies inside the display and starts and ends on a byte
+fn valid_window(x: u32, y: u32, width: u32, height: u32) -> bool {
+    width != 0
+        && height != 0
+        && x % 2 == 0
+        && width % 2 == 0
+        && x + width <= WIDTH
+        && y + height <= HEIGHT
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::{block_on, mocks};
 
     #[test]
     fn epd_size() {
@@ -296,4 +400,33 @@ mod tests {
         assert_eq!(HEIGHT, 448);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, OctColor::White);
     }
+
+    #[test]
+    fn partial_window() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        log.busy_level.set(true);
+        let mut epd = block_on(Epd5in65f::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        // 200x100 pixels at x = 300, y = 256 need the high bytes
+        log.clear();
+        let window = [0x11; 100 * 100];
+        block_on(epd.update_partial_frame(&mut spi, &window, 300, 256, 200, 100)).unwrap();
+        assert_eq!(log.commands(), [0x50, 0x91, 0x90, 0x10, 0x92]);
+        assert_eq!(
+            log.data_of(0x90, 0),
+            [0x01, 0x2C, 0x01, 0xF3, 0x01, 0x00, 0x01, 0x63, 0x01]
+        );
+        assert_eq!(log.data_of(0x10, 0), window);
+
+        log.clear();
+        let odd_x = block_on(epd.update_partial_frame(&mut spi, &window, 301, 256, 200, 100));
+        assert!(matches!(odd_x, Err(ErrorKind::InvalidWindow)));
+        let odd_width = block_on(epd.update_partial_frame(&mut spi, &window, 300, 256, 199, 100));
+        assert!(matches!(odd_width, Err(ErrorKind::InvalidWindow)));
+        let outside = block_on(epd.update_partial_frame(&mut spi, &window, 402, 256, 200, 100));
+        assert!(matches!(outside, Err(ErrorKind::InvalidWindow)));
+        let wrong_size = block_on(epd.update_partial_frame(&mut spi, &window, 300, 256, 100, 100));
+        assert!(matches!(wrong_size, Err(ErrorKind::InvalidWindow)));
+        assert!(log.commands().is_empty());
+    }
 }
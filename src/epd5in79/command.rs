@@ -0,0 +1,85 @@
+//! SPI Commands for the Waveshare 5.79" E-Ink Display
+
+use crate::traits;
+
+/// Epd5in79 commands
+///
+/// The panel is driven by a SSD1683 with two RAM banks. The commands for the
+/// right half (slave) are the ones of the left half (master) with bit 7 set.
+///
+/// Should rarely (never?) be needed directly.
+///
+/// For more infos about the addresses and what they are doing look into the PDFs.
+#[allow(dead_code)]
+#[derive(Copy, Clone)]
+pub(crate) enum Command {
+    /// Driver output control, sets the number of gates and the scanning order
+    DriverOutputControl = 0x01,
+    /// Booster soft start control
+    BoosterSoftStartControl = 0x0C,
+    /// Enters deep sleep mode
+    DeepSleepMode = 0x10,
+    /// Data entry mode setting of the left half
+    DataEntryModeSetting = 0x11,
+    /// Software reset, resets all commands and parameters to their default values
+    SwReset = 0x12,
+    /// Selects the internal or an external temperature sensor
+    TemperatureSensorSelection = 0x18,
+    /// Starts the update sequence selected with `DisplayUpdateControl2`
+    MasterActivation = 0x20,
+    /// RAM content options for the update
+    DisplayUpdateControl1 = 0x21,
+    /// Display update sequence option
+    DisplayUpdateControl2 = 0x22,
+    /// Write to the black/white RAM of the left half
+    WriteRam = 0x24,
+    /// Write to the previous image RAM of the left half
+    WriteRam2 = 0x26,
+    /// Border waveform control
+    BorderWaveformControl = 0x3C,
+    /// Start and end of the RAM window of the left half in x direction (in bytes)
+    SetRamXAddressStartEndPosition = 0x44,
+    /// Start and end of the RAM window of the left half in y direction
+    SetRamYAddressStartEndPosition = 0x45,
+    /// RAM x address counter of the left half
+    SetRamXAddressCounter = 0x4E,
+    /// RAM y address counter of the left half
+    SetRamYAddressCounter = 0x4F,
+    /// Data entry mode setting of the right half
+    SlaveDataEntryModeSetting = 0x91,
+    /// Write to the black/white RAM of the right half
+    SlaveWriteRam = 0xA4,
+    /// Write to the previous image RAM of the right half
+    SlaveWriteRam2 = 0xA6,
+    /// Start and end of the RAM window of the right half in x direction (in bytes)
+    SlaveSetRamXAddressStartEndPosition = 0xC4,
+    /// Start and end of the RAM window of the right half in y direction
+    SlaveSetRamYAddressStartEndPosition = 0xC5,
+    /// RAM x address counter of the right half
+    SlaveSetRamXAddressCounter = 0xCE,
+    /// RAM y address counter of the right half
+    SlaveSetRamYAddressCounter = 0xCF,
+}
+
+impl traits::Command for Command {
+    /// Returns the address of the command
+    fn address(self) -> u8 {
+        self as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn command_addr() {
+        assert_eq!(Command::WriteRam.address(), 0x24);
+        assert_eq!(Command::SlaveWriteRam.address(), 0x24 | 0x80);
+        assert_eq!(
+            Command::SlaveSetRamXAddressCounter.address(),
+            Command::SetRamXAddressCounter.address() | 0x80
+        );
+    }
+}
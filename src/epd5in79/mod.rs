@@ -0,0 +1,574 @@
+//! A simple Driver for the Waveshare 5.79" E-Ink Display via SPI
+//!
+//! The 792x272 panel is split into two halves of 396x272 pixels. Each half has its
+//! own RAM bank (400 pixels wide) inside of the SSD1683 controller, and the RAM of
+//! the right half is mirrored horizontally: its first pixel is the rightmost one of
+//! the panel.
+//!
+//! The driver takes care of splitting the rows, so the buffer is a plain
+//! `792 / 8 * 272` byte frame like for every other display. As 396 isn't a multiple
+//! of 8, the middle byte of every row (pixels 392..400) is sent to both halves.
+//!
+//! # References
+//!
+//! - [Waveshare product page](https://www.waveshare.com/wiki/5.79inch_e-Paper_Module_Manual)
+//! - [Waveshare C driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_5in79.c)
+//! - [Waveshare Python driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd5in79.py)
+use core::fmt::{Debug, Display};
+use core::ops::Range;
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+
+use crate::buffer_len;
+use crate::color::Color;
+use crate::error::ErrorKind;
+use crate::interface::{BusyLevel, DisplayDelay, DisplayInterface, ResetTiming};
+use crate::traits::{ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay};
+
+pub(crate) mod command;
+use self::command::Command;
+
+/// Full size buffer for use with the 5in79 EPD
+#[cfg(feature = "graphics")]
+pub type Display5in79 = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize, HEIGHT as usize) },
+    Color,
+>;
+
+/// Width of the display
+pub const WIDTH: u32 = 792;
+/// Height of the display
+pub const HEIGHT: u32 = 272;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+const BUSY_LEVEL: BusyLevel = BusyLevel::High;
+const RESET_TIMING: ResetTiming = ResetTiming::new(10_000, 2_000);
+const SINGLE_BYTE_WRITE: bool = false;
+
+/// Bytes of one row of the full frame
+const ROW_BYTES: usize = WIDTH as usize / 8;
+/// Bytes of one row of the RAM of one half (400 pixels)
+const HALF_BYTES: usize = 50;
+/// Row byte holding the last pixels of the left and the first of the right half
+const SHARED_BYTE: usize = 49;
+
+/// The RAM commands of one half of the panel
+#[derive(Clone, Copy)]
+struct Half {
+    data_entry: Command,
+    write_ram: Command,
+    write_ram2: Command,
+    x_window: Command,
+    y_window: Command,
+    x_counter: Command,
+    y_counter: Command,
+}
+
+/// Left half of the panel
+const MASTER: Half = Half {
+    data_entry: Command::DataEntryModeSetting,
+    write_ram: Command::WriteRam,
+    write_ram2: Command::WriteRam2,
+    x_window: Command::SetRamXAddressStartEndPosition,
+    y_window: Command::SetRamYAddressStartEndPosition,
+    x_counter: Command::SetRamXAddressCounter,
+    y_counter: Command::SetRamYAddressCounter,
+};
+
+/// Right half of the panel, its RAM is mirrored horizontally
+const SLAVE: Half = Half {
+    data_entry: Command::SlaveDataEntryModeSetting,
+    write_ram: Command::SlaveWriteRam,
+    write_ram2: Command::SlaveWriteRam2,
+    x_window: Command::SlaveSetRamXAddressStartEndPosition,
+    y_window: Command::SlaveSetRamYAddressStartEndPosition,
+    x_counter: Command::SlaveSetRamXAddressCounter,
+    y_counter: Command::SlaveSetRamYAddressCounter,
+};
+
+/// RAM byte addresses of the left and the right half covering the row bytes `x_start..x_end`
+fn half_ranges(x_start: usize, x_end: usize) -> (Range<usize>, Range<usize>) {
+    let master = x_start.min(HALF_BYTES)..x_end.min(HALF_BYTES);
+    let slave_start = x_start.max(SHARED_BYTE);
+    let slave = if x_end > slave_start {
+        (ROW_BYTES - x_end)..(ROW_BYTES - slave_start)
+    } else {
+        0..0
+    };
+    (master, slave)
+}
+
+/// Splits one row of a window starting at row byte `x_start` into the data for both halves
+///
+/// The left half is a sub slice of `row`, the mirrored data of the right half is
+/// written to `slave`.
+fn split_row<'a>(
+    row: &'a [u8],
+    x_start: usize,
+    slave: &'a mut [u8; HALF_BYTES],
+) -> (&'a [u8], &'a [u8]) {
+    let (master_range, slave_range) = half_ranges(x_start, x_start + row.len());
+
+    let master = if master_range.is_empty() {
+        &row[..0]
+    } else {
+        &row[master_range.start - x_start..master_range.end - x_start]
+    };
+
+    let len = slave_range.len();
+    for (byte, address) in slave.iter_mut().zip(slave_range) {
+        *byte = row[ROW_BYTES - 1 - address - x_start].reverse_bits();
+    }
+
+    (master, &slave[..len])
+}
+
+/// Epd5in79 driver
+///
+pub struct Epd5in79<SPI, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    color: Color,
+    /// Refresh LUT
+    refresh: RefreshLut,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST>
+    for Epd5in79<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    type Error = ErrorKind<SPI, BUSY, DC, RST>;
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Epd5in79<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.reset().await?;
+        self.wait_until_idle(spi).await?;
+
+        self.command(spi, Command::SwReset).await?;
+        self.wait_until_idle(spi).await?;
+
+        // use the internal temperature sensor
+        self.cmd_with_data(spi, Command::TemperatureSensorSelection, &[0x80])
+            .await?;
+
+        self.cmd_with_data(
+            spi,
+            Command::BoosterSoftStartControl,
+            &[0xAE, 0xC7, 0xC3, 0xC0, 0x80],
+        )
+        .await?;
+
+        // gate count (HEIGHT - 1) and the default scanning order
+        self.cmd_with_data(
+            spi,
+            Command::DriverOutputControl,
+            &[(HEIGHT - 1) as u8, ((HEIGHT - 1) >> 8) as u8, 0x00],
+        )
+        .await?;
+
+        self.cmd_with_data(spi, Command::BorderWaveformControl, &[0x01])
+            .await?;
+
+        // x increment, y increment for both halves, the mirroring is done while splitting the rows
+        for half in [MASTER, SLAVE] {
+            self.cmd_with_data(spi, half.data_entry, &[0x03]).await?;
+            self.set_ram_area(spi, half, 0..HALF_BYTES, 0, HEIGHT)
+                .await?;
+        }
+
+        self.wait_until_idle(spi).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd5in79<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    type DisplayColor = Color;
+    async fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        delay: DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Self::Error> {
+        let interface =
+            DisplayInterface::new(Some(busy), dc, rst, delay, RESET_TIMING, delay_us, None);
+
+        let mut epd = Epd5in79 {
+            interface,
+            color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+        };
+
+        epd.init(spi).await?;
+
+        Ok(epd)
+    }
+
+    async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::DeepSleepMode, &[0x01])
+            .await?;
+        self.interface.mark_asleep();
+        Ok(())
+    }
+
+    async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.require_reset_pin()?;
+        self.init(spi).await
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
+
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        assert!(buffer.len() == buffer_len(WIDTH as usize, HEIGHT as usize));
+        self.wait_until_idle(spi).await?;
+        self.write_window(spi, buffer, 0, 0, ROW_BYTES, HEIGHT)
+            .await
+    }
+
+    /// Writes `buffer` into the RAM window starting at (x, y)
+    ///
+    /// The window may span both halves, x and width need to be multiples of 8.
+    async fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        assert!(x % 8 == 0 && width % 8 == 0);
+        assert!(x + width <= WIDTH && y + height <= HEIGHT);
+        assert!(buffer.len() == buffer_len(width as usize, height as usize));
+
+        self.wait_until_idle(spi).await?;
+        self.write_window(spi, buffer, x as usize / 8, y, width as usize / 8, height)
+            .await?;
+
+        // restore the full windows for the next frame update
+        for half in [MASTER, SLAVE] {
+            self.set_ram_area(spi, half, 0..HALF_BYTES, 0, HEIGHT)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        let update = match self.refresh {
+            // Load temperature and waveform, DISPLAY with DISPLAY Mode 1
+            RefreshLut::Full => 0xF7,
+            // Same as above with DISPLAY Mode 2, only changed pixels are driven
+            RefreshLut::Quick => 0xFF,
+        };
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[update])
+            .await?;
+        self.command(spi, Command::MasterActivation).await?;
+        self.wait_until_idle(spi).await
+    }
+
+    async fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_frame(spi, buffer).await?;
+        self.display_frame(spi).await
+    }
+
+    async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        let color = self.color.get_byte_value();
+        self.wait_until_idle(spi).await?;
+
+        for half in [MASTER, SLAVE] {
+            for write_ram in [half.write_ram, half.write_ram2] {
+                self.set_ram_counter(spi, half, 0, 0).await?;
+                self.command(spi, write_ram).await?;
+                self.interface
+                    .data_x_times(spi, color, (HALF_BYTES as u32) * HEIGHT)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The waveforms are always taken from the OTP, `RefreshLut::Quick` selects
+    /// the differential display mode for the next refreshes.
+    async fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Self::Error> {
+        if let Some(refresh_lut) = refresh_rate {
+            self.refresh = refresh_lut;
+        }
+        Ok(())
+    }
+
+    async fn wait_until_idle(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.wait_until_idle(BUSY_LEVEL).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd5in79<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    async fn command(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.cmd(spi, command).await
+    }
+
+    async fn send_data(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.data(spi, data).await
+    }
+
+    async fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    /// Writes a window of `width` row bytes and `height` rows to both halves
+    ///
+    /// Every half is written on its own, so the rows are split twice.
+    async fn write_window(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x_start: usize,
+        y: u32,
+        width: usize,
+        height: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let (master_range, slave_range) = half_ranges(x_start, x_start + width);
+        let mut slave = [0u8; HALF_BYTES];
+
+        if !master_range.is_empty() {
+            self.set_ram_area(spi, MASTER, master_range.clone(), y, height)
+                .await?;
+            self.set_ram_counter(spi, MASTER, master_range.start, y)
+                .await?;
+            self.command(spi, MASTER.write_ram).await?;
+            for row in buffer.chunks(width) {
+                let (master, _) = split_row(row, x_start, &mut slave);
+                self.send_data(spi, master).await?;
+            }
+        }
+
+        if !slave_range.is_empty() {
+            self.set_ram_area(spi, SLAVE, slave_range.clone(), y, height)
+                .await?;
+            self.set_ram_counter(spi, SLAVE, slave_range.start, y)
+                .await?;
+            self.command(spi, SLAVE.write_ram).await?;
+            for row in buffer.chunks(width) {
+                let (_, slave) = split_row(row, x_start, &mut slave);
+                self.send_data(spi, slave).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets the RAM window of one half, x in bytes
+    async fn set_ram_area(
+        &mut self,
+        spi: &mut SPI,
+        half: Half,
+        x: Range<usize>,
+        y: u32,
+        height: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let end_y = y + height - 1;
+        self.cmd_with_data(spi, half.x_window, &[x.start as u8, (x.end - 1) as u8])
+            .await?;
+        self.cmd_with_data(
+            spi,
+            half.y_window,
+            &[y as u8, (y >> 8) as u8, end_y as u8, (end_y >> 8) as u8],
+        )
+        .await
+    }
+
+    /// Sets the RAM address counters of one half, x in bytes
+    async fn set_ram_counter(
+        &mut self,
+        spi: &mut SPI,
+        half: Half,
+        x: usize,
+        y: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.cmd_with_data(spi, half.x_counter, &[x as u8]).await?;
+        self.cmd_with_data(spi, half.y_counter, &[y as u8, (y >> 8) as u8])
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 792);
+        assert_eq!(HEIGHT, 272);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+        assert_eq!(ROW_BYTES, 99);
+    }
+
+    #[test]
+    fn split_full_row() {
+        // first and last pixel black, everything else white
+        let mut row = [0xFF; ROW_BYTES];
+        row[0] = 0b0111_1111;
+        row[ROW_BYTES - 1] = 0b1111_1110;
+        // a recognizable pattern on pixels 392..400, 392..396 belong to the left half
+        row[SHARED_BYTE] = 0b0101_0011;
+
+        let mut slave = [0u8; HALF_BYTES];
+        let (master, slave) = split_row(&row, 0, &mut slave);
+
+        assert_eq!(master.len(), HALF_BYTES);
+        assert_eq!(master[0], 0b0111_1111);
+        assert_eq!(master[1..SHARED_BYTE], [0xFF; SHARED_BYTE - 1]);
+        assert_eq!(master[SHARED_BYTE], 0b0101_0011);
+
+        // the rightmost pixel is the first one in the mirrored RAM
+        assert_eq!(slave.len(), HALF_BYTES);
+        assert_eq!(slave[0], 0b0111_1111);
+        assert_eq!(slave[1..SHARED_BYTE], [0xFF; SHARED_BYTE - 1]);
+        // pixels 396..400 end up in the upper bits of the last byte, mirrored
+        assert_eq!(slave[SHARED_BYTE], 0b1100_1010);
+    }
+
+    #[test]
+    fn split_window_rows() {
+        // window over the row bytes 47..51
+        let row = [0x01, 0x02, 0x04, 0x08];
+
+        let (master_range, slave_range) = half_ranges(47, 51);
+        assert_eq!(master_range, 47..50);
+        assert_eq!(slave_range, 48..50);
+
+        let mut slave = [0u8; HALF_BYTES];
+        let (master, slave) = split_row(&row, 47, &mut slave);
+        assert_eq!(master, [0x01, 0x02, 0x04]);
+        // row byte 50 at RAM address 48, the shared byte 49 at RAM address 49
+        assert_eq!(slave, [0x10, 0x20]);
+    }
+
+    #[test]
+    fn split_window_one_half() {
+        assert_eq!(half_ranges(0, 10), (0..10, 0..0));
+        assert!(half_ranges(60, 99).0.is_empty());
+        assert_eq!(half_ranges(60, 99).1, 0..39);
+
+        let row = [0x80; 39];
+        let mut slave = [0u8; HALF_BYTES];
+        let (master, slave) = split_row(&row, 60, &mut slave);
+        assert!(master.is_empty());
+        assert_eq!(slave, [0x01; 39]);
+    }
+}
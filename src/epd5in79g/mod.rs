@@ -0,0 +1,423 @@
+//! A simple Driver for the Waveshare 5.79" (G) E-Ink Display via SPI
+//!
+//! The panel shows black, white, yellow and red. Every pixel takes 2 bits, four
+//! pixels are packed into one byte, see [QuadColor].
+//!
+//! Like the black/white [epd5in79](crate::epd5in79), the 792x272 panel is split into
+//! two halves of 396x272 pixels with their own RAM, the RAM of the right half is
+//! mirrored horizontally. With 2 bits per pixel both halves are exactly 99 bytes wide,
+//! so no byte is shared between them.
+//!
+//! # References
+//!
+//! - [Waveshare product page](https://www.waveshare.com/wiki/5.79inch_e-Paper_Module_(G)_Manual)
+//! - [Waveshare C driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_5in79g.c)
+//! - [Waveshare Python driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd5in79g.py)
+use core::fmt::{Debug, Display};
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+
+use crate::color::QuadColor;
+use crate::error::ErrorKind;
+use crate::interface::{BusyLevel, DisplayDelay, DisplayInterface, ResetTiming};
+use crate::quad_buffer_len;
+use crate::traits::{ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay};
+
+pub(crate) mod command;
+use self::command::Command;
+
+/// Full size buffer for use with the 5in79g EPD
+///
+/// Every pixel takes 2 bits, so the buffer holds four pixels per byte.
+#[cfg(feature = "graphics")]
+pub type Display5in79g = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { quad_buffer_len(WIDTH as usize, HEIGHT as usize) },
+    QuadColor,
+>;
+
+/// Width of the display
+pub const WIDTH: u32 = 792;
+/// Height of the display
+pub const HEIGHT: u32 = 272;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: QuadColor = QuadColor::White;
+const BUSY_LEVEL: BusyLevel = BusyLevel::Low;
+const RESET_TIMING: ResetTiming = ResetTiming::new(10_000, 2_000);
+const SINGLE_BYTE_WRITE: bool = false;
+
+/// Bytes of one row of the full frame
+const ROW_BYTES: usize = WIDTH as usize * 2 / 8;
+/// Bytes of one row of one half
+const HALF_BYTES: usize = ROW_BYTES / 2;
+/// Bytes of a full frame with 2 bits per pixel
+const FRAME_SIZE: usize = quad_buffer_len(WIDTH as usize, HEIGHT as usize);
+
+/// Splits a row into the data for the left half and the mirrored data for the right half
+fn split_row<'a>(row: &'a [u8], slave: &'a mut [u8; HALF_BYTES]) -> (&'a [u8], &'a [u8]) {
+    let (master, right) = row.split_at(HALF_BYTES);
+    for (byte, &pixels) in slave.iter_mut().zip(right.iter().rev()) {
+        *byte = QuadColor::mirror_byte(pixels);
+    }
+    (master, &slave[..])
+}
+
+/// Epd5in79g driver
+///
+pub struct Epd5in79g<SPI, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    color: QuadColor,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST>
+    for Epd5in79g<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    type Error = ErrorKind<SPI, BUSY, DC, RST>;
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Epd5in79g<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.reset().await?;
+        self.wait_until_idle(spi).await?;
+
+        self.command(spi, Command::SwReset).await?;
+        self.wait_until_idle(spi).await?;
+
+        // use the internal temperature sensor
+        self.cmd_with_data(spi, Command::TemperatureSensorSelection, &[0x80])
+            .await?;
+
+        // gate count (HEIGHT - 1) and the default scanning order
+        self.cmd_with_data(
+            spi,
+            Command::DriverOutputControl,
+            &[(HEIGHT - 1) as u8, ((HEIGHT - 1) >> 8) as u8, 0x00],
+        )
+        .await?;
+
+        self.cmd_with_data(spi, Command::BorderWaveformControl, &[0x01])
+            .await?;
+
+        // x increment, y increment for both halves, the mirroring is done while splitting the rows
+        self.cmd_with_data(spi, Command::DataEntryModeSetting, &[0x03])
+            .await?;
+        self.cmd_with_data(spi, Command::SlaveDataEntryModeSetting, &[0x03])
+            .await?;
+        self.set_ram_area(spi).await?;
+
+        self.wait_until_idle(spi).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd5in79g<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    type DisplayColor = QuadColor;
+    async fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        delay: DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Self::Error> {
+        let interface =
+            DisplayInterface::new(Some(busy), dc, rst, delay, RESET_TIMING, delay_us, None);
+        let color = DEFAULT_BACKGROUND_COLOR;
+
+        let mut epd = Epd5in79g { interface, color };
+
+        epd.init(spi).await?;
+
+        Ok(epd)
+    }
+
+    async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::DeepSleepMode, &[0x01])
+            .await?;
+        self.interface.mark_asleep();
+        Ok(())
+    }
+
+    async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.require_reset_pin()?;
+        self.init(spi).await
+    }
+
+    fn set_background_color(&mut self, color: QuadColor) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &QuadColor {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
+
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    /// Transmits a full frame with 2 bits per pixel
+    ///
+    /// The buffer needs to be `792 * 2 / 8 * 272` bytes long, like the one of [Display5in79g].
+    async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        assert!(buffer.len() == FRAME_SIZE);
+        let mut slave = [0u8; HALF_BYTES];
+        self.wait_until_idle(spi).await?;
+
+        self.set_ram_counter(spi).await?;
+        self.command(spi, Command::WriteRam).await?;
+        for row in buffer.chunks(ROW_BYTES) {
+            let (master, _) = split_row(row, &mut slave);
+            self.send_data(spi, master).await?;
+        }
+
+        self.command(spi, Command::SlaveWriteRam).await?;
+        for row in buffer.chunks(ROW_BYTES) {
+            let (_, slave) = split_row(row, &mut slave);
+            self.send_data(spi, slave).await?;
+        }
+        Ok(())
+    }
+
+    /// The panel doesn't support partial updates, this always returns [ErrorKind::Unsupported]
+    async fn update_partial_frame(
+        &mut self,
+        _spi: &mut SPI,
+        _buffer: &[u8],
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+    ) -> Result<(), Self::Error> {
+        Err(ErrorKind::Unsupported)
+    }
+
+    async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xF7])
+            .await?;
+        self.command(spi, Command::MasterActivation).await?;
+        self.wait_until_idle(spi).await
+    }
+
+    async fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_frame(spi, buffer).await?;
+        self.display_frame(spi).await
+    }
+
+    async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        // same pixels on both halves, the mirroring doesn't matter here
+        let color = self.color.get_byte_value();
+        self.wait_until_idle(spi).await?;
+
+        self.set_ram_counter(spi).await?;
+        for write_ram in [Command::WriteRam, Command::SlaveWriteRam] {
+            self.command(spi, write_ram).await?;
+            self.interface
+                .data_x_times(spi, color, HALF_BYTES as u32 * HEIGHT)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// The panel has no selectable LUTs, this always returns [ErrorKind::Unsupported]
+    async fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        _refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Self::Error> {
+        Err(ErrorKind::Unsupported)
+    }
+
+    async fn wait_until_idle(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.wait_until_idle(BUSY_LEVEL).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd5in79g<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    async fn command(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.cmd(spi, command).await
+    }
+
+    async fn send_data(
+        &mut self,
+        spi: &mut SPI,
+        data: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.data(spi, data).await
+    }
+
+    async fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    /// Sets the full RAM window of both halves
+    async fn set_ram_area(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let end_x = (HALF_BYTES - 1) as u8;
+        let end_y = HEIGHT - 1;
+        let y_window = [0x00, 0x00, end_y as u8, (end_y >> 8) as u8];
+
+        self.cmd_with_data(spi, Command::SetRamXAddressStartEndPosition, &[0, end_x])
+            .await?;
+        self.cmd_with_data(spi, Command::SetRamYAddressStartEndPosition, &y_window)
+            .await?;
+        self.cmd_with_data(
+            spi,
+            Command::SlaveSetRamXAddressStartEndPosition,
+            &[0, end_x],
+        )
+        .await?;
+        self.cmd_with_data(spi, Command::SlaveSetRamYAddressStartEndPosition, &y_window)
+            .await
+    }
+
+    /// Moves the RAM address counters of both halves to the start
+    async fn set_ram_counter(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.cmd_with_data(spi, Command::SetRamXAddressCounter, &[0])
+            .await?;
+        self.cmd_with_data(spi, Command::SetRamYAddressCounter, &[0, 0])
+            .await?;
+        self.cmd_with_data(spi, Command::SlaveSetRamXAddressCounter, &[0])
+            .await?;
+        self.cmd_with_data(spi, Command::SlaveSetRamYAddressCounter, &[0, 0])
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 792);
+        assert_eq!(HEIGHT, 272);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, QuadColor::White);
+        assert_eq!(ROW_BYTES, 198);
+        // twice the size of a 1 bit per pixel buffer
+        assert_eq!(FRAME_SIZE, 53_856);
+        assert_eq!(
+            FRAME_SIZE,
+            2 * crate::buffer_len(WIDTH as usize, HEIGHT as usize)
+        );
+    }
+
+    #[test]
+    fn split_full_row() {
+        let mut row = [QuadColor::White.get_byte_value(); ROW_BYTES];
+        // first and last pixel red
+        row[0] = 0b11_01_01_01;
+        row[ROW_BYTES - 1] = 0b01_01_01_11;
+
+        let mut slave = [0u8; HALF_BYTES];
+        let (master, slave) = split_row(&row, &mut slave);
+        assert_eq!(master.len(), HALF_BYTES);
+        assert_eq!(master[0], 0b11_01_01_01);
+        // the rightmost pixel is the first one in the mirrored RAM
+        assert_eq!(slave.len(), HALF_BYTES);
+        assert_eq!(slave[0], 0b11_01_01_01);
+        assert!(slave[1..].iter().all(|&byte| byte == 0x55));
+    }
+}
@@ -33,7 +33,7 @@ pub(crate) enum Command {
     /// sequence. Once complete, the BUSY signal will become "1".
     PowerOn = 0x04,
 
-    /// Starting data transmission
+    /// Sets the soft start period, driving strength and minimum off time of the booster phases
     BoosterSoftStart = 0x06,
 
     /// This command makes the chip enter the deep-sleep mode to save power.
@@ -69,6 +69,18 @@ pub(crate) enum Command {
     /// Enables or disables Dual SPI mode
     DualSPI = 0x15,
 
+    /// Stores VCOM Look-Up Table with 7 groups of data. Each group contains information
+    /// for one state and is stored with 6 bytes.
+    LutForVcom = 0x20,
+    /// Stores White-to-White Look-Up Table with 7 groups of data
+    LutWhiteToWhite = 0x21,
+    /// Stores Black-to-White Look-Up Table with 7 groups of data
+    LutBlackToWhite = 0x22,
+    /// Stores White-to-Black Look-Up Table with 7 groups of data
+    LutWhiteToBlack = 0x23,
+    /// Stores Black-to-Black Look-Up Table with 7 groups of data
+    LutBlackToBlack = 0x24,
+
     /// The command controls the PLL clock frequency.
     PllControl = 0x30,
 
@@ -112,6 +124,13 @@ pub(crate) enum Command {
     PartialIn = 0x91,
     /// Quits partial update mode
     PartialOut = 0x92,
+
+    /// Cascade setting, bit 1 (TSFIX) makes the controller use the temperature of
+    /// [Command::ForceTemperature] instead of the measured one.
+    CascadeSetting = 0xE0,
+    /// Sets the temperature the waveform is selected for, used with
+    /// [Command::CascadeSetting].
+    ForceTemperature = 0xE5,
 }
 
 impl traits::Command for Command {
@@ -130,5 +149,6 @@ mod tests {
     fn command_addr() {
         assert_eq!(Command::PanelSetting.address(), 0x00);
         assert_eq!(Command::DisplayRefresh.address(), 0x12);
+        assert_eq!(Command::LutBlackToBlack.address(), 0x24);
     }
 }
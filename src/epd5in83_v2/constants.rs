@@ -0,0 +1,60 @@
+//! This file contains look-up-tables used to set voltages used during
+//! various categories of pixel refreshes.
+//!
+//! Every group takes 6 bytes: the level select of the 4 phases, their frame counts
+//! and the repeat count of the group. The full refresh uses the waveforms of the OTP.
+
+#[rustfmt::skip]
+pub(crate) const LUT_VCOM_QUICK: [u8; 42] = [
+    0x00, 0x0F, 0x0F, 0x00, 0x00, 0x01,
+    0x00, 0x0F, 0x01, 0x0F, 0x01, 0x02,
+    0x00, 0x0F, 0x0F, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const LUT_WW_QUICK: [u8; 42] = [
+    0x10, 0x0F, 0x0F, 0x00, 0x00, 0x01,
+    0x84, 0x0F, 0x01, 0x0F, 0x01, 0x02,
+    0x20, 0x0F, 0x0F, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const LUT_BW_QUICK: [u8; 42] = [
+    0x10, 0x0F, 0x0F, 0x00, 0x00, 0x01,
+    0x84, 0x0F, 0x01, 0x0F, 0x01, 0x02,
+    0x20, 0x0F, 0x0F, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const LUT_WB_QUICK: [u8; 42] = [
+    0x80, 0x0F, 0x0F, 0x00, 0x00, 0x01,
+    0x84, 0x0F, 0x01, 0x0F, 0x01, 0x02,
+    0x40, 0x0F, 0x0F, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[rustfmt::skip]
+pub(crate) const LUT_BB_QUICK: [u8; 42] = [
+    0x80, 0x0F, 0x0F, 0x00, 0x00, 0x01,
+    0x84, 0x0F, 0x01, 0x0F, 0x01, 0x02,
+    0x40, 0x0F, 0x0F, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
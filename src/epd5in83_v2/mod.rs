@@ -1,5 +1,14 @@
 //! A simple Driver for the Waveshare 5.83" v2 E-Ink Display via SPI
 //!
+//! The full refresh uses the waveforms of the OTP, `RefreshLut::Quick` uploads a
+//! faster waveform into the registers of the UC8179, see [WaveshareDisplay::set_lut].
+//!
+//! [QuickRefresh] only redraws the changed pixels with the quick waveform: write the
+//! base image with [QuickRefresh::update_old_frame], then new images (or parts of
+//! them) with [QuickRefresh::update_new_frame] and show them with
+//! [QuickRefresh::display_new_frame]. The controller keeps the shown image as the
+//! base of the next quick refresh.
+//!
 //! # References
 //!
 //! - [Datasheet](https://www.waveshare.com/5.83inch-e-paper-hat.htm)
@@ -7,13 +16,25 @@
 //! - [Waveshare Python driver](https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd5in83_V2.py)
 
 use core::fmt::{Debug, Display};
+use core::num::NonZeroU32;
 use embedded_hal::digital::{InputPin, OutputPin};
 use embedded_hal_async::{digital::Wait, spi::SpiDevice};
 
+use crate::booster::BoosterSoftStart;
 use crate::color::Color;
-use crate::interface::DisplayInterface;
+use crate::interface::{
+    BusyLevel, BusyWith, DisplayDelay, DisplayInterface, IdleDelays, ResetTiming,
+};
+use crate::pll::FrameRate;
 use crate::prelude::{ErrorKind, WaveshareDisplay};
-use crate::traits::{ErrorType, InternalWiAdditions, RefreshLut};
+use crate::traits::{
+    DualSpiBus, ErrorType, InternalWiAdditions, PartialRefreshes, PowerState, QuickRefresh,
+    RefreshLut,
+};
+
+//The Lookup Tables for the Display
+mod constants;
+use self::constants::*;
 
 pub(crate) mod command;
 use self::command::Command;
@@ -35,20 +56,155 @@ pub const WIDTH: u32 = 648;
 pub const HEIGHT: u32 = 480;
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
-const IS_BUSY_LOW: bool = true;
+const BUSY_LEVEL: BusyLevel = BusyLevel::Low;
+const RESET_TIMING: ResetTiming = ResetTiming::new(2_000, 50);
 const NUM_DISPLAY_BITS: u32 = WIDTH * HEIGHT / 8;
 const SINGLE_BYTE_WRITE: bool = true;
+/// DUSPI_EN of the dual SPI register
+const DUAL_SPI_ENABLE: u8 = 0x10;
+/// S2G and G2S non-overlap periods of 12 (reset value)
+const DEFAULT_TCON: u8 = 0x22;
+/// Waits without a BUSY pin
+///
+/// The datasheet gives no maximum times, these are guesses with a wide margin. A full
+/// refresh takes about 4 s at room temperature and gets slower in the cold.
+const IDLE_DELAYS: IdleDelays = IdleDelays {
+    reset_us: 10_000,
+    power_on_us: 300_000,
+    power_off_us: 300_000,
+    refresh_us: 10_000_000,
+};
+
+/// VCOM and data interval setting, the border color is set with
+/// [Epd5in83::set_border_color]
+///
+/// The default is the setting of the vendor driver.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VcomAndDataInterval {
+    /// BDZ: the border is left floating instead of driven with the border color
+    pub border_floating: bool,
+    /// CDI: interval between VCOM and data output in Hsync, from 2 to 17
+    pub interval: u8,
+}
+
+impl Default for VcomAndDataInterval {
+    fn default() -> Self {
+        VcomAndDataInterval {
+            border_floating: false,
+            interval: 10,
+        }
+    }
+}
+
+impl VcomAndDataInterval {
+    /// Returns the CDI\[3:0\] byte, counting down from 17 Hsync
+    pub const fn interval_bits(self) -> u8 {
+        assert!(self.interval >= 2 && self.interval <= 17);
+        17 - self.interval
+    }
+}
+
+/// Level of the gate voltages VGH and VGL (VGHL_LV)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GateVoltage {
+    /// VGH = 9 V, VGL = -9 V
+    V9 = 0b000,
+    /// VGH = 10 V, VGL = -10 V
+    V10 = 0b001,
+    /// VGH = 11 V, VGL = -11 V
+    V11 = 0b010,
+    /// VGH = 12 V, VGL = -12 V
+    V12 = 0b011,
+    /// VGH = 17 V, VGL = -17 V
+    V17 = 0b100,
+    /// VGH = 18 V, VGL = -18 V
+    V18 = 0b101,
+    /// VGH = 19 V, VGL = -19 V
+    V19 = 0b110,
+    /// VGH = 20 V, VGL = -20 V
+    V20 = 0b111,
+}
+
+/// Power setting of the gate and source voltages
+///
+/// The source voltages are given in mV from 2400 to 15000 in steps of 200, other
+/// values panic when the setting is sent. The default is the setting of the vendor driver: internal power, ±20 V gate and ±15 V
+/// source voltages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PowerSettings {
+    /// VG_EN: VGH and VGL are generated internally instead of supplied externally
+    pub internal_gate_power: bool,
+    /// VS_EN: VDH and VDL are generated internally instead of supplied externally
+    pub internal_source_power: bool,
+    /// VSR_EN: VDHR is generated internally instead of supplied externally
+    pub internal_red_source_power: bool,
+    /// VGHL_LV: level of VGH and VGL
+    pub gate_voltage: GateVoltage,
+    /// VDH: positive source voltage in mV
+    pub vdh_mv: u16,
+    /// VDL: magnitude of the negative source voltage in mV
+    pub vdl_mv: u16,
+    /// VDHR: red source voltage in mV, `None` keeps the reset value of the controller
+    pub vdhr_mv: Option<u16>,
+}
+
+impl Default for PowerSettings {
+    fn default() -> Self {
+        PowerSettings {
+            internal_gate_power: true,
+            internal_source_power: true,
+            internal_red_source_power: true,
+            gate_voltage: GateVoltage::V20,
+            vdh_mv: 15_000,
+            vdl_mv: 15_000,
+            vdhr_mv: None,
+        }
+    }
+}
+
+impl PowerSettings {
+    /// Returns the 6 bit level of a source voltage, 2.4 V plus 0.2 V per step
+    const fn source_voltage_bits(mv: u16) -> u8 {
+        assert!(mv >= 2400 && mv <= 15_000 && mv % 200 == 0);
+        ((mv - 2400) / 200) as u8
+    }
+}
 
 /// Epd5in83 driver
 ///
-pub struct Epd5in83<SPI, BUSY, DC, RST> {
+pub struct Epd5in83<SPI, BUSY, DC, RST, DELAY> {
     /// Connection Interface
-    interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
     /// Background Color
     color: Color,
+    /// Refresh LUT
+    refresh: RefreshLut,
+    /// Whether the old frame RAM holds a base frame for a quick refresh
+    base_frame: bool,
+    /// Byte the old frame RAM (DTM1) is filled with, `None` if it holds anything else
+    old_fill: Option<u8>,
+    /// Quick refreshes since the last full refresh, and when the next one is a full one
+    partial_refreshes: PartialRefreshes,
+    /// Temperature set with [Epd5in83::set_temperature]
+    temperature: Option<i8>,
+    /// Color of the border around the active area
+    border: Color,
+    /// VCOM and data interval setting
+    vcom_and_data_interval: VcomAndDataInterval,
+    /// Booster soft start set with [Epd5in83::set_booster_soft_start]
+    booster: Option<BoosterSoftStart>,
+    /// Power setting of the gate and source voltages
+    power_settings: PowerSettings,
+    /// Non-overlap periods set with [Epd5in83::set_tcon]
+    tcon: u8,
+    /// Frame rate set with [Epd5in83::set_frame_rate]
+    frame_rate: Option<FrameRate>,
+    /// Power state, to tell the deep sleep apart
+    power: PowerState,
 }
 
-impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd5in83<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST>
+    for Epd5in83<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -58,11 +214,13 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
 }
 
-impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd5in83<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Epd5in83<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -72,23 +230,32 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         // Reset the device
-        self.interface.reset(spi, 2000, 50).await?;
+        self.interface.reset().await?;
+        self.power = PowerState::Active;
 
-        // Set the power settings: VGH=20V,VGL=-20V,VDH=15V,VDL=-15V
-        self.cmd_with_data(spi, Command::PowerSetting, &[0x07, 0x07, 0x3F, 0x3F])
-            .await?;
+        self.send_power_settings(spi).await?;
+
+        // the reset value is used until one is set
+        if let Some(booster) = self.booster {
+            self.send_booster_soft_start(spi, booster).await?;
+        }
 
         // Power on
         self.command(spi, Command::PowerOn).await?;
-        //self.interface.delay(spi, 5000).await?;
+        self.interface.mark_busy(BusyWith::PowerOn);
+        //self.interface.delay(5000).await;
         self.wait_until_idle(spi).await?;
 
-        // Set the panel settings: BWOTP
-        self.cmd_with_data(spi, Command::PanelSetting, &[0x1F])
-            .await?;
+        // Set the panel settings and the LUTs
+        self.set_lut(spi, None).await?;
+        // the RAM doesn't survive the deep sleep
+        self.base_frame = false;
+        self.old_fill = None;
+        self.partial_refreshes.full_done();
 
         // Set the real resolution
         self.send_resolution(spi).await?;
@@ -97,19 +264,27 @@ where
         self.cmd_with_data(spi, Command::DualSPI, &[0x00]).await?;
 
         // Set Vcom and data interval
-        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x10, 0x07])
-            .await?;
+        self.send_vcom_and_data_interval(spi, false).await?;
 
-        // Set S2G and G2S non-overlap periods to 12 (default)
-        self.cmd_with_data(spi, Command::TconSetting, &[0x22])
+        // Set S2G and G2S non-overlap periods
+        self.cmd_with_data(spi, Command::TconSetting, &[self.tcon])
             .await?;
 
+        // the reset value (50 Hz) is used until one is set
+        if let Some(frame_rate) = self.frame_rate {
+            self.cmd_with_data(spi, Command::PllControl, &[frame_rate.uc8179_byte()])
+                .await?;
+        }
+
+        self.send_temperature(spi).await?;
+
         self.wait_until_idle(spi).await?;
         Ok(())
     }
 }
 
-impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd5in83<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd5in83<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -119,19 +294,18 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type DisplayColor = Color;
     async fn new(
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        delay: DELAY,
         delay_us: Option<u32>,
     ) -> Result<Self, Self::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
-        let color = DEFAULT_BACKGROUND_COLOR;
-
-        let mut epd = Epd5in83 { interface, color };
+        let mut epd = Self::new_uninitialized(Some(busy), dc, rst, delay, delay_us);
 
         epd.init(spi).await?;
 
@@ -141,13 +315,23 @@ where
     async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
         self.command(spi, Command::PowerOff).await?;
+        self.interface.mark_busy(BusyWith::PowerOff);
         self.wait_until_idle(spi).await?;
         self.cmd_with_data(spi, Command::DeepSleep, &[0xA5]).await?;
+        self.power = PowerState::DeepSleep;
         Ok(())
     }
 
     async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.init(spi).await
+        match self.power {
+            PowerState::Active => Ok(()),
+            PowerState::PoweredOff => self.power_on(spi).await,
+            PowerState::DeepSleep => {
+                self.interface.require_reset_pin()?;
+                self.init(spi).await
+            }
+            PowerState::Unknown => self.init(spi).await,
+        }
     }
 
     fn set_background_color(&mut self, color: Color) {
@@ -166,16 +350,49 @@ where
         HEIGHT
     }
 
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
+
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    /// Writes `buffer` into the new frame and fills the old frame with the background color
+    ///
+    /// The waveform then drives every pixel as if the panel showed a blank frame, which
+    /// costs some contrast on pixels that don't change, but leaves no ghost of an image
+    /// the driver doesn't know about. Use [Epd5in83::update_frame_with_old] to pass the
+    /// shown image instead, or [QuickRefresh::update_new_frame] if the old frame RAM
+    /// holds it already.
     async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
         let color_value = self.color.get_byte_value();
-
-        self.interface
-            .cmd(spi, Command::DataStartTransmission1)
-            .await?;
-        self.interface
-            .data_x_times(spi, color_value, WIDTH / 8 * HEIGHT)
-            .await?;
+        self.base_frame = false;
+        self.fill_old_frame(spi, color_value).await?;
 
         self.interface
             .cmd_with_data(spi, Command::DataStartTransmission2, buffer)
@@ -183,21 +400,28 @@ where
         Ok(())
     }
 
+    /// Writes `buffer` into a window of the new frame
+    ///
+    /// `x` and `width` are rounded out to byte boundaries, `buffer` has to cover the
+    /// rounded window. Returns [ErrorKind::InvalidWindow] if the window doesn't lie
+    /// inside the display or the buffer has a different size.
     async fn update_partial_frame(
         &mut self,
-        _spi: &mut SPI,
-        _buffer: &[u8],
-        _x: u32,
-        _y: u32,
-        _width: u32,
-        _height: u32,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
     ) -> Result<(), Self::Error> {
-        unimplemented!()
+        self.update_partial_new_frame(spi, buffer, x, y, width, height)
+            .await
     }
 
     async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.command(spi, Command::DisplayRefresh).await?;
+        self.display_refresh(spi).await?;
         self.wait_until_idle(spi).await?;
+        self.partial_refreshes.full_done();
         Ok(())
     }
 
@@ -211,36 +435,77 @@ where
         Ok(())
     }
 
+    /// Clears the frame to white
+    ///
+    /// The old frame RAM is only written if it doesn't hold the white fill already,
+    /// e.g. from the last `clear_frame` or [WaveshareDisplay::update_frame] with a white
+    /// background.
     async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
-
-        self.command(spi, Command::DataStartTransmission1).await?;
-        self.interface
-            .data_x_times(spi, 0xFF, NUM_DISPLAY_BITS)
-            .await?;
+        self.base_frame = false;
+        self.fill_old_frame(spi, 0xFF).await?;
 
         self.command(spi, Command::DataStartTransmission2).await?;
         self.interface
-            .data_x_times(spi, 0x00, NUM_DISPLAY_BITS)
+            .data_fill(spi, 0x00, NUM_DISPLAY_BITS)
             .await?;
 
         Ok(())
     }
 
+    /// Selects the waveforms of the OTP (`RefreshLut::Full`) or uploads the quick ones
+    ///
+    /// `None` applies the selected waveforms again, [WaveshareDisplay::wake_up] does
+    /// this as well.
     async fn set_lut(
         &mut self,
-        _spi: &mut SPI,
-        _refresh_rate: Option<RefreshLut>,
+        spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
     ) -> Result<(), Self::Error> {
-        unimplemented!();
+        if let Some(refresh_lut) = refresh_rate {
+            self.refresh = refresh_lut;
+        }
+        match self.refresh {
+            // KW mode, LUT from OTP
+            RefreshLut::Full => {
+                self.cmd_with_data(spi, Command::PanelSetting, &[0x1F])
+                    .await
+            }
+            // KW mode, LUT from register
+            RefreshLut::Quick => {
+                self.cmd_with_data(spi, Command::PanelSetting, &[0x3F])
+                    .await?;
+                self.set_lut_helper(
+                    spi,
+                    &LUT_VCOM_QUICK,
+                    &LUT_WW_QUICK,
+                    &LUT_BW_QUICK,
+                    &LUT_WB_QUICK,
+                    &LUT_BB_QUICK,
+                )
+                .await
+            }
+        }
     }
 
-    async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
+    /// Returns [ErrorKind::Asleep] in deep sleep instead of waiting for the busy pin
+    ///
+    /// The busy pin doesn't change until the controller is woken up by a reset. The
+    /// first wait after [Epd5in83::new_uninitialized] checks that the controller is
+    /// idle, the high voltages are treated as off until the next refresh.
+    async fn wait_until_idle(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        if self.power == PowerState::DeepSleep {
+            return Err(ErrorKind::Asleep);
+        }
+        self.interface.wait_until_idle(BUSY_LEVEL).await?;
+        if self.power == PowerState::Unknown {
+            self.power = PowerState::PoweredOff;
+        }
+        Ok(())
     }
 }
 
-impl<SPI, BUSY, DC, RST> Epd5in83<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> Epd5in83<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -250,7 +515,421 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
+    /// Creates the driver without touching the hardware, e.g. when a bootloader already
+    /// initialized the panel
+    ///
+    /// None of the settings of the driver are sent and the power state is unknown. The
+    /// first update waits for the busy pin and the first refresh turns the power on.
+    /// Call [Epd5in83::init] if the controller needs a reset and the full init instead.
+    ///
+    /// Pass `None::<NoBusyPin>` as `busy` if BUSY isn't connected, see
+    /// [NoBusyPin](crate::NoBusyPin). Every wait then lasts as long as the slowest case of
+    /// the operation before, a refresh blocks the next command for 10 s.
+    pub fn new_uninitialized(
+        busy: Option<BUSY>,
+        dc: DC,
+        rst: Option<RST>,
+        delay: DELAY,
+        delay_us: Option<u32>,
+    ) -> Self {
+        let mut interface =
+            DisplayInterface::new(busy, dc, rst, delay, RESET_TIMING, delay_us, None);
+        interface.set_idle_delays(IDLE_DELAYS);
+        Epd5in83 {
+            interface,
+            color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+            base_frame: false,
+            old_fill: None,
+            partial_refreshes: PartialRefreshes::default(),
+            temperature: None,
+            border: DEFAULT_BACKGROUND_COLOR,
+            vcom_and_data_interval: VcomAndDataInterval::default(),
+            booster: None,
+            power_settings: PowerSettings::default(),
+            tcon: DEFAULT_TCON,
+            frame_rate: None,
+            power: PowerState::Unknown,
+        }
+    }
+
+    /// Resets the controller and sends the full init, like [WaveshareDisplay::new] does
+    pub async fn init(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        InternalWiAdditions::init(self, spi).await
+    }
+
+    /// Replaces every `refreshes`th quick refresh with a full one, `None` disables it
+    ///
+    /// The full refresh shows the new frame with the waveform of the OTP, afterwards the
+    /// selected waveform is loaded again.
+    pub fn set_full_refresh_after(&mut self, refreshes: Option<NonZeroU32>) {
+        self.partial_refreshes.set_full_after(refreshes);
+    }
+
+    /// Quick refreshes since the last full refresh
+    pub fn partial_refreshes(&self) -> u32 {
+        self.partial_refreshes.count()
+    }
+
+    /// Selects the OTP waveform of the full refresh for `celsius` instead of the
+    /// measured temperature
+    ///
+    /// Use it with a more accurate sensor than the one of the controller. The
+    /// temperature is kept across [WaveshareDisplay::wake_up]. The waveform of
+    /// `RefreshLut::Quick` is uploaded by the driver and doesn't depend on it.
+    pub async fn set_temperature(
+        &mut self,
+        spi: &mut SPI,
+        celsius: i8,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.temperature = Some(celsius);
+        self.wait_until_idle(spi).await?;
+        self.send_temperature(spi).await
+    }
+
+    /// Like [WaveshareDisplay::update_frame], but writes the shown image `old` into the
+    /// old frame instead of the background fill
+    ///
+    /// The waveform only drives the pixels that change between `old` and `new`, so
+    /// unchanged pixels keep their contrast. If `old` isn't what the panel shows, the
+    /// differences leave ghosts until the next refresh with the right base image.
+    /// `old` is also the base of the next [QuickRefresh::display_new_frame].
+    pub async fn update_frame_with_old(
+        &mut self,
+        spi: &mut SPI,
+        old: &[u8],
+        new: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.update_old_frame(spi, old).await?;
+        self.update_new_frame(spi, new).await
+    }
+
+    /// Like [WaveshareDisplay::update_frame], but sends `buffer` over `bus` in dual SPI mode
+    ///
+    /// Even bytes of `buffer` go out on the first data line and odd bytes on the second
+    /// one, which nearly halves the transfer time. The mode is only enabled for this
+    /// transfer, commands and everything else keep using `spi`. Returns
+    /// [ErrorKind::InvalidWindow] if `buffer` isn't exactly one frame.
+    pub async fn update_frame_dual<D: DualSpiBus<Error = SPI::Error>>(
+        &mut self,
+        spi: &mut SPI,
+        bus: &mut D,
+        buffer: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if buffer.len() != NUM_DISPLAY_BITS as usize {
+            return Err(ErrorKind::InvalidWindow);
+        }
+        self.wait_until_idle(spi).await?;
+        let color_value = self.color.get_byte_value();
+        self.base_frame = false;
+        self.fill_old_frame(spi, color_value).await?;
+
+        self.cmd_with_data(spi, Command::DualSPI, &[DUAL_SPI_ENABLE])
+            .await?;
+        let mut result = self.command(spi, Command::DataStartTransmission2).await;
+        if result.is_ok() {
+            result = self.interface.data_dual(bus, buffer).await;
+        }
+        // back to single SPI on errors as well, the next commands go over `spi` again
+        let disabled = self.cmd_with_data(spi, Command::DualSPI, &[0x00]).await;
+        result.and(disabled)
+    }
+
+    /// Like [WaveshareDisplay::update_frame], but takes the bytes of the frame from
+    /// `frame`, e.g. rendered row by row without a full buffer in RAM
+    ///
+    /// `frame` yields the bytes in the order of the buffer, they are sent in blocks of 256
+    /// within one data phase. Returns [ErrorKind::InvalidWindow] if it ends before the
+    /// frame is full, the rest of the frame keeps its old content then. Further bytes
+    /// aren't read.
+    pub async fn update_frame_iter(
+        &mut self,
+        spi: &mut SPI,
+        frame: impl IntoIterator<Item = u8>,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let len = NUM_DISPLAY_BITS as usize;
+        self.wait_until_idle(spi).await?;
+        let color_value = self.color.get_byte_value();
+        self.base_frame = false;
+        self.fill_old_frame(spi, color_value).await?;
+
+        self.command(spi, Command::DataStartTransmission2).await?;
+        let sent = self
+            .interface
+            .data_from_iter(spi, frame.into_iter().take(len))
+            .await?;
+        if sent < len {
+            return Err(ErrorKind::InvalidWindow);
+        }
+        Ok(())
+    }
+
+    /// Turns the high voltages off, but keeps the controller and its RAM awake
+    ///
+    /// Unlike [WaveshareDisplay::sleep] no init is needed afterwards, the next refresh
+    /// turns the power on again, see [Epd5in83::power_on].
+    pub async fn power_off(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.wait_until_idle(spi).await?;
+        self.command(spi, Command::PowerOff).await?;
+        self.interface.mark_busy(BusyWith::PowerOff);
+        self.wait_until_idle(spi).await?;
+        self.power = PowerState::PoweredOff;
+        Ok(())
+    }
+
+    /// Turns the high voltages on again after [Epd5in83::power_off]
+    pub async fn power_on(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.wait_until_idle(spi).await?;
+        self.command(spi, Command::PowerOn).await?;
+        self.interface.mark_busy(BusyWith::PowerOn);
+        self.wait_until_idle(spi).await?;
+        self.power = PowerState::Active;
+        Ok(())
+    }
+
+    /// Refreshes the display, turning the power on first if needed
+    async fn display_refresh(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if self.power == PowerState::PoweredOff {
+            self.power_on(spi).await?;
+        }
+        self.command(spi, Command::DisplayRefresh).await?;
+        self.interface.mark_busy(BusyWith::Refresh);
+        Ok(())
+    }
+
+    /// Sets the frame rate of the refresh with the PLL control
+    ///
+    /// Until one is set the reset value of the controller, 50 Hz, is used. It takes
+    /// effect with the next refresh and is kept across [WaveshareDisplay::wake_up].
+    pub async fn set_frame_rate(
+        &mut self,
+        spi: &mut SPI,
+        frame_rate: FrameRate,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.frame_rate = Some(frame_rate);
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::PllControl, &[frame_rate.uc8179_byte()])
+            .await
+    }
+
+    /// Sets the soft start of the booster, for supplies which brown out while it starts
+    ///
+    /// Until one is set the reset value of the controller is used. It takes effect with
+    /// the next power on and is kept across [WaveshareDisplay::wake_up].
+    pub async fn set_booster_soft_start(
+        &mut self,
+        spi: &mut SPI,
+        booster: BoosterSoftStart,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.booster = Some(booster);
+        self.wait_until_idle(spi).await?;
+        self.send_booster_soft_start(spi, booster).await
+    }
+
+    async fn send_booster_soft_start(
+        &mut self,
+        spi: &mut SPI,
+        booster: BoosterSoftStart,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.cmd_with_data(spi, Command::BoosterSoftStart, &booster.bytes())
+            .await
+    }
+
+    /// Sets the color of the border around the active area
+    ///
+    /// It takes effect with the next refresh and is kept across
+    /// [WaveshareDisplay::wake_up].
+    pub async fn set_border_color(
+        &mut self,
+        spi: &mut SPI,
+        color: Color,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.border = color;
+        self.wait_until_idle(spi).await?;
+        self.send_vcom_and_data_interval(spi, false).await
+    }
+
+    /// Fills the old frame RAM (DTM1) with `value`, unless it holds that fill already
+    async fn fill_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        value: u8,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if self.old_fill == Some(value) {
+            return Ok(());
+        }
+        self.command(spi, Command::DataStartTransmission1).await?;
+        self.interface
+            .data_fill(spi, value, NUM_DISPLAY_BITS)
+            .await?;
+        self.old_fill = Some(value);
+        Ok(())
+    }
+
+    /// Sets the gate and source voltages, e.g. for a panel batch with other VDH/VDL
+    ///
+    /// It takes effect with the next power on and is kept across
+    /// [WaveshareDisplay::wake_up].
+    pub async fn set_power_settings(
+        &mut self,
+        spi: &mut SPI,
+        settings: PowerSettings,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.power_settings = settings;
+        self.wait_until_idle(spi).await?;
+        self.send_power_settings(spi).await
+    }
+
+    async fn send_power_settings(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let settings = self.power_settings;
+        let power = (settings.internal_red_source_power as u8) << 2
+            | (settings.internal_source_power as u8) << 1
+            | settings.internal_gate_power as u8;
+        let mut data = [
+            power,
+            settings.gate_voltage as u8,
+            PowerSettings::source_voltage_bits(settings.vdh_mv),
+            PowerSettings::source_voltage_bits(settings.vdl_mv),
+            0,
+        ];
+        let len = match settings.vdhr_mv {
+            Some(vdhr_mv) => {
+                data[4] = PowerSettings::source_voltage_bits(vdhr_mv);
+                5
+            }
+            None => 4,
+        };
+        self.cmd_with_data(spi, Command::PowerSetting, &data[..len])
+            .await
+    }
+
+    /// Sets the source to gate (`s2g`) and gate to source (`g2s`) non-overlap periods
+    ///
+    /// Both are 4 bit values, the period is 4 × (value + 1), so the default of 2 is 12.
+    /// Longer periods help against faint vertical lines, e.g. with long FPC extenders.
+    /// Returns [ErrorKind::InvalidSetting] if a value doesn't fit into 4 bits. The
+    /// periods are kept across [WaveshareDisplay::wake_up].
+    pub async fn set_tcon(
+        &mut self,
+        spi: &mut SPI,
+        s2g: u8,
+        g2s: u8,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if s2g > 0x0F || g2s > 0x0F {
+            return Err(ErrorKind::InvalidSetting);
+        }
+        self.tcon = s2g << 4 | g2s;
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::TconSetting, &[self.tcon])
+            .await
+    }
+
+    /// Reads the status flags of the controller (command 0x71)
+    ///
+    /// From bit 6 down to bit 0 they are PTL, I2C_ERR, I2C_BUSY, DATA, PON, POF and
+    /// BUSY_N, e.g. `status & 0x04 != 0` tells that the panel is powered on. It doesn't
+    /// wait until the controller is idle. The data line has to be connected to the MISO
+    /// of the SPI bus as well, which the Waveshare HATs don't do.
+    pub async fn status(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<u8, <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if self.power == PowerState::DeepSleep {
+            return Err(ErrorKind::Asleep);
+        }
+        let mut status = [0u8];
+        self.interface
+            .cmd_then_read(spi, Command::GetStatus, 0, &mut status)
+            .await?;
+        Ok(status[0])
+    }
+
+    /// Reads the revision of the controller (command 0x70) into `buffer`
+    ///
+    /// The LUT revision from the OTP comes first, followed by the chip revision. How
+    /// many bytes it sends depends on the controller, see its datasheet. Like
+    /// [Epd5in83::status] it needs the data line on the MISO.
+    pub async fn revision(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &mut [u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if self.power == PowerState::DeepSleep {
+            return Err(ErrorKind::Asleep);
+        }
+        self.interface
+            .cmd_then_read(spi, Command::Revision, 0, buffer)
+            .await
+    }
+
+    /// Tunes the interval between VCOM and data, e.g. against ghosting of a panel
+    ///
+    /// It takes effect with the next refresh and is kept across
+    /// [WaveshareDisplay::wake_up].
+    pub async fn set_vcom_and_data_interval(
+        &mut self,
+        spi: &mut SPI,
+        setting: VcomAndDataInterval,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.vcom_and_data_interval = setting;
+        self.wait_until_idle(spi).await?;
+        self.send_vcom_and_data_interval(spi, false).await
+    }
+
+    /// Sends the VCOM and data interval setting with the border color and
+    /// `copy_new_to_old` (N2OCP)
+    async fn send_vcom_and_data_interval(
+        &mut self,
+        spi: &mut SPI,
+        copy_new_to_old: bool,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let setting = self.vcom_and_data_interval;
+        // BDV with DDX = 0
+        let border = match self.border {
+            Color::Black => 0x20,
+            Color::White => 0x10,
+        };
+        let bdz = if setting.border_floating { 0x80 } else { 0x00 };
+        let n2ocp = if copy_new_to_old { 0x08 } else { 0x00 };
+        self.cmd_with_data(
+            spi,
+            Command::VcomAndDataIntervalSetting,
+            &[bdz | border | n2ocp, setting.interval_bits()],
+        )
+        .await
+    }
+
+    /// Forces the temperature of [Epd5in83::set_temperature], if there is one
+    async fn send_temperature(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if let Some(celsius) = self.temperature {
+            self.cmd_with_data(spi, Command::CascadeSetting, &[0x02])
+                .await?;
+            self.cmd_with_data(spi, Command::ForceTemperature, &[celsius as u8])
+                .await?;
+        }
+        Ok(())
+    }
+
     async fn command(
         &mut self,
         spi: &mut SPI,
@@ -276,6 +955,108 @@ where
         self.interface.cmd_with_data(spi, command, data).await
     }
 
+    async fn set_lut_helper(
+        &mut self,
+        spi: &mut SPI,
+        lut_vcom: &[u8],
+        lut_ww: &[u8],
+        lut_bw: &[u8],
+        lut_wb: &[u8],
+        lut_bb: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::LutForVcom, lut_vcom)
+            .await?;
+        self.cmd_with_data(spi, Command::LutWhiteToWhite, lut_ww)
+            .await?;
+        self.cmd_with_data(spi, Command::LutBlackToWhite, lut_bw)
+            .await?;
+        self.cmd_with_data(spi, Command::LutWhiteToBlack, lut_wb)
+            .await?;
+        self.cmd_with_data(spi, Command::LutBlackToBlack, lut_bb)
+            .await
+    }
+
+    /// Enters the partial mode and starts writing a window of the RAM selected by `command`
+    ///
+    /// Returns the number of bytes of the window, rounded out to byte boundaries.
+    /// Finish the write with [Command::PartialOut].
+    async fn start_partial(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<usize, <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let (start_x, end_x) =
+            byte_aligned_window(x, y, width, height).ok_or(ErrorKind::InvalidWindow)?;
+        self.wait_until_idle(spi).await?;
+
+        self.command(spi, Command::PartialIn).await?;
+        self.set_partial_window(spi, start_x, end_x, y, height)
+            .await?;
+        self.command(spi, command).await?;
+        Ok(buffer_len((end_x - start_x) as usize, height as usize))
+    }
+
+    /// Writes `buffer` into a window of the RAM selected by `command`
+    #[allow(clippy::too_many_arguments)]
+    async fn write_partial(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let (start_x, end_x) =
+            byte_aligned_window(x, y, width, height).ok_or(ErrorKind::InvalidWindow)?;
+        if buffer.len() != buffer_len((end_x - start_x) as usize, height as usize) {
+            return Err(ErrorKind::InvalidWindow);
+        }
+        self.start_partial(spi, command, x, y, width, height)
+            .await?;
+        self.send_data(spi, buffer).await?;
+        self.command(spi, Command::PartialOut).await
+    }
+
+    /// Sets the window of the partial mode, `end_x` is the first column after the window
+    async fn set_partial_window(
+        &mut self,
+        spi: &mut SPI,
+        start_x: u32,
+        end_x: u32,
+        y: u32,
+        height: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        // the lower 3 bits of the horizontal start are ignored, the ones of the end have to be set
+        let last_x = end_x - 1;
+        let last_y = y + height - 1;
+        // Gates scan both inside and outside of the partial window (default)
+        let pt_scan = 0x01;
+
+        self.cmd_with_data(
+            spi,
+            Command::PartialWindow,
+            &[
+                (start_x >> 8) as u8,
+                start_x as u8 & 0xF8,
+                (last_x >> 8) as u8,
+                last_x as u8 | 0x07,
+                (y >> 8) as u8,
+                y as u8,
+                (last_y >> 8) as u8,
+                last_y as u8,
+                pt_scan,
+            ],
+        )
+        .await
+    }
+
     async fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
         let w = self.width();
         let h = self.height();
@@ -288,9 +1069,162 @@ where
     }
 }
 
+impl<SPI, BUSY, DC, RST, DELAY> QuickRefresh<SPI, BUSY, DC, RST, DELAY>
+    for Epd5in83<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    /// Writes the base image into the old frame RAM
+    async fn update_old_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        assert!(buffer.len() == NUM_DISPLAY_BITS as usize);
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::DataStartTransmission1, buffer)
+            .await?;
+        self.base_frame = true;
+        self.old_fill = None;
+        Ok(())
+    }
+
+    /// Writes the new image into the new frame RAM
+    async fn update_new_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        assert!(buffer.len() == NUM_DISPLAY_BITS as usize);
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)
+            .await
+    }
+
+    /// Refreshes the display from the old to the new frame
+    ///
+    /// The controller copies the new frame into the old frame RAM afterwards, so it
+    /// is the base of the next quick refresh. Returns [ErrorKind::NoBaseFrame] if no
+    /// base image was written with [QuickRefresh::update_old_frame] since the last init.
+    ///
+    /// Once the limit set with [Epd5in83::set_full_refresh_after] is reached, this is a
+    /// full refresh instead.
+    async fn display_new_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        if !self.base_frame {
+            return Err(ErrorKind::NoBaseFrame);
+        }
+        self.wait_until_idle(spi).await?;
+        let full_refresh = self.partial_refreshes.full_due();
+        if full_refresh {
+            // KW mode, LUT from OTP
+            self.cmd_with_data(spi, Command::PanelSetting, &[0x1F])
+                .await?;
+        }
+        // copy the new data to the old data after the refresh
+        self.send_vcom_and_data_interval(spi, true).await?;
+        self.old_fill = None;
+        self.display_refresh(spi).await?;
+        self.wait_until_idle(spi).await?;
+        self.send_vcom_and_data_interval(spi, false).await?;
+
+        if full_refresh {
+            self.partial_refreshes.full_done();
+            self.set_lut(spi, None).await
+        } else {
+            self.partial_refreshes.partial_done();
+            Ok(())
+        }
+    }
+
+    async fn update_and_display_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_new_frame(spi, buffer).await?;
+        self.display_new_frame(spi).await
+    }
+
+    /// Writes a part of the base image, see [WaveshareDisplay::update_partial_frame]
+    /// for the window
+    async fn update_partial_old_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        self.old_fill = None;
+        self.write_partial(
+            spi,
+            Command::DataStartTransmission1,
+            buffer,
+            x,
+            y,
+            width,
+            height,
+        )
+        .await
+    }
+
+    /// Writes a part of the new image, see [WaveshareDisplay::update_partial_frame]
+    /// for the window
+    async fn update_partial_new_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        self.write_partial(
+            spi,
+            Command::DataStartTransmission2,
+            buffer,
+            x,
+            y,
+            width,
+            height,
+        )
+        .await
+    }
+
+    /// Fills a part of the new image with the background color
+    async fn clear_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        let color = self.color.get_byte_value();
+        let len = self
+            .start_partial(spi, Command::DataStartTransmission2, x, y, width, height)
+            .await?;
+        self.interface.data_fill(spi, color, len as u32).await?;
+        self.command(spi, Command::PartialOut).await
+    }
+}
+
+/// Rounds the window out to byte boundaries, returns its first and one past its last column
+///
+/// Returns `None` if the window is empty or doesn't lie inside the display.
+fn byte_aligned_window(x: u32, y: u32, width: u32, height: u32) -> Option<(u32, u32)> {
+    if width == 0 || height == 0 || x + width > WIDTH || y + height > HEIGHT {
+        return None;
+    }
+    Some((x / 8 * 8, (x + width).div_ceil(8) * 8))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::{block_on, mocks, DualBus};
+    use crate::NoBusyPin;
 
     #[test]
     fn epd_size() {
@@ -298,4 +1232,459 @@ mod tests {
         assert_eq!(HEIGHT, 480);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+    #[test]
+    fn frame_rate() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd5in83::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert!(!log.commands().contains(&0x30));
+
+        log.clear();
+        block_on(epd.set_frame_rate(&mut spi, FrameRate::Hz150)).unwrap();
+        assert_eq!(log.data_of(0x30, 0), [0x0E]);
+
+        block_on(epd.sleep(&mut spi)).unwrap();
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x30, 0), [0x0E]);
+    }
+
+    #[test]
+    fn booster_soft_start() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd5in83::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert!(!log.commands().contains(&0x06));
+
+        let booster = BoosterSoftStart::from_bytes(0xC4, 0xC4, 0x0C, 0x0C);
+        log.clear();
+        block_on(epd.set_booster_soft_start(&mut spi, booster)).unwrap();
+        assert_eq!(log.data_of(0x06, 0), [0xC4, 0xC4, 0x0C, 0x0C]);
+
+        block_on(epd.sleep(&mut spi)).unwrap();
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x06, 0), [0xC4, 0xC4, 0x0C, 0x0C]);
+    }
+
+    #[test]
+    fn window_is_rounded_to_bytes() {
+        assert_eq!(byte_aligned_window(13, 0, 20, 1), Some((8, 40)));
+        assert_eq!(byte_aligned_window(16, 0, 16, 1), Some((16, 32)));
+        assert_eq!(byte_aligned_window(640, 470, 8, 10), Some((640, 648)));
+        assert_eq!(byte_aligned_window(641, 0, 8, 1), None);
+        assert_eq!(byte_aligned_window(0, 400, 8, 81), None);
+        assert_eq!(byte_aligned_window(0, 0, 0, 1), None);
+    }
+
+    #[test]
+    fn partial_window() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd5in83::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        // x = 13 and width = 20 cover the columns 8 to 39, 4 bytes per row
+        log.clear();
+        let buffer = [0x00; 4 * 10];
+        block_on(epd.update_partial_frame(&mut spi, &buffer, 13, 300, 20, 10)).unwrap();
+        assert_eq!(log.commands(), [0x91, 0x90, 0x13, 0x92]);
+        assert_eq!(
+            log.data_of(0x90, 0),
+            [0x00, 0x08, 0x00, 0x27, 0x01, 0x2C, 0x01, 0x35, 0x01]
+        );
+        assert_eq!(log.data_of(0x13, 0).len(), buffer.len());
+    }
+
+    #[test]
+    fn dual_spi_frame() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd5in83::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        let mut bus = DualBus::default();
+
+        let frame = [0xF0; WIDTH as usize / 8 * HEIGHT as usize];
+        log.clear();
+        block_on(epd.update_frame_dual(&mut spi, &mut bus, &frame)).unwrap();
+        assert_eq!(log.commands(), [0x10, 0x15, 0x13, 0x15]);
+        assert_eq!(log.data_of(0x10, 0).len(), frame.len());
+        assert_eq!(log.data_of(0x15, 0), [0x10]);
+        assert!(log.data_of(0x13, 0).is_empty());
+        assert_eq!(log.data_of(0x15, 1), [0x00]);
+        assert_eq!(bus.0.len(), frame.len());
+        assert_eq!(bus.0[..2], [0xFF, 0x00]);
+
+        // a wrong size doesn't enable the mode
+        log.clear();
+        let result = block_on(epd.update_frame_dual(&mut spi, &mut bus, &frame[1..]));
+        assert!(matches!(result, Err(ErrorKind::InvalidWindow)));
+        assert!(log.commands().is_empty());
+    }
+
+    #[test]
+    fn invalid_partial_window() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd5in83::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        // the buffer doesn't include the padding of the window
+        let buffer = [0x00; 3 * 10];
+        let result = block_on(epd.update_partial_frame(&mut spi, &buffer, 13, 300, 20, 10));
+        assert!(matches!(result, Err(ErrorKind::InvalidWindow)));
+
+        let buffer = [0x00; 2 * 10];
+        let result = block_on(epd.update_partial_frame(&mut spi, &buffer, 640, 300, 16, 10));
+        assert!(matches!(result, Err(ErrorKind::InvalidWindow)));
+        assert!(log.writes().is_empty());
+    }
+    #[test]
+    fn quick_lut_is_restored_after_sleep() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd5in83::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert_eq!(log.data_of(0x00, 0), [0x1F]);
+        assert!(!log.commands().contains(&0x20));
+
+        log.clear();
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Quick))).unwrap();
+        assert_eq!(log.commands(), [0x00, 0x20, 0x21, 0x22, 0x23, 0x24]);
+        assert_eq!(log.data_of(0x00, 0), [0x3F]);
+        assert_eq!(log.data_of(0x20, 0), LUT_VCOM_QUICK);
+        assert_eq!(log.data_of(0x24, 0), LUT_BB_QUICK);
+
+        block_on(epd.sleep(&mut spi)).unwrap();
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x00, 0), [0x3F]);
+        assert_eq!(log.data_of(0x21, 0), LUT_WW_QUICK);
+
+        log.clear();
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Full))).unwrap();
+        assert_eq!(log.commands(), [0x00]);
+        assert_eq!(log.data_of(0x00, 0), [0x1F]);
+    }
+    #[test]
+    fn quick_refresh() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd5in83::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        let frame = [0xFF; NUM_DISPLAY_BITS as usize];
+
+        // there is no base frame yet
+        let result = block_on(epd.update_and_display_new_frame(&mut spi, &frame));
+        assert!(matches!(result, Err(ErrorKind::NoBaseFrame)));
+
+        log.clear();
+        block_on(epd.update_old_frame(&mut spi, &frame)).unwrap();
+        let digit = [0x00; 4 * 10];
+        block_on(epd.update_partial_new_frame(&mut spi, &digit, 13, 300, 20, 10)).unwrap();
+        block_on(epd.display_new_frame(&mut spi)).unwrap();
+        assert_eq!(
+            log.commands(),
+            [0x10, 0x91, 0x90, 0x13, 0x92, 0x50, 0x12, 0x50]
+        );
+        assert_eq!(log.data_of(0x10, 0).len(), frame.len());
+        assert_eq!(log.data_of(0x50, 0), [0x18, 0x07]);
+        assert_eq!(log.data_of(0x50, 1), [0x10, 0x07]);
+
+        // the base frame is lost with the deep sleep
+        block_on(epd.sleep(&mut spi)).unwrap();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        let result = block_on(epd.display_new_frame(&mut spi));
+        assert!(matches!(result, Err(ErrorKind::NoBaseFrame)));
+    }
+
+    #[test]
+    fn clear_partial_window() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd5in83::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        block_on(epd.clear_partial_frame(&mut spi, 13, 300, 20, 10)).unwrap();
+        assert_eq!(log.commands(), [0x91, 0x90, 0x13, 0x92]);
+        assert_eq!(log.data_of(0x13, 0), [0xFF; 4 * 10]);
+    }
+    #[test]
+    fn forced_temperature() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        log.busy_level.set(true);
+        let mut epd = block_on(Epd5in83::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert!(!log.commands().contains(&0xE5));
+
+        log.clear();
+        block_on(epd.set_temperature(&mut spi, -5)).unwrap();
+        assert_eq!(log.commands(), [0xE0, 0xE5]);
+        assert_eq!(log.data_of(0xE0, 0), [0x02]);
+        assert_eq!(log.data_of(0xE5, 0), [0xFB]);
+
+        // sent again after the reset of the wake up
+        block_on(epd.sleep(&mut spi)).unwrap();
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0xE5, 0), [0xFB]);
+    }
+
+    #[test]
+    fn border_color() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        log.busy_level.set(true);
+        let mut epd = block_on(Epd5in83::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert_eq!(log.data_of(0x50, 0), [0x10, 0x07]);
+
+        log.clear();
+        block_on(epd.set_border_color(&mut spi, Color::Black)).unwrap();
+        assert_eq!(log.commands(), [0x50]);
+        assert_eq!(log.data_of(0x50, 0), [0x20, 0x07]);
+
+        // kept by the quick refresh
+        let frame = [0xFF; NUM_DISPLAY_BITS as usize];
+        block_on(epd.update_old_frame(&mut spi, &frame)).unwrap();
+        log.clear();
+        block_on(epd.display_new_frame(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x50, 0), [0x28, 0x07]);
+        assert_eq!(log.data_of(0x50, 1), [0x20, 0x07]);
+    }
+
+    #[test]
+    fn power_settings() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        log.busy_level.set(true);
+        let mut epd = block_on(Epd5in83::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert_eq!(log.data_of(0x01, 0), [0x07, 0x07, 0x3F, 0x3F]);
+
+        let settings = PowerSettings {
+            internal_red_source_power: false,
+            gate_voltage: GateVoltage::V17,
+            vdh_mv: 11_000,
+            vdl_mv: 12_000,
+            vdhr_mv: Some(3_000),
+            ..PowerSettings::default()
+        };
+        log.clear();
+        block_on(epd.set_power_settings(&mut spi, settings)).unwrap();
+        assert_eq!(log.data_of(0x01, 0), [0x03, 0x04, 0x2B, 0x30, 0x03]);
+
+        // kept across the reset of the wake up
+        block_on(epd.sleep(&mut spi)).unwrap();
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x01, 0), [0x03, 0x04, 0x2B, 0x30, 0x03]);
+    }
+
+    #[test]
+    fn status_and_revision() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        log.busy_level.set(true);
+        let mut epd = block_on(Epd5in83::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        log.queue_reads(&[0x05]);
+        assert_eq!(block_on(epd.status(&mut spi)).unwrap(), 0x05);
+        assert_eq!(log.commands(), [0x71]);
+
+        log.clear();
+        log.queue_reads(&[0x0C, 0x01]);
+        let mut revision = [0u8; 2];
+        block_on(epd.revision(&mut spi, &mut revision)).unwrap();
+        assert_eq!(revision, [0x0C, 0x01]);
+        assert_eq!(log.commands(), [0x70]);
+
+        block_on(epd.sleep(&mut spi)).unwrap();
+        let asleep = block_on(epd.status(&mut spi));
+        assert!(matches!(asleep, Err(ErrorKind::Asleep)));
+    }
+
+    #[test]
+    fn tcon() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        log.busy_level.set(true);
+        let mut epd = block_on(Epd5in83::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert_eq!(log.data_of(0x60, 0), [0x22]);
+
+        log.clear();
+        block_on(epd.set_tcon(&mut spi, 0x05, 0x0A)).unwrap();
+        assert_eq!(log.data_of(0x60, 0), [0x5A]);
+
+        let too_long = block_on(epd.set_tcon(&mut spi, 0x10, 0x02));
+        assert!(matches!(too_long, Err(ErrorKind::InvalidSetting)));
+
+        // kept across the reset of the wake up
+        block_on(epd.sleep(&mut spi)).unwrap();
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x60, 0), [0x5A]);
+    }
+
+    #[test]
+    fn clear_skips_filled_old_frame() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        log.busy_level.set(true);
+        let mut epd = block_on(Epd5in83::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        block_on(epd.clear_frame(&mut spi)).unwrap();
+        assert_eq!(log.commands(), [0x10, 0x13]);
+        assert!(log.data_of(0x10, 0).iter().all(|&b| b == 0xFF));
+        assert_eq!(log.data_of(0x13, 0).len(), NUM_DISPLAY_BITS as usize);
+
+        // the old frame still holds the white fill
+        log.clear();
+        block_on(epd.clear_frame(&mut spi)).unwrap();
+        assert_eq!(log.commands(), [0x13]);
+        let frame = [0x00; NUM_DISPLAY_BITS as usize];
+        log.clear();
+        block_on(epd.update_frame(&mut spi, &frame)).unwrap();
+        assert_eq!(log.commands(), [0x13]);
+
+        // an explicit old frame replaces the fill
+        block_on(epd.update_old_frame(&mut spi, &frame)).unwrap();
+        log.clear();
+        block_on(epd.clear_frame(&mut spi)).unwrap();
+        assert_eq!(log.commands(), [0x10, 0x13]);
+
+        // and so does the reset of the wake up
+        block_on(epd.sleep(&mut spi)).unwrap();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        log.clear();
+        block_on(epd.clear_frame(&mut spi)).unwrap();
+        assert_eq!(log.commands(), [0x10, 0x13]);
+    }
+
+    #[test]
+    fn update_frame_with_old() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        log.busy_level.set(true);
+        let mut epd = block_on(Epd5in83::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        let old = [0xF0; NUM_DISPLAY_BITS as usize];
+        let new = [0x0F; NUM_DISPLAY_BITS as usize];
+        log.clear();
+        block_on(epd.update_frame_with_old(&mut spi, &old, &new)).unwrap();
+        assert_eq!(log.commands(), [0x10, 0x13]);
+        assert_eq!(log.data_of(0x10, 0), old);
+        assert_eq!(log.data_of(0x13, 0), new);
+
+        // the old frame doesn't hold the background fill anymore
+        log.clear();
+        block_on(epd.update_frame(&mut spi, &new)).unwrap();
+        assert_eq!(log.commands(), [0x10, 0x13]);
+        assert!(log.data_of(0x10, 0).iter().all(|&b| b == 0xFF));
+    }
+
+    #[test]
+    fn vcom_and_data_interval() {
+        assert_eq!(VcomAndDataInterval::default().interval_bits(), 0x07);
+
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        log.busy_level.set(true);
+        let mut epd = block_on(Epd5in83::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        let setting = VcomAndDataInterval {
+            border_floating: true,
+            interval: 2,
+        };
+        log.clear();
+        block_on(epd.set_vcom_and_data_interval(&mut spi, setting)).unwrap();
+        assert_eq!(log.data_of(0x50, 0), [0x90, 0x0F]);
+
+        // kept across the reset of the wake up
+        block_on(epd.sleep(&mut spi)).unwrap();
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x50, 0), [0x90, 0x0F]);
+    }
+
+    #[test]
+    fn new_uninitialized() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        log.busy_level.set(true);
+        let mut epd = Epd5in83::new_uninitialized(Some(busy), dc, Some(rst), delay, None);
+        assert!(log.writes().is_empty());
+
+        // the first update checks the busy pin, the refresh turns the power on
+        let frame = [0xFF; NUM_DISPLAY_BITS as usize];
+        block_on(epd.update_and_display_frame(&mut spi, &frame)).unwrap();
+        assert!(!log.waits().is_empty());
+        assert_eq!(log.commands(), [0x10, 0x13, 0x04, 0x12]);
+
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(log.commands(), [0x12]);
+
+        // the explicit init sends the same as new
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = Epd5in83::new_uninitialized(Some(busy), dc, Some(rst), delay, None);
+        block_on(epd.init(&mut spi)).unwrap();
+        let (new_log, mut spi, busy, dc, rst, delay) = mocks();
+        block_on(Epd5in83::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert_eq!(log.writes(), new_log.writes());
+    }
+
+    #[test]
+    fn no_busy_pin() {
+        let (log, mut spi, _, dc, rst, delay) = mocks();
+        let mut epd = Epd5in83::new_uninitialized(None::<NoBusyPin>, dc, Some(rst), delay, None);
+        block_on(epd.init(&mut spi)).unwrap();
+        let total_us = |log: &crate::mock::Log| {
+            log.delays().iter().map(|&ns| u64::from(ns)).sum::<u64>() / 1000
+        };
+
+        // the refresh is waited for with its worst case, split into several delays
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(total_us(&log), u64::from(IDLE_DELAYS.refresh_us));
+        assert!(log.delays().len() > 1);
+
+        // other waits only take delay_us
+        log.clear();
+        block_on(epd.update_frame(&mut spi, &[0xFF; NUM_DISPLAY_BITS as usize])).unwrap();
+        assert_eq!(total_us(&log), 10_000);
+        assert!(log.waits().is_empty());
+    }
+
+    #[test]
+    fn power_off_and_on() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        log.busy_level.set(true);
+        let mut epd = block_on(Epd5in83::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        block_on(epd.power_off(&mut spi)).unwrap();
+        assert_eq!(log.commands(), [0x02]);
+
+        // the quick refresh turns the power on again and keeps the base frame
+        let frame = [0xFF; NUM_DISPLAY_BITS as usize];
+        block_on(epd.update_old_frame(&mut spi, &frame)).unwrap();
+        log.clear();
+        block_on(epd.update_and_display_new_frame(&mut spi, &frame)).unwrap();
+        assert_eq!(log.commands()[1..], [0x50, 0x04, 0x12, 0x50]);
+
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(log.commands(), [0x12]);
+    }
+
+    #[test]
+    fn full_refresh_after_quick_refreshes() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        log.busy_level.set(true);
+        let mut epd = block_on(Epd5in83::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Quick))).unwrap();
+        epd.set_full_refresh_after(NonZeroU32::new(3));
+
+        let frame = [0xFF; NUM_DISPLAY_BITS as usize];
+        block_on(epd.update_old_frame(&mut spi, &frame)).unwrap();
+        for _ in 0..2 {
+            log.clear();
+            block_on(epd.update_and_display_new_frame(&mut spi, &frame)).unwrap();
+            assert!(!log.commands().contains(&0x00));
+        }
+        assert_eq!(epd.partial_refreshes(), 2);
+
+        // the third one uses the OTP waveform and uploads the quick one again
+        log.clear();
+        block_on(epd.update_and_display_new_frame(&mut spi, &frame)).unwrap();
+        assert_eq!(log.data_of(0x00, 0), [0x1F]);
+        assert_eq!(log.data_of(0x00, 1), [0x3F]);
+        assert!(log.commands().ends_with(&[0x20, 0x21, 0x22, 0x23, 0x24]));
+        assert_eq!(epd.partial_refreshes(), 0);
+
+        // an explicit full refresh restarts the count
+        block_on(epd.update_and_display_new_frame(&mut spi, &frame)).unwrap();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(epd.partial_refreshes(), 0);
+    }
 }
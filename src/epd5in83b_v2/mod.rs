@@ -1,5 +1,14 @@
 //! A simple Driver for the Waveshare 5.83" (B) v2 E-Ink Display via SPI
 //!
+//! This is the tri-color sibling of the [5.83" v2](crate::epd5in83_v2) and shares its
+//! resolution and most of its init sequence.
+//!
+//! `DataStartTransmission1` receives the black plane and `DataStartTransmission2` the
+//! chromatic plane. The Waveshare examples draw the red image with cleared bits for red
+//! pixels and invert it while sending it, so the controller ends up with set bits for
+//! red. The chromatic half of a [Display5in83b] is already stored that way and is sent
+//! unchanged.
+//!
 //! # References
 //!
 //! - [Datasheet](https://www.waveshare.com/5.83inch-e-Paper-B.htm)
@@ -11,7 +20,7 @@ use embedded_hal_async::{digital::Wait, spi::SpiDevice};
 
 use crate::color::{Color, TriColor};
 use crate::error::ErrorKind;
-use crate::interface::DisplayInterface;
+use crate::interface::{BusyLevel, DisplayDelay, DisplayInterface, ResetTiming};
 use crate::traits::{
     ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
 };
@@ -21,8 +30,10 @@ use self::command::Command;
 use crate::buffer_len;
 
 /// Full size buffer for use with the 5in83b v2 EPD
+///
+/// The black plane is followed by the chromatic plane.
 #[cfg(feature = "graphics")]
-pub type Display5in83 = crate::graphics::Display<
+pub type Display5in83b = crate::graphics::Display<
     WIDTH,
     HEIGHT,
     false,
@@ -30,26 +41,32 @@ pub type Display5in83 = crate::graphics::Display<
     TriColor,
 >;
 
+/// Former name of [Display5in83b]
+#[cfg(feature = "graphics")]
+pub type Display5in83 = Display5in83b;
+
 /// Width of the display
-pub const WIDTH: u32 = 648;
+pub const WIDTH: u32 = crate::epd5in83_v2::WIDTH;
 /// Height of the display
-pub const HEIGHT: u32 = 480;
+pub const HEIGHT: u32 = crate::epd5in83_v2::HEIGHT;
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
-const IS_BUSY_LOW: bool = true;
+const BUSY_LEVEL: BusyLevel = BusyLevel::Low;
+const RESET_TIMING: ResetTiming = ResetTiming::new(10_000, 10_000);
 const NUM_DISPLAY_BITS: u32 = WIDTH / 8 * HEIGHT;
 const SINGLE_BYTE_WRITE: bool = true;
 
-/// Epd7in5 driver
+/// Epd5in83 (B) v2 driver
 ///
-pub struct Epd5in83<SPI, BUSY, DC, RST> {
+pub struct Epd5in83<SPI, BUSY, DC, RST, DELAY> {
     /// Connection Interface
-    interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
     /// Background Color
     color: Color,
 }
 
-impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd5in83<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST>
+    for Epd5in83<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -59,11 +76,13 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
 }
 
-impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd5in83<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Epd5in83<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -73,10 +92,11 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         // Reset the device
-        self.interface.reset(spi, 10_000, 10_000).await?;
+        self.interface.reset().await?;
 
         // Start the booster
         self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x1e, 0x17])
@@ -88,7 +108,7 @@ where
 
         // Power on
         self.command(spi, Command::PowerOn).await?;
-        self.interface.delay(spi, 5000).await?;
+        self.interface.delay(5000).await;
         self.wait_until_idle(spi).await?;
 
         // Set the panel settings: BWROTP
@@ -113,8 +133,8 @@ where
     }
 }
 
-impl<SPI, BUSY, DC, RST> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST>
-    for Epd5in83<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd5in83<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -124,6 +144,7 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn update_color_frame(
         &mut self,
@@ -157,9 +178,30 @@ where
             .await?;
         Ok(())
     }
+
+    async fn clear_achromatic_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+
+        // White in the black plane
+        self.command(spi, Command::DataStartTransmission1).await?;
+        self.interface
+            .data_x_times(spi, 0xFF, NUM_DISPLAY_BITS)
+            .await
+    }
+
+    async fn clear_chromatic_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+
+        // No color in the chromatic plane
+        self.command(spi, Command::DataStartTransmission2).await?;
+        self.interface
+            .data_x_times(spi, 0x00, NUM_DISPLAY_BITS)
+            .await
+    }
 }
 
-impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd5in83<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd5in83<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -169,16 +211,19 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type DisplayColor = Color;
     async fn new(
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        delay: DELAY,
         delay_us: Option<u32>,
     ) -> Result<Self, Self::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        let interface =
+            DisplayInterface::new(Some(busy), dc, rst, delay, RESET_TIMING, delay_us, None);
         let color = DEFAULT_BACKGROUND_COLOR;
 
         let mut epd = Epd5in83 { interface, color };
@@ -192,10 +237,13 @@ where
         self.wait_until_idle(spi).await?;
         self.command(spi, Command::PowerOff).await?;
         self.wait_until_idle(spi).await?;
-        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5]).await
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5]).await?;
+        self.interface.mark_asleep();
+        Ok(())
     }
 
     async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.require_reset_pin()?;
         self.init(spi).await
     }
 
@@ -215,14 +263,42 @@ where
         HEIGHT
     }
 
-    async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
+
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
-        self.update_achromatic_frame(spi, buffer).await?;
-        let color = self.color.get_byte_value();
-        self.command(spi, Command::DataStartTransmission2).await?;
-        self.interface
-            .data_x_times(spi, color, NUM_DISPLAY_BITS)
-            .await
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    /// Sends a combined buffer as used by [Display5in83b]: the black plane followed by
+    /// the chromatic plane.
+    async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        let (black, chromatic) = buffer.split_at(NUM_DISPLAY_BITS as usize);
+        self.update_color_frame(spi, black, chromatic).await
     }
 
     async fn update_partial_frame(
@@ -289,18 +365,8 @@ where
     }
 
     async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.wait_until_idle(spi).await?;
-
-        // The Waveshare controllers all implement clear using 0x33
-        self.command(spi, Command::DataStartTransmission1).await?;
-        self.interface
-            .data_x_times(spi, 0xFF, NUM_DISPLAY_BITS)
-            .await?;
-
-        self.command(spi, Command::DataStartTransmission2).await?;
-        self.interface
-            .data_x_times(spi, 0x00, NUM_DISPLAY_BITS)
-            .await
+        self.clear_achromatic_frame(spi).await?;
+        self.clear_chromatic_frame(spi).await
     }
 
     async fn set_lut(
@@ -311,12 +377,12 @@ where
         unimplemented!();
     }
 
-    async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
+    async fn wait_until_idle(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.wait_until_idle(BUSY_LEVEL).await
     }
 }
 
-impl<SPI, BUSY, DC, RST> Epd5in83<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> Epd5in83<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -326,6 +392,7 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn command(
         &mut self,
@@ -377,4 +444,17 @@ mod tests {
         assert_eq!(HEIGHT, 480);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
     }
+
+    #[test]
+    fn same_resolution_as_5in83_v2() {
+        assert_eq!(WIDTH, crate::epd5in83_v2::WIDTH);
+        assert_eq!(HEIGHT, crate::epd5in83_v2::HEIGHT);
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn display_holds_both_planes() {
+        let display = Display5in83b::default();
+        assert_eq!(display.buffer().len(), 2 * NUM_DISPLAY_BITS as usize);
+    }
 }
@@ -0,0 +1,405 @@
+//! A simple Driver for the Waveshare 7.3inch e-Paper HAT (E) Spectra 6 Display via SPI
+//!
+//! The panel only knows six colors (black, white, yellow, red, blue and green) and uses
+//! different 4 bit codes for them than the ACeP panels. The driver keeps [OctColor] as
+//! its color type and translates the nibbles to the panel codes while transmitting, so
+//! [OctColor::Orange] is shown as red and [OctColor::HiZ] as white.
+//!
+//! # References
+//!
+//! - [Datasheet](https://www.waveshare.com/wiki/7.3inch_e-Paper_HAT_(E)_Manual)
+//! - [Waveshare C driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_7in3e.c)
+//! - [Waveshare Python driver](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd7in3e.py)
+
+use core::fmt::{Debug, Display};
+
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+
+use crate::{
+    buffer_len,
+    color::OctColor,
+    interface::{BusyLevel, DisplayDelay, DisplayInterface, ResetTiming},
+    prelude::ErrorKind,
+    traits::{ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay},
+};
+
+use self::command::Command;
+
+mod command;
+
+/// Full size buffer for use with the 7in3e EPD
+#[cfg(feature = "graphics")]
+pub type Display7in3e = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { buffer_len(WIDTH as usize, HEIGHT as usize * 4) },
+    OctColor,
+>;
+
+/// Width of the display
+pub const WIDTH: u32 = 800;
+/// Height of the display
+pub const HEIGHT: u32 = 480;
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: OctColor = OctColor::White;
+const BUSY_LEVEL: BusyLevel = BusyLevel::Low;
+const RESET_TIMING: ResetTiming = ResetTiming::new(20_000, 2_000);
+/// Default mode of writing data (single byte vs blockwise)
+const SINGLE_BYTE_WRITE: bool = true;
+/// Number of translated bytes sent per data transfer
+const CHUNK_SIZE: usize = 64;
+
+/// Maps an [OctColor] onto the 4 bit code used by the Spectra 6 panel
+pub fn spectra6_code(color: OctColor) -> u8 {
+    match color {
+        OctColor::Black => 0x00,
+        OctColor::White | OctColor::HiZ => 0x01,
+        OctColor::Yellow => 0x02,
+        OctColor::Red | OctColor::Orange => 0x03,
+        OctColor::Blue => 0x05,
+        OctColor::Green => 0x06,
+    }
+}
+
+/// Translates a byte of two [OctColor] nibbles into the two panel codes
+fn translate_byte(byte: u8) -> u8 {
+    let (high, low) = OctColor::split_byte(byte).unwrap_or((OctColor::White, OctColor::White));
+    spectra6_code(high) << 4 | spectra6_code(low)
+}
+
+/// Epd7in3e driver
+pub struct Epd7in3e<SPI, BUSY, DC, RST, DELAY> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    color: OctColor,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST>
+    for Epd7in3e<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    type Error = ErrorKind<SPI, BUSY, DC, RST>;
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Epd7in3e<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.reset().await?;
+        self.wait_until_idle(spi).await?;
+        self.interface.delay(30_000).await;
+
+        self.cmd_with_data(spi, Command::Cmdh, &[0x49, 0x55, 0x20, 0x08, 0x09, 0x18])
+            .await?;
+        self.cmd_with_data(spi, Command::PowerSetting, &[0x3F])
+            .await?;
+        self.cmd_with_data(spi, Command::PanelSetting, &[0x5F, 0x69])
+            .await?;
+        self.cmd_with_data(
+            spi,
+            Command::PowerOffSequenceSetting,
+            &[0x00, 0x54, 0x00, 0x44],
+        )
+        .await?;
+        self.cmd_with_data(spi, Command::BoosterSoftStart1, &[0x40, 0x1F, 0x1F, 0x2C])
+            .await?;
+        self.cmd_with_data(spi, Command::BoosterSoftStart2, &[0x6F, 0x1F, 0x17, 0x49])
+            .await?;
+        self.cmd_with_data(spi, Command::BoosterSoftStart3, &[0x6F, 0x1F, 0x1F, 0x22])
+            .await?;
+        self.cmd_with_data(spi, Command::PllControl, &[0x03])
+            .await?;
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x3F])
+            .await?;
+        self.cmd_with_data(spi, Command::TconSetting, &[0x02, 0x00])
+            .await?;
+        self.send_resolution(spi).await?;
+        self.cmd_with_data(spi, Command::TVcmDcSetting, &[0x01])
+            .await?;
+        self.cmd_with_data(spi, Command::PowerSaving, &[0x2F])
+            .await?;
+
+        self.command(spi, Command::PowerOn).await?;
+        self.wait_until_idle(spi).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd7in3e<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    type DisplayColor = OctColor;
+
+    async fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        delay: DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Self::Error>
+    where
+        Self: Sized,
+    {
+        let interface =
+            DisplayInterface::new(Some(busy), dc, rst, delay, RESET_TIMING, delay_us, None);
+        let color = DEFAULT_BACKGROUND_COLOR;
+
+        let mut epd = Epd7in3e { interface, color };
+
+        epd.init(spi).await?;
+
+        Ok(epd)
+    }
+
+    async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5]).await?;
+        self.interface.mark_asleep();
+        Ok(())
+    }
+
+    async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.require_reset_pin()?;
+        self.init(spi).await
+    }
+
+    fn set_background_color(&mut self, color: Self::DisplayColor) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &Self::DisplayColor {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        WIDTH
+    }
+
+    fn height(&self) -> u32 {
+        HEIGHT
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
+
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.command(spi, Command::DataStartTransmission).await?;
+
+        let mut chunk = [0u8; CHUNK_SIZE];
+        for data in buffer.chunks(CHUNK_SIZE) {
+            for (dst, src) in chunk.iter_mut().zip(data) {
+                *dst = translate_byte(*src);
+            }
+            self.interface.data(spi, &chunk[..data.len()]).await?;
+        }
+        Ok(())
+    }
+
+    /// The panel doesn't support partial updates, this always returns [ErrorKind::Unsupported]
+    async fn update_partial_frame(
+        &mut self,
+        _spi: &mut SPI,
+        _buffer: &[u8],
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+    ) -> Result<(), Self::Error> {
+        Err(ErrorKind::Unsupported)
+    }
+
+    async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.command(spi, Command::PowerOn).await?;
+        self.wait_until_idle(spi).await?;
+
+        // Second setting of the booster, as done by the vendor code before each refresh
+        self.cmd_with_data(spi, Command::BoosterSoftStart2, &[0x6F, 0x1F, 0x17, 0x49])
+            .await?;
+
+        self.cmd_with_data(spi, Command::DisplayRefresh, &[0x00])
+            .await?;
+        self.wait_until_idle(spi).await?;
+
+        self.cmd_with_data(spi, Command::PowerOff, &[0x00]).await?;
+        self.wait_until_idle(spi).await
+    }
+
+    async fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_frame(spi, buffer).await?;
+        self.display_frame(spi).await
+    }
+
+    async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        let bg = translate_byte(OctColor::colors_byte(self.color, self.color));
+
+        self.wait_until_idle(spi).await?;
+        self.command(spi, Command::DataStartTransmission).await?;
+        self.interface
+            .data_x_times(spi, bg, WIDTH * HEIGHT / 2)
+            .await?;
+
+        self.display_frame(spi).await
+    }
+
+    /// The panel has no selectable LUTs, this always returns [ErrorKind::Unsupported]
+    async fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        _refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Self::Error> {
+        Err(ErrorKind::Unsupported)
+    }
+
+    async fn wait_until_idle(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.wait_until_idle(BUSY_LEVEL).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY> Epd7in3e<SPI, BUSY, DC, RST, DELAY>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    async fn command(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.cmd(spi, command).await
+    }
+
+    async fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    async fn send_resolution(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let w = self.width();
+        let h = self.height();
+
+        self.command(spi, Command::TconResolution).await?;
+        self.interface
+            .data(spi, &[(w >> 8) as u8, w as u8, (h >> 8) as u8, h as u8])
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 800);
+        assert_eq!(HEIGHT, 480);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, OctColor::White);
+    }
+
+    #[test]
+    fn color_codes() {
+        assert_eq!(spectra6_code(OctColor::Black), 0x0);
+        assert_eq!(spectra6_code(OctColor::White), 0x1);
+        assert_eq!(spectra6_code(OctColor::Yellow), 0x2);
+        assert_eq!(spectra6_code(OctColor::Red), 0x3);
+        assert_eq!(spectra6_code(OctColor::Blue), 0x5);
+        assert_eq!(spectra6_code(OctColor::Green), 0x6);
+    }
+
+    #[test]
+    fn byte_packing() {
+        // OctColor codes: Red = 0x4, Green = 0x2, Blue = 0x3, Yellow = 0x5
+        assert_eq!(
+            translate_byte(OctColor::colors_byte(OctColor::Red, OctColor::Green)),
+            0x36
+        );
+        assert_eq!(
+            translate_byte(OctColor::colors_byte(OctColor::Blue, OctColor::Yellow)),
+            0x52
+        );
+        assert_eq!(
+            translate_byte(OctColor::colors_byte(OctColor::White, OctColor::Black)),
+            0x10
+        );
+        // the unused nibble values of the OctColor range end up as white
+        assert_eq!(translate_byte(0xF0), 0x11);
+    }
+}
@@ -1,50 +1,101 @@
+//! SPI Commands for the Waveshare 7.3" (F) E-Ink Display
+
 use crate::traits;
 
-#[allow(dead_code, clippy::upper_case_acronyms)]
-#[derive(Clone, Copy, Debug)]
+/// Epd7in3f commands
+///
+/// Should rarely (never?) be needed directly.
+///
+/// For more infos about the addresses and what they are doing look into the PDFs.
+#[allow(dead_code)]
+#[derive(Copy, Clone, Debug)]
 pub(crate) enum Command {
-    Ox00 = 0x00,
-    Ox01 = 0x01,
+    /// Set Resolution, LUT selection, BWR pixels, gate scan direction, source shift
+    /// direction, booster switch, soft reset.
+    PanelSetting = 0x00,
+
+    /// Selecting internal and external power
+    PowerSetting = 0x01,
 
+    /// After the Power Off command, the driver will power off following the Power Off
+    /// Sequence; BUSY signal will become "0".
     PowerOff = 0x02,
 
-    Ox03 = 0x03,
+    /// Setting Power OFF sequence
+    PowerOffSequenceSetting = 0x03,
 
+    /// Turning On the Power
+    ///
+    /// After the Power ON command, the driver will power on following the Power ON
+    /// sequence. Once complete, the BUSY signal will become "1".
     PowerOn = 0x04,
 
-    Ox05 = 0x05,
-    Ox06 = 0x06,
+    /// Booster soft start phase 1
+    BoosterSoftStart1 = 0x05,
+
+    /// Booster soft start phase 2
+    BoosterSoftStart2 = 0x06,
 
+    /// This command makes the chip enter the deep-sleep mode to save power.
+    ///
+    /// The deep sleep mode would return to stand-by by hardware reset.
+    ///
+    /// The only one parameter is a check code, the command would be excuted if check code = 0xA5.
     DeepSleep = 0x07,
 
-    Ox08 = 0x08,
+    /// Booster soft start phase 3
+    BoosterSoftStart3 = 0x08,
 
+    /// This command starts transmitting the 4 bit per pixel color data and writes them into SRAM.
     DataStartTransmission = 0x10,
 
-    DataFresh = 0x12,
+    /// After this command is issued, driver will refresh display (data/VCOM) according to
+    /// SRAM data and LUT.
+    ///
+    /// After Display Refresh command, BUSY signal will become "0" until the display
+    /// update is finished. On this panel this can take up to 30 seconds.
+    DisplayRefresh = 0x12,
+
+    /// Image Process Command
+    ImageProcess = 0x13,
+
+    /// The command controls the PLL clock frequency.
+    PllControl = 0x30,
 
-    IPC = 0x13,
+    /// This command selects the Internal or External temperature sensor.
+    TemperatureSensorSelection = 0x41,
 
-    Ox30 = 0x30,
+    /// This command indicates the interval of Vcom and data output. When setting the
+    /// vertical back porch, the total blanking will be kept (20 Hsync).
+    VcomAndDataIntervalSetting = 0x50,
 
-    TSE = 0x41,
+    /// This command defines non-overlap period of Gate and Source.
+    TconSetting = 0x60,
 
-    Ox50 = 0x50,
-    Ox60 = 0x60,
-    Ox61 = 0x61,
+    /// This command defines alternative resolution and this setting is of higher priority
+    /// than the RES\[1:0\] in R00H (PSR).
+    TconResolution = 0x61,
 
-    Ox82 = 0x82,
-    Ox84 = 0x84,
+    /// This command sets `VCOM_DC` value.
+    VcmDcSetting = 0x82,
 
-    CMDH = 0xAA,
+    /// This command sets `VCOM_DC` value used during the refresh.
+    TVcmDcSetting = 0x84,
 
-    AGID = 0x86,
+    /// Automatic gate idle detection
+    AutoGateIdleDetection = 0x86,
 
-    CCSET = 0xE0,
+    /// Command header (CMDH), unlocks the vendor specific registers
+    Cmdh = 0xAA,
 
-    OxE3 = 0xE3,
+    /// Cascade setting
+    CascadeSetting = 0xE0,
 
-    TSSET = 0xE6,
+    /// Power saving
+    PowerSaving = 0xE3,
+
+    /// Force temperature setting
+    ForceTemperature = 0xE6,
 }
 
 impl traits::Command for Command {
@@ -53,3 +104,16 @@ impl traits::Command for Command {
         self as u8
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::Command as CommandTrait;
+
+    #[test]
+    fn command_addr() {
+        assert_eq!(Command::PanelSetting.address(), 0x00);
+        assert_eq!(Command::DisplayRefresh.address(), 0x12);
+        assert_eq!(Command::Cmdh.address(), 0xAA);
+    }
+}
@@ -14,7 +14,7 @@ use embedded_hal_async::{digital::Wait, spi::SpiDevice};
 use crate::{
     buffer_len,
     color::OctColor,
-    interface::DisplayInterface,
+    interface::{BusyLevel, DisplayDelay, DisplayInterface, ResetTiming},
     prelude::ErrorKind,
     traits::{ErrorType, InternalWiAdditions, WaveshareDisplay},
 };
@@ -41,16 +41,19 @@ pub const HEIGHT: u32 = 480;
 pub const DEFAULT_BACKGROUND_COLOR: OctColor = OctColor::White;
 /// Default mode of writing data (single byte vs blockwise)
 const SINGLE_BYTE_WRITE: bool = true;
+const BUSY_LEVEL: BusyLevel = BusyLevel::Low;
+const RESET_TIMING: ResetTiming = ResetTiming::new(20_000, 2_000);
 
-/// Epd57n3f driver
-pub struct Epd7in3f<SPI, BUSY, DC, RST> {
+/// Epd7in3f driver
+pub struct Epd7in3f<SPI, BUSY, DC, RST, DELAY> {
     /// Connection Interface
-    interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
     /// Background Color
     color: OctColor,
 }
 
-impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd7in3f<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST>
+    for Epd7in3f<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -60,11 +63,13 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
 }
 
-impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd7in3f<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Epd7in3f<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -74,44 +79,63 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.interface.reset(spi, 20_000, 2_000).await?;
+        self.interface.reset().await?;
         self.wait_busy_low(spi).await?;
-        self.interface.delay(spi, 30).await?;
+        self.interface.delay(30).await;
 
-        self.cmd_with_data(spi, Command::CMDH, &[0x49, 0x55, 0x20, 0x08, 0x09, 0x18])
+        self.cmd_with_data(spi, Command::Cmdh, &[0x49, 0x55, 0x20, 0x08, 0x09, 0x18])
             .await?;
-        self.cmd_with_data(spi, Command::Ox01, &[0x3F, 0x00, 0x32, 0x2A, 0x0E, 0x2A])
+        self.cmd_with_data(
+            spi,
+            Command::PowerSetting,
+            &[0x3F, 0x00, 0x32, 0x2A, 0x0E, 0x2A],
+        )
+        .await?;
+        self.cmd_with_data(spi, Command::PanelSetting, &[0x5F, 0x69])
             .await?;
-        self.cmd_with_data(spi, Command::Ox00, &[0x5F, 0x69])
+        self.cmd_with_data(
+            spi,
+            Command::PowerOffSequenceSetting,
+            &[0x00, 0x54, 0x00, 0x44],
+        )
+        .await?;
+        self.cmd_with_data(spi, Command::BoosterSoftStart1, &[0x40, 0x1F, 0x1F, 0x2C])
             .await?;
-        self.cmd_with_data(spi, Command::Ox03, &[0x00, 0x54, 0x00, 0x44])
+        self.cmd_with_data(spi, Command::BoosterSoftStart2, &[0x6F, 0x1F, 0x1F, 0x22])
             .await?;
-        self.cmd_with_data(spi, Command::Ox05, &[0x40, 0x1F, 0x1F, 0x2C])
+        self.cmd_with_data(spi, Command::BoosterSoftStart3, &[0x6F, 0x1F, 0x1F, 0x22])
             .await?;
-        self.cmd_with_data(spi, Command::Ox06, &[0x6F, 0x1F, 0x1F, 0x22])
+        self.cmd_with_data(spi, Command::ImageProcess, &[0x00, 0x04])
             .await?;
-        self.cmd_with_data(spi, Command::Ox08, &[0x6F, 0x1F, 0x1F, 0x22])
+        self.cmd_with_data(spi, Command::PllControl, &[0x3C])
             .await?;
-        self.cmd_with_data(spi, Command::IPC, &[0x00, 0x04]).await?;
-        self.cmd_with_data(spi, Command::Ox30, &[0x3C]).await?;
-        self.cmd_with_data(spi, Command::TSE, &[0x00]).await?;
-        self.cmd_with_data(spi, Command::Ox50, &[0x3F]).await?;
-        self.cmd_with_data(spi, Command::Ox60, &[0x02, 0x00])
+        self.cmd_with_data(spi, Command::TemperatureSensorSelection, &[0x00])
             .await?;
-        self.cmd_with_data(spi, Command::Ox61, &[0x03, 0x20, 0x01, 0xE0])
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x3F])
             .await?;
-        self.cmd_with_data(spi, Command::Ox82, &[0x1E]).await?;
-        self.cmd_with_data(spi, Command::Ox84, &[0x00]).await?;
-        self.cmd_with_data(spi, Command::AGID, &[0x00]).await?;
-        self.cmd_with_data(spi, Command::OxE3, &[0x2F]).await?;
-        self.cmd_with_data(spi, Command::CCSET, &[0x00]).await?;
-        self.cmd_with_data(spi, Command::TSSET, &[0x00]).await
+        self.cmd_with_data(spi, Command::TconSetting, &[0x02, 0x00])
+            .await?;
+        self.send_resolution(spi).await?;
+        self.cmd_with_data(spi, Command::VcmDcSetting, &[0x1E])
+            .await?;
+        self.cmd_with_data(spi, Command::TVcmDcSetting, &[0x00])
+            .await?;
+        self.cmd_with_data(spi, Command::AutoGateIdleDetection, &[0x00])
+            .await?;
+        self.cmd_with_data(spi, Command::PowerSaving, &[0x2F])
+            .await?;
+        self.cmd_with_data(spi, Command::CascadeSetting, &[0x00])
+            .await?;
+        self.cmd_with_data(spi, Command::ForceTemperature, &[0x00])
+            .await
     }
 }
 
-impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd7in3f<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd7in3f<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -121,6 +145,7 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type DisplayColor = OctColor;
 
@@ -128,13 +153,15 @@ where
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        delay: DELAY,
         delay_us: Option<u32>,
     ) -> Result<Self, Self::Error>
     where
         Self: Sized,
     {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        let interface =
+            DisplayInterface::new(Some(busy), dc, rst, delay, RESET_TIMING, delay_us, None);
         let color = DEFAULT_BACKGROUND_COLOR;
 
         let mut epd = Epd7in3f { interface, color };
@@ -145,10 +172,13 @@ where
     }
 
     async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5]).await
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5]).await?;
+        self.interface.mark_asleep();
+        Ok(())
     }
 
     async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.require_reset_pin()?;
         self.init(spi).await
     }
 
@@ -168,6 +198,37 @@ where
         HEIGHT
     }
 
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
+
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
     async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
         self.cmd_with_data(spi, Command::DataStartTransmission, buffer)
@@ -190,7 +251,9 @@ where
         self.command(spi, Command::PowerOn).await?;
         self.wait_busy_low(spi).await?;
 
-        self.cmd_with_data(spi, Command::DataFresh, &[0x00]).await?;
+        // The refresh of all seven colors takes ~15-30s, BUSY stays low until it is done
+        self.cmd_with_data(spi, Command::DisplayRefresh, &[0x00])
+            .await?;
         self.wait_busy_low(spi).await?;
 
         self.cmd_with_data(spi, Command::PowerOff, &[0x00]).await?;
@@ -231,7 +294,7 @@ where
     }
 }
 
-impl<SPI, BUSY, DC, RST> Epd7in3f<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> Epd7in3f<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -241,6 +304,7 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn command(
         &mut self,
@@ -259,11 +323,24 @@ where
         self.interface.cmd_with_data(spi, command, data).await
     }
 
-    async fn wait_busy_low(
+    async fn send_resolution(
         &mut self,
         spi: &mut SPI,
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
-        self.interface.wait_until_idle(spi, true).await
+        let w = self.width();
+        let h = self.height();
+
+        self.command(spi, Command::TconResolution).await?;
+        self.interface
+            .data(spi, &[(w >> 8) as u8, w as u8, (h >> 8) as u8, h as u8])
+            .await
+    }
+
+    async fn wait_busy_low(
+        &mut self,
+        _spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.wait_until_idle(BUSY_LEVEL).await
     }
 
     /// Show 7 blocks of color, used for quick testing
@@ -306,3 +383,23 @@ where
         self.display_frame(spi).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epd_size() {
+        assert_eq!(WIDTH, 800);
+        assert_eq!(HEIGHT, 480);
+        assert_eq!(DEFAULT_BACKGROUND_COLOR, OctColor::White);
+    }
+
+    #[test]
+    fn two_pixels_per_byte() {
+        assert_eq!(
+            buffer_len(WIDTH as usize, HEIGHT as usize * 4),
+            (WIDTH * HEIGHT / 2) as usize
+        );
+    }
+}
@@ -4,6 +4,11 @@
 //! *EPD 7in5 HD:* White = 1/0xFF, Black = 0/0x00
 //! *EPD 7in5 V2:* White = 0/0x00, Black = 1/0xFF
 //!
+//! Unlike the UC8179 of the V2, the SSD1677 of the HD panel writes its RAM through
+//! windows, so [update_partial_frame](WaveshareDisplay::update_partial_frame) only
+//! transmits the changed rectangle. The X addresses are pixels, but partial windows
+//! still have to start and end on a byte boundary of the buffer.
+//!
 //! # References
 //!
 //! - [Datasheet](https://www.waveshare.com/w/upload/2/27/7inch_HD_e-Paper_Specification.pdf)
@@ -15,7 +20,7 @@ use embedded_hal_async::{digital::Wait, spi::SpiDevice};
 
 use crate::color::Color;
 use crate::error::ErrorKind;
-use crate::interface::DisplayInterface;
+use crate::interface::{BusyLevel, DisplayDelay, DisplayInterface, ResetTiming};
 use crate::traits::{ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay};
 
 pub(crate) mod command;
@@ -24,7 +29,7 @@ use crate::buffer_len;
 
 /// Full size buffer for use with the 7in5 HD EPD
 #[cfg(feature = "graphics")]
-pub type Display7in5 = crate::graphics::Display<
+pub type Display7in5hd = crate::graphics::Display<
     WIDTH,
     HEIGHT,
     false,
@@ -32,25 +37,35 @@ pub type Display7in5 = crate::graphics::Display<
     Color,
 >;
 
+/// Former name of [Display7in5hd]
+#[cfg(feature = "graphics")]
+pub type Display7in5 = Display7in5hd;
+
 /// Width of the display
 pub const WIDTH: u32 = 880;
 /// Height of the display
 pub const HEIGHT: u32 = 528;
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White; // Inverted for HD as compared to 7in5 v2 (HD: 0xFF = White)
-const IS_BUSY_LOW: bool = false;
+const BUSY_LEVEL: BusyLevel = BusyLevel::High;
+const RESET_TIMING: ResetTiming = ResetTiming::new(10_000, 2_000);
 const SINGLE_BYTE_WRITE: bool = false;
 
+/// Bytes of one row, 880 pixels fill exactly 110 bytes
+const ROW_BYTES: u32 = WIDTH / 8;
+/// Rows of the RAM window, the Y address runs down from 687 to 0
+const RAM_ROWS: u32 = 688;
+
 /// EPD7in5 (HD) driver
 ///
-pub struct Epd7in5<SPI, BUSY, DC, RST> {
+pub struct Epd7in5<SPI, BUSY, DC, RST, DELAY> {
     /// Connection Interface
-    interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
     /// Background Color
     color: Color,
 }
 
-impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd7in5<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST> for Epd7in5<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -60,11 +75,13 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
 }
 
-impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd7in5<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Epd7in5<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -74,10 +91,11 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         // Reset the device
-        self.interface.reset(spi, 10_000, 2_000).await?;
+        self.interface.reset().await?;
 
         // HD procedure as described here:
         // https://github.com/waveshare/e-Paper/blob/master/RaspberryPi_JetsonNano/python/lib/waveshare_epd/epd7in5_HD.py
@@ -103,14 +121,11 @@ where
 
         self.cmd_with_data(spi, Command::DataEntry, &[0x01]).await?;
 
-        self.cmd_with_data(spi, Command::SetRamXStartEnd, &[0x00, 0x00, 0x6F, 0x03])
-            .await?;
-        self.cmd_with_data(spi, Command::SetRamYStartEnd, &[0xAF, 0x02, 0x00, 0x00])
-            .await?;
-
         self.cmd_with_data(spi, Command::VbdControl, &[0x05])
             .await?;
 
+        // Select the internal sensor, then load the temperature and the waveform
+        // matching it. The first refresh after a reset needs both.
         self.cmd_with_data(spi, Command::TemperatureSensorControl, &[0x80])
             .await?;
 
@@ -120,14 +135,12 @@ where
         self.command(spi, Command::MasterActivation).await?;
         self.wait_until_idle(spi).await?;
 
-        self.cmd_with_data(spi, Command::SetRamXAc, &[0x00, 0x00])
-            .await?;
-        self.cmd_with_data(spi, Command::SetRamYAc, &[0x00, 0x00])
-            .await
+        self.use_full_frame(spi).await
     }
 }
 
-impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd7in5<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd7in5<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -137,16 +150,19 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type DisplayColor = Color;
     async fn new(
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        delay: DELAY,
         delay_us: Option<u32>,
     ) -> Result<Self, Self::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        let interface =
+            DisplayInterface::new(Some(busy), dc, rst, delay, RESET_TIMING, delay_us, None);
         let color = DEFAULT_BACKGROUND_COLOR;
 
         let mut epd = Epd7in5 { interface, color };
@@ -157,33 +173,66 @@ where
     }
 
     async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.require_reset_pin()?;
         self.init(spi).await
     }
 
     async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
-        self.cmd_with_data(spi, Command::DeepSleep, &[0x01]).await
+        self.cmd_with_data(spi, Command::DeepSleep, &[0x01]).await?;
+        self.interface.mark_asleep();
+        Ok(())
     }
 
     async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        assert!(buffer.len() == buffer_len(WIDTH as usize, HEIGHT as usize));
         self.wait_until_idle(spi).await?;
-        self.cmd_with_data(spi, Command::SetRamYAc, &[0x00, 0x00])
-            .await?;
+        self.use_full_frame(spi).await?;
         self.cmd_with_data(spi, Command::WriteRamBw, buffer).await?;
         self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xF7])
             .await
     }
 
+    /// Writes `buffer` into a RAM window, the rest of the RAM is untouched
+    ///
+    /// `x` and `width` must be multiples of 8. The next [display_frame](WaveshareDisplay::display_frame)
+    /// refreshes the whole display with the full waveform.
     async fn update_partial_frame(
         &mut self,
-        _spi: &mut SPI,
-        _buffer: &[u8],
-        _x: u32,
-        _y: u32,
-        _width: u32,
-        _height: u32,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
     ) -> Result<(), Self::Error> {
-        unimplemented!();
+        assert!(x % 8 == 0 && width % 8 == 0);
+        assert!(x + width <= WIDTH && y + height <= HEIGHT);
+        assert!(buffer.len() as u32 == width / 8 * height);
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+        self.wait_until_idle(spi).await?;
+
+        let mut rows = buffer;
+        let mut y = y;
+        if y == 0 {
+            // The first row is the only one before the Y address wraps around
+            let (first, rest) = rows.split_at((width / 8) as usize);
+            self.write_window(spi, first, x, width, ram_y(0), ram_y(0))
+                .await?;
+            rows = rest;
+            y = 1;
+        }
+        if !rows.is_empty() {
+            let last = y + rows.len() as u32 / (width / 8) - 1;
+            self.write_window(spi, rows, x, width, ram_y(y), ram_y(last))
+                .await?;
+        }
+
+        self.use_full_frame(spi).await?;
+        self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[0xF7])
+            .await
     }
 
     async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
@@ -201,12 +250,11 @@ where
     }
 
     async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        let pixel_count = WIDTH / 8 * HEIGHT;
+        let pixel_count = ROW_BYTES * HEIGHT;
         let background_color_byte = self.color.get_byte_value();
 
         self.wait_until_idle(spi).await?;
-        self.cmd_with_data(spi, Command::SetRamYAc, &[0x00, 0x00])
-            .await?;
+        self.use_full_frame(spi).await?;
 
         for cmd in &[Command::WriteRamBw, Command::WriteRamRed] {
             self.command(spi, *cmd).await?;
@@ -237,6 +285,37 @@ where
         HEIGHT
     }
 
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
+
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
     async fn set_lut(
         &mut self,
         _spi: &mut SPI,
@@ -245,12 +324,12 @@ where
         unimplemented!();
     }
 
-    async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.interface.wait_until_idle(spi, IS_BUSY_LOW).await
+    async fn wait_until_idle(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.wait_until_idle(BUSY_LEVEL).await
     }
 }
 
-impl<SPI, BUSY, DC, RST> Epd7in5<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> Epd7in5<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -260,6 +339,7 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn command(
         &mut self,
@@ -277,16 +357,132 @@ where
     ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
         self.interface.cmd_with_data(spi, command, data).await
     }
+
+    /// Selects the whole RAM for the next write, the sequence of the vendor init
+    async fn use_full_frame(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.cmd_with_data(spi, Command::SetRamXStartEnd, &[0x00, 0x00, 0x6F, 0x03])
+            .await?;
+        self.cmd_with_data(spi, Command::SetRamYStartEnd, &[0xAF, 0x02, 0x00, 0x00])
+            .await?;
+        self.cmd_with_data(spi, Command::SetRamXAc, &[0x00, 0x00])
+            .await?;
+        self.cmd_with_data(spi, Command::SetRamYAc, &[0x00, 0x00])
+            .await
+    }
+
+    /// Writes `rows` into the window from `start_y` down to `end_y` of the RAM
+    async fn write_window(
+        &mut self,
+        spi: &mut SPI,
+        rows: &[u8],
+        x: u32,
+        width: u32,
+        start_y: u32,
+        end_y: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let end_x = x + width - 1;
+        self.cmd_with_data(
+            spi,
+            Command::SetRamXStartEnd,
+            &[x as u8, (x >> 8) as u8, end_x as u8, (end_x >> 8) as u8],
+        )
+        .await?;
+        self.cmd_with_data(
+            spi,
+            Command::SetRamYStartEnd,
+            &[
+                start_y as u8,
+                (start_y >> 8) as u8,
+                end_y as u8,
+                (end_y >> 8) as u8,
+            ],
+        )
+        .await?;
+        self.cmd_with_data(spi, Command::SetRamXAc, &[x as u8, (x >> 8) as u8])
+            .await?;
+        self.cmd_with_data(
+            spi,
+            Command::SetRamYAc,
+            &[start_y as u8, (start_y >> 8) as u8],
+        )
+        .await?;
+        self.cmd_with_data(spi, Command::WriteRamBw, rows).await
+    }
+}
+
+/// RAM address of the row `y` of the buffer
+///
+/// A full frame is written from address 0 with the Y address counting down, so the
+/// first row lands on 0 and the following ones wrap around to 687, 686, and so on.
+const fn ram_y(y: u32) -> u32 {
+    (RAM_ROWS - y) % RAM_ROWS
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::{block_on, mocks};
 
     #[test]
     fn epd_size() {
         assert_eq!(WIDTH, 880);
         assert_eq!(HEIGHT, 528);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+        assert_eq!(ROW_BYTES, 110);
+    }
+
+    #[test]
+    fn ram_rows() {
+        assert_eq!(ram_y(0), 0);
+        assert_eq!(ram_y(1), 687);
+        assert_eq!(ram_y(HEIGHT - 1), 161);
+    }
+
+    #[test]
+    fn init_loads_temperature_and_waveform() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        block_on(Epd7in5::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert_eq!(log.data_of(0x18, 0), [0x80]);
+        assert_eq!(log.data_of(0x22, 0), [0xB1]);
+        let commands = log.commands();
+        let update = commands.iter().position(|&c| c == 0x22).unwrap();
+        assert_eq!(commands[update + 1], 0x20);
+    }
+
+    #[test]
+    fn partial_window() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd7in5::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        let buffer = [0x55; 2 * 10];
+        block_on(epd.update_partial_frame(&mut spi, &buffer, 16, 10, 16, 10)).unwrap();
+        // x from 16 to 31, y from 10 to 19 counting down from 678 to 669
+        assert_eq!(log.data_of(0x44, 0), [16, 0, 31, 0]);
+        assert_eq!(log.data_of(0x45, 0), [0xA6, 0x02, 0x9D, 0x02]);
+        assert_eq!(log.data_of(0x4E, 0), [16, 0]);
+        assert_eq!(log.data_of(0x4F, 0), [0xA6, 0x02]);
+        assert_eq!(log.data_of(0x24, 0), buffer);
+        // the full window is restored afterwards
+        assert_eq!(log.data_of(0x44, 1), [0x00, 0x00, 0x6F, 0x03]);
+        assert_eq!(log.data_of(0x22, 0), [0xF7]);
+    }
+
+    #[test]
+    fn partial_window_at_the_top() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd7in5::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        let buffer = [0x0F, 0x0F, 0xF0, 0xF0, 0xF0, 0xF0];
+        block_on(epd.update_partial_frame(&mut spi, &buffer, 0, 0, 16, 3)).unwrap();
+        // the first row can't share a window with the wrapped around ones
+        assert_eq!(log.data_of(0x45, 0), [0, 0, 0, 0]);
+        assert_eq!(log.data_of(0x24, 0), [0x0F, 0x0F]);
+        assert_eq!(log.data_of(0x45, 1), [0xAF, 0x02, 0xAE, 0x02]);
+        assert_eq!(log.data_of(0x24, 1), [0xF0, 0xF0, 0xF0, 0xF0]);
     }
 }
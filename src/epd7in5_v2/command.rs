@@ -33,7 +33,7 @@ pub(crate) enum Command {
     /// sequence. Once complete, the BUSY signal will become "1".
     PowerOn = 0x04,
 
-    /// Starting data transmission
+    /// Sets the soft start period, driving strength and minimum off time of the booster phases
     BoosterSoftStart = 0x06,
 
     /// This command makes the chip enter the deep-sleep mode to save power.
@@ -128,9 +128,20 @@ pub(crate) enum Command {
     ReadVcomValue = 0x81,
     /// This command sets `VCOM_DC` value.
     VcmDcSetting = 0x82,
-    // /// This is in all the Waveshare controllers for Epd7in5, but it's not documented
-    // /// anywhere in the datasheet `¯\_(ツ)_/¯`
-    // FlashMode = 0xE5,
+
+    /// Sets window size for the partial update
+    PartialWindow = 0x90,
+    /// Sets chip into partial update mode
+    PartialIn = 0x91,
+    /// Quits partial update mode
+    PartialOut = 0x92,
+
+    /// Cascade setting, bit 1 (TSFIX) makes the controller use the temperature of
+    /// [Command::ForceTemperature] instead of the measured one.
+    CascadeSetting = 0xE0,
+    /// Sets the temperature the waveform is selected for, used with
+    /// [Command::CascadeSetting].
+    ForceTemperature = 0xE5,
 }
 
 impl traits::Command for Command {
@@ -149,5 +160,6 @@ mod tests {
     fn command_addr() {
         assert_eq!(Command::PanelSetting.address(), 0x00);
         assert_eq!(Command::DisplayRefresh.address(), 0x12);
+        assert_eq!(Command::ForceTemperature.address(), 0xE5);
     }
 }
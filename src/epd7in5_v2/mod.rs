@@ -1,5 +1,14 @@
 //! A simple Driver for the Waveshare 7.5" E-Ink Display (V2) via SPI
 //!
+//! Besides the full refresh of about 4.5s, the panel supports the fast refresh of the
+//! 2023 vendor driver, which takes about 1.5s. Select it with
+//! [WaveshareDisplay::set_lut] and `RefreshLut::Quick`, it is kept until
+//! `RefreshLut::Full` is selected again, also across [WaveshareDisplay::wake_up].
+//!
+//! 4 level grayscale frames are shown with [Epd7in5::update_and_display_gray4], use a
+//! [Display7in5Gray] to draw them. The next black/white frame initializes the
+//! controller for the selected refresh again.
+//!
 //! # References
 //!
 //! - [Datasheet](https://www.waveshare.com/wiki/7.5inch_e-Paper_HAT)
@@ -14,14 +23,19 @@ use core::fmt::{Debug, Display};
 use embedded_hal::digital::{InputPin, OutputPin};
 use embedded_hal_async::{digital::Wait, spi::SpiDevice};
 
+use crate::booster::BoosterSoftStart;
 use crate::color::Color;
 use crate::error::ErrorKind;
-use crate::interface::DisplayInterface;
-use crate::traits::{ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay};
+use crate::interface::{BusyLevel, DisplayDelay, DisplayInterface, ResetTiming};
+use crate::pll::FrameRate;
+use crate::split_gray4;
+use crate::traits::{
+    DualSpiBus, ErrorType, InternalWiAdditions, PowerState, RefreshLut, WaveshareDisplay,
+};
 
 pub(crate) mod command;
 use self::command::Command;
-use crate::buffer_len;
+use crate::{buffer_len, quad_buffer_len};
 
 /// Full size buffer for use with the 7in5 v2 EPD
 #[cfg(feature = "graphics")]
@@ -33,25 +47,70 @@ pub type Display7in5 = crate::graphics::Display<
     Color,
 >;
 
+/// Display with a 4 level grayscale buffer for use with [Epd7in5::update_and_display_gray4]
+#[cfg(feature = "graphics")]
+pub type Display7in5Gray = crate::graphics::Display<
+    WIDTH,
+    HEIGHT,
+    false,
+    { quad_buffer_len(WIDTH as usize, HEIGHT as usize) },
+    embedded_graphics_core::pixelcolor::Gray2,
+>;
+
 /// Width of the display
 pub const WIDTH: u32 = 800;
 /// Height of the display
 pub const HEIGHT: u32 = 480;
 /// Default Background Color
 pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
-const IS_BUSY_LOW: bool = true;
+/// Default booster soft start of the full refresh
+pub const DEFAULT_BOOSTER_SOFT_START: BoosterSoftStart =
+    BoosterSoftStart::from_bytes(0x17, 0x17, 0x27, 0x17);
+/// Default frame rate
+pub const DEFAULT_FRAME_RATE: FrameRate = FrameRate::Hz50;
+const BUSY_LEVEL: BusyLevel = BusyLevel::Low;
+const RESET_TIMING: ResetTiming = ResetTiming::new(10_000, 2_000);
 const SINGLE_BYTE_WRITE: bool = false;
+/// S2G and G2S non-overlap periods of 12 (reset value)
+const DEFAULT_TCON: u8 = 0x22;
+
+/// Length of a 4 level grayscale buffer, 2 bits per pixel
+pub const GRAY4_BUFFER_LEN: usize = quad_buffer_len(WIDTH as usize, HEIGHT as usize);
+
+/// Bytes of one row in RAM
+const ROW_BYTES: usize = WIDTH as usize / 8;
+
+/// Temperature the waveform of the fast refresh is selected for
+const FAST_TEMPERATURE: u8 = 0x5A;
+/// Temperature which selects the 4 level grayscale waveform of the OTP
+const GRAY4_TEMPERATURE: u8 = 0x5F;
+/// DUSPI_EN of the dual SPI register
+const DUAL_SPI_ENABLE: u8 = 0x10;
 
 /// Epd7in5 (V2) driver
 ///
-pub struct Epd7in5<SPI, BUSY, DC, RST> {
+pub struct Epd7in5<SPI, BUSY, DC, RST, DELAY> {
     /// Connection Interface
-    interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
     /// Background Color
     color: Color,
+    /// Refresh LUT
+    refresh: RefreshLut,
+    /// The controller is set up for grayscale frames
+    gray4: bool,
+    /// Temperature set with [Epd7in5::set_temperature]
+    temperature: Option<i8>,
+    /// Booster soft start set with [Epd7in5::set_booster_soft_start]
+    booster: BoosterSoftStart,
+    /// Non-overlap periods set with [Epd7in5::set_tcon]
+    tcon: u8,
+    /// Frame rate set with [Epd7in5::set_frame_rate]
+    frame_rate: FrameRate,
+    /// Power state, to tell the deep sleep apart
+    power: PowerState,
 }
 
-impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd7in5<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST> for Epd7in5<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -61,11 +120,13 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
 }
 
-impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd7in5<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Epd7in5<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -75,38 +136,46 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         // Reset the device
-        self.interface.reset(spi, 10_000, 2_000).await?;
+        self.interface.reset().await?;
+        self.power = PowerState::Active;
+
+        self.gray4 = false;
+        if let RefreshLut::Quick = self.refresh {
+            return self.init_fast(spi, FAST_TEMPERATURE).await;
+        }
 
         // V2 procedure as described here:
         // https://github.com/waveshare/e-Paper/blob/master/RaspberryPi%26JetsonNano/python/lib/waveshare_epd/epd7in5bc_V2.py
         // and as per specs:
         // https://www.waveshare.com/w/upload/6/60/7.5inch_e-Paper_V2_Specification.pdf
 
-        self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x17, 0x17, 0x27, 0x17])
-            .await?;
+        self.send_booster_soft_start(spi).await?;
         self.cmd_with_data(spi, Command::PowerSetting, &[0x07, 0x17, 0x3F, 0x3F])
             .await?;
         self.command(spi, Command::PowerOn).await?;
         self.wait_until_idle(spi).await?;
         self.cmd_with_data(spi, Command::PanelSetting, &[0x1F])
             .await?;
-        self.cmd_with_data(spi, Command::PllControl, &[0x06])
+        self.cmd_with_data(spi, Command::PllControl, &[self.frame_rate.uc8179_byte()])
             .await?;
         self.cmd_with_data(spi, Command::TconResolution, &[0x03, 0x20, 0x01, 0xE0])
             .await?;
         self.cmd_with_data(spi, Command::DualSpi, &[0x00]).await?;
-        self.cmd_with_data(spi, Command::TconSetting, &[0x22])
+        self.cmd_with_data(spi, Command::TconSetting, &[self.tcon])
             .await?;
         self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x10, 0x07])
             .await?;
+        self.send_temperature(spi).await?;
         self.wait_until_idle(spi).await
     }
 }
 
-impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd7in5<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd7in5<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -116,19 +185,32 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type DisplayColor = Color;
     async fn new(
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        delay: DELAY,
         delay_us: Option<u32>,
     ) -> Result<Self, Self::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        let interface =
+            DisplayInterface::new(Some(busy), dc, rst, delay, RESET_TIMING, delay_us, None);
         let color = DEFAULT_BACKGROUND_COLOR;
 
-        let mut epd = Epd7in5 { interface, color };
+        let mut epd = Epd7in5 {
+            interface,
+            color,
+            refresh: RefreshLut::Full,
+            gray4: false,
+            temperature: None,
+            booster: DEFAULT_BOOSTER_SOFT_START,
+            tcon: DEFAULT_TCON,
+            frame_rate: DEFAULT_FRAME_RATE,
+            power: PowerState::Active,
+        };
 
         epd.init(spi).await?;
 
@@ -136,37 +218,67 @@ where
     }
 
     async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
-        self.init(spi).await
+        match self.power {
+            PowerState::Active => Ok(()),
+            PowerState::PoweredOff => self.power_on(spi).await,
+            PowerState::DeepSleep => {
+                self.interface.require_reset_pin()?;
+                self.init(spi).await
+            }
+            PowerState::Unknown => self.init(spi).await,
+        }
     }
 
     async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
         self.command(spi, Command::PowerOff).await?;
         self.wait_until_idle(spi).await?;
-        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5]).await
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5]).await?;
+        self.power = PowerState::DeepSleep;
+        Ok(())
     }
 
     async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
+        self.leave_gray4(spi).await?;
         self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)
             .await
     }
 
+    /// Writes `buffer` into a window of the frame
+    ///
+    /// `x` and `width` are rounded out to byte boundaries, `buffer` has to cover the
+    /// rounded window. Returns [ErrorKind::InvalidWindow] if the window doesn't lie
+    /// inside the display or the buffer has a different size. The rest of the frame
+    /// is kept, [WaveshareDisplay::display_frame] shows it together with the window.
     async fn update_partial_frame(
         &mut self,
-        _spi: &mut SPI,
-        _buffer: &[u8],
-        _x: u32,
-        _y: u32,
-        _width: u32,
-        _height: u32,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
     ) -> Result<(), Self::Error> {
-        unimplemented!();
+        let (start_x, end_x) =
+            byte_aligned_window(x, y, width, height).ok_or(ErrorKind::InvalidWindow)?;
+        if buffer.len() != buffer_len((end_x - start_x) as usize, height as usize) {
+            return Err(ErrorKind::InvalidWindow);
+        }
+        self.wait_until_idle(spi).await?;
+        self.leave_gray4(spi).await?;
+
+        self.command(spi, Command::PartialIn).await?;
+        self.set_partial_window(spi, start_x, end_x, y, height)
+            .await?;
+        self.cmd_with_data(spi, Command::DataStartTransmission2, buffer)
+            .await?;
+        self.command(spi, Command::PartialOut).await
     }
 
     async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
-        self.command(spi, Command::DisplayRefresh).await
+        self.display_refresh(spi).await
     }
 
     async fn update_and_display_frame(
@@ -175,11 +287,12 @@ where
         buffer: &[u8],
     ) -> Result<(), Self::Error> {
         self.update_frame(spi, buffer).await?;
-        self.command(spi, Command::DisplayRefresh).await
+        self.display_refresh(spi).await
     }
 
     async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
+        self.leave_gray4(spi).await?;
         self.send_resolution(spi).await?;
 
         self.command(spi, Command::DataStartTransmission1).await?;
@@ -192,7 +305,7 @@ where
             .data_x_times(spi, 0x00, WIDTH / 8 * HEIGHT)
             .await?;
 
-        self.command(spi, Command::DisplayRefresh).await
+        self.display_refresh(spi).await
     }
 
     fn set_background_color(&mut self, color: Color) {
@@ -211,22 +324,66 @@ where
         HEIGHT
     }
 
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
+
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    /// Selects the full (`RefreshLut::Full`) or the fast (`RefreshLut::Quick`) refresh
+    ///
+    /// The controller is initialized again for the selected refresh.
     async fn set_lut(
         &mut self,
-        _spi: &mut SPI,
-        _refresh_rate: Option<RefreshLut>,
+        spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
     ) -> Result<(), Self::Error> {
-        unimplemented!();
+        if let Some(refresh_lut) = refresh_rate {
+            self.refresh = refresh_lut;
+        }
+        self.wait_until_idle(spi).await?;
+        self.init(spi).await
     }
 
+    /// Returns [ErrorKind::Asleep] in deep sleep instead of waiting for the busy pin
+    ///
+    /// The busy pin doesn't change until the controller is woken up by a reset.
     async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        if self.power == PowerState::DeepSleep {
+            return Err(ErrorKind::Asleep);
+        }
         self.interface
-            .wait_until_idle_with_cmd(spi, IS_BUSY_LOW, Command::GetStatus)
+            .wait_until_idle_with_cmd(spi, BUSY_LEVEL, Command::GetStatus)
             .await
     }
 }
 
-impl<SPI, BUSY, DC, RST> Epd7in5<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> Epd7in5<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -236,7 +393,363 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
+    /// Shows a 4 level grayscale frame
+    ///
+    /// `buffer` holds 2 bits per pixel, MSB first, with `0b00` black, `0b01` dark gray,
+    /// `0b10` light gray and `0b11` white, like a [Display7in5Gray]. It has to be
+    /// [GRAY4_BUFFER_LEN] bytes long.
+    ///
+    /// The controller is initialized for the grayscale waveform of the OTP. Like the
+    /// vendor driver, the old data RAM takes the inverted low bit and the new data RAM
+    /// the inverted high bit of a pixel.
+    pub async fn update_and_display_gray4(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        assert!(buffer.len() == GRAY4_BUFFER_LEN);
+        self.wait_until_idle(spi).await?;
+        if !self.gray4 {
+            self.interface.reset().await?;
+            self.power = PowerState::Active;
+            self.init_fast(spi, GRAY4_TEMPERATURE).await?;
+            self.gray4 = true;
+        }
+
+        self.write_gray4_plane(spi, Command::DataStartTransmission1, buffer, false)
+            .await?;
+        self.write_gray4_plane(spi, Command::DataStartTransmission2, buffer, true)
+            .await?;
+
+        self.display_refresh(spi).await?;
+        self.wait_until_idle(spi).await
+    }
+
+    /// Like [WaveshareDisplay::update_frame], but sends `buffer` over `bus` in dual SPI mode
+    ///
+    /// Even bytes of `buffer` go out on the first data line and odd bytes on the second
+    /// one, which nearly halves the transfer time. The mode is only enabled for this
+    /// transfer, commands and everything else keep using `spi`. Returns
+    /// [ErrorKind::InvalidWindow] if `buffer` isn't exactly one frame.
+    pub async fn update_frame_dual<D: DualSpiBus<Error = SPI::Error>>(
+        &mut self,
+        spi: &mut SPI,
+        bus: &mut D,
+        buffer: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if buffer.len() != ROW_BYTES * HEIGHT as usize {
+            return Err(ErrorKind::InvalidWindow);
+        }
+        self.wait_until_idle(spi).await?;
+        self.leave_gray4(spi).await?;
+        self.cmd_with_data(spi, Command::DualSpi, &[DUAL_SPI_ENABLE])
+            .await?;
+        let mut result = self.command(spi, Command::DataStartTransmission2).await;
+        if result.is_ok() {
+            result = self.interface.data_dual(bus, buffer).await;
+        }
+        // back to single SPI on errors as well, the next commands go over `spi` again
+        let disabled = self.cmd_with_data(spi, Command::DualSpi, &[0x00]).await;
+        result.and(disabled)
+    }
+
+    /// Like [WaveshareDisplay::update_frame], but takes the bytes of the frame from
+    /// `frame`, e.g. rendered row by row without a full buffer in RAM
+    ///
+    /// `frame` yields the bytes in the order of the buffer, they are sent in blocks of 256
+    /// within one data phase. Returns [ErrorKind::InvalidWindow] if it ends before the
+    /// frame is full, the rest of the frame keeps its old content then. Further bytes
+    /// aren't read.
+    pub async fn update_frame_iter(
+        &mut self,
+        spi: &mut SPI,
+        frame: impl IntoIterator<Item = u8>,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let len = ROW_BYTES * HEIGHT as usize;
+        self.wait_until_idle(spi).await?;
+        self.leave_gray4(spi).await?;
+        self.command(spi, Command::DataStartTransmission2).await?;
+        let sent = self
+            .interface
+            .data_from_iter(spi, frame.into_iter().take(len))
+            .await?;
+        if sent < len {
+            return Err(ErrorKind::InvalidWindow);
+        }
+        Ok(())
+    }
+
+    /// Like [WaveshareDisplay::update_partial_frame], but writes the shown image `old`
+    /// of the window into the old frame as well
+    ///
+    /// The refresh then drives the window by the difference between `old` and `new`,
+    /// which leaves less ghosting than an old frame that doesn't match the panel. Both
+    /// buffers have to cover the window with `x` and `width` rounded out to byte
+    /// boundaries, otherwise it returns [ErrorKind::InvalidWindow].
+    #[allow(clippy::too_many_arguments)]
+    pub async fn update_partial_frame_with_old(
+        &mut self,
+        spi: &mut SPI,
+        old: &[u8],
+        new: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let (start_x, end_x) =
+            byte_aligned_window(x, y, width, height).ok_or(ErrorKind::InvalidWindow)?;
+        let len = buffer_len((end_x - start_x) as usize, height as usize);
+        if old.len() != len || new.len() != len {
+            return Err(ErrorKind::InvalidWindow);
+        }
+        self.wait_until_idle(spi).await?;
+        self.leave_gray4(spi).await?;
+
+        self.command(spi, Command::PartialIn).await?;
+        self.set_partial_window(spi, start_x, end_x, y, height)
+            .await?;
+        self.cmd_with_data(spi, Command::DataStartTransmission1, old)
+            .await?;
+        self.cmd_with_data(spi, Command::DataStartTransmission2, new)
+            .await?;
+        self.command(spi, Command::PartialOut).await
+    }
+
+    /// Turns the high voltages off, but keeps the controller and its RAM awake
+    ///
+    /// Unlike [WaveshareDisplay::sleep] no init is needed afterwards, the next refresh
+    /// turns the power on again, see [Epd7in5::power_on].
+    pub async fn power_off(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.wait_until_idle(spi).await?;
+        self.command(spi, Command::PowerOff).await?;
+        self.wait_until_idle(spi).await?;
+        self.power = PowerState::PoweredOff;
+        Ok(())
+    }
+
+    /// Turns the high voltages on again after [Epd7in5::power_off]
+    pub async fn power_on(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.wait_until_idle(spi).await?;
+        self.command(spi, Command::PowerOn).await?;
+        self.wait_until_idle(spi).await?;
+        self.power = PowerState::Active;
+        Ok(())
+    }
+
+    /// Refreshes the display, turning the power on first if needed
+    async fn display_refresh(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if self.power == PowerState::PoweredOff {
+            self.power_on(spi).await?;
+        }
+        self.command(spi, Command::DisplayRefresh).await
+    }
+
+    /// Sets the frame rate of the refresh with the PLL control
+    ///
+    /// The default is [DEFAULT_FRAME_RATE]. It takes effect with the next refresh and is
+    /// kept across [WaveshareDisplay::wake_up].
+    pub async fn set_frame_rate(
+        &mut self,
+        spi: &mut SPI,
+        frame_rate: FrameRate,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.frame_rate = frame_rate;
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::PllControl, &[frame_rate.uc8179_byte()])
+            .await
+    }
+
+    /// Sets the soft start of the booster, for supplies which brown out while it starts
+    ///
+    /// It takes effect with the next power on and is kept across
+    /// [WaveshareDisplay::wake_up]. The fast refresh (`RefreshLut::Quick`) uses the
+    /// enhanced drive of the Waveshare examples instead.
+    pub async fn set_booster_soft_start(
+        &mut self,
+        spi: &mut SPI,
+        booster: BoosterSoftStart,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.booster = booster;
+        self.wait_until_idle(spi).await?;
+        self.send_booster_soft_start(spi).await
+    }
+
+    async fn send_booster_soft_start(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let bytes = self.booster.bytes();
+        self.cmd_with_data(spi, Command::BoosterSoftStart, &bytes)
+            .await
+    }
+
+    /// Sets the source to gate (`s2g`) and gate to source (`g2s`) non-overlap periods
+    ///
+    /// Both are 4 bit values, the period is 4 × (value + 1), so the default of 2 is 12.
+    /// Longer periods help against faint vertical lines, e.g. with long FPC extenders.
+    /// Returns [ErrorKind::InvalidSetting] if a value doesn't fit into 4 bits. The
+    /// periods are kept across [WaveshareDisplay::wake_up].
+    pub async fn set_tcon(
+        &mut self,
+        spi: &mut SPI,
+        s2g: u8,
+        g2s: u8,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if s2g > 0x0F || g2s > 0x0F {
+            return Err(ErrorKind::InvalidSetting);
+        }
+        self.tcon = s2g << 4 | g2s;
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::TconSetting, &[self.tcon])
+            .await
+    }
+
+    /// Reads the status flags of the controller (command 0x71)
+    ///
+    /// From bit 6 down to bit 0 they are PTL, I2C_ERR, I2C_BUSY, DATA, PON, POF and
+    /// BUSY_N, e.g. `status & 0x04 != 0` tells that the panel is powered on. It doesn't
+    /// wait until the controller is idle. The data line has to be connected to the MISO
+    /// of the SPI bus as well, which the Waveshare HATs don't do.
+    pub async fn status(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<u8, <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if self.power == PowerState::DeepSleep {
+            return Err(ErrorKind::Asleep);
+        }
+        let mut status = [0u8];
+        self.interface
+            .cmd_then_read(spi, Command::GetStatus, 0, &mut status)
+            .await?;
+        Ok(status[0])
+    }
+
+    /// Reads the revision of the controller (command 0x70) into `buffer`
+    ///
+    /// The LUT revision from the OTP comes first, followed by the chip revision. How
+    /// many bytes it sends depends on the controller, see its datasheet. Like
+    /// [Epd7in5::status] it needs the data line on the MISO.
+    pub async fn revision(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &mut [u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if self.power == PowerState::DeepSleep {
+            return Err(ErrorKind::Asleep);
+        }
+        self.interface
+            .cmd_then_read(spi, Command::Revision, 0, buffer)
+            .await
+    }
+
+    /// Selects the waveform of the full refresh for `celsius` instead of the measured
+    /// temperature
+    ///
+    /// Use it with a more accurate sensor than the one of the controller. The
+    /// temperature is kept across [WaveshareDisplay::wake_up]. The fast refresh and
+    /// grayscale frames select their waveform with a temperature of their own, so it
+    /// only takes effect on `RefreshLut::Full`.
+    pub async fn set_temperature(
+        &mut self,
+        spi: &mut SPI,
+        celsius: i8,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.temperature = Some(celsius);
+        if self.refresh == RefreshLut::Full && !self.gray4 {
+            self.wait_until_idle(spi).await?;
+            self.send_temperature(spi).await?;
+        }
+        Ok(())
+    }
+
+    /// Forces the temperature of [Epd7in5::set_temperature], if there is one
+    async fn send_temperature(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if let Some(celsius) = self.temperature {
+            self.cmd_with_data(spi, Command::CascadeSetting, &[0x02])
+                .await?;
+            self.cmd_with_data(spi, Command::ForceTemperature, &[celsius as u8])
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Writes one bit of every pixel of a grayscale frame into a RAM
+    async fn write_gray4_plane(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        buffer: &[u8],
+        high: bool,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.command(spi, command).await?;
+
+        let mut row = [0u8; ROW_BYTES];
+        for pixels in buffer.chunks(ROW_BYTES * 2) {
+            for (byte, pixels) in row.iter_mut().zip(pixels.chunks(2)) {
+                let (high_bits, low_bits) = split_gray4(pixels);
+                *byte = !if high { high_bits } else { low_bits };
+            }
+            self.send_data(spi, &row).await?;
+        }
+        Ok(())
+    }
+
+    /// Goes back to the selected black/white refresh after a grayscale frame
+    async fn leave_gray4(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        if self.gray4 {
+            self.init(spi).await?;
+        }
+        Ok(())
+    }
+
+    /// Init sequence of the fast and the grayscale refresh from the vendor driver
+    ///
+    /// The OTP waveform is selected for a forced temperature instead of the measured
+    /// one. Only a reset restores the normal waveform.
+    async fn init_fast(
+        &mut self,
+        spi: &mut SPI,
+        temperature: u8,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.cmd_with_data(spi, Command::PanelSetting, &[0x1F])
+            .await?;
+        self.cmd_with_data(spi, Command::VcomAndDataIntervalSetting, &[0x10, 0x07])
+            .await?;
+        self.command(spi, Command::PowerOn).await?;
+        self.wait_until_idle(spi).await?;
+
+        // Enhanced display drive
+        self.cmd_with_data(spi, Command::BoosterSoftStart, &[0x27, 0x27, 0x18, 0x17])
+            .await?;
+        // the reset value is already in place
+        if self.tcon != DEFAULT_TCON {
+            self.cmd_with_data(spi, Command::TconSetting, &[self.tcon])
+                .await?;
+        }
+        self.cmd_with_data(spi, Command::CascadeSetting, &[0x02])
+            .await?;
+        self.cmd_with_data(spi, Command::ForceTemperature, &[temperature])
+            .await
+    }
+
     async fn command(
         &mut self,
         spi: &mut SPI,
@@ -262,6 +775,39 @@ where
         self.interface.cmd_with_data(spi, command, data).await
     }
 
+    /// Sets the window of the partial mode, `end_x` is the first column after the window
+    async fn set_partial_window(
+        &mut self,
+        spi: &mut SPI,
+        start_x: u32,
+        end_x: u32,
+        y: u32,
+        height: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        // the lower 3 bits of the horizontal start are ignored, the ones of the end have to be set
+        let last_x = end_x - 1;
+        let last_y = y + height - 1;
+        // Gates scan both inside and outside of the partial window (default)
+        let pt_scan = 0x01;
+
+        self.cmd_with_data(
+            spi,
+            Command::PartialWindow,
+            &[
+                (start_x >> 8) as u8,
+                start_x as u8 & 0xF8,
+                (last_x >> 8) as u8,
+                last_x as u8 | 0x07,
+                (y >> 8) as u8,
+                y as u8,
+                (last_y >> 8) as u8,
+                last_y as u8,
+                pt_scan,
+            ],
+        )
+        .await
+    }
+
     async fn send_resolution(&mut self, spi: &mut SPI) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
         let w = self.width();
         let h = self.height();
@@ -274,14 +820,325 @@ where
     }
 }
 
+/// Rounds the window out to byte boundaries, returns its first and one past its last column
+///
+/// Returns `None` if the window is empty or doesn't lie inside the display.
+fn byte_aligned_window(x: u32, y: u32, width: u32, height: u32) -> Option<(u32, u32)> {
+    if width == 0 || height == 0 || x + width > WIDTH || y + height > HEIGHT {
+        return None;
+    }
+    Some((x / 8 * 8, (x + width).div_ceil(8) * 8))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::{block_on, mocks, DualBus};
 
     #[test]
     fn epd_size() {
         assert_eq!(WIDTH, 800);
         assert_eq!(HEIGHT, 480);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, Color::White);
+        assert_eq!(GRAY4_BUFFER_LEN, 2 * ROW_BYTES * HEIGHT as usize);
+    }
+    #[test]
+    fn max_transfer_size() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        log.busy_level.set(true);
+        let mut epd = block_on(Epd7in5::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        let frame = [0x0F; WIDTH as usize / 8 * HEIGHT as usize];
+
+        // the chunks are all sent as data
+        epd.set_max_transfer_size(Some(4096));
+        log.clear();
+        block_on(epd.update_frame(&mut spi, &frame)).unwrap();
+        let transfers = log.transfers();
+        assert_eq!(transfers.iter().max(), Some(&4096));
+        assert_eq!(transfers.last(), Some(&(frame.len() % 4096)));
+        assert_eq!(log.data_of(0x13, 0), frame);
+
+        epd.set_max_transfer_size(None);
+        log.clear();
+        block_on(epd.update_frame(&mut spi, &frame)).unwrap();
+        assert_eq!(log.transfers().iter().max(), Some(&frame.len()));
+    }
+
+    #[test]
+    fn frame_rate() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        log.busy_level.set(true);
+        let mut epd = block_on(Epd7in5::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert_eq!(log.data_of(0x30, 0), [0x06]);
+
+        log.clear();
+        block_on(epd.set_frame_rate(&mut spi, FrameRate::Hz150)).unwrap();
+        assert_eq!(log.data_of(0x30, 0), [0x0E]);
+
+        block_on(epd.sleep(&mut spi)).unwrap();
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x30, 0), [0x0E]);
+    }
+
+    #[test]
+    fn booster_soft_start() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        log.busy_level.set(true);
+        let mut epd = block_on(Epd7in5::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert_eq!(log.data_of(0x06, 0), [0x17, 0x17, 0x27, 0x17]);
+
+        let booster = BoosterSoftStart::from_bytes(0xC4, 0xC4, 0x0C, 0x0C);
+        log.clear();
+        block_on(epd.set_booster_soft_start(&mut spi, booster)).unwrap();
+        assert_eq!(log.data_of(0x06, 0), [0xC4, 0xC4, 0x0C, 0x0C]);
+
+        block_on(epd.sleep(&mut spi)).unwrap();
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x06, 0), [0xC4, 0xC4, 0x0C, 0x0C]);
+    }
+
+    #[test]
+    fn status_and_revision() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        log.busy_level.set(true);
+        let mut epd = block_on(Epd7in5::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        log.queue_reads(&[0x05]);
+        assert_eq!(block_on(epd.status(&mut spi)).unwrap(), 0x05);
+        assert_eq!(log.commands(), [0x71]);
+
+        log.clear();
+        log.queue_reads(&[0x0C, 0x01]);
+        let mut revision = [0u8; 2];
+        block_on(epd.revision(&mut spi, &mut revision)).unwrap();
+        assert_eq!(revision, [0x0C, 0x01]);
+        assert_eq!(log.commands(), [0x70]);
+
+        block_on(epd.sleep(&mut spi)).unwrap();
+        let asleep = block_on(epd.status(&mut spi));
+        assert!(matches!(asleep, Err(ErrorKind::Asleep)));
+    }
+
+    #[test]
+    fn tcon() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        log.busy_level.set(true);
+        let mut epd = block_on(Epd7in5::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert_eq!(log.data_of(0x60, 0), [0x22]);
+
+        log.clear();
+        block_on(epd.set_tcon(&mut spi, 0x05, 0x0A)).unwrap();
+        assert_eq!(log.data_of(0x60, 0), [0x5A]);
+
+        let too_long = block_on(epd.set_tcon(&mut spi, 0x02, 0x10));
+        assert!(matches!(too_long, Err(ErrorKind::InvalidSetting)));
+
+        // kept across the reset of the wake up, also by the fast refresh
+        block_on(epd.sleep(&mut spi)).unwrap();
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x60, 0), [0x5A]);
+
+        log.clear();
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Quick))).unwrap();
+        assert_eq!(log.data_of(0x60, 0), [0x5A]);
+    }
+
+    #[test]
+    fn fast_refresh() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        log.busy_level.set(true);
+        let mut epd = block_on(Epd7in5::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        assert!(!log.commands().contains(&0xE5));
+
+        log.clear();
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Quick))).unwrap();
+        assert_eq!(log.data_of(0x06, 0), [0x27, 0x27, 0x18, 0x17]);
+        assert_eq!(log.data_of(0xE0, 0), [0x02]);
+        assert_eq!(log.data_of(0xE5, 0), [0x5A]);
+
+        // the fast refresh is kept after the deep sleep
+        block_on(epd.sleep(&mut spi)).unwrap();
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0xE5, 0), [0x5A]);
+
+        log.clear();
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Full))).unwrap();
+        assert_eq!(log.data_of(0x06, 0), [0x17, 0x17, 0x27, 0x17]);
+        assert!(!log.commands().contains(&0xE5));
+    }
+    #[test]
+    fn partial_window() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        log.busy_level.set(true);
+        let mut epd = block_on(Epd7in5::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        // a 200x100 status region in the bottom right corner
+        log.clear();
+        let status = [0xFF; 200 / 8 * 100];
+        block_on(epd.update_partial_frame(&mut spi, &status, 600, 380, 200, 100)).unwrap();
+        assert_eq!(log.commands(), [0x91, 0x90, 0x13, 0x92]);
+        assert_eq!(
+            log.data_of(0x90, 0),
+            [0x02, 0x58, 0x03, 0x1F, 0x01, 0x7C, 0x01, 0xDF, 0x01]
+        );
+        assert_eq!(log.data_of(0x13, 0).len(), status.len());
+
+        // an unaligned window is padded to whole bytes
+        log.clear();
+        let buffer = [0xFF; 4 * 10];
+        block_on(epd.update_partial_frame(&mut spi, &buffer, 13, 0, 20, 10)).unwrap();
+        assert_eq!(log.data_of(0x90, 0)[..4], [0x00, 0x08, 0x00, 0x27]);
+
+        let result = block_on(epd.update_partial_frame(&mut spi, &buffer[..30], 13, 0, 20, 10));
+        assert!(matches!(result, Err(ErrorKind::InvalidWindow)));
+        let result = block_on(epd.update_partial_frame(&mut spi, &status, 608, 380, 200, 100));
+        assert!(matches!(result, Err(ErrorKind::InvalidWindow)));
+    }
+    #[test]
+    fn partial_window_with_old() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        log.busy_level.set(true);
+        let mut epd = block_on(Epd7in5::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        let old = [0x00; 4 * 10];
+        let new = [0xFF; 4 * 10];
+        block_on(epd.update_partial_frame_with_old(&mut spi, &old, &new, 13, 0, 20, 10)).unwrap();
+        assert_eq!(log.commands(), [0x91, 0x90, 0x10, 0x13, 0x92]);
+        assert_eq!(log.data_of(0x90, 0)[..4], [0x00, 0x08, 0x00, 0x27]);
+        assert_eq!(log.data_of(0x10, 0), old);
+        assert_eq!(log.data_of(0x13, 0), new);
+
+        let result =
+            block_on(epd.update_partial_frame_with_old(&mut spi, &old[..30], &new, 13, 0, 20, 10));
+        assert!(matches!(result, Err(ErrorKind::InvalidWindow)));
+    }
+
+    #[test]
+    fn gray4_planes() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        log.busy_level.set(true);
+        let mut epd = block_on(Epd7in5::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        // black, dark gray, light gray and white at the start of the first row
+        let mut buffer = [0xFF; GRAY4_BUFFER_LEN];
+        buffer[0] = 0b00_01_10_11;
+
+        log.clear();
+        block_on(epd.update_and_display_gray4(&mut spi, &buffer)).unwrap();
+        assert_eq!(log.data_of(0xE5, 0), [GRAY4_TEMPERATURE]);
+
+        // inverted low bits
+        let old = log.data_of(0x10, 0);
+        assert_eq!(old.len(), ROW_BYTES * HEIGHT as usize);
+        assert_eq!(old[0], 0b1010_0000);
+        assert!(old[1..].iter().all(|&byte| byte == 0x00));
+
+        // inverted high bits
+        let new = log.data_of(0x13, 0);
+        assert_eq!(new.len(), ROW_BYTES * HEIGHT as usize);
+        assert_eq!(new[0], 0b1100_0000);
+        assert!(new[1..].iter().all(|&byte| byte == 0x00));
+        assert!(log.commands().ends_with(&[0x12]));
+
+        // the next black/white frame initializes the controller again
+        log.clear();
+        let frame = [0x00; ROW_BYTES * HEIGHT as usize];
+        block_on(epd.update_and_display_frame(&mut spi, &frame)).unwrap();
+        assert_eq!(log.data_of(0x06, 0), [0x17, 0x17, 0x27, 0x17]);
+        assert!(!log.commands().contains(&0xE5));
+    }
+    #[test]
+    fn dual_spi_frame() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        log.busy_level.set(true);
+        let mut epd = block_on(Epd7in5::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        let mut bus = DualBus::default();
+
+        let mut frame = [0x00; WIDTH as usize / 8 * HEIGHT as usize];
+        frame[0] = 0xFF;
+        frame[3] = 0x81;
+        log.clear();
+        block_on(epd.update_frame_dual(&mut spi, &mut bus, &frame)).unwrap();
+        assert_eq!(log.commands(), [0x15, 0x13, 0x15]);
+        assert_eq!(log.data_of(0x15, 0), [0x10]);
+        assert!(log.data_of(0x13, 0).is_empty());
+        assert_eq!(log.data_of(0x15, 1), [0x00]);
+
+        // two bytes of the frame share a pair of bytes on the bus
+        assert_eq!(bus.0.len(), frame.len());
+        assert_eq!(bus.0[..4], [0x55, 0x55, 0x80, 0x02]);
+
+        // a wrong size doesn't enable the mode
+        log.clear();
+        let result = block_on(epd.update_frame_dual(&mut spi, &mut bus, &frame[1..]));
+        assert!(matches!(result, Err(ErrorKind::InvalidWindow)));
+        assert!(log.commands().is_empty());
+    }
+
+    #[test]
+    fn frame_from_iter() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        log.busy_level.set(true);
+        let mut epd = block_on(Epd7in5::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        let mut frame = [0; ROW_BYTES * HEIGHT as usize];
+        for (i, byte) in frame.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+
+        log.clear();
+        block_on(epd.update_frame(&mut spi, &frame)).unwrap();
+        let expected = log.writes();
+
+        // rendered row by row, the extra bytes are ignored
+        log.clear();
+        let rows = (0..HEIGHT as usize)
+            .flat_map(|y| (0..ROW_BYTES).map(move |x| (y * ROW_BYTES + x) as u8));
+        block_on(epd.update_frame_iter(&mut spi, rows.chain([0xAA]))).unwrap();
+        assert_eq!(log.writes(), expected);
+        let blocks = log.transfers().iter().filter(|&&len| len == 256).count();
+        assert_eq!(blocks, frame.len() / 256);
+
+        let short = frame.iter().copied().take(100);
+        assert!(matches!(
+            block_on(epd.update_frame_iter(&mut spi, short)),
+            Err(ErrorKind::InvalidWindow)
+        ));
+    }
+
+    #[test]
+    fn forced_temperature() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        log.busy_level.set(true);
+        let mut epd = block_on(Epd7in5::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        block_on(epd.set_temperature(&mut spi, -5)).unwrap();
+        assert_eq!(log.commands(), [0xE0, 0xE5]);
+        assert_eq!(log.data_of(0xE0, 0), [0x02]);
+        assert_eq!(log.data_of(0xE5, 0), [0xFB]);
+
+        // sent again after the reset of the wake up
+        block_on(epd.sleep(&mut spi)).unwrap();
+        log.clear();
+        block_on(epd.wake_up(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0xE5, 0), [0xFB]);
+
+        // the fast refresh keeps its own temperature
+        log.clear();
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Quick))).unwrap();
+        assert_eq!(log.data_of(0xE5, 0), [0x5A]);
+        log.clear();
+        block_on(epd.set_temperature(&mut spi, 10)).unwrap();
+        assert!(log.commands().is_empty());
+
+        log.clear();
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Full))).unwrap();
+        assert_eq!(log.data_of(0xE5, 0), [10]);
     }
 }
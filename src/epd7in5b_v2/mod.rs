@@ -1,8 +1,18 @@
 //! A simple Driver for the Waveshare 7.5" (B) E-Ink Display (V2) via SPI
 //!
+//! The black plane is sent with `DataStartTransmission1`, the red one with
+//! `DataStartTransmission2`. The vendor drivers invert their red image before sending it,
+//! as a cleared bit marks a red pixel in their buffers. The red RAM expects a set bit
+//! for red, which is what the chromatic plane of [Display7in5b] already holds, so it is
+//! sent unchanged.
+//!
+//! A full refresh takes about 16 seconds. Every refresh waits for the busy line
+//! before returning.
+//!
 //! # References
 //!
 //! - [Datasheet](https://www.waveshare.com/wiki/7.5inch_e-Paper_HAT)
+//! - [Waveshare C driver (B) V2](https://github.com/waveshareteam/e-Paper/blob/master/RaspberryPi_JetsonNano/c/lib/e-Paper/EPD_7in5b_V2.c)
 //! - [Waveshare C driver](https://github.com/waveshare/e-Paper/blob/702def0/RaspberryPi%26JetsonNano/c/lib/e-Paper/EPD_7in5_V2.c)
 //! - [Waveshare Python driver](https://github.com/waveshare/e-Paper/blob/702def0/RaspberryPi%26JetsonNano/python/lib/waveshare_epd/epd7in5_V2.py)
 //!
@@ -16,7 +26,7 @@ use embedded_hal_async::{digital::Wait, spi::SpiDevice};
 
 use crate::color::TriColor;
 use crate::error::ErrorKind;
-use crate::interface::DisplayInterface;
+use crate::interface::{BusyLevel, DisplayDelay, DisplayInterface, ResetTiming};
 use crate::traits::{
     ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
 };
@@ -26,8 +36,10 @@ use self::command::Command;
 use crate::buffer_len;
 
 /// Full size buffer for use with the 7in5b v2 EPD
+///
+/// The black plane is followed by the chromatic plane.
 #[cfg(feature = "graphics")]
-pub type Display7in5 = crate::graphics::Display<
+pub type Display7in5b = crate::graphics::Display<
     WIDTH,
     HEIGHT,
     false,
@@ -35,6 +47,10 @@ pub type Display7in5 = crate::graphics::Display<
     TriColor,
 >;
 
+/// Former name of [Display7in5b]
+#[cfg(feature = "graphics")]
+pub type Display7in5 = Display7in5b;
+
 /// Width of the display
 pub const WIDTH: u32 = 800;
 /// Height of the display
@@ -44,19 +60,20 @@ pub const DEFAULT_BACKGROUND_COLOR: TriColor = TriColor::White;
 
 /// Number of bytes for b/w buffer and same for chromatic buffer bits
 const NUM_DISPLAY_BITS: usize = WIDTH as usize / 8 * HEIGHT as usize;
-const IS_BUSY_LOW: bool = true;
+const BUSY_LEVEL: BusyLevel = BusyLevel::Low;
+const RESET_TIMING: ResetTiming = ResetTiming::new(200_000, 2_000);
 const SINGLE_BYTE_WRITE: bool = false;
 
 /// Epd7in5 (V2) driver
 ///
-pub struct Epd7in5<SPI, BUSY, DC, RST> {
+pub struct Epd7in5<SPI, BUSY, DC, RST, DELAY> {
     /// Connection Interface
-    interface: DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>,
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
     /// Background Color
     color: TriColor,
 }
 
-impl<SPI, BUSY, DC, RST> ErrorType<SPI, BUSY, DC, RST> for Epd7in5<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> ErrorType<SPI, BUSY, DC, RST> for Epd7in5<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -66,11 +83,13 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type Error = ErrorKind<SPI, BUSY, DC, RST>;
 }
 
-impl<SPI, BUSY, DC, RST> InternalWiAdditions<SPI, BUSY, DC, RST> for Epd7in5<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Epd7in5<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -80,11 +99,12 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         // Reset the device
         // C driver does 200/2 original rust driver does 10/2
-        self.interface.reset(spi, 200_000, 2_000).await?;
+        self.interface.reset().await?;
 
         // V2 procedure as described here:
         // https://github.com/waveshare/e-Paper/blob/master/RaspberryPi%26JetsonNano/python/lib/waveshare_epd/epd7in5bc_V2.py
@@ -126,8 +146,8 @@ where
     }
 }
 
-impl<SPI, BUSY, DC, RST> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST>
-    for Epd7in5<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd7in5<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -137,6 +157,7 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     async fn update_color_frame(
         &mut self,
@@ -156,6 +177,8 @@ where
         spi: &mut SPI,
         black: &[u8],
     ) -> Result<(), Self::Error> {
+        assert!(black.len() == NUM_DISPLAY_BITS);
+        self.wait_until_idle(spi).await?;
         self.interface
             .cmd(spi, Command::DataStartTransmission1)
             .await?;
@@ -171,6 +194,7 @@ where
         spi: &mut SPI,
         chromatic: &[u8],
     ) -> Result<(), Self::Error> {
+        assert!(chromatic.len() == NUM_DISPLAY_BITS);
         self.interface
             .cmd(spi, Command::DataStartTransmission2)
             .await?;
@@ -179,9 +203,30 @@ where
 
         self.wait_until_idle(spi).await
     }
+
+    async fn clear_achromatic_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+
+        // The planes are filled byte by byte, no frame sized buffer is needed
+        self.command(spi, Command::DataStartTransmission1).await?;
+        self.interface
+            .data_x_times(spi, 0xFF, NUM_DISPLAY_BITS as u32)
+            .await
+    }
+
+    async fn clear_chromatic_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+
+        // A cleared bit means no red
+        self.command(spi, Command::DataStartTransmission2).await?;
+        self.interface
+            .data_x_times(spi, 0x00, NUM_DISPLAY_BITS as u32)
+            .await
+    }
 }
 
-impl<SPI, BUSY, DC, RST> WaveshareDisplay<SPI, BUSY, DC, RST> for Epd7in5<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
+    for Epd7in5<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -191,16 +236,19 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     type DisplayColor = TriColor;
     async fn new(
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        delay: DELAY,
         delay_us: Option<u32>,
     ) -> Result<Self, Self::Error> {
-        let interface = DisplayInterface::new(busy, dc, rst, delay_us);
+        let interface =
+            DisplayInterface::new(Some(busy), dc, rst, delay, RESET_TIMING, delay_us, None);
         let color = DEFAULT_BACKGROUND_COLOR;
 
         let mut epd = Epd7in5 { interface, color };
@@ -211,6 +259,7 @@ where
     }
 
     async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.require_reset_pin()?;
         self.init(spi).await
     }
 
@@ -218,26 +267,17 @@ where
         self.wait_until_idle(spi).await?;
         self.command(spi, Command::PowerOff).await?;
         self.wait_until_idle(spi).await?;
-        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5]).await
+        self.cmd_with_data(spi, Command::DeepSleep, &[0xA5]).await?;
+        self.interface.mark_asleep();
+        Ok(())
     }
 
+    /// Transmits the black and the chromatic plane of a [Display7in5b] buffer
     async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
-        self.wait_until_idle(spi).await?;
+        assert!(buffer.len() == 2 * NUM_DISPLAY_BITS);
         // (B) version sends one buffer for black and one for red
-        self.cmd_with_data(
-            spi,
-            Command::DataStartTransmission1,
-            &buffer[..NUM_DISPLAY_BITS],
-        )
-        .await?;
-        self.cmd_with_data(
-            spi,
-            Command::DataStartTransmission2,
-            &buffer[NUM_DISPLAY_BITS..],
-        )
-        .await?;
-        self.interface.cmd(spi, Command::DataStop).await?;
-        Ok(())
+        let (black, chromatic) = buffer.split_at(NUM_DISPLAY_BITS);
+        self.update_color_frame(spi, black, chromatic).await
     }
 
     async fn update_partial_frame(
@@ -254,7 +294,8 @@ where
 
     async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
-        self.command(spi, Command::DisplayRefresh).await
+        self.command(spi, Command::DisplayRefresh).await?;
+        self.wait_until_idle(spi).await
     }
 
     async fn update_and_display_frame(
@@ -263,28 +304,18 @@ where
         buffer: &[u8],
     ) -> Result<(), Self::Error> {
         self.update_frame(spi, buffer).await?;
-        self.command(spi, Command::DisplayRefresh).await
+        self.display_frame(spi).await
     }
 
     async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.wait_until_idle(spi).await?;
         self.send_resolution(spi).await?;
-
-        self.command(spi, Command::DataStartTransmission1).await?;
-        self.interface
-            .data_x_times(spi, 0xFF, WIDTH / 8 * HEIGHT)
-            .await?;
-
-        self.command(spi, Command::DataStartTransmission2).await?;
-        self.interface
-            .data_x_times(spi, 0x00, WIDTH / 8 * HEIGHT)
-            .await?;
-
+        self.clear_achromatic_frame(spi).await?;
+        self.clear_chromatic_frame(spi).await?;
         self.interface.cmd(spi, Command::DataStop).await?;
 
         self.command(spi, Command::DisplayRefresh).await?;
-
-        Ok(())
+        self.wait_until_idle(spi).await
     }
 
     fn set_background_color(&mut self, color: Self::DisplayColor) {
@@ -303,6 +334,37 @@ where
         HEIGHT
     }
 
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
+
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
     async fn set_lut(
         &mut self,
         _spi: &mut SPI,
@@ -314,12 +376,12 @@ where
     /// wait
     async fn wait_until_idle(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
         self.interface
-            .wait_until_idle_with_cmd(spi, IS_BUSY_LOW, Command::GetStatus)
+            .wait_until_idle_with_cmd(spi, BUSY_LEVEL, Command::GetStatus)
             .await
     }
 }
 
-impl<SPI, BUSY, DC, RST> Epd7in5<SPI, BUSY, DC, RST>
+impl<SPI, BUSY, DC, RST, DELAY> Epd7in5<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -329,7 +391,21 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
+    /// Transmits both planes and refreshes the display
+    ///
+    /// Returns once the refresh is done, which takes about 16 seconds.
+    pub async fn update_and_display_color_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+        chromatic: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.update_color_frame(spi, black, chromatic).await?;
+        self.display_frame(spi).await
+    }
+
     /// temporary replacement for missing delay in the trait to call wait_until_idle
     #[allow(clippy::too_many_arguments)]
     pub async fn update_partial_frame2(
@@ -419,11 +495,63 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock::{block_on, mocks};
 
     #[test]
     fn epd_size() {
         assert_eq!(WIDTH, 800);
         assert_eq!(HEIGHT, 480);
         assert_eq!(DEFAULT_BACKGROUND_COLOR, TriColor::White);
+        assert_eq!(NUM_DISPLAY_BITS, 48_000);
+    }
+
+    #[test]
+    fn planes_are_sent_unchanged() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        // the status is polled until the busy line is high
+        log.busy_level.set(true);
+        let mut epd = block_on(Epd7in5::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        let black = [0xAA; NUM_DISPLAY_BITS];
+        let chromatic = [0x0F; NUM_DISPLAY_BITS];
+        block_on(epd.update_and_display_color_frame(&mut spi, &black, &chromatic)).unwrap();
+        assert_eq!(log.commands(), [0x10, 0x11, 0x13, 0x11, 0x12]);
+        assert_eq!(log.data_of(0x10, 0), black);
+        assert_eq!(log.data_of(0x13, 0), chromatic);
+    }
+
+    #[test]
+    fn update_frame_splits_the_buffer() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        log.busy_level.set(true);
+        let mut epd = block_on(Epd7in5::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        let mut buffer = [0xFF; 2 * NUM_DISPLAY_BITS];
+        buffer[NUM_DISPLAY_BITS..].fill(0x00);
+        buffer[NUM_DISPLAY_BITS] = 0x80;
+        block_on(epd.update_and_display_frame(&mut spi, &buffer)).unwrap();
+        assert_eq!(log.commands(), [0x10, 0x11, 0x13, 0x11, 0x12]);
+        assert_eq!(log.data_of(0x10, 0), buffer[..NUM_DISPLAY_BITS]);
+        assert_eq!(log.data_of(0x13, 0), buffer[NUM_DISPLAY_BITS..]);
+    }
+
+    #[test]
+    fn clear_planes() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        log.busy_level.set(true);
+        let mut epd = block_on(Epd7in5::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        block_on(epd.clear_frame(&mut spi)).unwrap();
+        assert_eq!(log.commands(), [0x61, 0x10, 0x13, 0x11, 0x12]);
+        let black = log.data_of(0x10, 0);
+        let red = log.data_of(0x13, 0);
+        assert_eq!(black.len(), NUM_DISPLAY_BITS);
+        assert!(black.iter().all(|&byte| byte == 0xFF));
+        // 0x00 is "no red" in the red RAM
+        assert_eq!(red.len(), NUM_DISPLAY_BITS);
+        assert!(red.iter().all(|&byte| byte == 0x00));
     }
 }
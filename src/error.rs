@@ -24,12 +24,34 @@ where
     /// Encountered an error on Busy GPIO
     BusyError(BUSY::Error),
 
+    /// The busy pin didn't turn idle within the timeout, e.g. after losing the panel's
+    /// supply or a broken wire. A hardware reset may recover the controller.
+    BusyTimeout,
+
     /// Encountered an error on DC GPIO
     DcError(DC::Error),
 
     /// Encountered an error on RST GPIO
     RstError(RST::Error),
 
+    /// The display doesn't support the requested operation
+    Unsupported,
+
+    /// The partial window doesn't lie inside the display, or the buffer doesn't match its size
+    InvalidWindow,
+
+    /// A quick refresh needs a base frame, but none was written since the last init
+    NoBaseFrame,
+
+    /// The display is in deep sleep, it has to be woken up first
+    Asleep,
+
+    /// A setting doesn't fit into the register of the controller
+    InvalidSetting,
+
+    /// The controller only leaves deep sleep by a hardware reset, but there's no reset pin
+    ResetRequired,
+
     /// Anything else
     Other,
 }
@@ -78,8 +100,15 @@ where
         match self {
             Self::SpiError(err) => Display::fmt(&err, f),
             Self::BusyError(err) => Display::fmt(&err, f),
+            Self::BusyTimeout => write!(f, "The display stayed busy longer than the timeout"),
             Self::DcError(err) => Display::fmt(&err, f),
             Self::RstError(err) => Display::fmt(&err, f),
+            Self::Unsupported => write!(f, "The display doesn't support this operation"),
+            Self::InvalidWindow => write!(f, "The window doesn't fit the display or the buffer"),
+            Self::NoBaseFrame => write!(f, "No base frame was written for the quick refresh"),
+            Self::Asleep => write!(f, "The display is in deep sleep, wake it up first"),
+            Self::InvalidSetting => write!(f, "The setting doesn't fit the controller register"),
+            Self::ResetRequired => write!(f, "Leaving deep sleep needs a reset pin"),
             Self::Other => write!(
                 f,
                 "A different error occurred. The original error may contain more information"
@@ -103,8 +132,15 @@ where
         match self {
             Self::SpiError(err) => Debug::fmt(&err, f),
             Self::BusyError(err) => Debug::fmt(&err, f),
+            Self::BusyTimeout => write!(f, "BusyTimeout"),
             Self::DcError(err) => Debug::fmt(&err, f),
             Self::RstError(err) => Debug::fmt(&err, f),
+            Self::Unsupported => write!(f, "Unsupported"),
+            Self::InvalidWindow => write!(f, "InvalidWindow"),
+            Self::NoBaseFrame => write!(f, "NoBaseFrame"),
+            Self::Asleep => write!(f, "Asleep"),
+            Self::InvalidSetting => write!(f, "InvalidSetting"),
+            Self::ResetRequired => write!(f, "ResetRequired"),
             Self::Other => write!(
                 f,
                 "A different error occurred. The original error may contain more information"
@@ -37,12 +37,9 @@ const fn line_bytes(width: u32, bits_per_pixel: usize) -> usize {
 ///
 /// More on BWRBIT:
 ///
-/// Different chromatic displays differently treat the bits in chromatic color planes.
-/// Some of them ([crate::epd2in13bc]) will render a color pixel if bit is set for that pixel,
-/// which is a [DisplayColorRendering::Positive] mode.
-///
-/// Other displays, like [crate::epd5in83b_v2] in opposite, will draw color pixel if bit is
-/// cleared for that pixel, which is a [DisplayColorRendering::Negative] mode.
+/// A set bit of the chromatic plane is always a chromatic pixel, the drivers of
+/// controllers that draw cleared bits in color (like [crate::epd2in13bc]) invert the
+/// plane in [crate::traits::WaveshareThreeColorDisplay::update_chromatic_frame].
 ///
 /// BWRBIT=true: chromatic doesn't override white, white bit cleared for black, white bit set for white, both bits set for chromatic
 /// BWRBIT=false: chromatic does override white, both bits cleared for black, white bit set for white, red bit set for black
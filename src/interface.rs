@@ -1,31 +1,501 @@
-use crate::{error::ErrorKind, traits::Command};
+use crate::{
+    error::ErrorKind,
+    interleave_dual,
+    trace::epd_trace,
+    traits::{Command, DualSpiBus},
+};
+use core::cell::RefCell;
+use core::convert::Infallible;
 use core::fmt::{Debug, Display};
 use core::marker::PhantomData;
 use embedded_hal::{
-    digital::{InputPin, OutputPin},
+    digital::{self, InputPin, OutputPin, PinState},
     spi::Operation,
 };
-use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+use embedded_hal_async::{delay::DelayNs, digital::Wait, spi::SpiDevice};
+
+/// A fixed set of BUSY pins, e.g. of the controllers of a cascaded panel
+///
+/// [BusyGroup::wait_until_idle] returns once every pin is idle. It can also join two
+/// independent panels, which refresh at the same time after both were started.
+pub struct BusyGroup<BUSY, const N: usize> {
+    /// The BUSY pins
+    pins: [BUSY; N],
+    /// Level of the pins while busy
+    level: BusyLevel,
+}
+
+impl<BUSY, const N: usize> BusyGroup<BUSY, N>
+where
+    BUSY: InputPin + Wait,
+{
+    /// Creates a group of `pins` which are at `level` while busy, like the single pin of
+    /// a driver
+    pub fn new(pins: [BUSY; N], level: BusyLevel) -> Self {
+        BusyGroup { pins, level }
+    }
+
+    /// Waits until none of the pins is busy anymore
+    pub async fn wait_until_idle(&mut self) -> Result<(), BUSY::Error> {
+        for pin in self.pins.iter_mut() {
+            match self.level {
+                BusyLevel::Low => pin.wait_for_high().await?,
+                BusyLevel::High => pin.wait_for_low().await?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks if any of the pins is busy
+    pub fn is_busy(&mut self) -> Result<bool, BUSY::Error> {
+        for pin in self.pins.iter_mut() {
+            let busy = match self.level {
+                BusyLevel::Low => pin.is_low()?,
+                BusyLevel::High => pin.is_high()?,
+            };
+            if busy {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Returns the pins
+    pub fn release(self) -> [BUSY; N] {
+        self.pins
+    }
+}
+
+/// Stands in for the BUSY pin of a panel whose BUSY isn't connected to a GPIO
+///
+/// Only drivers that know how long their operations take accept `None::<NoBusyPin>` as
+/// `busy`, e.g. [Epd5in83::new_uninitialized](crate::epd5in83_v2::Epd5in83::new_uninitialized).
+/// They wait the worst case of every operation instead of polling the pin.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoBusyPin;
+
+impl digital::ErrorType for NoBusyPin {
+    type Error = Infallible;
+}
+
+impl InputPin for NoBusyPin {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+}
+
+impl Wait for NoBusyPin {
+    async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// What a [PollingBusyPin] or a [BusyWaitDelay] does between two polls of BUSY
+///
+/// Every [DelayNs] sleeps for the poll interval. Implement it e.g. to yield to the
+/// scheduler of an RTOS instead.
+pub trait BusyWaitStrategy {
+    /// Pauses before the pin is read again, `interval_us` is the poll interval
+    async fn pause(&mut self, interval_us: u32);
+}
+
+impl<D: DelayNs> BusyWaitStrategy for D {
+    async fn pause(&mut self, interval_us: u32) {
+        self.delay_us(interval_us).await;
+    }
+}
+
+/// The waits of a driver, `delay` of its `new`
+///
+/// Every [DelayNs] is one, it sleeps for the reset timing and the waits without a BUSY
+/// pin as well as between the polls of BUSY with a busy timeout or a status command.
+/// A [BusyWaitDelay] pauses with a [BusyWaitStrategy] between the polls instead.
+pub trait DisplayDelay {
+    /// Sleeps for `us` µs
+    async fn sleep_us(&mut self, us: u32);
+
+    /// Pauses before BUSY is polled again, `interval_us` is the poll interval
+    async fn pause(&mut self, interval_us: u32);
+
+    /// Returns the time in µs to measure the waits for BUSY with, `None` without a clock
+    ///
+    /// A [TimedDelay] adds a [MonotonicClock] to a delay.
+    fn now_us(&mut self) -> Option<u32> {
+        None
+    }
+}
+
+impl<D: DelayNs> DisplayDelay for D {
+    async fn sleep_us(&mut self, us: u32) {
+        self.delay_us(us).await;
+    }
+
+    async fn pause(&mut self, interval_us: u32) {
+        self.delay_us(interval_us).await;
+    }
+}
+
+/// Sleeps with a [DelayNs], but pauses with a [BusyWaitStrategy] between the polls of BUSY
+///
+/// Pass it as `delay` to the `new` of a driver, e.g. to yield to the scheduler of an
+/// RTOS while a refresh is polled with a busy timeout or a status command.
+pub struct BusyWaitDelay<D, W> {
+    delay: D,
+    wait: W,
+}
+
+impl<D, W> BusyWaitDelay<D, W>
+where
+    D: DelayNs,
+    W: BusyWaitStrategy,
+{
+    /// Sleeps with `delay` and pauses with `wait`
+    pub fn new(delay: D, wait: W) -> Self {
+        BusyWaitDelay { delay, wait }
+    }
+
+    /// Returns the delay and the wait strategy
+    pub fn release(self) -> (D, W) {
+        (self.delay, self.wait)
+    }
+}
+
+impl<D, W> DisplayDelay for BusyWaitDelay<D, W>
+where
+    D: DelayNs,
+    W: BusyWaitStrategy,
+{
+    async fn sleep_us(&mut self, us: u32) {
+        self.delay.delay_us(us).await;
+    }
+
+    async fn pause(&mut self, interval_us: u32) {
+        self.wait.pause(interval_us).await;
+    }
+}
+
+/// Monotonic µs counter, e.g. a free running timer, see [TimedDelay]
+///
+/// The counter may wrap around, the durations are taken with a wrapping subtraction and
+/// are only right for waits shorter than the wrap-around.
+pub trait MonotonicClock {
+    /// Returns the current count in µs
+    fn now_us(&mut self) -> u32;
+}
+
+/// Waits with a [DisplayDelay] and measures the waits for BUSY with a [MonotonicClock]
+///
+/// Pass it as `delay` to the `new` of a driver, then
+/// [WaveshareDisplay::last_busy_duration](crate::traits::WaveshareDisplay::last_busy_duration)
+/// returns how long e.g. the last refresh took.
+pub struct TimedDelay<D, C> {
+    delay: D,
+    clock: C,
+}
+
+impl<D, C> TimedDelay<D, C>
+where
+    D: DisplayDelay,
+    C: MonotonicClock,
+{
+    /// Waits with `delay` and measures with `clock`
+    pub fn new(delay: D, clock: C) -> Self {
+        TimedDelay { delay, clock }
+    }
+
+    /// Returns the delay and the clock
+    pub fn release(self) -> (D, C) {
+        (self.delay, self.clock)
+    }
+}
+
+impl<D, C> DisplayDelay for TimedDelay<D, C>
+where
+    D: DisplayDelay,
+    C: MonotonicClock,
+{
+    async fn sleep_us(&mut self, us: u32) {
+        self.delay.sleep_us(us).await;
+    }
+
+    async fn pause(&mut self, interval_us: u32) {
+        self.delay.pause(interval_us).await;
+    }
+
+    fn now_us(&mut self) -> Option<u32> {
+        Some(self.clock.now_us())
+    }
+}
+
+/// Busy pin for HALs without [Wait], polls an [InputPin] instead of waiting for its edge
+///
+/// The pin is read every `poll_interval_us`, pausing with `wait` in between, which is
+/// usually a [DelayNs]. Every driver accepts it as `busy`.
+pub struct PollingBusyPin<PIN, WAIT> {
+    pin: PIN,
+    wait: WAIT,
+    poll_interval_us: u32,
+}
+
+impl<PIN, WAIT> PollingBusyPin<PIN, WAIT>
+where
+    PIN: InputPin,
+    WAIT: BusyWaitStrategy,
+{
+    /// Polls `pin` every `poll_interval_us`
+    pub fn new(pin: PIN, wait: WAIT, poll_interval_us: u32) -> Self {
+        PollingBusyPin {
+            pin,
+            wait,
+            poll_interval_us,
+        }
+    }
+
+    /// Returns the pin and the wait strategy
+    pub fn release(self) -> (PIN, WAIT) {
+        (self.pin, self.wait)
+    }
+
+    /// Pauses until the pin is at `high`
+    async fn poll_for(&mut self, high: bool) -> Result<(), PIN::Error> {
+        while self.pin.is_high()? != high {
+            self.wait.pause(self.poll_interval_us).await;
+        }
+        Ok(())
+    }
+}
+
+impl<PIN: InputPin, WAIT> digital::ErrorType for PollingBusyPin<PIN, WAIT> {
+    type Error = PIN::Error;
+}
+
+impl<PIN: InputPin, WAIT> InputPin for PollingBusyPin<PIN, WAIT> {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        self.pin.is_high()
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        self.pin.is_low()
+    }
+}
+
+impl<PIN, WAIT> Wait for PollingBusyPin<PIN, WAIT>
+where
+    PIN: InputPin,
+    WAIT: BusyWaitStrategy,
+{
+    async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+        self.poll_for(true).await
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+        self.poll_for(false).await
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+        self.poll_for(false).await?;
+        self.poll_for(true).await
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+        self.poll_for(true).await?;
+        self.poll_for(false).await
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+        let high = self.pin.is_high()?;
+        self.poll_for(!high).await
+    }
+}
+
+/// Level of the BUSY pin while the controller is busy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BusyLevel {
+    /// BUSY is low while busy, e.g. the UC81xx controllers
+    Low,
+    /// BUSY is high while busy, e.g. the SSD16xx controllers
+    High,
+}
+
+impl BusyLevel {
+    /// Returns the opposite level
+    pub const fn inverted(self) -> Self {
+        match self {
+            BusyLevel::Low => BusyLevel::High,
+            BusyLevel::High => BusyLevel::Low,
+        }
+    }
+}
+
+/// Timing of the hardware reset
+///
+/// RST is held high for `deassert_us`, pulled low for `assert_us` and released again,
+/// the controller gets `post_us` to start up afterwards. Every driver sets the timing
+/// of its panel, [WaveshareDisplay::set_reset_timing](crate::traits::WaveshareDisplay::set_reset_timing)
+/// overrides it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResetTiming {
+    /// RST high before the reset pulse
+    pub deassert_us: u32,
+    /// RST low
+    pub assert_us: u32,
+    /// Wait after releasing RST, also without a reset pin
+    pub post_us: u32,
+}
+
+impl ResetTiming {
+    /// Creates the timing of a reset pulse, followed by 200 ms of startup like in the
+    /// vendor drivers
+    pub const fn new(deassert_us: u32, assert_us: u32) -> Self {
+        ResetTiming {
+            deassert_us,
+            assert_us,
+            post_us: 200_000,
+        }
+    }
+}
+
+/// Longest time a controller stays busy with an operation, used without a BUSY pin
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct IdleDelays {
+    /// After a reset, on top of the wait of [DisplayInterface::reset]
+    pub(crate) reset_us: u32,
+    /// After turning the high voltages on
+    pub(crate) power_on_us: u32,
+    /// After turning the high voltages off
+    pub(crate) power_off_us: u32,
+    /// After starting a refresh
+    pub(crate) refresh_us: u32,
+}
+
+/// An operation the controller stays busy with, see [IdleDelays]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BusyWith {
+    /// Reset
+    Reset,
+    /// Power on
+    PowerOn,
+    /// Power off
+    PowerOff,
+    /// Refresh
+    Refresh,
+}
+
+/// Stands in for the reset pin of a panel whose RST isn't connected to a GPIO
+///
+/// Pass `None::<NoResetPin>` as `rst` to the `new` of a driver. Without a reset pin the
+/// controller relies on its power-on reset and the software reset of the SSD
+/// controllers, and [WaveshareDisplay::wake_up](crate::traits::WaveshareDisplay::wake_up)
+/// returns [ErrorKind::ResetRequired], as deep sleep is only left by a hardware reset.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoResetPin;
+
+impl digital::ErrorType for NoResetPin {
+    type Error = Infallible;
+}
+
+impl OutputPin for NoResetPin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Output pin shared by several displays, e.g. one DC line for two panels on one bus
+///
+/// Every call borrows the pin for just that call. The displays set DC before each
+/// SPI write, but [SpiDevice] can't set it once it holds the bus, so a display
+/// writing between the two would still be sent with the wrong level. Drive the
+/// displays one after the other, e.g. from one task, or put both behind one lock.
+///
+/// Sharing RST works the same way, but every init of one display resets the others
+/// as well and they have to be initialized again.
+#[derive(Debug, Clone, Copy)]
+pub struct SharedPin<'a, P> {
+    pin: &'a RefCell<P>,
+}
+
+impl<'a, P: OutputPin> SharedPin<'a, P> {
+    /// Shares `pin`, create one for every display
+    pub fn new(pin: &'a RefCell<P>) -> Self {
+        SharedPin { pin }
+    }
+}
+
+impl<P: OutputPin> digital::ErrorType for SharedPin<'_, P> {
+    type Error = P::Error;
+}
+
+impl<P: OutputPin> OutputPin for SharedPin<'_, P> {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.pin.borrow_mut().set_low()
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.pin.borrow_mut().set_high()
+    }
+}
 
 /// The Connection Interface of all (?) Waveshare EPD-Devices
 ///
 /// SINGLE_BYTE_WRITE defines if a data block is written bytewise
 /// or blockwise to the spi device
-pub(crate) struct DisplayInterface<SPI, BUSY, DC, RST, const SINGLE_BYTE_WRITE: bool> {
+pub(crate) struct DisplayInterface<SPI, BUSY, DC, RST, DELAY, const SINGLE_BYTE_WRITE: bool> {
     /// SPI
     _spi: PhantomData<SPI>,
-    /// Low for busy, Wait until display is ready!
-    busy: BUSY,
+    /// Low for busy, Wait until display is ready! `None` if BUSY isn't connected
+    busy: Option<BUSY>,
     /// Data/Command Control Pin (High for data, Low for command)
     dc: DC,
-    /// Pin for Resetting
-    rst: RST,
-    /// number of ms the idle loop should sleep on
+    /// Pin for Resetting, `None` if RST isn't connected
+    rst: Option<RST>,
+    /// Sleeps for the reset timing and the fixed delays, pauses between the polls of BUSY
+    delay: DELAY,
+    /// Timing of [DisplayInterface::reset]
+    reset_timing: ResetTiming,
+    /// number of µs the idle loop should sleep on
     delay_us: u32,
+    /// Longest write, longer data is split up, `None` for no limit
+    max_transfer_size: Option<usize>,
+    /// Longest wait for the busy pin, `None` waits forever
+    busy_timeout_us: Option<u32>,
+    /// Inverts the busy level of all waits, for panels with the opposite polarity
+    invert_busy: bool,
+    /// Waits without a busy pin
+    idle_delays: IdleDelays,
+    /// Wait for the operations marked since the last wait, only used without a busy pin
+    pending_us: u32,
+    /// The controller was sent to deep sleep and BUSY doesn't answer until a hardware reset
+    asleep: bool,
+    /// Duration of the last wait for BUSY in µs, `None` without a clock
+    last_busy_us: Option<u32>,
 }
 
-impl<SPI, BUSY, DC, RST, const SINGLE_BYTE_WRITE: bool>
-    DisplayInterface<SPI, BUSY, DC, RST, SINGLE_BYTE_WRITE>
+impl<SPI, BUSY, DC, RST, DELAY, const SINGLE_BYTE_WRITE: bool>
+    DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -35,11 +505,24 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     /// Creates a new `DisplayInterface` struct
     ///
-    /// If no delay is given, a default delay of 10ms is used.
-    pub fn new(busy: BUSY, dc: DC, rst: RST, delay_us: Option<u32>) -> Self {
+    /// `delay` sleeps or pauses for all waits which don't wait for the edge of BUSY. If no
+    /// `delay_us` is given, a default poll interval of 10ms is used. `reset_timing` is the
+    /// datasheet timing of the panel. `busy` and `rst` are `None` if the pin isn't
+    /// connected, see [NoBusyPin] and [NoResetPin]. Writes longer than
+    /// `max_transfer_size` are split up, `None` for no limit.
+    pub fn new(
+        busy: Option<BUSY>,
+        dc: DC,
+        rst: Option<RST>,
+        delay: DELAY,
+        reset_timing: ResetTiming,
+        delay_us: Option<u32>,
+        max_transfer_size: Option<usize>,
+    ) -> Self {
         // default delay of 10ms
         let delay_us = delay_us.unwrap_or(10_000);
         DisplayInterface {
@@ -47,10 +530,85 @@ where
             busy,
             dc,
             rst,
+            delay,
+            reset_timing,
             delay_us,
+            max_transfer_size,
+            busy_timeout_us: None,
+            invert_busy: false,
+            idle_delays: IdleDelays::default(),
+            pending_us: 0,
+            asleep: false,
+            last_busy_us: None,
         }
     }
 
+    /// Sets how long the operations take, the waits use them if there's no busy pin
+    pub(crate) fn set_idle_delays(&mut self, delays: IdleDelays) {
+        self.idle_delays = delays;
+    }
+
+    /// Notes that the controller started `operation`
+    ///
+    /// Without a busy pin the next wait lasts as long as the longest operation noted
+    /// since the last wait, but at least `delay_us`.
+    pub(crate) fn mark_busy(&mut self, operation: BusyWith) {
+        let duration = match operation {
+            BusyWith::Reset => self.idle_delays.reset_us,
+            BusyWith::PowerOn => self.idle_delays.power_on_us,
+            BusyWith::PowerOff => self.idle_delays.power_off_us,
+            BusyWith::Refresh => self.idle_delays.refresh_us,
+        };
+        self.pending_us = self.pending_us.max(duration);
+    }
+
+    /// Notes that the controller was sent to deep sleep
+    ///
+    /// BUSY doesn't answer in deep sleep, the waits return [ErrorKind::Asleep] instead of
+    /// hanging until the next hardware [reset](DisplayInterface::reset).
+    pub(crate) fn mark_asleep(&mut self) {
+        self.asleep = true;
+    }
+
+    /// Replaces the timing of the next resets
+    pub(crate) fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.reset_timing = timing;
+    }
+
+    /// Limits the waits for the busy pin to `timeout_us`, `None` waits forever
+    ///
+    /// With a timeout the pin is polled every `delay_us` of [DisplayInterface::new]
+    /// instead of waiting for its edge.
+    pub(crate) fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.busy_timeout_us = timeout_us;
+    }
+
+    /// Waits for the opposite busy level of every wait, for panels whose BUSY is inverted
+    pub(crate) fn set_busy_inverted(&mut self, inverted: bool) {
+        self.invert_busy = inverted;
+    }
+
+    /// The level of BUSY while busy, with [set_busy_inverted](DisplayInterface::set_busy_inverted)
+    /// applied
+    fn busy_level(&self, level: BusyLevel) -> BusyLevel {
+        if self.invert_busy {
+            level.inverted()
+        } else {
+            level
+        }
+    }
+
+    /// Splits the data into writes of at most `size` bytes, `None` sends it in one write
+    ///
+    /// Linux' spidev rejects writes longer than 4096 bytes by default. Every chunk is a
+    /// [SpiDevice::write] of its own, so chip select may toggle between them while DC
+    /// stays high. The controllers tell commands and data apart by DC only, the
+    /// `SINGLE_BYTE_WRITE` ones toggle chip select after every byte anyway. A single
+    /// transaction wouldn't help with spidev, its limit applies to the whole message.
+    pub(crate) fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.max_transfer_size = size;
+    }
+
     /// Basic function for sending [Commands](Command).
     ///
     /// Enables direct interaction with the device with the help of [data()](DisplayInterface::data())
@@ -59,11 +617,9 @@ where
         spi: &mut SPI,
         command: T,
     ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
-        // low for commands
-        let _ = self.dc.set_low().map_err(ErrorKind::DcError)?;
-
-        // Transfer the command over spi
-        self.write(spi, &[command.address()]).await
+        epd_trace!("EPD cmd {:#x}", command.address());
+        // Transfer the command over spi, low for commands
+        self.write(spi, PinState::Low, &[command.address()]).await
     }
 
     /// Basic function for sending an array of u8-values of data over spi
@@ -74,24 +630,93 @@ where
         spi: &mut SPI,
         data: &[u8],
     ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
-        // high for data
-        let _ = self.dc.set_high().map_err(ErrorKind::DcError)?;
+        epd_trace!("EPD data len={}", data.len());
 
+        // high for data
         if SINGLE_BYTE_WRITE {
             for val in data.iter().copied() {
                 // Transfer data one u8 at a time over spi
-                self.write(spi, &[val]).await?;
+                self.write(spi, PinState::High, &[val]).await?;
             }
         } else {
-            self.write(spi, data).await?;
+            self.write(spi, PinState::High, data).await?;
         }
 
         Ok(())
     }
 
+    /// Sends data over a [DualSpiBus] instead of the SPI device
+    ///
+    /// Even bytes go out on the first data line and odd bytes on the second one, `data`
+    /// needs an even length, otherwise this returns [ErrorKind::InvalidWindow] without
+    /// sending anything. DC is set before every write like in [DisplayInterface::data].
+    pub(crate) async fn data_dual<D: DualSpiBus<Error = SPI::Error>>(
+        &mut self,
+        bus: &mut D,
+        data: &[u8],
+    ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        if data.len() % 2 != 0 {
+            return Err(ErrorKind::InvalidWindow);
+        }
+        epd_trace!("EPD dual data len={}", data.len());
+
+        let mut interleaved = [0; 256];
+        for chunk in data.chunks(interleaved.len()) {
+            for (pair, bytes) in chunk.chunks(2).zip(interleaved.chunks_mut(2)) {
+                bytes.copy_from_slice(&interleave_dual(pair[0], pair[1]));
+            }
+            // high for data
+            self.dc.set_high().map_err(ErrorKind::DcError)?;
+            bus.write_dual(&interleaved[..chunk.len()])
+                .await
+                .map_err(ErrorKind::SpiError)?;
+        }
+        Ok(())
+    }
+
+    /// Basic function for sending a [Command] and reading the answer of the device
+    ///
+    /// The first `dummy_bytes` (at most 8) after the command are dropped, some
+    /// controllers clock them out before the data is valid, e.g. for RAM reads. They are
+    /// read in one transaction with `buffer`. The command goes out in a transaction of its
+    /// own, as DC can't change within one, the controllers latch every byte by itself.
+    ///
+    /// The device answers on the data line, so it has to be connected to the MISO of
+    /// the SPI bus as well.
+    pub(crate) async fn cmd_then_read<T: Command>(
+        &mut self,
+        spi: &mut SPI,
+        command: T,
+        dummy_bytes: usize,
+        buffer: &mut [u8],
+    ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        let mut dummy = [0; 8];
+        assert!(dummy_bytes <= dummy.len());
+        self.cmd(spi, command).await?;
+        epd_trace!("EPD read len={} dummy={}", buffer.len(), dummy_bytes);
+
+        // high for data
+        self.dc.set_high().map_err(ErrorKind::DcError)?;
+
+        if dummy_bytes == 0 {
+            spi.read(buffer).await.map_err(ErrorKind::SpiError)
+        } else {
+            spi.transaction(&mut [
+                Operation::Read(&mut dummy[..dummy_bytes]),
+                Operation::Read(buffer),
+            ])
+            .await
+            .map_err(ErrorKind::SpiError)
+        }
+    }
+
     /// Basic function for sending [Commands](Command) and the data belonging to it.
     ///
-    /// TODO: directly use ::write? cs wouldn't needed to be changed twice than
+    /// The command and the data are separate transactions, DC has to change between
+    /// them and a [SpiDevice] transaction can't drive it. Chip select toggles in between,
+    /// the controllers latch every byte by itself and tell data from commands by DC only.
+    /// DC isn't held in between either, with a [SharedPin] another display may set it
+    /// before this one gets the bus again, see there.
     pub(crate) async fn cmd_with_data<T: Command>(
         &mut self,
         spi: &mut SPI,
@@ -105,38 +730,92 @@ where
     /// Basic function for sending the same byte of data (one u8) multiple times over spi
     ///
     /// Enables direct interaction with the device with the help of [command()](ConnectionInterface::command())
+    ///
+    /// The bytes go out in blocks like with [data_fill](DisplayInterface::data_fill),
+    /// only the `SINGLE_BYTE_WRITE` controllers get one write per byte.
     pub(crate) async fn data_x_times(
         &mut self,
         spi: &mut SPI,
         val: u8,
         repetitions: u32,
     ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
-        // high for data
-        let _ = self.dc.set_high().map_err(ErrorKind::DcError)?;
-        // Transfer data (u8) over spi
+        if !SINGLE_BYTE_WRITE {
+            return self.data_fill(spi, val, repetitions).await;
+        }
+        epd_trace!("EPD data {:#x} x{}", val, repetitions);
+        // Transfer data (u8) over spi, high for data
         for _ in 0..repetitions {
-            self.write(spi, &[val]).await?;
+            self.write(spi, PinState::High, &[val]).await?;
+        }
+        Ok(())
+    }
+
+    /// Sends the bytes of `data` in blocks of up to 256, without the whole data in RAM
+    ///
+    /// DC stays high for all of them, like for one call of [data](DisplayInterface::data).
+    /// Returns the number of bytes sent.
+    pub(crate) async fn data_from_iter(
+        &mut self,
+        spi: &mut SPI,
+        data: impl Iterator<Item = u8>,
+    ) -> Result<usize, ErrorKind<SPI, BUSY, DC, RST>> {
+        let mut block = [0; 256];
+        let mut len = 0;
+        let mut sent = 0;
+        for byte in data {
+            block[len] = byte;
+            len += 1;
+            if len == block.len() {
+                self.data(spi, &block).await?;
+                sent += len;
+                len = 0;
+            }
+        }
+        if len > 0 {
+            self.data(spi, &block[..len]).await?;
+            sent += len;
+        }
+        Ok(sent)
+    }
+
+    /// Like [data_x_times](DisplayInterface::data_x_times), but sends the bytes in blocks
+    ///
+    /// Only for controllers which take blocks of data, whatever `SINGLE_BYTE_WRITE` is.
+    pub(crate) async fn data_fill(
+        &mut self,
+        spi: &mut SPI,
+        val: u8,
+        repetitions: u32,
+    ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        epd_trace!("EPD data {:#x} x{}", val, repetitions);
+        let block = [val; 256];
+        let mut left = repetitions as usize;
+        while left > 0 {
+            let len = left.min(block.len());
+            // high for data
+            self.write(spi, PinState::High, &block[..len]).await?;
+            left -= len;
         }
         Ok(())
     }
 
     // spi write helper/abstraction function
+    //
+    // DC is set before every write, a display sharing it may have changed it while
+    // this one was waiting for the bus.
     async fn write(
         &mut self,
         spi: &mut SPI,
+        dc: PinState,
         data: &[u8],
     ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
-        // transfer spi data
-        // Be careful!! Linux has a default limit of 4096 bytes per spi transfer
-        // see https://raspberrypi.stackexchange.com/questions/65595/spi-transfer-fails-with-buffer-size-greater-than-4096
-        if cfg!(target_os = "linux") {
-            for data_chunk in data.chunks(4096) {
-                spi.write(data_chunk).await.map_err(ErrorKind::SpiError)?;
-            }
-            Ok(())
-        } else {
-            spi.write(data).await.map_err(ErrorKind::SpiError)
+        // transfer spi data, split up for limited drivers like Linux' spidev
+        let size = self.max_transfer_size.unwrap_or(usize::MAX).max(1);
+        for data_chunk in data.chunks(size) {
+            self.dc.set_state(dc).map_err(ErrorKind::DcError)?;
+            spi.write(data_chunk).await.map_err(ErrorKind::SpiError)?;
         }
+        Ok(())
     }
 
     /// Waits until device isn't busy anymore (busy == HIGH)
@@ -145,52 +824,181 @@ where
     /// but in the case you send data and commands directly you might need to check
     /// if the device is still busy
     ///
-    /// is_busy_low
+    /// level, the level of BUSY while busy
     ///
-    ///  - TRUE for epd4in2, epd2in13, epd2in7, epd5in83, epd7in5
-    ///  - FALSE for epd2in9, epd1in54 (for all Display Type A ones?)
+    ///  - [BusyLevel::Low] for epd4in2, epd2in13, epd2in7, epd5in83, epd7in5
+    ///  - [BusyLevel::High] for epd2in9, epd1in54 (for all Display Type A ones?)
     ///
     /// Most likely there was a mistake with the 2in9 busy connection
+    ///
+    /// Returns [ErrorKind::Asleep] in deep sleep, see [DisplayInterface::mark_asleep].
     pub(crate) async fn wait_until_idle(
         &mut self,
-        _spi: &mut SPI,
-        is_busy_low: bool,
+        level: BusyLevel,
     ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        if let Some(timeout_us) = self.busy_timeout_us {
+            return self.wait_until_idle_with_timeout(level, timeout_us).await;
+        }
+        if self.asleep {
+            return Err(ErrorKind::Asleep);
+        }
+        let start = self.delay.now_us();
+        let result = self.wait_for_idle_edge(level).await;
+        self.measure_busy(start);
+        result
+    }
+
+    /// Waits for the edge of BUSY, or for the marked operations without a busy pin
+    async fn wait_for_idle_edge(
+        &mut self,
+        level: BusyLevel,
+    ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        let is_busy_low = self.busy_level(level) == BusyLevel::Low;
+        let Some(busy) = self.busy.as_mut() else {
+            return self.wait_pending().await;
+        };
+        epd_trace!("EPD busy wait, busy low: {}", is_busy_low);
         if is_busy_low {
-            self.busy
-                .wait_for_high()
-                .await
-                .map_err(ErrorKind::BusyError)
+            busy.wait_for_high().await.map_err(ErrorKind::BusyError)?;
         } else {
-            self.busy.wait_for_low().await.map_err(ErrorKind::BusyError)
+            busy.wait_for_low().await.map_err(ErrorKind::BusyError)?;
+        }
+        epd_trace!("EPD busy done");
+        Ok(())
+    }
+
+    /// Waits for the operations marked with [DisplayInterface::mark_busy] instead of a
+    /// busy pin
+    async fn wait_pending(&mut self) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        let duration = core::mem::take(&mut self.pending_us).max(self.delay_us);
+        epd_trace!("EPD busy {}ms without pin", duration / 1000);
+        self.delay(duration).await;
+        Ok(())
+    }
+
+    /// Same as `wait_until_idle`, but gives up with [ErrorKind::BusyTimeout] after
+    /// `timeout_us`
+    ///
+    /// The pin is polled every `delay_us`, at least every µs, pausing with
+    /// [DisplayDelay::pause] in between.
+    pub(crate) async fn wait_until_idle_with_timeout(
+        &mut self,
+        level: BusyLevel,
+        timeout_us: u32,
+    ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        if self.asleep {
+            return Err(ErrorKind::Asleep);
+        }
+        let start = self.delay.now_us();
+        let result = self.poll_until_idle(level, timeout_us).await;
+        self.measure_busy(start);
+        result
+    }
+
+    /// Polls BUSY until it's idle or `timeout_us` passed
+    async fn poll_until_idle(
+        &mut self,
+        level: BusyLevel,
+        timeout_us: u32,
+    ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        let is_busy_low = self.busy_level(level) == BusyLevel::Low;
+        let mut waited = 0;
+        loop {
+            let busy = match self.busy.as_mut() {
+                Some(busy) if is_busy_low => busy.is_low(),
+                Some(busy) => busy.is_high(),
+                None => return self.wait_pending().await,
+            };
+            if !busy.map_err(ErrorKind::BusyError)? {
+                epd_trace!("EPD busy {}ms", waited / 1000);
+                return Ok(());
+            }
+            if waited >= timeout_us {
+                epd_trace!("EPD busy timeout after {}ms", waited / 1000);
+                return Err(ErrorKind::BusyTimeout);
+            }
+            let step = self.delay_us.max(1).min(timeout_us - waited);
+            self.delay.pause(step).await;
+            waited += step;
         }
     }
 
     /// Same as `wait_until_idle` for device needing a command to probe Busy pin
+    ///
+    /// The command is sent every `delay_us`, at least every µs, pausing with
+    /// [DisplayDelay::pause] in between.
     pub(crate) async fn wait_until_idle_with_cmd<T: Command>(
         &mut self,
         spi: &mut SPI,
-        is_busy_low: bool,
+        level: BusyLevel,
         status_command: T,
     ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
-        // TODO: would be better implemented with racing the busy pin state and the delay
-        while self.is_busy(is_busy_low) {
-            self.cmd(spi, status_command).await?;
-            if self.delay_us > 0 {
-                self.delay(spi, self.delay_us).await?;
-            }
+        if self.asleep {
+            return Err(ErrorKind::Asleep);
         }
-        Ok(())
+        let start = self.delay.now_us();
+        let result = self.poll_with_cmd(spi, level, status_command).await;
+        self.measure_busy(start);
+        result
     }
 
-    pub(crate) async fn delay(
+    /// Sends `status_command` until BUSY is idle or the busy timeout passed
+    async fn poll_with_cmd<T: Command>(
         &mut self,
         spi: &mut SPI,
-        duration: u32,
+        level: BusyLevel,
+        status_command: T,
     ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
-        spi.transaction(&mut [Operation::DelayNs(duration * 1000)])
-            .await
-            .map_err(ErrorKind::SpiError)
+        if self.busy.is_none() {
+            return self.wait_pending().await;
+        }
+        // TODO: would be better implemented with racing the busy pin state and the delay
+        let mut waited = 0u32;
+        while self.is_busy(level) {
+            if matches!(self.busy_timeout_us, Some(timeout_us) if waited >= timeout_us) {
+                epd_trace!("EPD busy timeout after {}ms", waited / 1000);
+                return Err(ErrorKind::BusyTimeout);
+            }
+            self.cmd(spi, status_command).await?;
+            // at least every µs, so a timeout fires with a `delay_us` of 0 as well
+            let step = self.delay_us.max(1);
+            self.delay.pause(step).await;
+            waited = waited.saturating_add(step);
+        }
+        epd_trace!("EPD busy {}ms", waited / 1000);
+        Ok(())
+    }
+
+    /// Notes how long the wait since `start` took, see [DisplayInterface::last_busy_us]
+    fn measure_busy(&mut self, start: Option<u32>) {
+        self.last_busy_us = start
+            .zip(self.delay.now_us())
+            .map(|(start, now)| now.wrapping_sub(start));
+    }
+
+    /// Returns how long the last wait for BUSY took in µs, timed out ones included
+    ///
+    /// `None` if the `delay` has no clock, see [TimedDelay], or nothing was waited for yet.
+    pub(crate) fn last_busy_us(&self) -> Option<u32> {
+        self.last_busy_us
+    }
+
+    /// Sleeps for `duration` µs
+    pub(crate) async fn delay(&mut self, duration: u32) {
+        self.delay.sleep_us(duration).await;
+    }
+
+    /// Checks that a reset pin is connected, deep sleep is only left by a hardware reset
+    pub(crate) fn require_reset_pin(&self) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        match self.rst {
+            Some(_) => Ok(()),
+            None => Err(ErrorKind::ResetRequired),
+        }
+    }
+
+    /// Returns whether a reset pin is connected
+    pub(crate) fn has_reset_pin(&self) -> bool {
+        self.rst.is_some()
     }
 
     /// Checks if device is still busy
@@ -199,39 +1007,503 @@ where
     /// but in the case you send data and commands directly you might need to check
     /// if the device is still busy
     ///
-    /// is_busy_low
+    /// level, the level of BUSY while busy
     ///
-    ///  - TRUE for epd4in2, epd2in13, epd2in7, epd5in83, epd7in5
-    ///  - FALSE for epd2in9, epd1in54 (for all Display Type A ones?)
+    ///  - [BusyLevel::Low] for epd4in2, epd2in13, epd2in7, epd5in83, epd7in5
+    ///  - [BusyLevel::High] for epd2in9, epd1in54 (for all Display Type A ones?)
     ///
     /// Most likely there was a mistake with the 2in9 busy connection
     /// //TODO: use the #cfg feature to make this compile the right way for the certain types
-    pub(crate) fn is_busy(&mut self, is_busy_low: bool) -> bool {
-        (is_busy_low && self.busy.is_low().unwrap_or(false))
-            || (!is_busy_low && self.busy.is_high().unwrap_or(false))
+    ///
+    /// Always `false` without a busy pin.
+    pub(crate) fn is_busy(&mut self, level: BusyLevel) -> bool {
+        let is_busy_low = self.busy_level(level) == BusyLevel::Low;
+        match self.busy.as_mut() {
+            Some(busy) if is_busy_low => busy.is_low().unwrap_or(false),
+            Some(busy) => busy.is_high().unwrap_or(false),
+            None => false,
+        }
     }
 
-    /// Resets the device.
+    /// Resets the device with its [ResetTiming].
     ///
     /// Often used to awake the module from deep sleep. See [Epd4in2::sleep()](Epd4in2::sleep())
     ///
     /// The timing of keeping the reset pin low seems to be important and different per device.
     /// Most displays seem to require keeping it low for 10ms, but the 7in5_v2 only seems to reset
     /// properly with 2ms
-    pub(crate) async fn reset(
+    ///
+    /// Without a reset pin this only waits `post_us`, the SSD controllers send their
+    /// software reset afterwards. Only the hardware reset leaves deep sleep.
+    pub(crate) async fn reset(&mut self) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        let timing = self.reset_timing;
+        self.mark_busy(BusyWith::Reset);
+        epd_trace!(
+            "EPD reset low={}us post={}us pin={}",
+            timing.assert_us,
+            timing.post_us,
+            self.rst.is_some()
+        );
+        if self.rst.is_some() {
+            self.set_rst(PinState::High)?;
+            self.delay(timing.deassert_us).await;
+
+            self.set_rst(PinState::Low)?;
+            self.delay(timing.assert_us).await;
+            self.set_rst(PinState::High)?;
+            self.asleep = false;
+        }
+        //TODO: the upstream libraries always sleep for 200ms here
+        // 10ms works fine with just for the 7in5_v2 but this needs to be validated for other devices
+        self.delay(timing.post_us).await;
+        Ok(())
+    }
+
+    /// Resets the device with the software reset `command` and waits until it's done
+    ///
+    /// Much faster than [DisplayInterface::reset] as it skips the pulse and `post_us`,
+    /// but the controller has to be awake for it, deep sleep is only left by a
+    /// hardware reset.
+    pub(crate) async fn sw_reset<T: Command>(
         &mut self,
         spi: &mut SPI,
-        initial_delay: u32,
-        duration: u32,
+        command: T,
+        level: BusyLevel,
     ) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
-        self.rst.set_high().map_err(ErrorKind::RstError)?;
-        self.delay(spi, initial_delay).await?;
+        epd_trace!("EPD software reset");
+        self.cmd(spi, command).await?;
+        self.mark_busy(BusyWith::Reset);
+        self.wait_until_idle(level).await
+    }
 
-        self.rst.set_low().map_err(ErrorKind::RstError)?;
-        self.delay(spi, duration).await?;
-        self.rst.set_high().map_err(ErrorKind::RstError)?;
-        //TODO: the upstream libraries always sleep for 200ms here
-        // 10ms works fine with just for the 7in5_v2 but this needs to be validated for other devices
-        self.delay(spi, 200_000).await
+    /// Drives the reset pin, if there is one
+    fn set_rst(&mut self, state: PinState) -> Result<(), ErrorKind<SPI, BUSY, DC, RST>> {
+        match self.rst.as_mut() {
+            Some(rst) => rst.set_state(state).map_err(ErrorKind::RstError),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::mock::{mocks, Log, Spi, Write};
+
+    #[test]
+    fn busy_timeout() {
+        let (log, _, busy, dc, rst, delay) = mocks();
+        let mut interface: DisplayInterface<Spi, _, _, _, _, true> = DisplayInterface::new(
+            Some(busy),
+            dc,
+            Some(rst),
+            delay,
+            ResetTiming::new(0, 0),
+            Some(1_000),
+            None,
+        );
+        interface.set_busy_timeout(Some(2_500));
+
+        // busy high stays busy
+        log.busy_level.set(true);
+        let result = crate::mock::block_on(interface.wait_until_idle(BusyLevel::High));
+        assert!(matches!(result, Err(ErrorKind::BusyTimeout)));
+        // polled instead of waiting for the edge
+        assert!(log.waits().is_empty());
+
+        log.busy_level.set(false);
+        crate::mock::block_on(interface.wait_until_idle(BusyLevel::High)).unwrap();
+
+        interface.set_busy_timeout(None);
+        crate::mock::block_on(interface.wait_until_idle(BusyLevel::High)).unwrap();
+        assert_eq!(log.waits(), [false]);
+    }
+
+    /// Counts the µs the mock delay slept
+    struct Clock(Rc<Log>);
+
+    impl MonotonicClock for Clock {
+        fn now_us(&mut self) -> u32 {
+            let slept: u64 = self.0.delays().iter().map(|&ns| u64::from(ns)).sum();
+            (slept / 1000) as u32
+        }
+    }
+
+    #[test]
+    fn last_busy_duration() {
+        let (log, _, busy, dc, rst, delay) = mocks();
+        let delay = TimedDelay::new(delay, Clock(log.clone()));
+        let mut interface: DisplayInterface<Spi, _, _, _, _, true> = DisplayInterface::new(
+            Some(busy),
+            dc,
+            Some(rst),
+            delay,
+            ResetTiming::new(0, 0),
+            Some(1_000),
+            None,
+        );
+        assert_eq!(interface.last_busy_us(), None);
+        crate::mock::block_on(interface.wait_until_idle(BusyLevel::High)).unwrap();
+        assert_eq!(interface.last_busy_us(), Some(0));
+
+        // timed out waits count as well
+        interface.set_busy_timeout(Some(2_500));
+        log.busy_level.set(true);
+        let result = crate::mock::block_on(interface.wait_until_idle(BusyLevel::High));
+        assert!(matches!(result, Err(ErrorKind::BusyTimeout)));
+        assert_eq!(interface.last_busy_us(), Some(2_500));
+
+        // nothing to measure with without a clock
+        let (_, _, busy, dc, rst, delay) = mocks();
+        let mut interface: DisplayInterface<Spi, _, _, _, _, true> = DisplayInterface::new(
+            Some(busy),
+            dc,
+            Some(rst),
+            delay,
+            ResetTiming::new(0, 0),
+            None,
+            None,
+        );
+        crate::mock::block_on(interface.wait_until_idle(BusyLevel::High)).unwrap();
+        assert_eq!(interface.last_busy_us(), None);
+    }
+
+    #[test]
+    fn busy_timeout_with_cmd() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut interface: DisplayInterface<Spi, _, _, _, _, true> = DisplayInterface::new(
+            Some(busy),
+            dc,
+            Some(rst),
+            delay,
+            ResetTiming::new(0, 0),
+            Some(0),
+            None,
+        );
+        interface.set_busy_timeout(Some(100));
+
+        // without a delay the status command is sent every µs
+        log.busy_level.set(true);
+        let command = crate::epd5in83_v2::command::Command::GetStatus;
+        let result = crate::mock::block_on(interface.wait_until_idle_with_cmd(
+            &mut spi,
+            BusyLevel::High,
+            command,
+        ));
+        assert!(matches!(result, Err(ErrorKind::BusyTimeout)));
+        assert_eq!(log.commands().len(), 100);
+    }
+
+    #[test]
+    fn busy_wait_delay() {
+        /// Counts the pauses instead of sleeping
+        struct Yield(u32);
+
+        impl BusyWaitStrategy for Yield {
+            async fn pause(&mut self, _interval_us: u32) {
+                self.0 += 1;
+            }
+        }
+
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut interface: DisplayInterface<Spi, _, _, _, _, true> = DisplayInterface::new(
+            Some(busy),
+            dc,
+            Some(rst),
+            BusyWaitDelay::new(delay, Yield(0)),
+            ResetTiming::new(1, 2),
+            Some(1_000),
+            None,
+        );
+        interface.set_busy_timeout(Some(2_500));
+
+        // both polling loops pause with the strategy
+        log.busy_level.set(true);
+        let result = crate::mock::block_on(interface.wait_until_idle(BusyLevel::High));
+        assert!(matches!(result, Err(ErrorKind::BusyTimeout)));
+        let command = crate::epd5in83_v2::command::Command::GetStatus;
+        let result = crate::mock::block_on(interface.wait_until_idle_with_cmd(
+            &mut spi,
+            BusyLevel::High,
+            command,
+        ));
+        assert!(matches!(result, Err(ErrorKind::BusyTimeout)));
+        assert!(log.delays().is_empty());
+
+        // the fixed waits still sleep with the delay
+        crate::mock::block_on(interface.reset()).unwrap();
+        assert_eq!(log.delays(), [1_000, 2_000, 200_000_000]);
+        let (_, wait) = interface.delay.release();
+        assert_eq!(wait.0, 6);
+    }
+
+    #[test]
+    fn reset_timing() {
+        let (log, _, busy, dc, rst, delay) = mocks();
+        let timing = ResetTiming {
+            deassert_us: 1,
+            assert_us: 2,
+            post_us: 3,
+        };
+        let mut interface: DisplayInterface<Spi, _, _, _, _, true> =
+            DisplayInterface::new(Some(busy), dc, Some(rst), delay, timing, None, None);
+        crate::mock::block_on(interface.reset()).unwrap();
+        assert_eq!(log.delays(), [1_000, 2_000, 3_000]);
+
+        log.clear();
+        interface.set_reset_timing(ResetTiming::new(4, 5));
+        crate::mock::block_on(interface.reset()).unwrap();
+        assert_eq!(log.delays(), [4_000, 5_000, 200_000_000]);
+
+        // only the startup without a reset pin
+        let (log, _, busy, dc, _, delay) = mocks();
+        let mut interface: DisplayInterface<Spi, _, _, NoResetPin, _, true> =
+            DisplayInterface::new(Some(busy), dc, None, delay, timing, None, None);
+        crate::mock::block_on(interface.reset()).unwrap();
+        assert_eq!(log.delays(), [3_000]);
+    }
+
+    #[test]
+    fn asleep_until_reset() {
+        let (_, _, busy, dc, rst, delay) = mocks();
+        let mut interface: DisplayInterface<Spi, _, _, _, _, true> = DisplayInterface::new(
+            Some(busy),
+            dc,
+            Some(rst),
+            delay,
+            ResetTiming::new(10, 10),
+            None,
+            None,
+        );
+        interface.mark_asleep();
+        let result = crate::mock::block_on(interface.wait_until_idle(BusyLevel::Low));
+        assert!(matches!(result, Err(ErrorKind::Asleep)));
+        let result =
+            crate::mock::block_on(interface.wait_until_idle_with_timeout(BusyLevel::Low, 10));
+        assert!(matches!(result, Err(ErrorKind::Asleep)));
+
+        crate::mock::block_on(interface.reset()).unwrap();
+        assert!(!interface.asleep);
+        crate::mock::block_on(interface.wait_until_idle(BusyLevel::Low)).unwrap();
+
+        // deep sleep is only left by a hardware reset
+        let (_, _, busy, dc, _, delay) = mocks();
+        let mut interface: DisplayInterface<Spi, _, _, NoResetPin, _, true> = DisplayInterface::new(
+            Some(busy),
+            dc,
+            None,
+            delay,
+            ResetTiming::new(10, 10),
+            None,
+            None,
+        );
+        interface.mark_asleep();
+        crate::mock::block_on(interface.reset()).unwrap();
+        assert!(interface.asleep);
+    }
+
+    #[test]
+    fn cmd_then_read() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut interface: DisplayInterface<Spi, _, _, _, _, true> = DisplayInterface::new(
+            Some(busy),
+            dc,
+            Some(rst),
+            delay,
+            ResetTiming::new(0, 0),
+            None,
+            None,
+        );
+        let command = crate::epd5in83_v2::command::Command::Revision;
+
+        log.queue_reads(&[0x01, 0x02, 0x03, 0x04]);
+        let mut buffer = [0; 2];
+        crate::mock::block_on(interface.cmd_then_read(&mut spi, command, 0, &mut buffer)).unwrap();
+        assert_eq!(log.commands(), [0x70]);
+        assert_eq!(buffer, [0x01, 0x02]);
+
+        // the dummy byte is dropped
+        crate::mock::block_on(interface.cmd_then_read(&mut spi, command, 1, &mut buffer)).unwrap();
+        assert_eq!(buffer, [0x04, 0x00]);
+    }
+
+    #[test]
+    fn data_x_times() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut interface: DisplayInterface<Spi, _, _, _, _, false> = DisplayInterface::new(
+            Some(busy),
+            dc,
+            Some(rst),
+            delay,
+            ResetTiming::new(0, 0),
+            None,
+            None,
+        );
+        crate::mock::block_on(interface.data_x_times(&mut spi, 0xAA, 600)).unwrap();
+        assert_eq!(log.transfers(), [256, 256, 88]);
+        assert_eq!(log.writes().len(), 600);
+
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut interface: DisplayInterface<Spi, _, _, _, _, true> = DisplayInterface::new(
+            Some(busy),
+            dc,
+            Some(rst),
+            delay,
+            ResetTiming::new(0, 0),
+            None,
+            None,
+        );
+        crate::mock::block_on(interface.data_x_times(&mut spi, 0xAA, 600)).unwrap();
+        assert_eq!(log.transfers().len(), 600);
+    }
+
+    #[test]
+    fn busy_inverted() {
+        let (log, _, busy, dc, rst, delay) = mocks();
+        let mut interface: DisplayInterface<Spi, _, _, _, _, true> = DisplayInterface::new(
+            Some(busy),
+            dc,
+            Some(rst),
+            delay,
+            ResetTiming::new(0, 0),
+            None,
+            None,
+        );
+
+        crate::mock::block_on(interface.wait_until_idle(BusyLevel::High)).unwrap();
+        interface.set_busy_inverted(true);
+        crate::mock::block_on(interface.wait_until_idle(BusyLevel::High)).unwrap();
+        crate::mock::block_on(interface.wait_until_idle(BusyLevel::Low)).unwrap();
+        assert_eq!(log.waits(), [false, true, false]);
+
+        // busy high is idle for an inverted busy high
+        log.busy_level.set(true);
+        assert!(!interface.is_busy(BusyLevel::High));
+        assert!(interface.is_busy(BusyLevel::Low));
+    }
+
+    #[test]
+    fn polling_busy_pin() {
+        /// Busy until it was read `busy_reads` times
+        struct Pin {
+            busy_reads: u32,
+        }
+
+        impl digital::ErrorType for Pin {
+            type Error = Infallible;
+        }
+
+        impl InputPin for Pin {
+            fn is_high(&mut self) -> Result<bool, Self::Error> {
+                self.busy_reads = self.busy_reads.saturating_sub(1);
+                Ok(self.busy_reads > 0)
+            }
+
+            fn is_low(&mut self) -> Result<bool, Self::Error> {
+                self.is_high().map(|high| !high)
+            }
+        }
+
+        /// Sums up the delays
+        struct Delay(u32);
+
+        impl DelayNs for Delay {
+            async fn delay_ns(&mut self, ns: u32) {
+                self.0 += ns;
+            }
+        }
+
+        let (_, _, _, dc, rst, delay) = mocks();
+        let busy = PollingBusyPin::new(Pin { busy_reads: 4 }, Delay(0), 5_000);
+        let mut interface: DisplayInterface<Spi, _, _, _, _, true> = DisplayInterface::new(
+            Some(busy),
+            dc,
+            Some(rst),
+            delay,
+            ResetTiming::new(0, 0),
+            None,
+            None,
+        );
+
+        // busy high, polled until the fourth read
+        crate::mock::block_on(interface.wait_until_idle(BusyLevel::High)).unwrap();
+        let (pin, delay) = interface.busy.take().unwrap().release();
+        assert_eq!(pin.busy_reads, 0);
+        assert_eq!(delay.0, 3 * 5_000_000);
+
+        /// Counts the pauses instead of sleeping
+        struct Yield(u32);
+
+        impl BusyWaitStrategy for Yield {
+            async fn pause(&mut self, _interval_us: u32) {
+                self.0 += 1;
+            }
+        }
+
+        let mut busy = PollingBusyPin::new(Pin { busy_reads: 3 }, Yield(0), 5_000);
+        crate::mock::block_on(busy.wait_for_low()).unwrap();
+        let (_, wait) = busy.release();
+        assert_eq!(wait.0, 2);
+    }
+
+    #[test]
+    fn shared_dc() {
+        let (log, mut spi, busy, dc, _, delay) = mocks();
+        let (_, _, other_busy, _, _, other_delay) = mocks();
+        let dc = RefCell::new(dc);
+        let mut first: DisplayInterface<Spi, _, _, NoResetPin, _, false> = DisplayInterface::new(
+            Some(busy),
+            SharedPin::new(&dc),
+            None,
+            delay,
+            ResetTiming::new(0, 0),
+            None,
+            None,
+        );
+        let mut second: DisplayInterface<Spi, _, _, NoResetPin, _, false> = DisplayInterface::new(
+            Some(other_busy),
+            SharedPin::new(&dc),
+            None,
+            other_delay,
+            ResetTiming::new(0, 0),
+            None,
+            None,
+        );
+
+        let command = crate::epd5in83_v2::command::Command::Revision;
+        crate::mock::block_on(first.cmd(&mut spi, command)).unwrap();
+        crate::mock::block_on(second.data(&mut spi, &[0x01])).unwrap();
+        crate::mock::block_on(first.cmd(&mut spi, command)).unwrap();
+        assert_eq!(
+            log.writes(),
+            [
+                Write::Command(0x70),
+                Write::Data(0x01),
+                Write::Command(0x70)
+            ]
+        );
+    }
+
+    #[test]
+    fn busy_group() {
+        let (log, _, busy, _, _, _) = mocks();
+        let other = busy.clone();
+        let mut group = BusyGroup::new([busy, other], BusyLevel::Low);
+        log.busy_level.set(false);
+        assert_eq!(group.is_busy(), Ok(true));
+        log.busy_level.set(true);
+        assert_eq!(group.is_busy(), Ok(false));
+
+        crate::mock::block_on(group.wait_until_idle()).unwrap();
+        assert_eq!(log.waits(), [true, true]);
+
+        let [busy, other] = group.release();
+        let mut group = BusyGroup::new([busy, other], BusyLevel::High);
+        log.clear();
+        crate::mock::block_on(group.wait_until_idle()).unwrap();
+        assert_eq!(log.waits(), [false, false]);
     }
 }
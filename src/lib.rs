@@ -28,7 +28,7 @@
 //!# let mut delay = delay::NoopDelay::new();
 //!
 //!// Setup EPD
-//!let mut epd = Epd1in54::new(&mut spi, busy_in, dc, rst, None)?;
+//!let mut epd = Epd1in54::new(&mut spi, busy_in, dc, Some(rst), delay, None)?;
 //!
 //!// Use display graphics from embedded-graphics
 //!let mut display = Display1in54::default();
@@ -60,6 +60,18 @@
 //!
 //! Maximum speed tested by myself was 8Mhz but more should be possible (Ben Krasnow used 18Mhz with his implemenation)
 //!
+//! ### Delays
+//!
+//! The drivers don't spin for their delays. The reset pulse, the waits after it and
+//! the polling of the busy pin sleep with the [`DelayNs`] passed to `new`, with
+//! `embassy_time::Delay` the executor sleeps the core meanwhile. Refreshes wait for
+//! the edge of the busy pin unless a busy timeout is set. To do something else between
+//! the polls, e.g. yield to the scheduler of an RTOS, pass a [`BusyWaitDelay`] with a
+//! [`BusyWaitStrategy`] instead. A [`TimedDelay`] adds a [`MonotonicClock`] to measure
+//! how long the refreshes take.
+//!
+//! [`DelayNs`]: embedded_hal_async::delay::DelayNs
+//!
 #![no_std]
 #![deny(missing_docs)]
 #![allow(stable_features, incomplete_features, async_fn_in_trait)]
@@ -72,42 +84,81 @@ pub mod graphics;
 mod error;
 mod traits;
 
+pub mod booster;
 pub mod color;
+pub mod pll;
+
+mod trace;
 
 /// Interface for the physical connection between display and the controlling device
 mod interface;
+pub use interface::{
+    BusyGroup, BusyLevel, BusyWaitDelay, BusyWaitStrategy, DisplayDelay, MonotonicClock, NoBusyPin,
+    NoResetPin, PollingBusyPin, ResetTiming, SharedPin, TimedDelay,
+};
+
+#[cfg(test)]
+pub(crate) mod mock;
 
+pub mod epd10in2b;
+pub mod epd12in48b;
+pub mod epd13in3b;
+pub mod epd13in3k;
+pub mod epd1in02;
 pub mod epd1in54;
 pub mod epd1in54_v2;
 pub mod epd1in54b;
+pub mod epd1in54b_v2;
 pub mod epd1in54c;
+pub mod epd1in64g;
 pub mod epd2in13_v2;
+pub mod epd2in13_v4;
+pub mod epd2in13b_v4;
 pub mod epd2in13bc;
+pub mod epd2in13g;
+pub mod epd2in15b;
+pub mod epd2in36g;
+pub mod epd2in66;
 pub mod epd2in66b;
+pub mod epd2in66g;
+pub mod epd2in7_v2;
 pub mod epd2in7b;
+pub mod epd2in7b_v2;
 pub mod epd2in9;
 pub mod epd2in9_v2;
+pub mod epd2in9b_v4;
 pub mod epd2in9bc;
 pub mod epd2in9d;
+pub mod epd3in0g;
+pub mod epd3in52;
 pub mod epd3in7;
+pub mod epd4in01f;
 pub mod epd4in2;
+pub mod epd4in26;
+pub mod epd4in2b_v2;
+pub mod epd4in37g;
 pub mod epd5in65f;
+pub mod epd5in79;
+pub mod epd5in79g;
 pub mod epd5in83_v2;
 pub mod epd5in83b_v2;
+pub mod epd7in3e;
 pub mod epd7in3f;
 pub mod epd7in5;
 pub mod epd7in5_hd;
 pub mod epd7in5_v2;
 pub mod epd7in5b_v2;
 pub use epd7in5b_v2 as epd7in5b_v3;
+pub mod ssd1681;
 
 pub(crate) mod type_a;
 
 /// Includes everything important besides the chosen Display
 pub mod prelude {
-    pub use crate::color::{Color, OctColor, TriColor};
+    pub use crate::color::{Color, OctColor, QuadColor, TriColor};
     pub use crate::traits::{
-        QuickRefresh, RefreshLut, WaveshareDisplay, WaveshareThreeColorDisplay,
+        DualSpiBus, QuickRefresh, RefreshLut, UpdateMode, WaveshareDisplay,
+        WaveshareThreeColorDisplay,
     };
 
     pub use crate::error::*;
@@ -131,6 +182,41 @@ pub const fn buffer_len(width: usize, height: usize) -> usize {
     (width + 7) / 8 * height
 }
 
+/// Computes the needed buffer length for displays with 2 bits per pixel, like the
+/// ones using [QuadColor](color::QuadColor). Four pixels share a byte and every row
+/// is rounded up to full bytes.
+pub const fn quad_buffer_len(width: usize, height: usize) -> usize {
+    (width * 2 + 7) / 8 * height
+}
+
+/// Splits 8 pixels with 2 bits each into one byte of high bits and one of low bits
+///
+/// Pixels are stored MSB first, the 4 level grayscale drivers write both bytes into
+/// the two RAM banks of the controller.
+pub(crate) fn split_gray4(pixels: &[u8]) -> (u8, u8) {
+    let mut high = 0;
+    let mut low = 0;
+    for &byte in pixels {
+        for shift in [6, 4, 2, 0] {
+            let pixel = byte >> shift;
+            high = (high << 1) | ((pixel >> 1) & 1);
+            low = (low << 1) | (pixel & 1);
+        }
+    }
+    (high, low)
+}
+
+/// Interleaves the bits of two bytes for [DualSpiBus](traits::DualSpiBus)
+///
+/// `first` ends up on the first data line and `second` on the second one.
+pub(crate) fn interleave_dual(first: u8, second: u8) -> [u8; 2] {
+    let mut bits: u16 = 0;
+    for bit in (0..8).rev() {
+        bits = (bits << 2) | (((second >> bit) & 1) << 1) as u16 | ((first >> bit) & 1) as u16;
+    }
+    bits.to_be_bytes()
+}
+
 use embedded_hal::spi::{Mode, Phase, Polarity};
 
 /// SPI mode -
@@ -0,0 +1,330 @@
+//! Recording mocks of the SPI device and the pins for the driver tests
+//!
+//! All mocks share one [Log], so the bytes written over SPI can be tagged with the
+//! state of the DC pin at the time of the write.
+extern crate std;
+
+use core::cell::{Cell, RefCell};
+use core::convert::Infallible;
+use core::future::Future;
+use core::pin::pin;
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+use std::rc::Rc;
+use std::vec::Vec;
+
+use embedded_hal::digital::{self, InputPin, OutputPin};
+use embedded_hal::spi::{self, Operation};
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::digital::Wait;
+use embedded_hal_async::spi::SpiDevice;
+
+use crate::traits::DualSpiBus;
+
+/// A byte written over SPI, tagged with the state of the DC pin
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Write {
+    /// DC was low
+    Command(u8),
+    /// DC was high
+    Data(u8),
+}
+
+/// Everything the mocks have seen
+#[derive(Default)]
+pub(crate) struct Log {
+    dc: Cell<bool>,
+    writes: RefCell<Vec<Write>>,
+    /// Levels the busy pin was waited for, `true` for high
+    waits: RefCell<Vec<bool>>,
+    /// Level returned by [Busy::is_high]
+    pub(crate) busy_level: Cell<bool>,
+    /// Bytes returned by SPI reads, 0 once they are used up
+    reads: RefCell<Vec<u8>>,
+    /// Lengths of the SPI write operations
+    transfers: RefCell<Vec<usize>>,
+    /// Skipped delays in ns
+    delays: RefCell<Vec<u32>>,
+}
+
+impl Log {
+    /// All bytes written so far
+    pub(crate) fn writes(&self) -> Vec<Write> {
+        self.writes.borrow().clone()
+    }
+
+    /// All commands written so far
+    pub(crate) fn commands(&self) -> Vec<u8> {
+        self.writes
+            .borrow()
+            .iter()
+            .filter_map(|write| match write {
+                Write::Command(command) => Some(*command),
+                Write::Data(_) => None,
+            })
+            .collect()
+    }
+
+    /// The data written after the `n`th occurrence of `command`
+    pub(crate) fn data_of(&self, command: u8, n: usize) -> Vec<u8> {
+        self.writes
+            .borrow()
+            .iter()
+            .skip_while({
+                let mut seen = 0;
+                move |write| {
+                    if **write == Write::Command(command) {
+                        seen += 1;
+                        return seen <= n;
+                    }
+                    true
+                }
+            })
+            .skip(1)
+            .map_while(|write| match write {
+                Write::Data(data) => Some(*data),
+                Write::Command(_) => None,
+            })
+            .collect()
+    }
+
+    /// Levels the busy pin was waited for, `true` for high
+    pub(crate) fn waits(&self) -> Vec<bool> {
+        self.waits.borrow().clone()
+    }
+
+    /// Lengths of the SPI write operations so far
+    pub(crate) fn transfers(&self) -> Vec<usize> {
+        self.transfers.borrow().clone()
+    }
+
+    /// Skipped delays so far in ns
+    pub(crate) fn delays(&self) -> Vec<u32> {
+        self.delays.borrow().clone()
+    }
+
+    /// Queues bytes to be returned by the next SPI reads
+    pub(crate) fn queue_reads(&self, bytes: &[u8]) {
+        self.reads.borrow_mut().extend_from_slice(bytes);
+    }
+
+    /// Forgets everything seen so far
+    pub(crate) fn clear(&self) {
+        self.writes.borrow_mut().clear();
+        self.waits.borrow_mut().clear();
+        self.transfers.borrow_mut().clear();
+        self.delays.borrow_mut().clear();
+    }
+}
+
+/// Creates a set of mocks sharing one [Log]
+pub(crate) fn mocks() -> (Rc<Log>, Spi, Busy, Dc, Rst, Delay) {
+    let log = Rc::new(Log::default());
+    (
+        log.clone(),
+        Spi(log.clone()),
+        Busy(log.clone()),
+        Dc(log.clone()),
+        Rst,
+        Delay(log),
+    )
+}
+
+/// SPI device recording all written bytes
+pub(crate) struct Spi(Rc<Log>);
+
+impl spi::ErrorType for Spi {
+    type Error = Infallible;
+}
+
+impl SpiDevice for Spi {
+    async fn transaction(
+        &mut self,
+        operations: &mut [Operation<'_, u8>],
+    ) -> Result<(), Self::Error> {
+        for operation in operations {
+            match operation {
+                Operation::Write(bytes) => {
+                    self.0.transfers.borrow_mut().push(bytes.len());
+                    let dc = self.0.dc.get();
+                    self.0.writes.borrow_mut().extend(bytes.iter().map(|&byte| {
+                        if dc {
+                            Write::Data(byte)
+                        } else {
+                            Write::Command(byte)
+                        }
+                    }));
+                }
+                Operation::Read(bytes) => {
+                    let mut reads = self.0.reads.borrow_mut();
+                    for byte in bytes.iter_mut() {
+                        *byte = if reads.is_empty() { 0 } else { reads.remove(0) };
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Busy pin which is never busy while waiting, but records the waited for level
+///
+/// A clone reads and records on the same log.
+#[derive(Clone)]
+pub(crate) struct Busy(Rc<Log>);
+
+impl digital::ErrorType for Busy {
+    type Error = Infallible;
+}
+
+impl InputPin for Busy {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.0.busy_level.get())
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!self.0.busy_level.get())
+    }
+}
+
+impl Wait for Busy {
+    async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+        self.0.waits.borrow_mut().push(true);
+        Ok(())
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+        self.0.waits.borrow_mut().push(false);
+        Ok(())
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// DC pin, its level decides how the written bytes are recorded
+pub(crate) struct Dc(Rc<Log>);
+
+impl digital::ErrorType for Dc {
+    type Error = Infallible;
+}
+
+impl OutputPin for Dc {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.0.dc.set(false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.0.dc.set(true);
+        Ok(())
+    }
+}
+
+/// Reset pin doing nothing
+pub(crate) struct Rst;
+
+impl digital::ErrorType for Rst {
+    type Error = Infallible;
+}
+
+impl OutputPin for Rst {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Delay recording the delays instead of sleeping
+pub(crate) struct Delay(Rc<Log>);
+
+impl DelayNs for Delay {
+    async fn delay_ns(&mut self, ns: u32) {
+        self.0.delays.borrow_mut().push(ns);
+    }
+}
+
+/// Dual SPI bus recording the interleaved bytes
+#[derive(Default)]
+pub(crate) struct DualBus(pub(crate) Vec<u8>);
+
+impl DualSpiBus for DualBus {
+    type Error = Infallible;
+
+    async fn write_dual(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        self.0.extend_from_slice(data);
+        Ok(())
+    }
+}
+
+/// Runs a future which never has to wait, as all futures of the mocks are ready at once
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    fn noop_raw_waker() -> RawWaker {
+        fn no_op(_: *const ()) {}
+        fn clone(_: *const ()) -> RawWaker {
+            noop_raw_waker()
+        }
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, no_op, no_op, no_op);
+        RawWaker::new(core::ptr::null(), &VTABLE)
+    }
+
+    // Safety: the vtable functions don't touch the data pointer
+    let waker = unsafe { Waker::from_raw(noop_raw_waker()) };
+    let mut context = Context::from_waker(&waker);
+    match pin!(future).poll(&mut context) {
+        Poll::Ready(output) => output,
+        Poll::Pending => panic!("the mocks never block"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_writes_with_dc() {
+        let (log, mut spi, _busy, mut dc, _rst, _delay) = mocks();
+        block_on(async {
+            dc.set_low().unwrap();
+            spi.write(&[0x10]).await.unwrap();
+            dc.set_high().unwrap();
+            spi.write(&[0x01, 0x02]).await.unwrap();
+            dc.set_low().unwrap();
+            spi.write(&[0x12]).await.unwrap();
+        });
+        assert_eq!(
+            log.writes(),
+            [
+                Write::Command(0x10),
+                Write::Data(0x01),
+                Write::Data(0x02),
+                Write::Command(0x12)
+            ]
+        );
+        assert_eq!(log.commands(), [0x10, 0x12]);
+        assert_eq!(log.data_of(0x10, 0), [0x01, 0x02]);
+        assert!(log.data_of(0x12, 0).is_empty());
+    }
+
+    #[test]
+    fn returns_queued_reads() {
+        let (log, mut spi, _busy, _dc, _rst, _delay) = mocks();
+        log.queue_reads(&[0x19, 0x80]);
+        let mut bytes = [0xFF; 3];
+        block_on(spi.read(&mut bytes)).unwrap();
+        assert_eq!(bytes, [0x19, 0x80, 0x00]);
+        assert!(log.writes().is_empty());
+    }
+}
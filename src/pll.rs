@@ -0,0 +1,56 @@
+//! Frame rate settings of the UC8151, UC8176 and UC8179 controllers
+//!
+//! The PLL control command (0x30) sets the frame rate of the refresh. A lower one
+//! lowers the peak current, a higher one shortens the waveform.
+
+/// Frame rate of the refresh
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameRate {
+    /// 50 Hz
+    Hz50,
+    /// 100 Hz
+    Hz100,
+    /// 150 Hz
+    Hz150,
+    /// 200 Hz
+    Hz200,
+}
+
+impl FrameRate {
+    /// Data of the PLL control command of the UC8151 and UC8176, the M and N dividers
+    pub const fn uc8151_byte(self) -> u8 {
+        match self {
+            FrameRate::Hz50 => 0x3C,
+            FrameRate::Hz100 => 0x3A,
+            FrameRate::Hz150 => 0x29,
+            FrameRate::Hz200 => 0x39,
+        }
+    }
+
+    /// Data of the PLL control command of the UC8179, the frame rate select bits
+    pub const fn uc8179_byte(self) -> u8 {
+        match self {
+            FrameRate::Hz50 => 0x06,
+            FrameRate::Hz100 => 0x0B,
+            FrameRate::Hz150 => 0x0E,
+            FrameRate::Hz200 => 0x0F,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pll_bytes() {
+        let rates = [
+            FrameRate::Hz50,
+            FrameRate::Hz100,
+            FrameRate::Hz150,
+            FrameRate::Hz200,
+        ];
+        assert_eq!(rates.map(FrameRate::uc8151_byte), [0x3C, 0x3A, 0x29, 0x39]);
+        assert_eq!(rates.map(FrameRate::uc8179_byte), [0x06, 0x0B, 0x0E, 0x0F]);
+    }
+}
@@ -0,0 +1,534 @@
+//! A generic Driver for black/white E-Ink Displays with a SSD1681 controller via SPI
+//!
+//! Waveshare's 1.54" V2 and many Good Display panels (GDEY0154D67 and friends) use the
+//! same controller, but differ in their resolution and in how the gates are wired.
+//! Instead of a module per panel, [Ssd1681] takes the resolution as const generics
+//! and the gate scan settings of the driver output control as a [GateScan]:
+//!
+//!```rust, ignore
+//!use epd_waveshare::ssd1681::{GateScan, Ssd1681};
+//!
+//!// a 200x200 panel, scanning the gates from the bottom to the top
+//!let gate_scan = GateScan { bottom_to_top: true, ..GateScan::default() };
+//!let mut epd: Ssd1681<_, _, _, _, 200, 200> =
+//!    Ssd1681::new_with_gate_scan(&mut spi, busy, dc, Some(rst), delay, None, gate_scan).await?;
+//!```
+//!
+//! The waveforms are always loaded from the OTP. `RefreshLut::Full` refreshes the whole
+//! panel, `RefreshLut::Quick` switches to the differential display mode which only
+//! drives the pixels differing from the previous image in RAM 0x26.
+//! [update_partial_frame](WaveshareDisplay::update_partial_frame) writes a window of
+//! the RAM, its x position and width need to be multiples of 8.
+//!
+//! As the buffer length can't be computed from the generic resolution, use
+//! [Display](crate::graphics::Display) with [buffer_len](crate::buffer_len) for a
+//! graphics buffer, e.g. `Display<200, 200, false, { buffer_len(200, 200) }, Color>`.
+use core::fmt::{Debug, Display};
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal_async::{digital::Wait, spi::SpiDevice};
+
+use crate::color::Color;
+use crate::error::ErrorKind;
+use crate::interface::{BusyLevel, DisplayDelay, DisplayInterface, ResetTiming};
+use crate::traits::{ErrorType, InternalWiAdditions, RefreshLut, WaveshareDisplay};
+
+use crate::type_a::command::Command;
+
+/// Default Background Color
+pub const DEFAULT_BACKGROUND_COLOR: Color = Color::White;
+const BUSY_LEVEL: BusyLevel = BusyLevel::High;
+const RESET_TIMING: ResetTiming = ResetTiming::new(10_000, 2_000);
+const SINGLE_BYTE_WRITE: bool = false;
+
+/// Gate scan settings, the last byte of the driver output control
+///
+/// The default is the reset value of the controller, all settings off.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GateScan {
+    /// GD: the first output gate is G1 instead of G0
+    pub first_gate_g1: bool,
+    /// SM: the left and right gates are interlaced
+    pub interlaced: bool,
+    /// TB: the gates are scanned from the bottom to the top
+    pub bottom_to_top: bool,
+}
+
+impl GateScan {
+    /// Returns the B\[2:0\] byte of the driver output control
+    pub const fn bits(self) -> u8 {
+        (self.first_gate_g1 as u8) << 2 | (self.interlaced as u8) << 1 | self.bottom_to_top as u8
+    }
+}
+
+/// Ssd1681 driver for a panel of `W` x `H` pixels
+///
+pub struct Ssd1681<SPI, BUSY, DC, RST, DELAY, const W: u32, const H: u32> {
+    /// Connection Interface
+    interface: DisplayInterface<SPI, BUSY, DC, RST, DELAY, SINGLE_BYTE_WRITE>,
+    /// Background Color
+    color: Color,
+    /// Refresh LUT
+    refresh: RefreshLut,
+    /// Gate scan settings of the driver output control
+    gate_scan: GateScan,
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, const W: u32, const H: u32> ErrorType<SPI, BUSY, DC, RST>
+    for Ssd1681<SPI, BUSY, DC, RST, DELAY, W, H>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    type Error = ErrorKind<SPI, BUSY, DC, RST>;
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, const W: u32, const H: u32> InternalWiAdditions<SPI, BUSY, DC, RST>
+    for Ssd1681<SPI, BUSY, DC, RST, DELAY, W, H>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    async fn init(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.reset().await?;
+        self.wait_until_idle(spi).await?;
+
+        self.command(spi, Command::SwReset).await?;
+        self.wait_until_idle(spi).await?;
+
+        // number of gates minus one, followed by the gate scan settings
+        let mux = H - 1;
+        self.cmd_with_data(
+            spi,
+            Command::DriverOutputControl,
+            &[mux as u8, (mux >> 8) as u8, self.gate_scan.bits()],
+        )
+        .await?;
+
+        // x increment, y increment, address counter is updated in x direction
+        self.cmd_with_data(spi, Command::DataEntryModeSetting, &[0x03])
+            .await?;
+
+        // follow LUT for the border
+        self.cmd_with_data(spi, Command::BorderWaveformControl, &[0x05])
+            .await?;
+
+        // 0x80: internal temperature sensor
+        self.cmd_with_data(spi, Command::TemperatureSensorSelection, &[0x80])
+            .await?;
+
+        self.use_full_frame(spi).await?;
+        self.wait_until_idle(spi).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, const W: u32, const H: u32>
+    WaveshareDisplay<SPI, BUSY, DC, RST, DELAY> for Ssd1681<SPI, BUSY, DC, RST, DELAY, W, H>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    type DisplayColor = Color;
+    async fn new(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        delay: DELAY,
+        delay_us: Option<u32>,
+    ) -> Result<Self, Self::Error> {
+        Self::new_with_gate_scan(spi, busy, dc, rst, delay, delay_us, GateScan::default()).await
+    }
+
+    async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        // Deep Sleep Mode 1, a hardware reset is needed to wake up
+        self.cmd_with_data(spi, Command::DeepSleepMode, &[0x01])
+            .await
+    }
+
+    async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.require_reset_pin()?;
+        self.init(spi).await
+    }
+
+    fn set_background_color(&mut self, color: Color) {
+        self.color = color;
+    }
+
+    fn background_color(&self) -> &Color {
+        &self.color
+    }
+
+    fn width(&self) -> u32 {
+        W
+    }
+
+    fn height(&self) -> u32 {
+        H
+    }
+
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>) {
+        self.interface.set_busy_timeout(timeout_us);
+    }
+
+    fn set_max_transfer_size(&mut self, size: Option<usize>) {
+        self.interface.set_max_transfer_size(size);
+    }
+
+    fn last_busy_duration(&self) -> Option<u32> {
+        self.interface.last_busy_us()
+    }
+
+    fn set_reset_timing(&mut self, timing: ResetTiming) {
+        self.interface.set_reset_timing(timing);
+    }
+
+    fn set_busy_polarity(&mut self, level: BusyLevel) {
+        self.interface.set_busy_inverted(level != BUSY_LEVEL);
+    }
+
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.wait_until_idle(spi).await?;
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error> {
+        assert!(buffer.len() == Self::FRAME_SIZE as usize);
+        self.wait_until_idle(spi).await?;
+        self.use_full_frame(spi).await?;
+        self.cmd_with_data(spi, Command::WriteRam, buffer).await
+    }
+
+    /// Writes `buffer` into the RAM window starting at (x, y)
+    ///
+    /// x and width need to be multiples of 8
+    async fn update_partial_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Self::Error> {
+        assert!(x % 8 == 0 && width % 8 == 0);
+        assert!(x + width <= W && y + height <= H);
+        assert!(buffer.len() as u32 == width / 8 * height);
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+
+        self.wait_until_idle(spi).await?;
+        self.set_ram_area(spi, x, y, x + width - 1, y + height - 1)
+            .await?;
+        self.set_ram_counter(spi, x, y).await?;
+        self.cmd_with_data(spi, Command::WriteRam, buffer).await?;
+
+        // restore the full window for the next frame update
+        self.use_full_frame(spi).await
+    }
+
+    async fn display_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        let update = match self.refresh {
+            // Load temperature and waveform, DISPLAY with DISPLAY Mode 1
+            RefreshLut::Full => 0xF7,
+            // Same as above with DISPLAY Mode 2, only changed pixels are driven
+            RefreshLut::Quick => 0xFF,
+        };
+        self.wait_until_idle(spi).await?;
+        self.cmd_with_data(spi, Command::DisplayUpdateControl2, &[update])
+            .await?;
+        self.command(spi, Command::MasterActivation).await?;
+        self.wait_until_idle(spi).await
+    }
+
+    async fn update_and_display_frame(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+    ) -> Result<(), Self::Error> {
+        self.update_frame(spi, buffer).await?;
+        self.display_frame(spi).await
+    }
+
+    async fn clear_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error> {
+        let color = self.color.get_byte_value();
+        self.wait_until_idle(spi).await?;
+
+        self.use_full_frame(spi).await?;
+        self.command(spi, Command::WriteRam).await?;
+        self.interface
+            .data_x_times(spi, color, Self::FRAME_SIZE)
+            .await?;
+
+        self.use_full_frame(spi).await?;
+        self.command(spi, Command::WriteRam2).await?;
+        self.interface
+            .data_x_times(spi, color, Self::FRAME_SIZE)
+            .await
+    }
+
+    /// The waveforms are always taken from the OTP, `RefreshLut::Quick` selects
+    /// the differential display mode for the next refreshes.
+    async fn set_lut(
+        &mut self,
+        _spi: &mut SPI,
+        refresh_rate: Option<RefreshLut>,
+    ) -> Result<(), Self::Error> {
+        if let Some(refresh_lut) = refresh_rate {
+            self.refresh = refresh_lut;
+        }
+        Ok(())
+    }
+
+    async fn wait_until_idle(&mut self, _spi: &mut SPI) -> Result<(), Self::Error> {
+        self.interface.wait_until_idle(BUSY_LEVEL).await
+    }
+}
+
+impl<SPI, BUSY, DC, RST, DELAY, const W: u32, const H: u32> Ssd1681<SPI, BUSY, DC, RST, DELAY, W, H>
+where
+    SPI: SpiDevice,
+    SPI::Error: Copy + Debug + Display,
+    BUSY: InputPin + Wait,
+    BUSY::Error: Copy + Debug + Display,
+    DC: OutputPin,
+    DC::Error: Copy + Debug + Display,
+    RST: OutputPin,
+    RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
+{
+    /// Bytes of a full frame, rows are padded to full bytes
+    const FRAME_SIZE: u32 = (W + 7) / 8 * H;
+
+    /// Creates a new driver like [new](WaveshareDisplay::new) with the given gate scan
+    /// settings
+    pub async fn new_with_gate_scan(
+        spi: &mut SPI,
+        busy: BUSY,
+        dc: DC,
+        rst: Option<RST>,
+        delay: DELAY,
+        delay_us: Option<u32>,
+        gate_scan: GateScan,
+    ) -> Result<Self, <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        let interface =
+            DisplayInterface::new(Some(busy), dc, rst, delay, RESET_TIMING, delay_us, None);
+
+        let mut epd = Ssd1681 {
+            interface,
+            color: DEFAULT_BACKGROUND_COLOR,
+            refresh: RefreshLut::Full,
+            gate_scan,
+        };
+
+        epd.init(spi).await?;
+
+        Ok(epd)
+    }
+
+    async fn command(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.cmd(spi, command).await
+    }
+
+    async fn cmd_with_data(
+        &mut self,
+        spi: &mut SPI,
+        command: Command,
+        data: &[u8],
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.interface.cmd_with_data(spi, command, data).await
+    }
+
+    async fn use_full_frame(
+        &mut self,
+        spi: &mut SPI,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        // choose full frame/ram
+        self.set_ram_area(spi, 0, 0, W - 1, H - 1).await?;
+
+        // start from the beginning
+        self.set_ram_counter(spi, 0, 0).await
+    }
+
+    /// Sets both X and Y pixels ranges
+    ///
+    /// The SSD1681 addresses x in bytes, so x positions are divided by 8
+    async fn set_ram_area(
+        &mut self,
+        spi: &mut SPI,
+        start_x: u32,
+        start_y: u32,
+        end_x: u32,
+        end_y: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.cmd_with_data(
+            spi,
+            Command::SetRamXAddressStartEndPosition,
+            &[(start_x >> 3) as u8, (end_x >> 3) as u8],
+        )
+        .await?;
+
+        self.cmd_with_data(
+            spi,
+            Command::SetRamYAddressStartEndPosition,
+            &[
+                start_y as u8,
+                (start_y >> 8) as u8,
+                end_y as u8,
+                (end_y >> 8) as u8,
+            ],
+        )
+        .await
+    }
+
+    /// Sets both X and Y pixels counters when writing data to RAM
+    async fn set_ram_counter(
+        &mut self,
+        spi: &mut SPI,
+        x: u32,
+        y: u32,
+    ) -> Result<(), <Self as ErrorType<SPI, BUSY, DC, RST>>::Error> {
+        self.cmd_with_data(spi, Command::SetRamXAddressCounter, &[(x >> 3) as u8])
+            .await?;
+
+        self.cmd_with_data(
+            spi,
+            Command::SetRamYAddressCounter,
+            &[y as u8, (y >> 8) as u8],
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{block_on, mocks, Busy, Dc, Delay, Rst, Spi};
+
+    type Epd200 = Ssd1681<Spi, Busy, Dc, Rst, Delay, 200, 200>;
+
+    #[test]
+    fn gate_scan_bits() {
+        assert_eq!(GateScan::default().bits(), 0x00);
+        let gate_scan = GateScan {
+            bottom_to_top: true,
+            ..GateScan::default()
+        };
+        assert_eq!(gate_scan.bits(), 0x01);
+        let gate_scan = GateScan {
+            first_gate_g1: true,
+            interlaced: true,
+            bottom_to_top: false,
+        };
+        assert_eq!(gate_scan.bits(), 0x06);
+    }
+
+    #[test]
+    fn init_uses_resolution_and_gate_scan() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let gate_scan = GateScan {
+            bottom_to_top: true,
+            ..GateScan::default()
+        };
+        let epd = block_on(Epd200::new_with_gate_scan(
+            &mut spi,
+            busy,
+            dc,
+            Some(rst),
+            delay,
+            None,
+            gate_scan,
+        ))
+        .unwrap();
+        assert_eq!(epd.width(), 200);
+        assert_eq!(epd.height(), 200);
+        assert_eq!(log.data_of(0x01, 0), [0xC7, 0x00, 0x01]);
+        assert_eq!(log.data_of(0x44, 0), [0x00, 0x18]);
+        assert_eq!(log.data_of(0x45, 0), [0x00, 0x00, 0xC7, 0x00]);
+    }
+
+    #[test]
+    fn unaligned_width_is_padded() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Ssd1681::<_, _, _, _, _, 122, 250>::new(
+            &mut spi,
+            busy,
+            dc,
+            Some(rst),
+            delay,
+            None,
+        ))
+        .unwrap();
+        assert_eq!(log.data_of(0x01, 0), [0xF9, 0x00, 0x00]);
+
+        log.clear();
+        block_on(epd.clear_frame(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x24, 0).len(), 16 * 250);
+        assert_eq!(log.data_of(0x26, 0).len(), 16 * 250);
+    }
+
+    #[test]
+    fn partial_window() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd200::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        let buffer = [0xAA; 2 * 4];
+        block_on(epd.update_partial_frame(&mut spi, &buffer, 16, 10, 16, 4)).unwrap();
+        assert_eq!(log.data_of(0x44, 0), [2, 3]);
+        assert_eq!(log.data_of(0x45, 0), [10, 0, 13, 0]);
+        assert_eq!(log.data_of(0x4E, 0), [2]);
+        assert_eq!(log.data_of(0x4F, 0), [10, 0]);
+        assert_eq!(log.data_of(0x24, 0), buffer);
+        // the full window is restored afterwards
+        assert_eq!(log.data_of(0x44, 1), [0x00, 0x18]);
+    }
+
+    #[test]
+    fn quick_refresh_uses_display_mode_2() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd200::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+
+        log.clear();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x22, 0), [0xF7]);
+
+        log.clear();
+        block_on(epd.set_lut(&mut spi, Some(RefreshLut::Quick))).unwrap();
+        block_on(epd.display_frame(&mut spi)).unwrap();
+        assert_eq!(log.data_of(0x22, 0), [0xFF]);
+        assert_eq!(log.commands(), [0x22, 0x20]);
+    }
+}
@@ -0,0 +1,19 @@
+//! Protocol tracing of the [DisplayInterface](crate::interface::DisplayInterface)
+//!
+//! With the `defmt` or `log` feature every command byte, data length, reset pulse and
+//! busy wait is logged at debug level, e.g. `EPD cmd 0x13` and `EPD data len=38880`.
+//! Without either feature [epd_trace] expands to nothing and its arguments aren't
+//! evaluated.
+
+/// Logs a protocol event with `defmt` and/or `log`, the arguments need to be
+/// formattable by both
+macro_rules! epd_trace {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "defmt")]
+        defmt::debug!($($arg)*);
+        #[cfg(feature = "log")]
+        log::debug!($($arg)*);
+    };
+}
+
+pub(crate) use epd_trace;
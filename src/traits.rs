@@ -1,9 +1,18 @@
 use core::fmt::{Debug, Display};
 use core::marker::Sized;
+use core::num::NonZeroU32;
 use embedded_hal::digital::{InputPin, OutputPin};
 use embedded_hal_async::{digital::Wait, spi::SpiDevice};
 
+use crate::buffer_len;
 use crate::error::ErrorKind;
+use crate::interface::{BusyLevel, DisplayDelay, ResetTiming};
+
+#[cfg(feature = "graphics")]
+use embedded_graphics_core::{
+    geometry::{Point, Size},
+    primitives::Rectangle,
+};
 
 pub trait Error<SPI, BUSY, DC, RST>: core::fmt::Debug
 where
@@ -55,6 +64,14 @@ pub(crate) trait Command: Copy {
     fn address(self) -> u8;
 }
 
+/// A raw command address, see [WaveshareDisplay::send_raw_command]
+#[cfg(feature = "raw-commands")]
+impl Command for u8 {
+    fn address(self) -> u8 {
+        self
+    }
+}
+
 /// Seperates the different LUT for the Display Refresh process
 #[derive(Debug, Clone, PartialEq, Eq, Copy, Default)]
 pub enum RefreshLut {
@@ -66,6 +83,69 @@ pub enum RefreshLut {
     Quick,
 }
 
+/// Display update sequence of the SSD16xx controllers, started by the master activation
+#[derive(Debug, Clone, PartialEq, Eq, Copy)]
+pub enum UpdateMode {
+    /// Display Mode 1, drives all pixels with the loaded LUT
+    Full,
+    /// Display Mode 2, only drives the pixels which differ from the old frame
+    Partial,
+    /// Loads the temperature and the LUT of the OTP before a refresh in Display Mode 1
+    ///
+    /// This replaces the LUT in the registers until it is uploaded again.
+    FullWithLoadLut,
+}
+
+/// Power state of the controller, for the drivers which keep track of it
+#[derive(Debug, Clone, PartialEq, Eq, Copy)]
+pub(crate) enum PowerState {
+    /// Initialized and powered on
+    Active,
+    /// The high voltages are off, the controller and its RAM are still awake
+    PoweredOff,
+    /// In deep sleep, only a reset wakes the controller up again
+    DeepSleep,
+    /// Attached without init, e.g. after a bootloader drove the panel
+    Unknown,
+}
+
+/// Counts the partial refreshes of a [QuickRefresh] driver, for its
+/// `set_full_refresh_after`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct PartialRefreshes {
+    /// Partial refreshes since the last full refresh
+    count: u32,
+    /// Every how many refreshes a full one is done instead of a partial one
+    full_after: Option<NonZeroU32>,
+}
+
+impl PartialRefreshes {
+    /// Makes every `refreshes`th refresh a full one, `None` disables it
+    pub(crate) fn set_full_after(&mut self, refreshes: Option<NonZeroU32>) {
+        self.full_after = refreshes;
+    }
+
+    /// Partial refreshes since the last full refresh
+    pub(crate) fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Whether the next refresh of a new frame has to be a full one
+    pub(crate) fn full_due(&self) -> bool {
+        matches!(self.full_after, Some(refreshes) if self.count.saturating_add(1) >= refreshes.get())
+    }
+
+    /// Counts a partial refresh
+    pub(crate) fn partial_done(&mut self) {
+        self.count = self.count.saturating_add(1);
+    }
+
+    /// Starts counting again after a full refresh or an init
+    pub(crate) fn full_done(&mut self) {
+        self.count = 0;
+    }
+}
+
 pub(crate) trait InternalWiAdditions<SPI, BUSY, DC, RST>:
     ErrorType<SPI, BUSY, DC, RST>
 where
@@ -92,8 +172,18 @@ where
 }
 
 /// Functions to interact with three color panels
-pub trait WaveshareThreeColorDisplay<SPI, BUSY, DC, RST>:
-    WaveshareDisplay<SPI, BUSY, DC, RST>
+///
+/// The planes are passed in the layout of the tri-color graphics buffers, whatever
+/// polarity the controller uses:
+///
+/// - achromatic plane: a set bit is white, a cleared bit is black
+/// - chromatic plane: a set bit is chromatic (red or yellow), a cleared bit shows the
+///   achromatic plane
+///
+/// So `bw_buffer()` and `chromatic_buffer()` of a `TriColor` display render the same
+/// on every driver, the drivers convert the planes where their controller needs it.
+pub trait WaveshareThreeColorDisplay<SPI, BUSY, DC, RST, DELAY>:
+    WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -103,6 +193,7 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     /// Transmit data to the SRAM of the EPD
     ///
@@ -126,12 +217,46 @@ where
     /// Update only the chromatic data of the display.
     ///
     /// This should be preceded by a call to `update_achromatic_frame`.
-    /// This data takes precedence over the black/white data.
+    /// This data takes precedence over the black/white data, set bits are chromatic.
     async fn update_chromatic_frame(
         &mut self,
         spi: &mut SPI,
         chromatic: &[u8],
     ) -> Result<(), Self::Error>;
+
+    /// Clears only the black/white data, with the value [WaveshareDisplay::clear_frame] uses
+    ///
+    /// The chromatic data is kept.
+    async fn clear_achromatic_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error>;
+
+    /// Clears only the chromatic data, with the value [WaveshareDisplay::clear_frame] uses
+    ///
+    /// The black/white data is kept, e.g. to remove a highlight without redrawing the rest.
+    async fn clear_chromatic_frame(&mut self, spi: &mut SPI) -> Result<(), Self::Error>;
+
+    /// Transmits both layers with [update_color_frame](WaveshareThreeColorDisplay::update_color_frame)
+    /// and refreshes the display, waiting for the refresh to finish
+    ///
+    /// Returns [ErrorKind::InvalidWindow] without sending anything if a buffer doesn't
+    /// hold exactly one bit per pixel of the display.
+    async fn update_and_display_color_frame(
+        &mut self,
+        spi: &mut SPI,
+        black: &[u8],
+        chromatic: &[u8],
+    ) -> Result<(), Self::Error>
+    where
+        Self::Error: From<ErrorKind<SPI, BUSY, DC, RST>>,
+    {
+        let len = buffer_len(self.width() as usize, self.height() as usize);
+        if black.len() != len || chromatic.len() != len {
+            return Err(ErrorKind::InvalidWindow.into());
+        }
+
+        self.update_color_frame(spi, black, chromatic).await?;
+        self.display_frame(spi).await?;
+        self.wait_until_idle(spi).await
+    }
 }
 
 /// All the functions to interact with the EPDs
@@ -155,9 +280,10 @@ where
 ///# let busy_in = pin::Mock::new(&expectations);
 ///# let dc = pin::Mock::new(&expectations);
 ///# let rst = pin::Mock::new(&expectations);
+///# let mut delay = delay::NoopDelay::new();
 ///
 ///// Setup EPD
-///let mut epd = Epd4in2::new(&mut spi, busy_in, dc, rst, None)?;
+///let mut epd = Epd4in2::new(&mut spi, busy_in, dc, Some(rst), delay, None)?;
 ///
 ///// Use display graphics from embedded-graphics
 ///let mut display = Display4in2::default();
@@ -177,7 +303,7 @@ where
 ///# Ok(())
 ///# }
 ///```
-pub trait WaveshareDisplay<SPI, BUSY, DC, RST>: ErrorType<SPI, BUSY, DC, RST>
+pub trait WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>: ErrorType<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -187,21 +313,31 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     /// The Color Type used by the Display
     type DisplayColor;
     /// Creates a new driver from a SPI peripheral, CS Pin, Busy InputPin, DC
     ///
+    /// `delay` sleeps for the reset and the other fixed waits, and between the polls
+    /// of the busy pin. With an async delay like `embassy_time::Delay` the executor can
+    /// sleep meanwhile. A [BusyWaitDelay](crate::BusyWaitDelay) pauses between the polls
+    /// with a [BusyWaitStrategy](crate::BusyWaitStrategy) instead.
+    ///
     /// `delay_us` is the number of us the idle loop should sleep on.
     /// Setting it to 0 implies busy waiting.
     /// Setting it to None means a default value is used.
     ///
+    /// `rst` is the reset pin, or `None::<NoResetPin>` if RST isn't connected to a GPIO, see
+    /// [NoResetPin](crate::NoResetPin).
+    ///
     /// This already initialises the device.
     async fn new(
         spi: &mut SPI,
         busy: BUSY,
         dc: DC,
-        rst: RST,
+        rst: Option<RST>,
+        delay: DELAY,
         delay_us: Option<u32>,
     ) -> Result<Self, Self::Error>
     where
@@ -209,12 +345,14 @@ where
 
     /// Let the device enter deep-sleep mode to save power.
     ///
-    /// The deep sleep mode returns to standby with a hardware reset.
+    /// The deep sleep mode returns to standby with a hardware reset. Until then the busy
+    /// line doesn't answer and the commands waiting for it return [ErrorKind::Asleep].
     async fn sleep(&mut self, spi: &mut SPI) -> Result<(), Self::Error>;
 
     /// Wakes the device up from sleep
     ///
-    /// Also reintialises the device if necessary.
+    /// Also reintialises the device if necessary. Leaving deep sleep needs a hardware reset,
+    /// without a reset pin this returns [ErrorKind::ResetRequired].
     async fn wake_up(&mut self, spi: &mut SPI) -> Result<(), Self::Error>;
 
     /// Sets the backgroundcolor for various commands like [clear_frame](WaveshareDisplay::clear_frame)
@@ -229,6 +367,56 @@ where
     /// Get the height of the display
     fn height(&self) -> u32;
 
+    /// Limits every wait for the busy pin to `timeout_us`, `None` (the default) waits
+    /// forever
+    ///
+    /// A wait running longer returns [ErrorKind::BusyTimeout], e.g. when the panel lost
+    /// its supply, and [WaveshareDisplay::wake_up] resets the controller again. The
+    /// timeout has to cover the longest refresh of the panel, which takes more than
+    /// 30 s on the color ones. With a timeout the busy pin is polled every `delay_us`
+    /// of [WaveshareDisplay::new].
+    fn set_busy_timeout(&mut self, timeout_us: Option<u32>);
+
+    /// Splits long SPI writes like the frame data into chunks of at most `size` bytes
+    ///
+    /// `None`, the default, sends the frame in one write. Use `Some(4096)` with Linux'
+    /// spidev, it rejects longer transfers by default. Every chunk is a write of its own,
+    /// chip select may toggle between them.
+    fn set_max_transfer_size(&mut self, size: Option<usize>);
+
+    /// Returns how long the last wait for the busy pin took in µs, e.g. the refresh of
+    /// [WaveshareDisplay::display_frame]
+    ///
+    /// Only measured with a clock, pass a [TimedDelay](crate::TimedDelay) as `delay` to
+    /// [WaveshareDisplay::new]. Otherwise it's `None`.
+    fn last_busy_duration(&self) -> Option<u32>;
+
+    /// Replaces the timing of the hardware reset, e.g. for a board with a slow RST line
+    ///
+    /// The default is the timing of the panel in the vendor driver. It takes effect with
+    /// the next [WaveshareDisplay::wake_up].
+    fn set_reset_timing(&mut self, timing: ResetTiming);
+
+    /// Sets the level of BUSY while the panel is busy, for clones with the opposite
+    /// polarity of the datasheet
+    ///
+    /// The default is the level in the datasheet. Waits for which the driver expects
+    /// the opposite level are inverted as well.
+    fn set_busy_polarity(&mut self, level: BusyLevel);
+
+    /// Sends the command `command` followed by `data` once the controller is idle
+    ///
+    /// Meant for experiments with registers the driver doesn't cover. The driver doesn't
+    /// know what the command changed, a later call may undo it or run into a state the
+    /// driver doesn't expect. Only available with the `raw-commands` feature.
+    #[cfg(feature = "raw-commands")]
+    async fn send_raw_command(
+        &mut self,
+        spi: &mut SPI,
+        command: u8,
+        data: &[u8],
+    ) -> Result<(), Self::Error>;
+
     /// Transmit a full frame to the SRAM of the EPD
     async fn update_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error>;
 
@@ -248,6 +436,30 @@ where
         height: u32,
     ) -> Result<(), Self::Error>;
 
+    /// Transmits partial data to the SRAM of the EPD, like
+    /// [update_partial_frame](WaveshareDisplay::update_partial_frame) for `rect`
+    ///
+    /// `buffer` has to cover `rect`, the driver rounds the window like for
+    /// `update_partial_frame`. Returns [ErrorKind::InvalidWindow] if `rect` is empty or
+    /// doesn't lie completely on the display, `buffer` is laid out with the rows of
+    /// `rect` and can't be cut to a part of it.
+    #[cfg(feature = "graphics")]
+    async fn update_partial_frame_rect(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        rect: &Rectangle,
+    ) -> Result<(), Self::Error>
+    where
+        Self::Error: From<ErrorKind<SPI, BUSY, DC, RST>>,
+    {
+        let Some((x, y, width, height)) = rect_window(rect, self.width(), self.height()) else {
+            return Err(ErrorKind::InvalidWindow.into());
+        };
+        self.update_partial_frame(spi, buffer, x, y, width, height)
+            .await
+    }
+
     /// Displays the frame data from SRAM
     ///
     /// This function waits until the device isn`t busy anymore
@@ -310,9 +522,10 @@ where
 ///# let busy_in = pin::Mock::new(&expectations);
 ///# let dc = pin::Mock::new(&expectations);
 ///# let rst = pin::Mock::new(&expectations);
+///# let mut delay = delay::NoopDelay::new();
 ///#
 ///# // Setup EPD
-///# let mut epd = Epd4in2::new(&mut spi, busy_in, dc, rst, None)?;
+///# let mut epd = Epd4in2::new(&mut spi, busy_in, dc, Some(rst), delay, None)?;
 ///let (x, y, frame_width, frame_height) = (20, 40, 80,80);
 ///
 ///let mut buffer = [DEFAULT_BACKGROUND_COLOR.get_byte_value(); 80 / 8 * 80];
@@ -329,7 +542,7 @@ where
 ///# Ok(())
 ///# }
 ///```
-pub trait QuickRefresh<SPI, BUSY, DC, RST>: ErrorType<SPI, BUSY, DC, RST>
+pub trait QuickRefresh<SPI, BUSY, DC, RST, DELAY>: ErrorType<SPI, BUSY, DC, RST>
 where
     SPI: SpiDevice,
     SPI::Error: Copy + Debug + Display,
@@ -339,6 +552,7 @@ where
     DC::Error: Copy + Debug + Display,
     RST: OutputPin,
     RST::Error: Copy + Debug + Display,
+    DELAY: DisplayDelay,
 {
     /// Updates the old frame.
     async fn update_old_frame(&mut self, spi: &mut SPI, buffer: &[u8]) -> Result<(), Self::Error>;
@@ -390,4 +604,356 @@ where
         width: u32,
         height: u32,
     ) -> Result<(), Self::Error>;
+
+    /// Updates the old frame for `rect`, which has to lie on the display like for
+    /// [WaveshareDisplay::update_partial_frame_rect]
+    #[cfg(feature = "graphics")]
+    async fn update_partial_old_frame_rect(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        rect: &Rectangle,
+    ) -> Result<(), Self::Error>
+    where
+        Self: WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>,
+        <Self as ErrorType<SPI, BUSY, DC, RST>>::Error: From<ErrorKind<SPI, BUSY, DC, RST>>,
+    {
+        let Some((x, y, width, height)) = rect_window(rect, self.width(), self.height()) else {
+            return Err(ErrorKind::InvalidWindow.into());
+        };
+        self.update_partial_old_frame(spi, buffer, x, y, width, height)
+            .await
+    }
+
+    /// Updates the new frame for `rect`, which has to lie on the display like for
+    /// [WaveshareDisplay::update_partial_frame_rect]
+    #[cfg(feature = "graphics")]
+    async fn update_partial_new_frame_rect(
+        &mut self,
+        spi: &mut SPI,
+        buffer: &[u8],
+        rect: &Rectangle,
+    ) -> Result<(), Self::Error>
+    where
+        Self: WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>,
+        <Self as ErrorType<SPI, BUSY, DC, RST>>::Error: From<ErrorKind<SPI, BUSY, DC, RST>>,
+    {
+        let Some((x, y, width, height)) = rect_window(rect, self.width(), self.height()) else {
+            return Err(ErrorKind::InvalidWindow.into());
+        };
+        self.update_partial_new_frame(spi, buffer, x, y, width, height)
+            .await
+    }
+
+    /// Clears `rect` of the new frame
+    ///
+    /// There's no buffer to lay out, so `rect` is clamped to the display. Returns
+    /// [ErrorKind::InvalidWindow] if nothing of it lies on the display.
+    #[cfg(feature = "graphics")]
+    async fn clear_partial_frame_rect(
+        &mut self,
+        spi: &mut SPI,
+        rect: &Rectangle,
+    ) -> Result<(), Self::Error>
+    where
+        Self: WaveshareDisplay<SPI, BUSY, DC, RST, DELAY>,
+        <Self as ErrorType<SPI, BUSY, DC, RST>>::Error: From<ErrorKind<SPI, BUSY, DC, RST>>,
+    {
+        let Some((x, y, width, height)) = clamp_rect(rect, self.width(), self.height()) else {
+            return Err(ErrorKind::InvalidWindow.into());
+        };
+        self.clear_partial_frame(spi, x, y, width, height).await
+    }
+}
+
+/// Clamps `rect` to a display of `width` x `height` pixels, returns x, y, width and height
+///
+/// `None` if nothing of `rect` is left.
+#[cfg(feature = "graphics")]
+fn clamp_rect(rect: &Rectangle, width: u32, height: u32) -> Option<(u32, u32, u32, u32)> {
+    let display = Rectangle::new(Point::zero(), Size::new(width, height));
+    let window = rect.intersection(&display);
+    if window.is_zero_sized() {
+        return None;
+    }
+    Some((
+        window.top_left.x as u32,
+        window.top_left.y as u32,
+        window.size.width,
+        window.size.height,
+    ))
+}
+
+/// Converts `rect` to x, y, width and height, `None` if it's empty or sticks out of a
+/// display of `width` x `height` pixels
+#[cfg(feature = "graphics")]
+fn rect_window(rect: &Rectangle, width: u32, height: u32) -> Option<(u32, u32, u32, u32)> {
+    let window = clamp_rect(rect, width, height)?;
+    (window.2 == rect.size.width && window.3 == rect.size.height).then_some(window)
+}
+
+/// A bus which shifts out two bits per clock, for the dual SPI mode of some controllers
+///
+/// `embedded-hal` doesn't model dual SPI, so this has to be implemented for the QSPI or
+/// dual SPI peripheral of the platform. The chip select has to be handled by the
+/// implementation like with a [SpiDevice], the data/command pin is set by the driver.
+pub trait DualSpiBus {
+    /// Error of the bus, the same as the one of the [SpiDevice] of the driver, which
+    /// returns it as [ErrorKind::SpiError]
+    type Error: Debug;
+
+    /// Writes `data` MSB first with two bits per clock
+    ///
+    /// The higher bit of every pair goes out on the second data line (IO1), the lower
+    /// one on the first data line (SDA/IO0).
+    async fn write_dual(&mut self, data: &[u8]) -> Result<(), Self::Error>;
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use std::vec;
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::epd1in54b::Epd1in54b;
+    use crate::epd2in13bc::Epd2in13bc;
+    use crate::epd2in7b::Epd2in7b;
+    use crate::epd2in9bc::Epd2in9bc;
+    use crate::epd4in2b_v2::Epd4in2b;
+    use crate::epd5in83b_v2::Epd5in83;
+    use crate::epd7in5b_v2::Epd7in5;
+    use crate::mock::{block_on, mocks, Busy, Dc, Delay, Log, Rst, Spi};
+
+    /// Checks the chromatic plane written by `update_chromatic_frame` against the one of
+    /// `clear_frame`: a cleared chromatic buffer has to match it, a set one its inverse.
+    fn check_chromatic_plane<EPD>(log: &Log, spi: &mut Spi, epd: &mut EPD, command: u8)
+    where
+        EPD: WaveshareThreeColorDisplay<Spi, Busy, Dc, Rst, Delay>,
+    {
+        let len = (epd.width() / 8 * epd.height()) as usize;
+
+        log.clear();
+        block_on(epd.clear_frame(spi)).unwrap();
+        let cleared = log.data_of(command, 0);
+        assert_eq!(cleared.len(), len);
+
+        log.clear();
+        block_on(epd.update_chromatic_frame(spi, &vec![0x00; len])).unwrap();
+        assert_eq!(log.data_of(command, 0), cleared);
+
+        log.clear();
+        block_on(epd.update_chromatic_frame(spi, &vec![0xFF; len])).unwrap();
+        let colored: Vec<u8> = cleared.iter().map(|byte| !byte).collect();
+        assert_eq!(log.data_of(command, 0), colored);
+    }
+
+    #[test]
+    fn partial_refreshes() {
+        let mut refreshes = PartialRefreshes::default();
+        refreshes.partial_done();
+        assert!(!refreshes.full_due());
+
+        // every refresh is a full one
+        refreshes.set_full_after(NonZeroU32::new(1));
+        assert!(refreshes.full_due());
+
+        refreshes.set_full_after(NonZeroU32::new(3));
+        refreshes.full_done();
+        refreshes.partial_done();
+        assert!(!refreshes.full_due());
+        refreshes.partial_done();
+        assert!(refreshes.full_due());
+        assert_eq!(refreshes.count(), 2);
+        refreshes.full_done();
+        assert_eq!(refreshes.count(), 0);
+    }
+
+    #[test]
+    fn chromatic_plane_polarity() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd1in54b::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        check_chromatic_plane(&log, &mut spi, &mut epd, 0x13);
+
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd =
+            block_on(Epd2in13bc::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        check_chromatic_plane(&log, &mut spi, &mut epd, 0x13);
+
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in9bc::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        check_chromatic_plane(&log, &mut spi, &mut epd, 0x13);
+
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd4in2b::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        check_chromatic_plane(&log, &mut spi, &mut epd, 0x26);
+
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd5in83::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        check_chromatic_plane(&log, &mut spi, &mut epd, 0x13);
+
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        log.busy_level.set(true);
+        let mut epd = block_on(Epd7in5::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        check_chromatic_plane(&log, &mut spi, &mut epd, 0x13);
+    }
+
+    /// Checks that each selective clear writes its plane like `clear_frame` and doesn't
+    /// touch the other one
+    fn check_selective_clears<EPD>(log: &Log, spi: &mut Spi, epd: &mut EPD, planes: [u8; 2])
+    where
+        EPD: WaveshareThreeColorDisplay<Spi, Busy, Dc, Rst, Delay>,
+    {
+        let [achromatic, chromatic] = planes;
+        log.clear();
+        block_on(epd.clear_frame(spi)).unwrap();
+        let cleared = planes.map(|command| log.data_of(command, 0));
+        assert!(!cleared[0].is_empty() && !cleared[1].is_empty());
+
+        log.clear();
+        block_on(epd.clear_achromatic_frame(spi)).unwrap();
+        assert_eq!(log.data_of(achromatic, 0), cleared[0]);
+        assert!(!log.commands().contains(&chromatic));
+
+        log.clear();
+        block_on(epd.clear_chromatic_frame(spi)).unwrap();
+        assert_eq!(log.data_of(chromatic, 0), cleared[1]);
+        assert!(!log.commands().contains(&achromatic));
+    }
+
+    #[test]
+    fn selective_clears() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd1in54b::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        check_selective_clears(&log, &mut spi, &mut epd, [0x10, 0x13]);
+
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in7b::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        check_selective_clears(&log, &mut spi, &mut epd, [0x10, 0x13]);
+
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd2in9bc::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        check_selective_clears(&log, &mut spi, &mut epd, [0x10, 0x13]);
+
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd4in2b::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        check_selective_clears(&log, &mut spi, &mut epd, [0x24, 0x26]);
+
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        log.busy_level.set(true);
+        let mut epd = block_on(Epd7in5::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        check_selective_clears(&log, &mut spi, &mut epd, [0x10, 0x13]);
+    }
+
+    /// Checks that `update_and_display_color_frame` refuses buffers of the wrong length
+    /// and otherwise writes both planes before the refresh
+    ///
+    /// `expansion` is the number of bytes the driver sends per byte of the black buffer.
+    fn check_color_frame<EPD>(
+        log: &Log,
+        spi: &mut Spi,
+        epd: &mut EPD,
+        commands: [u8; 3],
+        expansion: usize,
+    ) where
+        EPD: WaveshareThreeColorDisplay<Spi, Busy, Dc, Rst, Delay>,
+        EPD::Error: From<ErrorKind<Spi, Busy, Dc, Rst>>,
+    {
+        let len = buffer_len(epd.width() as usize, epd.height() as usize);
+        let [achromatic, chromatic, refresh] = commands;
+
+        log.clear();
+        let result = block_on(epd.update_and_display_color_frame(
+            spi,
+            &vec![0xFF; len],
+            &vec![0x00; len - 1],
+        ));
+        assert!(matches!(
+            result.map_err(|error| *error.kind()),
+            Err(ErrorKind::InvalidWindow)
+        ));
+        assert!(log.commands().is_empty());
+
+        block_on(epd.update_and_display_color_frame(spi, &vec![0xFF; len], &vec![0x00; len]))
+            .unwrap();
+        let sent = log.commands();
+        let position = |command| sent.iter().position(|&sent| sent == command).unwrap();
+        assert_eq!(log.data_of(achromatic, 0).len(), len * expansion);
+        assert_eq!(log.data_of(chromatic, 0).len(), len);
+        assert!(position(achromatic) < position(refresh));
+        assert!(position(chromatic) < position(refresh));
+    }
+
+    #[test]
+    #[cfg(feature = "graphics")]
+    fn partial_frame_rect() {
+        use crate::epd5in83_v2::Epd5in83 as Epd5in83V2;
+
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd =
+            block_on(Epd5in83V2::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        let buffer = [0xAA; 2 * 4];
+
+        // the window is rounded like for the raw coordinates
+        log.clear();
+        let rect = Rectangle::new(Point::new(3, 10), Size::new(10, 4));
+        block_on(epd.update_partial_frame_rect(&mut spi, &buffer, &rect)).unwrap();
+        let window = log.writes();
+        log.clear();
+        block_on(epd.update_partial_frame(&mut spi, &buffer, 3, 10, 10, 4)).unwrap();
+        assert_eq!(window, log.writes());
+
+        log.clear();
+        block_on(epd.update_partial_new_frame_rect(&mut spi, &buffer, &rect)).unwrap();
+        assert_eq!(window, log.writes());
+
+        // clearing cuts off the part left of the display
+        log.clear();
+        let rect = Rectangle::new(Point::new(-5, 10), Size::new(21, 4));
+        block_on(epd.clear_partial_frame_rect(&mut spi, &rect)).unwrap();
+        let clamped = log.writes();
+        log.clear();
+        block_on(epd.clear_partial_frame(&mut spi, 0, 10, 16, 4)).unwrap();
+        assert_eq!(clamped, log.writes());
+
+        log.clear();
+        for rect in [
+            Rectangle::new(Point::new(8, 8), Size::zero()),
+            Rectangle::new(Point::new(-16, 0), Size::new(16, 8)),
+            Rectangle::new(Point::new(0, epd.height() as i32), Size::new(8, 8)),
+        ] {
+            let result = block_on(epd.update_partial_old_frame_rect(&mut spi, &buffer, &rect));
+            assert!(matches!(result, Err(ErrorKind::InvalidWindow)));
+            let result = block_on(epd.clear_partial_frame_rect(&mut spi, &rect));
+            assert!(matches!(result, Err(ErrorKind::InvalidWindow)));
+        }
+
+        // a buffer laid out for a rect which sticks out can't be sent
+        for rect in [
+            Rectangle::new(Point::new(-5, 10), Size::new(21, 4)),
+            Rectangle::new(Point::new(0, epd.height() as i32 - 2), Size::new(16, 4)),
+        ] {
+            let result = block_on(epd.update_partial_frame_rect(&mut spi, &buffer, &rect));
+            assert!(matches!(result, Err(ErrorKind::InvalidWindow)));
+            let result = block_on(epd.update_partial_new_frame_rect(&mut spi, &buffer, &rect));
+            assert!(matches!(result, Err(ErrorKind::InvalidWindow)));
+        }
+        assert!(log.writes().is_empty());
+    }
+
+    #[test]
+    fn update_and_display_color_frame() {
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd1in54b::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        check_color_frame(&log, &mut spi, &mut epd, [0x10, 0x13, 0x12], 2);
+
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        let mut epd = block_on(Epd4in2b::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        check_color_frame(&log, &mut spi, &mut epd, [0x24, 0x26, 0x20], 1);
+
+        let (log, mut spi, busy, dc, rst, delay) = mocks();
+        log.busy_level.set(true);
+        let mut epd = block_on(Epd7in5::new(&mut spi, busy, dc, Some(rst), delay, None)).unwrap();
+        check_color_frame(&log, &mut spi, &mut epd, [0x10, 0x13, 0x12], 1);
+    }
 }
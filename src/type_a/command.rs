@@ -45,6 +45,8 @@ pub(crate) enum Command {
 
     TemperatureSensorControl = 0x1A,
 
+    TemperatureSensorRead = 0x1B,
+
     MasterActivation = 0x20,
 
     DisplayUpdateControl1 = 0x21,
@@ -80,6 +82,17 @@ pub(crate) enum Command {
     Nop = 0xFF,
 }
 
+/// Mode of [Command::DeepSleepMode]
+///
+/// These controllers only have one deep sleep mode, which keeps the RAM content.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeepSleepMode {
+    /// Doesn't sleep, the controller and its RAM stay accessible (POR)
+    Normal = 0x00,
+    /// Sleeps without access to RAM/controller but keeps RAM content, a reset wakes it up
+    Mode1 = 0x01,
+}
+
 impl traits::Command for Command {
     /// Returns the address of the command
     fn address(self) -> u8 {
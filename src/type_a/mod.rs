@@ -1,2 +1,66 @@
+use crate::color::Color;
+use crate::traits::UpdateMode;
+
 pub(crate) mod command;
 pub(crate) mod constants;
+
+/// Value of [command::Command::BorderWaveformControl] which drives the border with the
+/// gray scale transition of a pixel staying `color`
+pub(crate) const fn border_waveform(color: Color) -> u8 {
+    match color {
+        // GS0 -> GS0
+        Color::Black => 0x00,
+        // GS1 -> GS1
+        Color::White => 0x03,
+    }
+}
+
+/// Value of [command::Command::BorderWaveformControl] which holds the border at VCOM,
+/// so it doesn't follow the waveform of a refresh
+pub(crate) const BORDER_VCOM: u8 = 0x80;
+
+/// Last byte of [command::Command::DriverOutputControl], `y_flip` scans the gates
+/// from the last one to G0 (TB)
+pub(crate) const fn gate_scan(y_flip: bool) -> u8 {
+    if y_flip {
+        0x01
+    } else {
+        0x00
+    }
+}
+
+/// Value of [command::Command::DataEntryModeSetting], `x_flip` decrements the x address
+/// instead of incrementing it
+pub(crate) const fn data_entry_mode(x_flip: bool) -> u8 {
+    if x_flip {
+        0x02
+    } else {
+        0x03
+    }
+}
+
+/// RAM x address of the byte holding column `x` of a panel `width` pixels wide
+///
+/// With `x_flip` the columns are counted from the other side, the bits of the bytes
+/// have to be reversed as well.
+pub(crate) const fn ram_x(x: u32, width: u32, x_flip: bool) -> u8 {
+    if x_flip {
+        ((width - 1 - x) >> 3) as u8
+    } else {
+        (x >> 3) as u8
+    }
+}
+
+/// Value of [command::Command::DisplayUpdateControl2] for the update sequence of `mode`
+///
+/// Clock and charge pump stay enabled afterwards, like with the 0xC4 of the drivers.
+pub(crate) const fn display_update_control(mode: UpdateMode) -> u8 {
+    match mode {
+        // enable clock signal, enable cp, initial display
+        UpdateMode::Full => 0xC8,
+        // enable clock signal, enable cp, pattern display
+        UpdateMode::Partial => 0xC4,
+        // additionally load temperature and LUT
+        UpdateMode::FullWithLoadLut => 0xF8,
+    }
+}